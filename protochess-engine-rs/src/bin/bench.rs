@@ -0,0 +1,35 @@
+use protochess_engine_rs::types::Depth;
+use protochess_engine_rs::utils::bench::{run_bench, DEFAULT_BENCH_DEPTH};
+
+pub fn main() {
+    // Usage: cargo run --release --bin bench -- [depth] [expected_signature]
+    // Searches a fixed set of positions (standard chess, atomic, horde, antichess, racing kings and a 10x10 variant)
+    // and prints the total number of nodes, the nodes per second and the signature (the total number of nodes).
+    // If <expected_signature> is given, exits with an error if the signature is different.
+    // Example: cargo run --release --bin bench -- 7
+
+    let args: Vec<String> = std::env::args().collect();
+    let exit_with_usage = || -> ! {
+        println!("Usage: {} [depth] [expected_signature]", args[0]);
+        std::process::exit(1);
+    };
+    let depth = match args.get(1) {
+        Some(arg) => arg.parse::<Depth>().ok().filter(|depth| *depth > 0).unwrap_or_else(|| exit_with_usage()),
+        None => DEFAULT_BENCH_DEPTH,
+    };
+    let expected_signature = args.get(2).map(|arg| arg.parse::<u64>().unwrap_or_else(|_| exit_with_usage()));
+
+    let result = run_bench(depth).unwrap_or_else(|e| {
+        println!("Error during the benchmark: {e}");
+        std::process::exit(1);
+    });
+    println!("\n========================================\n");
+    println!("{result}");
+    if let Some(expected) = expected_signature {
+        if result.signature() != expected {
+            println!("\nSignature mismatch: expected {expected}, got {}", result.signature());
+            std::process::exit(1);
+        }
+        println!("\nThe signature matches");
+    }
+}
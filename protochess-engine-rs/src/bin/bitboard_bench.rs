@@ -0,0 +1,151 @@
+use std::hint::black_box;
+
+use instant::Instant;
+use numext_fixed_uint::U256;
+
+use protochess_engine_rs::types::Bitboard;
+
+const NUM_BOARDS: usize = 1024;
+const DEFAULT_ITERATIONS: u32 = 2000;
+
+/// Operations used by the move generator, implemented by both 256-bit types
+trait BenchBoard: Clone {
+    fn from_words(words: [u64; 4]) -> Self;
+    fn and(&self, other: &Self) -> Self;
+    fn or(&self, other: &Self) -> Self;
+    fn xor(&self, other: &Self) -> Self;
+    fn not(&self) -> Self;
+    fn shl(&self, shift: u8) -> Self;
+    fn shr(&self, shift: u8) -> Self;
+    fn lowest_one(&self) -> Option<u8>;
+    fn clear_bit(&mut self, index: u8);
+    fn count_ones(&self) -> u32;
+}
+
+impl BenchBoard for Bitboard {
+    fn from_words(words: [u64; 4]) -> Self {
+        let mut board = Bitboard::zero();
+        *board.get_inner_mut() = words;
+        board
+    }
+    fn and(&self, other: &Self) -> Self { self & other }
+    fn or(&self, other: &Self) -> Self { self | other }
+    fn xor(&self, other: &Self) -> Self { self ^ other }
+    fn not(&self) -> Self { !self }
+    fn shl(&self, shift: u8) -> Self { self << shift }
+    fn shr(&self, shift: u8) -> Self { self >> shift }
+    fn lowest_one(&self) -> Option<u8> { Bitboard::lowest_one(self) }
+    fn clear_bit(&mut self, index: u8) { Bitboard::clear_bit(self, index) }
+    fn count_ones(&self) -> u32 { Bitboard::count_ones(self) }
+}
+
+impl BenchBoard for U256 {
+    fn from_words(words: [u64; 4]) -> Self { U256(words) }
+    fn and(&self, other: &Self) -> Self { self & other }
+    fn or(&self, other: &Self) -> Self { self | other }
+    fn xor(&self, other: &Self) -> Self { self ^ other }
+    fn not(&self) -> Self { !self }
+    fn shl(&self, shift: u8) -> Self { self << shift }
+    fn shr(&self, shift: u8) -> Self { self >> shift }
+    fn lowest_one(&self) -> Option<u8> { U256::lowest_one(self).map(|i| i as u8) }
+    fn clear_bit(&mut self, index: u8) { self.set_bit(index as usize, false); }
+    fn count_ones(&self) -> u32 { U256::count_ones(self) }
+}
+
+pub fn main() {
+    // Usage: cargo run --release --features numext-fixed-uint --bin bitboard_bench -- [iterations]
+    // Times the bitboard operations used by the move generator on the Bitboard type and on numext's U256
+    // (the previous implementation of Bitboard), with the same pseudo-random boards.
+
+    let args: Vec<String> = std::env::args().collect();
+    let iterations = match args.get(1) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            println!("Usage: {} [iterations]", args[0]);
+            std::process::exit(1);
+        }),
+        None => DEFAULT_ITERATIONS,
+    };
+    let words = random_words();
+
+    println!("{:<12} {:>12} {:>12} {:>8}", "Operation", "Bitboard", "U256", "Speedup");
+    let mut total = (0.0, 0.0);
+    for ((name, bench_bitboard), (_, bench_u256)) in benchmarks::<Bitboard>().into_iter().zip(benchmarks::<U256>()) {
+        let (time_bitboard, result_bitboard) = time(&words, iterations, bench_bitboard);
+        let (time_u256, result_u256) = time(&words, iterations, bench_u256);
+        assert_eq!(result_bitboard, result_u256, "Different results in {name}");
+        println!("{name:<12} {:>10.1}ms {:>10.1}ms {:>7.2}x", time_bitboard * 1000.0, time_u256 * 1000.0, time_u256 / time_bitboard);
+        total.0 += time_bitboard;
+        total.1 += time_u256;
+    }
+    println!("{:<12} {:>10.1}ms {:>10.1}ms {:>7.2}x", "Total", total.0 * 1000.0, total.1 * 1000.0, total.1 / total.0);
+}
+
+type Benchmark<B> = (&'static str, fn(&[B]) -> u64);
+
+fn benchmarks<B: BenchBoard>() -> Vec<Benchmark<B>> {
+    vec![
+        ("logic", logic::<B>),
+        ("shifts", shifts::<B>),
+        ("bit scan", bit_scan::<B>),
+        ("count ones", count_ones::<B>),
+    ]
+}
+
+// Returns the time per run (in seconds) and the checksum of the last run
+fn time<B: BenchBoard>(words: &[[u64; 4]], iterations: u32, bench: fn(&[B]) -> u64) -> (f64, u64) {
+    let boards: Vec<B> = words.iter().map(|w| B::from_words(*w)).collect();
+    let start = Instant::now();
+    let mut result = 0;
+    for _ in 0..iterations {
+        result = bench(black_box(&boards));
+    }
+    (start.elapsed().as_secs_f64(), result)
+}
+
+fn logic<B: BenchBoard>(boards: &[B]) -> u64 {
+    let mut checksum = 0;
+    for pair in boards.windows(2) {
+        let mixed = pair[0].and(&pair[1].not()).or(&pair[1].xor(&pair[0]));
+        checksum += mixed.count_ones() as u64;
+    }
+    checksum
+}
+
+fn shifts<B: BenchBoard>(boards: &[B]) -> u64 {
+    let mut checksum = 0;
+    for (i, board) in boards.iter().enumerate() {
+        // The steps of the board directions (1, 15, 16 and 17) and a long shift across words
+        let shift = [1, 15, 16, 17, 100][i % 5];
+        checksum += board.shl(shift).or(&board.shr(shift)).count_ones() as u64;
+    }
+    checksum
+}
+
+fn bit_scan<B: BenchBoard>(boards: &[B]) -> u64 {
+    let mut checksum = 0;
+    for board in boards {
+        let mut board = board.clone();
+        while let Some(index) = board.lowest_one() {
+            board.clear_bit(index);
+            checksum += index as u64;
+        }
+    }
+    checksum
+}
+
+fn count_ones<B: BenchBoard>(boards: &[B]) -> u64 {
+    boards.iter().map(|board| board.count_ones() as u64).sum()
+}
+
+// Sparse boards with a few pieces in each word, like the piece bitboards of a position
+fn random_words() -> Vec<[u64; 4]> {
+    let mut seed = 0x9E37_79B9_7F4A_7C15_u64;
+    let mut next = move || {
+        // Xorshift
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    (0..NUM_BOARDS).map(|_| [next() & next(), next() & next(), next() & next() & next(), next() & next() & next()]).collect()
+}
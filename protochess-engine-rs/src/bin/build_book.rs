@@ -0,0 +1,57 @@
+use std::convert::TryFrom;
+
+use protochess_engine_rs::{BookFormat, GameState};
+use protochess_engine_rs::book::builder::BookBuilder;
+
+pub fn main() {
+    // Usage: cargo run --bin build_book -- <output.bin> <format> <max_ply> <file.pgn>...
+    // <format> is "polyglot" (standard chess only) or "protochess" (any variant)
+    // Games without a Variant tag are assumed to be standard chess
+    // Protochess books use the default zobrist keys (seed 0, see Engine::set_seed() and Engine::set_zobrist_seed())
+    // Example: cargo run --bin build_book -- book.bin polyglot 16 games.pgn
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 5 {
+        println!("Usage: {} <output.bin> <polyglot | protochess> <max_ply> <file.pgn>...", args[0]);
+        std::process::exit(1);
+    }
+    let format = BookFormat::try_from(args[2].as_str()).unwrap_or_else(|e| {
+        println!("{e}");
+        std::process::exit(1);
+    });
+    let max_ply = args[3].parse::<usize>().unwrap_or_else(|_| {
+        println!("Invalid max_ply: {}", args[3]);
+        std::process::exit(1);
+    });
+
+    let default_variant = GameState::default().initial_state;
+    let mut builder = BookBuilder::new(format, max_ply, 0);
+    let mut total_games = 0;
+    for path in &args[4..] {
+        let contents = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            println!("Cannot read {path}: {e}");
+            std::process::exit(1);
+        });
+        match builder.add_pgn(&contents, &default_variant) {
+            Ok(games) => {
+                println!("{path}: {games} games");
+                total_games += games;
+            },
+            Err(e) => {
+                println!("{path}: {e}");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    let book = builder.build();
+    let bytes = book.to_bytes().unwrap_or_else(|e| {
+        println!("Cannot create the book: {e}");
+        std::process::exit(1);
+    });
+    std::fs::write(&args[1], bytes).unwrap_or_else(|e| {
+        println!("Cannot write {}: {e}", args[1]);
+        std::process::exit(1);
+    });
+    println!("Wrote {} entries from {total_games} games to {}", book.len(), args[1]);
+}
@@ -0,0 +1,172 @@
+use std::convert::TryFrom;
+use std::io::Write;
+
+use protochess_engine_rs::{EvalParams, SearchParams, Skill};
+use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+use protochess_engine_rs::types::GameMode;
+use protochess_engine_rs::utils::epd::parse_epd;
+use protochess_engine_rs::utils::self_play::{run_match, EngineConfig, MatchSettings, Opening, SearchLimit, Sprt, SprtStatus, DEFAULT_OPENINGS};
+
+const USAGE: &str = "Usage: engine_match <first> <second> [options]
+  <first>, <second>: engine settings, as a comma-separated list of key=value (or 'default'):
+      name=<name>        Name written in the PGN
+      eval=<file>        Evaluation parameters (see EvalParams::parse())
+      disable=<h>        Disable a search heuristic: all, aspiration, rfp, qfutility, lmp, razoring
+      skill=<level>      Weakened play, from 0 to 19
+      elo=<elo>          Weakened play, approximate Elo rating
+Options:
+  --depth <n> | --time <seconds> | --nodes <n>   Search limit of each move (default: --depth 6)
+  --openings <file.epd>   Starting positions, with an optional 'variant' opcode (default: 8 standard openings)
+  --variant <name>        Play from the starting position of a variant instead
+  --games <n>             Number of games (default: 2 per opening)
+  --concurrency <n>       Number of games played at the same time (default: 1)
+  --max-ply <n>           Adjudicate the game as a draw after this many plies (default: 300)
+  --pgn <file>            Write the games to a PGN file
+  --sprt <elo0> <elo1>    Stop when the SPRT (alpha = beta = 0.05) accepts one of the hypotheses
+  --seed <n>              Seed of the random choices of the engines (default: 0)";
+
+pub fn main() {
+    // Plays a match between 2 engine configurations and prints the result from the point of view of <first>.
+    // Each opening is played twice, once with each color.
+    // Example: cargo run --release --bin engine_match -- name=tuned,eval=params.txt default --nodes 20000 --games 1000 --concurrency 4 --sprt 0 10 --pgn games.pgn
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        exit_with(USAGE);
+    }
+    let mut first = parse_config(&args[1]);
+    let second = parse_config(&args[2]);
+    if first.name == second.name {
+        first.name.push_str(" (1)");
+    }
+
+    let mut limit = SearchLimit::Depth(6);
+    let mut openings: Vec<Opening> = DEFAULT_OPENINGS.iter().map(|fen| Opening::from_debug_fen(fen)).collect();
+    let mut num_games = None;
+    let mut concurrency = 1;
+    let mut max_ply = 300;
+    let mut pgn_path = None;
+    let mut sprt = None;
+    let mut seed = 0;
+    let mut i = 3;
+    while i < args.len() {
+        let value = |offset: usize| -> &str {
+            args.get(i + offset).map(|s| s.as_str()).unwrap_or_else(|| exit_with(&format!("Missing value for {}", args[i])))
+        };
+        match args[i].as_str() {
+            "--depth" => limit = SearchLimit::Depth(parse_number(value(1))),
+            "--time" => limit = SearchLimit::Time(parse_number(value(1))),
+            "--nodes" => limit = SearchLimit::Nodes(parse_number(value(1))),
+            "--openings" => openings = read_openings(value(1)),
+            "--variant" => {
+                let variant = GameMode::try_from(value(1)).unwrap_or_else(|e| exit_with(&e));
+                let fen = VariantFactory::new(variant).make_initial_state().fen;
+                openings = vec![Opening { fen, variant }];
+            },
+            "--games" => num_games = Some(parse_number(value(1))),
+            "--concurrency" => concurrency = parse_number(value(1)),
+            "--max-ply" => max_ply = parse_number(value(1)),
+            "--pgn" => pgn_path = Some(value(1).to_string()),
+            "--seed" => seed = parse_number(value(1)),
+            "--sprt" => {
+                sprt = Some(Sprt::new(parse_number(value(1)), parse_number(value(2))));
+                i += 1;
+            },
+            other => exit_with(&format!("Unknown option: {other}\n{USAGE}")),
+        }
+        i += 2;
+    }
+    let settings = MatchSettings {
+        num_games: num_games.unwrap_or(2 * openings.len() as u32),
+        openings,
+        limit,
+        max_ply,
+        concurrency,
+        sprt,
+        seed,
+    };
+
+    let mut pgn_file = pgn_path.map(|path| std::fs::File::create(&path).unwrap_or_else(|e| {
+        exit_with(&format!("Cannot create {path}: {e}"))
+    }));
+    let start = instant::Instant::now();
+    let result = run_match(&first, &second, &settings, |game, total| {
+        let (white, black) = if game.first_is_white { (&first, &second) } else { (&second, &first) };
+        if let Some(file) = &mut pgn_file {
+            let pgn = game.record.to_pgn(&white.name, &black.name, game.index + 1);
+            writeln!(file, "{pgn}").unwrap_or_else(|e| exit_with(&format!("Cannot write the PGN file: {e}")));
+        }
+        print!("Game #{} ({} vs {}): {}. Total: {total}", game.index + 1, white.name, black.name,
+            game.record.outcome.pgn_result());
+        if let Some(sprt) = &settings.sprt {
+            let (lower, upper) = sprt.bounds();
+            print!(", LLR: {:.2} ({lower:.2}, {upper:.2})", sprt.llr(total));
+        }
+        println!(" [time: {:?}]", start.elapsed());
+    });
+    let (result, status) = result.unwrap_or_else(|e| exit_with(&format!("Error during the match: {e}")));
+
+    println!("\n========================================\n");
+    println!("{} vs {}: {result}", first.name, second.name);
+    if let Some(sprt) = settings.sprt {
+        let conclusion = match status {
+            SprtStatus::AcceptH0 => "H0 accepted",
+            SprtStatus::AcceptH1 => "H1 accepted",
+            SprtStatus::Continue => "inconclusive",
+        };
+        println!("SPRT [{}, {}]: {conclusion} (LLR: {:.2})", sprt.elo0, sprt.elo1, sprt.llr(&result));
+    }
+}
+
+// Parses the settings of an engine, see USAGE
+fn parse_config(arg: &str) -> EngineConfig {
+    let mut config = EngineConfig::default();
+    if arg == "default" {
+        return config;
+    }
+    for option in arg.split(',') {
+        let (key, value) = option.split_once('=').unwrap_or_else(|| exit_with(&format!("Expected key=value, got '{option}'")));
+        match key {
+            "name" => config.name = value.to_string(),
+            "eval" => {
+                let contents = std::fs::read_to_string(value).unwrap_or_else(|e| exit_with(&format!("Cannot read {value}: {e}")));
+                config.eval_params = EvalParams::parse(&contents).unwrap_or_else(|e| exit_with(&format!("Invalid parameters in {value}: {e}")));
+            },
+            "disable" => disable_heuristic(&mut config.search_params, value),
+            "skill" => config.skill = Some(Skill::from_level(parse_number(value))),
+            "elo" => config.skill = Some(Skill::from_elo(parse_number(value))),
+            other => exit_with(&format!("Unknown engine setting: {other}")),
+        }
+    }
+    config
+}
+
+fn disable_heuristic(params: &mut SearchParams, heuristic: &str) {
+    match heuristic {
+        "all" => *params = SearchParams::disabled(),
+        "aspiration" => params.aspiration_window = None,
+        "rfp" => params.reverse_futility_margin = None,
+        "qfutility" => params.qsearch_futility_margin = None,
+        "lmp" => params.late_move_pruning = false,
+        "razoring" => params.razoring_margin = None,
+        other => exit_with(&format!("Unknown heuristic: {other}")),
+    }
+}
+
+fn read_openings(path: &str) -> Vec<Opening> {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|e| exit_with(&format!("Cannot read {path}: {e}")));
+    let entries = parse_epd(&contents).unwrap_or_else(|e| exit_with(&format!("Invalid EPD file: {e}")));
+    if entries.is_empty() {
+        exit_with(&format!("No openings in {path}"));
+    }
+    entries.iter().map(Opening::from).collect()
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> T {
+    value.parse().unwrap_or_else(|_| exit_with(&format!("Invalid number: {value}")))
+}
+
+fn exit_with(message: &str) -> ! {
+    println!("{message}");
+    std::process::exit(1);
+}
@@ -0,0 +1,110 @@
+use protochess_engine_rs::{Engine, NotationMode};
+use protochess_engine_rs::searcher::mate_in;
+use protochess_engine_rs::types::Depth;
+use protochess_engine_rs::utils::epd::{parse_epd, EpdEntry};
+
+/// Search limit for each position
+#[derive(Clone, Copy)]
+enum Limit {
+    Depth(Depth),
+    Seconds(u64),
+}
+
+pub fn main() {
+    // Usage: cargo run --bin epd -- <file.epd> <limit>
+    // <limit> is the search depth, or the time in seconds followed by 't'. By default, <limit> is 5t
+    // Example: cargo run --bin epd -- wac.epd 10
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        exit_with_usage(&args[0]);
+    }
+    let contents = std::fs::read_to_string(&args[1]).unwrap_or_else(|e| {
+        println!("Cannot read {}: {e}", args[1]);
+        std::process::exit(1);
+    });
+    let entries = parse_epd(&contents).unwrap_or_else(|e| {
+        println!("Invalid EPD file: {e}");
+        std::process::exit(1);
+    });
+    let limit = match args.get(2) {
+        None => Limit::Seconds(5),
+        Some(arg) => {
+            let parsed = match arg.strip_suffix('t') {
+                Some(seconds) => seconds.parse().map(Limit::Seconds).ok(),
+                None => arg.parse().map(Limit::Depth).ok(),
+            };
+            parsed.unwrap_or_else(|| exit_with_usage(&args[0]))
+        },
+    };
+
+    let mut engine = Engine::default();
+    engine.set_notation_mode(NotationMode::Standard).unwrap();
+
+    let mut solved = Vec::new();
+    let mut failed = Vec::new();
+    let mut total_nodes = 0;
+    let start = instant::Instant::now();
+    for (i, entry) in entries.iter().enumerate() {
+        let name = entry.id.clone().unwrap_or_else(|| format!("#{}", i + 1));
+        let entry_start = instant::Instant::now();
+        match run_entry(&mut engine, entry, limit) {
+            Ok((notation, is_solved)) => {
+                let nodes = engine.get_nodes_searched();
+                total_nodes += nodes;
+                let status = if is_solved { "solved" } else { "FAILED" };
+                println!("{name}: {status} (played {notation}, expected {}) [time: {:?}, nodes: {nodes}]",
+                    expected_str(entry), entry_start.elapsed());
+                if is_solved { solved.push(name); } else { failed.push(name); }
+            },
+            Err(e) => {
+                println!("{name}: FAILED ({e})");
+                failed.push(name);
+            },
+        }
+    }
+    let elapsed = start.elapsed();
+    let nps = total_nodes as f64 / elapsed.as_secs_f64();
+    println!("\n========================================\n");
+    println!("Solved: {}/{}", solved.len(), entries.len());
+    if !failed.is_empty() {
+        println!("Failed: {}", failed.join(", "));
+    }
+    println!("Time: {elapsed:?}, nodes: {total_nodes}, nps: {nps:.0}");
+}
+
+/// Searches the position of the entry, returns the SAN of the best move and whether it solves the position
+fn run_entry(engine: &mut Engine, entry: &EpdEntry, limit: Limit) -> Result<(String, bool), String> {
+    let _result = engine.set_state(entry.game_state())?;
+    let (mv, score) = match limit {
+        Limit::Depth(depth) => engine.get_best_move(depth)?,
+        Limit::Seconds(seconds) => {
+            let (mv, score, _depth) = engine.get_best_move_timeout(seconds)?;
+            (mv, score)
+        },
+    };
+    let result = engine.make_move(&mv);
+    engine.undo()?;
+    let notation = result.move_notation.unwrap_or_else(|| mv.to_string());
+    let is_solved = entry.is_solved(&notation, &mv, mate_in(score));
+    Ok((notation, is_solved))
+}
+
+fn expected_str(entry: &EpdEntry) -> String {
+    let mut parts = Vec::new();
+    if !entry.best_moves.is_empty() {
+        parts.push(format!("bm {}", entry.best_moves.join(" ")));
+    }
+    if !entry.avoid_moves.is_empty() {
+        parts.push(format!("am {}", entry.avoid_moves.join(" ")));
+    }
+    if let Some(n) = entry.mate_in {
+        parts.push(format!("dm {n}"));
+    }
+    parts.join(", ")
+}
+
+fn exit_with_usage(program: &str) -> ! {
+    println!("Usage: {program} <file.epd> [depth | seconds't']");
+    std::process::exit(1);
+}
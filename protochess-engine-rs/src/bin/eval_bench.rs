@@ -0,0 +1,48 @@
+use protochess_engine_rs::GameState;
+use protochess_engine_rs::position::create::position_factory::PositionFactory;
+use protochess_engine_rs::utils::perft::eval_perft;
+
+// Positions with captures, promotions and explosions
+const DEFAULT_POSITIONS: [&str; 4] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC",
+];
+
+pub fn main() {
+    // Usage: cargo run --release --bin eval_bench -- [depth] [fen]
+    // Evaluates all the leaf nodes of the perft tree of each position, and prints the number of nodes per second.
+    // By default, depth 4 is used on 4 positions of standard and atomic chess.
+    // Example: cargo run --release --bin eval_bench -- 5
+
+    let args: Vec<String> = std::env::args().collect();
+    let depth = match args.get(1) {
+        Some(depth) => depth.parse::<u8>().unwrap_or_else(|_| {
+            println!("Usage: {} [depth] [fen]", args[0]);
+            std::process::exit(1);
+        }),
+        None => 4,
+    };
+    let positions: Vec<&str> = match args.get(2) {
+        Some(fen) => vec![fen],
+        None => DEFAULT_POSITIONS.to_vec(),
+    };
+
+    let mut total_nodes = 0;
+    let start = instant::Instant::now();
+    for fen in positions {
+        let mut position = PositionFactory::default().set_state(GameState::from_debug_fen(fen), None)
+            .unwrap_or_else(|e| {
+                println!("Invalid position {fen}: {e}");
+                std::process::exit(1);
+            })
+            .unwrap();
+        let position_start = instant::Instant::now();
+        let (nodes, checksum) = eval_perft(&mut position, depth);
+        println!("{fen}: {nodes} nodes, checksum {checksum} [time: {:?}]", position_start.elapsed());
+        total_nodes += nodes;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    println!("\nTotal: {total_nodes} nodes in {elapsed:.2}s ({:.0} nodes/s)", total_nodes as f64 / elapsed);
+}
@@ -0,0 +1,74 @@
+use std::convert::TryFrom;
+
+use protochess_engine_rs::Network;
+use protochess_engine_rs::nnue::trainer::Trainer;
+use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+use protochess_engine_rs::types::GameMode;
+use protochess_engine_rs::utils::texel::parse_labeled_positions;
+
+// Size of the hidden layer of new networks
+const HIDDEN_SIZE: usize = 64;
+// Weight of the handcrafted evaluation in the training target (the rest is the result of the game)
+const EVAL_WEIGHT: f32 = 0.5;
+const LEARNING_RATE: f32 = 0.05;
+
+pub fn main() {
+    // Usage: cargo run --release --features nnue --bin nnue -- <positions.txt> <network.nnue> <epochs> [variant]
+    // Trains a network with the positions in <positions.txt>, which can be generated with the texel binary
+    // (cargo run --release --bin texel -- generate ...). If <network.nnue> exists, it's used as the starting point.
+    // The network is written to <network.nnue> after each epoch.
+    // [variant] is a variant name (see GameMode), by default standard chess.
+    // Example: cargo run --release --features nnue --bin nnue -- positions.txt atomic.nnue 20 ATOMIC
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 4 {
+        println!("Usage: {} <positions.txt> <network.nnue> <epochs> [variant]", args[0]);
+        std::process::exit(1);
+    }
+    let epochs = args[3].parse::<u32>().unwrap_or_else(|_| {
+        println!("Invalid number of epochs: {}", args[3]);
+        std::process::exit(1);
+    });
+    let variant = match args.get(4) {
+        Some(name) => GameMode::try_from(name.as_str()).unwrap_or_else(|e| {
+            println!("{e}");
+            std::process::exit(1);
+        }),
+        None => GameMode::Standard,
+    };
+    let initial_state = VariantFactory::new(variant).make_initial_state();
+
+    let contents = std::fs::read_to_string(&args[1]).unwrap_or_else(|e| {
+        println!("Cannot read {}: {e}", args[1]);
+        std::process::exit(1);
+    });
+    let positions = parse_labeled_positions(&contents).unwrap_or_else(|e| {
+        println!("Invalid positions file: {e}");
+        std::process::exit(1);
+    });
+    let output = &args[2];
+    let network = match std::fs::read(output) {
+        Ok(bytes) => Network::from_bytes(&bytes).unwrap_or_else(|e| {
+            println!("Cannot load {output}: {e}");
+            std::process::exit(1);
+        }),
+        Err(_) => Network::new(&initial_state, HIDDEN_SIZE, 0),
+    };
+    let mut trainer = Trainer::new(&initial_state, &positions, network, EVAL_WEIGHT, 0).unwrap_or_else(|e| {
+        println!("{e}");
+        std::process::exit(1);
+    });
+    println!("Loaded {} positions. Initial error: {:.6}", trainer.num_positions(), trainer.error());
+
+    let start = instant::Instant::now();
+    for epoch in 1..=epochs {
+        let error = trainer.train_epoch(LEARNING_RATE);
+        println!("Epoch {epoch}: error {error:.6} [time: {:?}]", start.elapsed());
+        // Save the progress after each epoch
+        std::fs::write(output, trainer.get_network().to_bytes()).unwrap_or_else(|e| {
+            println!("Cannot write {output}: {e}");
+            std::process::exit(1);
+        });
+    }
+    println!("Final error: {:.6} (network saved to {output})", trainer.error());
+}
@@ -0,0 +1,103 @@
+use instant::Instant;
+
+use protochess_engine_rs::{Engine, GameState, Position};
+use protochess_engine_rs::position::create::position_factory::PositionFactory;
+use protochess_engine_rs::utils::perft_suite::{find_divide_mismatch, parse_perft_suite, DivideReference};
+
+const USAGE: &str = "Usage: perft <command> [arguments]
+  suite <file> [max_depth]         Checks the expected results of a perft suite file (see utils::perft_suite)
+  divide <depth> <fen>             Prints the number of nodes below each move, in the format of the reference files
+  diff <depth> <reference> <fen>   Finds the first move with a wrong number of nodes, using a file of divide results
+  stats <depth> <fen>              Prints the number of captures, checks, ... at each depth
+The FEN can be followed by the name of the variant (for example, '... w - - 0 1 ATOMIC').";
+
+pub fn main() {
+    // Tools to find bugs in the move generation, for example after adding a new type of piece.
+    // Example: cargo run --release --bin perft -- suite tests/data/perft_suite.epd 5
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        exit_with(USAGE);
+    }
+    match args[1].as_str() {
+        "suite" => run_suite(&args[2], args.get(3).map(|d| parse_depth(d)).unwrap_or(u8::MAX)),
+        "divide" if args.len() >= 4 => {
+            let mut engine = load_engine(&args[3..]);
+            let start = Instant::now();
+            let divide = engine.perft_divide(parse_depth(&args[2]));
+            for (mv, nodes) in &divide {
+                println!("{mv}: {nodes}");
+            }
+            println!("\nNodes searched: {} [time: {:?}]", divide.iter().map(|(_, n)| n).sum::<usize>(), start.elapsed());
+        },
+        "diff" if args.len() >= 5 => {
+            let contents = std::fs::read_to_string(&args[3]).unwrap_or_else(|e| exit_with(&format!("Cannot read {}: {e}", args[3])));
+            let reference = DivideReference::parse(&contents).unwrap_or_else(|e| exit_with(&format!("Invalid reference file: {e}")));
+            let mut position = load_position(&args[4..]);
+            match find_divide_mismatch(&mut position, parse_depth(&args[2]), &reference) {
+                Ok(Some(mismatch)) => exit_with(&mismatch.to_string()),
+                Ok(None) => println!("The results match the reference"),
+                Err(e) => exit_with(&e),
+            }
+        },
+        "stats" if args.len() >= 4 => {
+            let mut engine = load_engine(&args[3..]);
+            for (i, stats) in engine.perft_stats(parse_depth(&args[2])).iter().enumerate() {
+                println!("Depth {}: {stats}", i + 1);
+            }
+        },
+        _ => exit_with(USAGE),
+    }
+}
+
+fn run_suite(path: &str, max_depth: u8) {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|e| exit_with(&format!("Cannot read {path}: {e}")));
+    let entries = parse_perft_suite(&contents).unwrap_or_else(|e| exit_with(&format!("Invalid perft suite: {e}")));
+    let start = Instant::now();
+    let mut failed = 0;
+    let mut total = 0;
+    for entry in entries {
+        let checks = entry.run(&[], max_depth).unwrap_or_else(|e| exit_with(&format!("Error in {}: {e}", entry.fen)));
+        for check in checks {
+            println!("{check}");
+            total += 1;
+            if !check.passed() {
+                failed += 1;
+            }
+        }
+    }
+    println!("\n{} of {total} checks passed [time: {:?}]", total - failed, start.elapsed());
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+// The engine uses the narrowest bitboards that fit the board, so it's faster than `load_position()`
+fn load_engine(fen_args: &[String]) -> Engine {
+    let fen = fen_args.join(" ");
+    let mut engine = Engine::default();
+    if let Err(e) = engine.set_state(GameState::from_debug_fen(&fen)) {
+        exit_with(&format!("Invalid FEN: {e}"));
+    }
+    engine
+}
+
+// The FEN may be split in several arguments
+fn load_position(fen_args: &[String]) -> Position {
+    let fen = fen_args.join(" ");
+    PositionFactory::default().set_state(GameState::from_debug_fen(&fen), None)
+        .unwrap_or_else(|e| exit_with(&format!("Invalid FEN: {e}")))
+        .unwrap_or_else(|| exit_with(&format!("Invalid FEN: {fen}")))
+}
+
+fn parse_depth(value: &str) -> u8 {
+    match value.parse() {
+        Ok(depth) if depth > 0 => depth,
+        _ => exit_with(&format!("Invalid depth: {value}")),
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    println!("{message}");
+    std::process::exit(1);
+}
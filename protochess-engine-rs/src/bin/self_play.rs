@@ -0,0 +1,66 @@
+use protochess_engine_rs::{GameState, SearchParams};
+use protochess_engine_rs::utils::epd::parse_epd;
+use protochess_engine_rs::utils::self_play::{play_match, MatchResult, SearchLimit, DEFAULT_OPENINGS};
+
+pub fn main() {
+    // Usage: cargo run --release --bin self_play -- <limit> <heuristic> [openings.epd]
+    // Plays a match between the default search parameters and the same parameters with <heuristic> disabled.
+    // <limit> is the search depth, or the time in seconds followed by 't'
+    // <heuristic> is one of: all, aspiration, rfp, qfutility, lmp, razoring
+    // Each opening is played twice, once with each color. By default, 8 standard chess openings are used.
+    // Example: cargo run --release --bin self_play -- 6 rfp
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        println!("Usage: {} <depth | seconds't'> <all | aspiration | rfp | qfutility | lmp | razoring> [openings.epd]", args[0]);
+        std::process::exit(1);
+    }
+    let limit = {
+        if args[1].contains('t') {
+            SearchLimit::Time(args[1].replace('t', "").parse::<u64>().unwrap())
+        } else {
+            SearchLimit::Depth(args[1].parse::<u8>().unwrap())
+        }
+    };
+    let mut baseline = SearchParams::default();
+    match args[2].as_str() {
+        "all" => baseline = SearchParams::disabled(),
+        "aspiration" => baseline.aspiration_window = None,
+        "rfp" => baseline.reverse_futility_margin = None,
+        "qfutility" => baseline.qsearch_futility_margin = None,
+        "lmp" => baseline.late_move_pruning = false,
+        "razoring" => baseline.razoring_margin = None,
+        other => {
+            println!("Unknown heuristic: {other}");
+            std::process::exit(1);
+        },
+    }
+    let openings: Vec<GameState> = {
+        if args.len() > 3 {
+            let contents = std::fs::read_to_string(&args[3]).unwrap_or_else(|e| {
+                println!("Cannot read {}: {e}", args[3]);
+                std::process::exit(1);
+            });
+            let entries = parse_epd(&contents).unwrap_or_else(|e| {
+                println!("Invalid EPD file: {e}");
+                std::process::exit(1);
+            });
+            entries.iter().map(|entry| entry.game_state()).collect()
+        } else {
+            DEFAULT_OPENINGS.iter().map(|fen| GameState::from_debug_fen(fen)).collect()
+        }
+    };
+
+    let start = instant::Instant::now();
+    let mut total = MatchResult::default();
+    for (i, opening) in openings.iter().enumerate() {
+        let result = play_match(SearchParams::default(), baseline, std::slice::from_ref(opening), limit, 300).unwrap_or_else(|e| {
+            println!("Error in opening #{}: {e}", i + 1);
+            std::process::exit(1);
+        });
+        total += result;
+        println!("\nOpening #{}: {result}. Total: {total} [time: {:?}]\n", i + 1, start.elapsed());
+    }
+    println!("\n========================================\n");
+    println!("Default vs {} disabled: {total}", args[2]);
+}
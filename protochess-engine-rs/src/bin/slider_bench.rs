@@ -0,0 +1,226 @@
+use std::hint::black_box;
+
+use instant::Instant;
+
+use protochess_engine_rs::MoveGen;
+use protochess_engine_rs::types::{BCoord, BIndex, Bitboard};
+use protochess_engine_rs::utils::{from_index, to_index};
+
+const NUM_POSITIONS: usize = 2000;
+const DEFAULT_ITERATIONS: u32 = 200;
+
+pub fn main() {
+    // Usage: cargo run --release --bin slider_bench -- [iterations]
+    // Times the rook, bishop and queen attacks of AttackTables::get_sliding_moves_bb() on random occupancies of
+    // 8x8 and 16x16 boards, and compares them with the previous implementation (lookups of 16-bit ranks and
+    // square-by-square loops for the files and diagonals).
+
+    let args: Vec<String> = std::env::args().collect();
+    let iterations = match args.get(1) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            println!("Usage: {} [iterations]", args[0]);
+            std::process::exit(1);
+        }),
+        None => DEFAULT_ITERATIONS,
+    };
+    let tables = MoveGen::attack_tables();
+    let previous = PreviousAttackTables::new();
+
+    println!("{:<8} {:<8} {:>12} {:>12} {:>8}", "Board", "Piece", "Rays", "Previous", "Speedup");
+    for size in [8, 16] {
+        let positions = random_positions(size);
+        for (piece, dirs) in [("rook", ROOK), ("bishop", BISHOP), ("queen", QUEEN)] {
+            let (time_rays, sum_rays) = time(&positions, iterations, |index, occ| tables.get_sliding_moves_bb(
+                index, occ, dirs[0], dirs[1], dirs[2], dirs[3], dirs[4], dirs[5], dirs[6], dirs[7]));
+            let (time_previous, sum_previous) = time(&positions, iterations, |index, occ| previous.get_sliding_moves_bb(
+                index, occ, dirs[0], dirs[1], dirs[2], dirs[3], dirs[4], dirs[5], dirs[6], dirs[7]));
+            assert_eq!(sum_rays, sum_previous, "Different attacks for the {piece} on {size}x{size}");
+            println!("{:<8} {piece:<8} {:>10.1}ms {:>10.1}ms {:>7.2}x", format!("{size}x{size}"),
+                time_rays * 1000.0, time_previous * 1000.0, time_previous / time_rays);
+        }
+    }
+}
+
+// Directions: north, east, south, west, northeast, northwest, southeast, southwest
+const ROOK: [bool; 8] = [true, true, true, true, false, false, false, false];
+const BISHOP: [bool; 8] = [false, false, false, false, true, true, true, true];
+const QUEEN: [bool; 8] = [true; 8];
+
+// Returns the total time and the number of attacked squares in the last iteration (to compare the results)
+fn time(positions: &[(BIndex, Bitboard)], iterations: u32, attacks: impl Fn(BIndex, &Bitboard) -> Bitboard) -> (f64, u32) {
+    let start = Instant::now();
+    let mut count = 0;
+    for _ in 0..iterations {
+        count = 0;
+        for (index, occ) in black_box(positions) {
+            count += attacks(*index, occ).count_ones();
+        }
+    }
+    (start.elapsed().as_secs_f64(), count)
+}
+
+// Random squares of a size x size board, with about 1/4 of the squares occupied. Like `Position::occ_or_out_bounds`,
+// the squares outside the board are also occupied.
+fn random_positions(size: BCoord) -> Vec<(BIndex, Bitboard)> {
+    let mut seed = 0x9E37_79B9_7F4A_7C15_u64;
+    let mut next = move || {
+        // Xorshift
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    (0..NUM_POSITIONS).map(|_| {
+        let mut occ = Bitboard::zero();
+        for index in 0..=255 {
+            let (x, y) = from_index(index);
+            if x >= size || y >= size || next() % 4 == 0 {
+                occ.set_bit(index);
+            }
+        }
+        let index = to_index((next() % size as u64) as BCoord, (next() % size as u64) as BCoord);
+        (index, occ)
+    }).collect()
+}
+
+/// The implementation of `AttackTables` before the ray tables, used as a reference
+struct PreviousAttackTables {
+    // Precomputed slider moves for a given 16-bit row and index
+    rank_slides: Vec<Vec<u16>>,
+    // Precomputed masks for bits at the left and right of a given square
+    left_masks: Vec<Bitboard>,
+    right_masks: Vec<Bitboard>,
+    // L-shaped masks for edges of the board
+    top_right: Bitboard,
+    bottom_left: Bitboard,
+    top_left: Bitboard,
+    bottom_right: Bitboard,
+    // Masks for edges of the board
+    horizontal_edges: Bitboard,
+    vertical_edges: Bitboard,
+}
+
+impl PreviousAttackTables {
+    fn new() -> PreviousAttackTables {
+        //16 * 2^16 possible states; 16 squares in 1 rank, 2^16 possible occupancies per rank
+        let mut slider_attacks = vec![vec![0; 65536]; 16];
+        let mut top_bits = Bitboard::zero();
+        let mut bottom_bits = Bitboard::zero();
+        let mut left_bits = Bitboard::zero();
+        let mut right_bits = Bitboard::zero();
+        let mut left_masks = vec![Bitboard::zero(); 256];
+        let mut right_masks = vec![Bitboard::zero(); 256];
+        for i in 0..16 {
+            for occ in 0..=65535u16 {
+                fn get_left_attack(src: u16) -> u16 {
+                    if src == 0 { 0 } else { src - 1 }
+                }
+                fn get_right_attack(src: u16) -> u16 {
+                    !src & !get_left_attack(src)
+                }
+                let sq = 1u16 << i;
+                let mut left_attack = get_left_attack(sq);
+                let left_blockers = occ & left_attack;
+                if left_blockers != 0 {
+                    let msb_blockers = 1u16 << (15 - left_blockers.leading_zeros());
+                    left_attack ^= get_left_attack(msb_blockers);
+                }
+                let mut right_attack = get_right_attack(sq);
+                let right_blockers = occ & right_attack;
+                if right_blockers != 0 {
+                    let lsb_blockers = 1u16 << right_blockers.trailing_zeros();
+                    right_attack ^= get_right_attack(lsb_blockers);
+                }
+                slider_attacks[i as usize][occ as usize] = right_attack ^ left_attack;
+            }
+            top_bits.set_bit_at(i, 15);
+            bottom_bits.set_bit_at(i, 0);
+            left_bits.set_bit_at(0, i);
+            right_bits.set_bit_at(15, i);
+        }
+        for x in 0..16 {
+            for y in 0..16 {
+                let index = to_index(x, y) as usize;
+                for j in 0..x {
+                    left_masks[index].set_bit_at(j, y);
+                }
+                for j in (x + 1)..16 {
+                    right_masks[index].set_bit_at(j, y);
+                }
+            }
+        }
+        PreviousAttackTables {
+            rank_slides: slider_attacks,
+            left_masks,
+            right_masks,
+            top_right: &top_bits | &right_bits,
+            bottom_left: &bottom_bits | &left_bits,
+            top_left: &top_bits | &left_bits,
+            bottom_right: &bottom_bits | &right_bits,
+            horizontal_edges: &left_bits | &right_bits,
+            vertical_edges: &top_bits | &bottom_bits,
+        }
+    }
+
+    fn get_rank_slide(&self, loc_index: BIndex, occ: &Bitboard) -> Bitboard {
+        let (x, y) = from_index(loc_index);
+        let word_index = y / 4;
+        let word = occ.get_inner()[word_index as usize];
+        let line_index = y % 4;
+        let occ_index = (word >> (line_index * 16)) as u16;
+        let attack = self.rank_slides[x as usize][occ_index as usize];
+        let mut return_bb = Bitboard::zero();
+        return_bb.get_inner_mut()[word_index as usize] = (attack as u64) << (line_index * 16);
+        return_bb
+    }
+
+    fn add_slide(out: &mut Bitboard, start_index: BIndex, occ: &Bitboard, step: i16) {
+        let mut index = start_index as i16 + step;
+        loop {
+            out.set_bit(index as BIndex);
+            if occ.get_bit(index as BIndex) {
+                break;
+            }
+            index += step;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn get_sliding_moves_bb(&self, index: BIndex, occ: &Bitboard, north: bool, east: bool, south: bool, west: bool,
+                            northeast: bool, northwest: bool, southeast: bool, southwest: bool) -> Bitboard {
+        let mut moves = {
+            if east || west {
+                let mut m = self.get_rank_slide(index, occ);
+                if !east {
+                    m &= &self.left_masks[index as usize];
+                } else if !west {
+                    m &= &self.right_masks[index as usize];
+                }
+                m
+            } else {
+                Bitboard::zero()
+            }
+        };
+        let mut occ = occ | &self.vertical_edges;
+        if north && index < 240 {
+            Self::add_slide(&mut moves, index, &occ, 16);
+        }
+        if south && index > 15 {
+            Self::add_slide(&mut moves, index, &occ, -16);
+        }
+        occ |= &self.horizontal_edges;
+        if northeast && !self.top_right.get_bit(index) {
+            Self::add_slide(&mut moves, index, &occ, 17);
+        }
+        if southwest && !self.bottom_left.get_bit(index) {
+            Self::add_slide(&mut moves, index, &occ, -17);
+        }
+        if northwest && !self.top_left.get_bit(index) {
+            Self::add_slide(&mut moves, index, &occ, 15);
+        }
+        if southeast && !self.bottom_right.get_bit(index) {
+            Self::add_slide(&mut moves, index, &occ, -15);
+        }
+        moves
+    }
+}
@@ -0,0 +1,46 @@
+use std::convert::TryFrom;
+
+use instant::Instant;
+
+use protochess_engine_rs::Tablebase;
+use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+use protochess_engine_rs::types::GameMode;
+
+pub fn main() {
+    // Usage: cargo run --release --bin tablebase -- <output.tb> <variant> <max_pieces | material>...
+    // <variant> is one of the GameMode names (standard, atomic, kingofthehill...)
+    // Each material is a list of piece ids, for example KRk. All the reachable tables are generated too.
+    // Example: cargo run --release --bin tablebase -- kqk.tb standard KQk KRk
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 4 {
+        println!("Usage: {} <output.tb> <variant> <max_pieces | material>...", args[0]);
+        std::process::exit(1);
+    }
+    let mode = GameMode::try_from(args[2].as_str()).unwrap_or_else(|e| {
+        println!("{e}");
+        std::process::exit(1);
+    });
+    let initial_state = VariantFactory::new(mode).make_initial_state();
+
+    let start = Instant::now();
+    let result = match args[3].parse::<u32>() {
+        Ok(max_pieces) => Tablebase::generate_up_to(&initial_state, max_pieces),
+        Err(_) => {
+            let materials: Vec<&str> = args[3..].iter().map(|m| m.as_str()).collect();
+            Tablebase::generate(&initial_state, &materials)
+        },
+    };
+    let tablebase = result.unwrap_or_else(|e| {
+        println!("Cannot generate the tablebase: {e}");
+        std::process::exit(1);
+    });
+    let bytes = tablebase.to_bytes();
+    std::fs::write(&args[1], &bytes).unwrap_or_else(|e| {
+        println!("Cannot write {}: {e}", args[1]);
+        std::process::exit(1);
+    });
+    println!("Generated {} tables in {:.1}s: {}", tablebase.get_materials().len(),
+        start.elapsed().as_secs_f64(), tablebase.get_materials().join(" "));
+    println!("Wrote {} bytes to {}", bytes.len(), args[1]);
+}
@@ -0,0 +1,106 @@
+use std::convert::TryFrom;
+
+use protochess_engine_rs::{EvalParams, GameState};
+use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+use protochess_engine_rs::types::GameMode;
+use protochess_engine_rs::utils::self_play::SearchLimit;
+use protochess_engine_rs::utils::texel::{generate_positions, parse_labeled_positions, Tuner};
+
+// Number of random moves played at the start of each game
+const RANDOM_PLIES: u32 = 8;
+// Games that are not over after this number of plies are considered a draw
+const MAX_PLY: u32 = 300;
+// Maximum number of passes over all the parameters
+const MAX_ITERATIONS: u32 = 100;
+
+pub fn main() {
+    // Usage:
+    //   cargo run --release --bin texel -- generate <num_games> <limit> <positions.txt> [variant]
+    //   cargo run --release --bin texel -- tune <positions.txt> <params.txt> [variant]
+    // generate: plays <num_games> games of the engine against itself, and writes the quiet positions to <positions.txt>
+    //   along with the result of each game. <limit> is the search depth, or the time in seconds followed by 't'.
+    // tune: optimizes the evaluation parameters for the positions in <positions.txt>. If <params.txt> exists,
+    //   it's used as the starting point. The tuned parameters are written to <params.txt>.
+    // [variant] is a variant name (see GameMode), by default standard chess.
+    // Example: cargo run --release --bin texel -- generate 1000 4 positions.txt ATOMIC
+
+    let args: Vec<String> = std::env::args().collect();
+    let usage = || {
+        println!("Usage:");
+        println!("  {} generate <num_games> <depth | seconds't'> <positions.txt> [variant]", args[0]);
+        println!("  {} tune <positions.txt> <params.txt> [variant]", args[0]);
+        std::process::exit(1);
+    };
+    let variant_arg = match args.get(1).map(|s| s.as_str()) {
+        Some("generate") if args.len() >= 5 => args.get(5),
+        Some("tune") if args.len() >= 4 => args.get(4),
+        _ => usage(),
+    };
+    let variant = match variant_arg {
+        Some(name) => GameMode::try_from(name.as_str()).unwrap_or_else(|e| {
+            println!("{e}");
+            std::process::exit(1);
+        }),
+        None => GameMode::Standard,
+    };
+    let initial_state = VariantFactory::new(variant).make_initial_state();
+
+    if args[1] == "generate" {
+        let num_games = args[2].parse::<u32>().unwrap();
+        let limit = {
+            if args[3].contains('t') {
+                SearchLimit::Time(args[3].replace('t', "").parse::<u64>().unwrap())
+            } else {
+                SearchLimit::Depth(args[3].parse::<u8>().unwrap())
+            }
+        };
+        let opening = GameState { initial_state, initial_fen: None, move_history: vec![] };
+        let start = instant::Instant::now();
+        let positions = generate_positions(&opening, num_games, RANDOM_PLIES, limit, MAX_PLY, 0).unwrap_or_else(|e| {
+            println!("Error while generating the positions: {e}");
+            std::process::exit(1);
+        });
+        let contents: String = positions.iter().map(|p| format!("{p}\n")).collect();
+        std::fs::write(&args[4], contents).unwrap_or_else(|e| {
+            println!("Cannot write {}: {e}", args[4]);
+            std::process::exit(1);
+        });
+        println!("Wrote {} positions from {num_games} games to {} [time: {:?}]", positions.len(), args[4], start.elapsed());
+        return;
+    }
+
+    let contents = std::fs::read_to_string(&args[2]).unwrap_or_else(|e| {
+        println!("Cannot read {}: {e}", args[2]);
+        std::process::exit(1);
+    });
+    let positions = parse_labeled_positions(&contents).unwrap_or_else(|e| {
+        println!("Invalid positions file: {e}");
+        std::process::exit(1);
+    });
+    let initial_params = match std::fs::read_to_string(&args[3]) {
+        Ok(contents) => EvalParams::parse(&contents).unwrap_or_else(|e| {
+            println!("Invalid parameters file: {e}");
+            std::process::exit(1);
+        }),
+        Err(_) => EvalParams::default(),
+    };
+    let mut tuner = Tuner::new(&initial_state, &positions).unwrap_or_else(|e| {
+        println!("{e}");
+        std::process::exit(1);
+    });
+    let k = tuner.find_k(&initial_params).unwrap();
+    let initial_error = tuner.error(&initial_params).unwrap();
+    println!("Loaded {} positions. K = {k:.4}, initial error: {initial_error:.6}", tuner.num_positions());
+
+    let start = instant::Instant::now();
+    let output = &args[3];
+    let tuned = tuner.tune(&initial_params, MAX_ITERATIONS, |iteration, params, error| {
+        println!("Iteration {iteration}: error {error:.6} [time: {:?}]", start.elapsed());
+        // Save the progress after each iteration
+        std::fs::write(output, params.to_string()).unwrap_or_else(|e| {
+            println!("Cannot write {output}: {e}");
+            std::process::exit(1);
+        });
+    }).unwrap();
+    println!("\nTuned parameters (saved to {output}):\n{tuned}");
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::position::create::position_factory::PositionFactory;
+use crate::position::create::variant_factory::VariantFactory;
+use crate::types::{GameMode, Move};
+use crate::utils::notation::{get_algebraic_notation, normalize_san, NotationMode};
+use crate::utils::pgn::parse_pgn;
+use crate::{GameState, InitialState, MoveGen, MoveInfo, Position, wrap_res, err};
+
+use super::{BookEntry, BookFormat, OpeningBook};
+
+/// Builds an `OpeningBook` from a collection of games.
+/// The weight of each move is the number of games in which it was played.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    format: BookFormat,
+    max_ply: usize,
+    /// Creates the positions of the games, with the zobrist keys of the engine that will read the book
+    factory: PositionFactory,
+    counts: HashMap<(u64, MoveInfo), u32>,
+}
+
+impl BookBuilder {
+    /// Creates an empty builder. Only the first `max_ply` moves of each game are added to the book.
+    /// Books in the `Protochess` format are keyed on the zobrist keys, so `zobrist_seed` must be the seed of the
+    /// engine that will read the book (see `Engine::get_zobrist_seed()`, 0 by default). Polyglot books ignore it.
+    pub fn new(format: BookFormat, max_ply: usize, zobrist_seed: u64) -> Self {
+        let mut factory = PositionFactory::default();
+        factory.set_zobrist_seed(zobrist_seed);
+        BookBuilder { format, max_ply, factory, counts: HashMap::new() }
+    }
+
+    /// Adds a game to the book. The moves must be in SAN (see `NotationMode::Standard`) or in the format
+    /// used by `MoveInfo` (`e2e4`).
+    pub fn add_game(&mut self, state: &GameState, moves: &[String]) -> wrap_res!() {
+        let mut pos = match self.factory.set_state(state.clone(), None)? {
+            Some(pos) => pos,
+            None => err!("Could not create the starting position"),
+        };
+        for san in moves.iter().take(self.max_ply) {
+            let mv = find_move(&mut pos, san)?;
+            let key = match super::position_key(self.format, &pos) {
+                Some(key) => key,
+                None => err!("This position cannot be stored in a {:?} book", self.format),
+            };
+            *self.counts.entry((key, MoveInfo::from(mv))).or_insert(0) += 1;
+            pos.make_move(mv);
+        }
+        Ok(())
+    }
+
+    /// Adds all the games of a PGN file to the book. Returns the number of games added.
+    /// The variant of each game is read from the `Variant` tag (see `GameMode`), or `default_variant` if
+    /// the tag is missing. The starting position is read from the `FEN` tag, if present.
+    pub fn add_pgn(&mut self, pgn: &str, default_variant: &InitialState) -> wrap_res!(usize) {
+        let games = parse_pgn(pgn)?;
+        for (i, game) in games.iter().enumerate() {
+            let initial_state = match game.get_tag("Variant") {
+                Some(variant) => VariantFactory::new(variant_from_tag(variant)?).make_initial_state(),
+                None => default_variant.clone(),
+            };
+            let initial_fen = game.get_tag("FEN").map(|fen| fen.to_string());
+            let state = GameState { initial_state, initial_fen, move_history: vec![] };
+            if let Err(e) = self.add_game(&state, &game.moves) {
+                err!("Game {}: {e}", i + 1);
+            }
+        }
+        Ok(games.len())
+    }
+
+    /// Creates the book with all the games added so far
+    pub fn build(&self) -> OpeningBook {
+        let max_count = self.counts.values().copied().max().unwrap_or(0);
+        let mut entries: Vec<BookEntry> = self.counts.iter()
+            .map(|((key, mv), count)| {
+                // Scale the weights if they don't fit in 16 bits
+                let weight = {
+                    if max_count <= u16::MAX as u32 { *count }
+                    else { std::cmp::max(1, (*count as u64 * u16::MAX as u64 / max_count as u64) as u32) }
+                };
+                BookEntry { key: *key, mv: *mv, weight: weight as u16 }
+            })
+            .collect();
+        // Most played moves first, sort the rest so that the output is deterministic
+        entries.sort_by_key(|e| (e.key, std::cmp::Reverse(e.weight), e.mv.from, e.mv.to, e.mv.promotion));
+        OpeningBook::new(self.format, entries)
+    }
+}
+
+/// Finds the legal move that matches the given SAN
+fn find_move(pos: &mut Position, san: &str) -> wrap_res!(Move) {
+    let legal_moves = MoveGen::get_legal_moves(pos);
+    let target = normalize_san(san);
+    for mv in &legal_moves {
+        if normalize_san(&get_algebraic_notation(pos, *mv, &legal_moves, NotationMode::Standard)) == target {
+            return Ok(*mv);
+        }
+    }
+    // Also accept the format used by MoveInfo
+    if let Ok(mv_info) = MoveInfo::try_from(san) {
+        if let Some(mv) = legal_moves.iter().find(|mv| mv_info == **mv) {
+            return Ok(*mv);
+        }
+    }
+    err!("Invalid move: '{san}'")
+}
+
+/// Converts the `Variant` tag of a PGN file (for example `"King of the Hill"` or `"Three-check"`) to a `GameMode`
+fn variant_from_tag(variant: &str) -> wrap_res!(GameMode) {
+    let name: String = variant.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+    match name.to_lowercase().as_str() {
+        "threecheck" => Ok(GameMode::ThreeCheck),
+        "fivecheck" => Ok(GameMode::FiveCheck),
+        name => GameMode::try_from(name),
+    }
+}
@@ -0,0 +1,201 @@
+// This directory contains the opening books: Polyglot books for standard chess, and books keyed on
+// the engine's own zobrist key for custom variants.
+
+use std::convert::{TryFrom, TryInto};
+
+use rand::Rng;
+
+use crate::{MoveGen, MoveInfo, Position, wrap_res, err_assert, err};
+use crate::utils::{to_index, from_index};
+
+mod polyglot_random;
+pub mod polyglot;
+pub mod builder;
+
+/// Each entry of a book file takes 16 bytes
+const ENTRY_SIZE: usize = 16;
+
+/// Format of an opening book file. Both formats store a list of 16-byte entries, sorted by key.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookFormat {
+    /// Polyglot `.bin` format, only for standard chess.
+    /// Each entry contains the Polyglot key, the move, the weight and a learn value (ignored).
+    Polyglot,
+    /// Format for any variant, keyed on `Position::get_zobrist()`.
+    /// Each entry contains the key, the origin and target squares (`BIndex`), the weight and the promotion piece (as `u32`).
+    Protochess,
+}
+
+impl TryFrom<&str> for BookFormat {
+    type Error = String;
+    fn try_from(value: &str) -> wrap_res!(Self) {
+        match value.to_lowercase().as_str() {
+            "polyglot" => Ok(BookFormat::Polyglot),
+            "protochess" | "custom" => Ok(BookFormat::Protochess),
+            _ => err!("Invalid book format: '{value}'"),
+        }
+    }
+}
+
+/// Returns the key of the position in the given format, or `None` if the position cannot be stored
+fn position_key<const W: usize>(format: BookFormat, pos: &Position<W>) -> Option<u64> {
+    match format {
+        BookFormat::Polyglot => polyglot::polyglot_key(pos),
+        BookFormat::Protochess => Some(pos.get_zobrist()),
+    }
+}
+
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: MoveInfo,
+    pub weight: u16,
+}
+
+/// List of moves that can be played in each position, along with their weights
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct OpeningBook {
+    format: BookFormat,
+    // Sorted by key
+    entries: Vec<BookEntry>,
+}
+
+impl OpeningBook {
+    /// Creates a book from a list of entries, in any order
+    pub fn new(format: BookFormat, mut entries: Vec<BookEntry>) -> Self {
+        // Stable sort, keep the order of the moves of each position
+        entries.sort_by_key(|e| e.key);
+        OpeningBook { format, entries }
+    }
+
+    /// Loads a book from the contents of a book file
+    pub fn from_bytes(bytes: &[u8], format: BookFormat) -> wrap_res!(Self) {
+        err_assert!(bytes.len().is_multiple_of(ENTRY_SIZE), "Invalid book file: the size must be a multiple of {ENTRY_SIZE} bytes");
+        let mut entries = Vec::with_capacity(bytes.len() / ENTRY_SIZE);
+        for chunk in bytes.chunks_exact(ENTRY_SIZE) {
+            let key = u64::from_be_bytes(chunk[0..8].try_into().unwrap());
+            let entry = match format {
+                BookFormat::Polyglot => {
+                    let mv = polyglot::decode_move(u16::from_be_bytes([chunk[8], chunk[9]]));
+                    let weight = u16::from_be_bytes([chunk[10], chunk[11]]);
+                    BookEntry { key, mv, weight }
+                },
+                BookFormat::Protochess => {
+                    let weight = u16::from_be_bytes([chunk[10], chunk[11]]);
+                    let promotion = match u32::from_be_bytes(chunk[12..16].try_into().unwrap()) {
+                        0 => None,
+                        p => match char::from_u32(p) {
+                            Some(c) => Some(c),
+                            None => err!("Invalid book file: invalid promotion piece ({p})"),
+                        }
+                    };
+                    let mv = MoveInfo { from: from_index(chunk[8]), to: from_index(chunk[9]), promotion };
+                    BookEntry { key, mv, weight }
+                },
+            };
+            entries.push(entry);
+        }
+        Ok(OpeningBook::new(format, entries))
+    }
+
+    /// Returns the contents of the book file. Fails if a move cannot be represented in the format of the book.
+    pub fn to_bytes(&self) -> wrap_res!(Vec<u8>) {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            match self.format {
+                BookFormat::Polyglot => {
+                    let mv = match polyglot::encode_move(&entry.mv) {
+                        Some(mv) => mv,
+                        None => err!("The move {} cannot be stored in a Polyglot book", entry.mv),
+                    };
+                    bytes.extend_from_slice(&mv.to_be_bytes());
+                    bytes.extend_from_slice(&entry.weight.to_be_bytes());
+                    // Learn value
+                    bytes.extend_from_slice(&0u32.to_be_bytes());
+                },
+                BookFormat::Protochess => {
+                    bytes.push(to_index(entry.mv.from.0, entry.mv.from.1));
+                    bytes.push(to_index(entry.mv.to.0, entry.mv.to.1));
+                    bytes.extend_from_slice(&entry.weight.to_be_bytes());
+                    let promotion = entry.mv.promotion.map_or(0, |c| c as u32);
+                    bytes.extend_from_slice(&promotion.to_be_bytes());
+                },
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub fn get_format(&self) -> BookFormat {
+        self.format
+    }
+
+    /// Returns the number of entries in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the key of the position in the format of this book, or `None` if the position cannot
+    /// be stored in this book
+    pub fn key<const W: usize>(&self, pos: &Position<W>) -> Option<u64> {
+        position_key(self.format, pos)
+    }
+
+    /// Returns all the entries for the given position (the moves are not checked for legality)
+    pub fn get_moves<const W: usize>(&self, pos: &Position<W>) -> Vec<BookEntry> {
+        let key = match self.key(pos) {
+            Some(key) => key,
+            None => return Vec::new(),
+        };
+        let start = self.entries.partition_point(|e| e.key < key);
+        let mut result: Vec<BookEntry> = self.entries[start..].iter()
+            .take_while(|e| e.key == key)
+            .copied()
+            .collect();
+        if self.format == BookFormat::Polyglot {
+            // Polyglot doesn't store the color of the promotion piece
+            for entry in &mut result {
+                if pos.whos_turn == 0 {
+                    entry.mv.promotion = entry.mv.promotion.map(|c| c.to_ascii_uppercase());
+                }
+            }
+        }
+        result
+    }
+
+    /// Picks a random legal move for the given position, with a probability proportional to its weight.
+    /// If all the moves have weight 0, they are picked uniformly.
+    /// Returns `None` if the position is not in the book.
+    pub fn pick_move<R: Rng, const W: usize>(&self, pos: &mut Position<W>, rng: &mut R) -> Option<MoveInfo> {
+        let entries = self.get_moves(pos);
+        if entries.is_empty() {
+            return None;
+        }
+        let legal_moves = MoveGen::get_legal_moves(pos);
+        let entries: Vec<BookEntry> = entries.into_iter()
+            .filter(|e| legal_moves.iter().any(|mv| e.mv == *mv))
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        let total_weight: u32 = entries.iter().map(|e| e.weight as u32).sum();
+        if total_weight == 0 {
+            return Some(entries[rng.gen_range(0, entries.len())].mv);
+        }
+        let mut target = rng.gen_range(0, total_weight);
+        for entry in &entries {
+            if target < entry.weight as u32 {
+                return Some(entry.mv);
+            }
+            target -= entry.weight as u32;
+        }
+        unreachable!("The target weight is always lower than the total weight");
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::OnceLock;
+
+use crate::{MoveInfo, Position, PieceId, PieceDefinition, GlobalRules};
+use crate::piece::PieceFactory;
+use crate::types::{BCoord, GameMode};
+use crate::utils::{to_index, from_index};
+
+use super::polyglot_random::POLYGLOT_RANDOM;
+
+// Order of the pieces in the Polyglot random table
+const PIECE_KINDS: [PieceId; 12] = ['p', 'P', 'n', 'N', 'b', 'B', 'r', 'R', 'q', 'Q', 'k', 'K'];
+// Promotion pieces, encoded in bits 12-14 of a Polyglot move
+const PROMOTION_PIECES: [PieceId; 4] = ['n', 'b', 'r', 'q'];
+
+/// Computes the Polyglot key of a position. This key is different from `Position::get_zobrist()`.
+/// Returns `None` if the position is not a standard chess position (8x8 board with the standard pieces and rules).
+/// Variants like atomic or antichess use the same piece ids, but the book moves of standard chess don't apply to them.
+pub fn polyglot_key<const W: usize>(pos: &Position<W>) -> Option<u64> {
+    if pos.dimensions.width != 8 || pos.dimensions.height != 8 || !is_standard_chess(pos) {
+        return None;
+    }
+    let mut key = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            if let Some(piece) = pos.piece_at(to_index(x, y)) {
+                let kind = PIECE_KINDS.iter().position(|id| *id == piece.get_piece_id())?;
+                key ^= POLYGLOT_RANDOM[64 * kind + 8 * y as usize + x as usize];
+            }
+        }
+    }
+
+    // Castling rights: the king and the rook must not have moved
+    let unmoved = |id: PieceId, x: BCoord, y: BCoord| {
+        let index = to_index(x, y);
+        match pos.piece_at(index) {
+            Some(piece) => piece.get_piece_id() == id && piece.has_not_moved(index),
+            None => false,
+        }
+    };
+    let castling = [
+        unmoved('K', 4, 0) && unmoved('R', 7, 0),
+        unmoved('K', 4, 0) && unmoved('R', 0, 0),
+        unmoved('k', 4, 7) && unmoved('r', 7, 7),
+        unmoved('k', 4, 7) && unmoved('r', 0, 7),
+    ];
+    for (i, can_castle) in castling.iter().enumerate() {
+        if *can_castle {
+            key ^= POLYGLOT_RANDOM[768 + i];
+        }
+    }
+
+    // En passant: only if a pawn of the player to move is next to the pawn that just moved
+    if let Some(ep_square) = pos.get_ep_square() {
+        let (victim_x, victim_y) = from_index(pos.get_ep_victim());
+        let pawn_id = if pos.whos_turn == 0 { 'P' } else { 'p' };
+        let has_pawn = |x: BCoord| {
+            match pos.piece_at(to_index(x, victim_y)) {
+                Some(piece) => piece.get_piece_id() == pawn_id,
+                None => false,
+            }
+        };
+        let left = victim_x > 0 && has_pawn(victim_x - 1);
+        let right = victim_x < 7 && has_pawn(victim_x + 1);
+        if left || right {
+            key ^= POLYGLOT_RANDOM[772 + from_index(ep_square).0 as usize];
+        }
+    }
+
+    if pos.whos_turn == 0 {
+        key ^= POLYGLOT_RANDOM[780];
+    }
+    Some(key)
+}
+
+/// Returns `true` if the position uses the rules and the piece definitions of standard chess
+fn is_standard_chess<const W: usize>(pos: &Position<W>) -> bool {
+    static STANDARD_PIECES: OnceLock<Vec<PieceDefinition>> = OnceLock::new();
+    if pos.global_rules != GlobalRules::for_mode(GameMode::Standard) {
+        return false;
+    }
+    let standard = STANDARD_PIECES.get_or_init(|| PieceFactory::new(GameMode::Standard).make_piece_set(8, 8));
+    pos.pieces.iter().flat_map(|piece_set| piece_set.iter()).all(|piece| standard.contains(piece.get_movement()))
+}
+
+/// Converts a move to the Polyglot format. Castling is encoded as the king capturing the rook, like in `MoveInfo`.
+/// Returns `None` if the move cannot be represented.
+pub fn encode_move(mv: &MoveInfo) -> Option<u16> {
+    let ((from_x, from_y), (to_x, to_y)) = (mv.from, mv.to);
+    if from_x >= 8 || from_y >= 8 || to_x >= 8 || to_y >= 8 {
+        return None;
+    }
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(id) => PROMOTION_PIECES.iter().position(|p| *p == id.to_ascii_lowercase())? as u16 + 1,
+    };
+    Some(promotion << 12 | (from_y as u16) << 9 | (from_x as u16) << 6 | (to_y as u16) << 3 | to_x as u16)
+}
+
+/// Converts a move from the Polyglot format. The promotion piece is always lowercase, `OpeningBook::get_moves()`
+/// converts it to the piece of the player to move.
+pub fn decode_move(raw: u16) -> MoveInfo {
+    let to_x = (raw & 7) as BCoord;
+    let to_y = (raw >> 3 & 7) as BCoord;
+    let from_x = (raw >> 6 & 7) as BCoord;
+    let from_y = (raw >> 9 & 7) as BCoord;
+    let promotion = match raw >> 12 & 7 {
+        p @ 1..=4 => Some(PROMOTION_PIECES[p as usize - 1]),
+        _ => None,
+    };
+    MoveInfo { from: (from_x, from_y), to: (to_x, to_y), promotion }
+}
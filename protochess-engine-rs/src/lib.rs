@@ -1,6 +1,5 @@
 #[macro_use]
 extern crate lazy_static;
-extern crate impl_ops;
 
 pub mod piece;
 pub mod move_generator;
@@ -8,28 +7,104 @@ pub mod types;
 pub mod position;
 pub mod searcher;
 pub mod utils;
+pub mod book;
+pub mod tablebase;
+#[cfg(feature = "nnue")]
+pub mod nnue;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
+use std::sync::Arc;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 
 use position::create::position_factory::PositionFactory;
-use types::{BCoord, Centipawns, Depth, Player, ZobKey};
+use position::create::fen::FenData;
+use types::{BCoord, Centipawns, Depth, Player, ZobKey, words_for_height};
 use searcher::Searcher;
 use utils::{to_index, from_index};
+use utils::perft::PerftStats;
 
 pub use position::Position;
 pub use position::create::game_state::*;
+pub use position::create::fen_dialect::FenDialect;
 pub use position::global_rules::GlobalRules;
 pub use move_generator::MoveGen;
+pub use utils::notation::NotationMode;
 pub use piece::{Piece, PieceId, PieceDefinition};
+pub use book::{OpeningBook, BookFormat};
+pub use tablebase::{Tablebase, Tablebases, ProbeResult};
+#[cfg(feature = "syzygy")]
+pub use tablebase::syzygy::{Syzygy, Wdl};
+#[cfg(feature = "nnue")]
+pub use nnue::Network;
+pub use searcher::{SearchParams, EvalParams, Skill};
 pub use types::{MoveInfo, MoveList, MakeMoveResult, MakeMoveResultFlag, MakeMoveResultWinner};
+pub use types::{EvalBreakdown, SideEvalBreakdown, PieceMaterial, PiecePositional};
+
+// The position of the engine, with bitboards of the smallest width that fits the board (see `Bitboard`)
+#[derive(Debug, Clone)]
+enum SizedPosition {
+    W1(Position<1>),
+    W2(Position<2>),
+    W4(Position<4>),
+}
+
+// Evaluates `$body` with `$pos` bound to the position inside a `SizedPosition`, whatever its width
+macro_rules! with_position {
+    ($position:expr, $pos:ident => $body:expr) => {
+        match $position {
+            SizedPosition::W1($pos) => $body,
+            SizedPosition::W2($pos) => $body,
+            SizedPosition::W4($pos) => $body,
+        }
+    };
+}
+
+impl SizedPosition {
+    // Creates the position of `state`, or updates `self` if it has the right width (see `PositionFactory::set_state()`)
+    fn set_state(&mut self, factory: &mut PositionFactory, state: GameState, reuse: bool) -> wrap_res!() {
+        let words = words_for_height(state.initial_state.board_height);
+        match (self, words) {
+            (SizedPosition::W1(pos), 1) => Self::update(pos, factory, state, reuse)?,
+            (SizedPosition::W2(pos), 2) => Self::update(pos, factory, state, reuse)?,
+            (SizedPosition::W4(pos), 4) => Self::update(pos, factory, state, reuse)?,
+            (this, _) => *this = Self::new(factory, state)?,
+        }
+        Ok(())
+    }
+    // Creates the position of `state` from scratch
+    fn new(factory: &mut PositionFactory, state: GameState) -> wrap_res!(Self) {
+        let words = words_for_height(state.initial_state.board_height);
+        Ok(match words {
+            1 => SizedPosition::W1(factory.set_state_sized(state, None)?.unwrap()),
+            2 => SizedPosition::W2(factory.set_state_sized(state, None)?.unwrap()),
+            _ => SizedPosition::W4(factory.set_state_sized(state, None)?.unwrap()),
+        })
+    }
+    fn update<const W: usize>(pos: &mut Position<W>, factory: &mut PositionFactory, state: GameState, reuse: bool) -> wrap_res!() {
+        let reused_position = if reuse { Some(&mut *pos) } else { None };
+        if let Some(new_pos) = factory.set_state_sized(state, reused_position)? {
+            *pos = new_pos;
+        }
+        Ok(())
+    }
+}
 
 /// Starting point for the engine
 #[derive(Debug, Clone)]
 pub struct Engine{
-    position: Position,
+    position: SizedPosition,
     factory: PositionFactory,
     num_threads: u32,
+    nodes_searched: u64,
+    book: Option<OpeningBook>,
+    rng: StdRng,
+    tablebases: Tablebases,
+    search_params: SearchParams,
+    skill: Option<Skill>,
+    deterministic: bool,
 }
 
 impl Engine {
@@ -37,53 +112,70 @@ impl Engine {
     /// Returns the `MakeMoveResult` of the last move in `state.move_history`, or an `Ok` result
     /// if `state.move_history` is empty.
     pub fn set_state(&mut self, state: GameState) -> wrap_res!(MakeMoveResult) {
-        let new_pos = self.factory.set_state(state, Some(&mut self.position))?;
-        if let Some(new_pos) = new_pos {
-            self.position = new_pos;
-        }
+        self.position.set_state(&mut self.factory, state, true)?;
         Ok(self.factory.get_last_result())
     }
     /// Updates the engine by loading a fen string. The variant is unchanged.
     pub fn load_fen(&mut self, fen: &str) -> wrap_res!() {
-        self.position = self.factory.load_fen(fen)?;
+        with_position!(&mut self.position, pos => *pos = self.factory.load_fen_sized(fen)?);
         Ok(())
     }
+    /// Updates the engine by loading a fen string written in the given dialect. The variant is unchanged.
+    pub fn load_fen_dialect(&mut self, fen: &str, dialect: FenDialect) -> wrap_res!() {
+        let fen_data = FenData::parse_fen_dialect(fen, dialect, &self.factory.get_state().initial_state)?;
+        self.load_fen(&fen_data.to_string())
+    }
+    /// Returns the current position as a fen string in the given dialect
+    pub fn get_fen(&self, dialect: FenDialect) -> wrap_res!(String) {
+        let fen_data = with_position!(&self.position, pos => FenData::from(pos));
+        fen_data.to_fen_dialect(dialect, &self.factory.get_state().initial_state)
+    }
     /// Returns the current `GameState`, which can later be used in `set_state()`
     pub fn get_state(&mut self) -> &GameState {
         self.factory.get_state()
     }
     /// Returns the current state, but only the fields that can change during a game
     pub fn get_state_diff(&mut self) -> StateDiff {
-        StateDiff::from(&mut self.position)
+        with_position!(&mut self.position, pos => StateDiff::from(pos))
     }
     /// Returns the current move history in algebraic notation
     pub fn get_move_history(&mut self) -> &Vec<String> {
         self.factory.get_notation()
     }
+    /// Changes the style of algebraic notation used in the move history and in the result of `make_move()`.
+    /// The move history of the current game is regenerated using the new style.
+    pub fn set_notation_mode(&mut self, mode: NotationMode) -> wrap_res!() {
+        self.factory.set_notation_mode(mode);
+        let state = self.factory.get_state().clone();
+        self.position.set_state(&mut self.factory, state, false)
+    }
     
     /// Returns the id (can be uppercase or lowercase) of the piece at the given coordinates
     pub fn get_piece_at(&self, position: (BCoord, BCoord)) -> wrap_res!(PieceId) {
-        let piece = self.position.piece_at(to_index(position.0, position.1));
-        err_assert!(piece.is_some(), "No piece at the given coordinates");
-        Ok(piece.unwrap().get_piece_id())
+        let piece_id = with_position!(&self.position, pos => pos.piece_at(to_index(position.0, position.1)).map(|p| p.get_piece_id()));
+        err_assert!(piece_id.is_some(), "No piece at the given coordinates");
+        Ok(piece_id.unwrap())
     }
 
     /// Adds a new piece on the board. If the piece is not used for castling, `has_moved` is ignored.
     pub fn add_piece(&mut self, piece_id: PieceId, x: BCoord, y: BCoord, has_moved: bool) -> wrap_res!() {
-        self.position.public_add_piece(piece_id, to_index(x,y), !has_moved)?;
+        with_position!(&mut self.position, pos => pos.public_add_piece(piece_id, to_index(x,y), !has_moved))?;
         Ok(())
     }
 
     /// Removes a piece on the board, if it exists
     pub fn remove_piece(&mut self, x: BCoord, y: BCoord) -> wrap_res!() {
-        err_assert!(self.position.in_bounds(x, y), "Coordinates ({x}, {y}) are out of bounds");
-        self.position.public_remove_piece(to_index(x,y))?;
+        with_position!(&mut self.position, pos => {
+            err_assert!(pos.in_bounds(x, y), "Coordinates ({x}, {y}) are out of bounds");
+            pos.public_remove_piece(to_index(x,y))?;
+        });
         Ok(())
     }
 
     /// Attempts a move on the current board position
     pub fn make_move(&mut self, target_move: &MoveInfo) -> MakeMoveResult {
-        let result = self.position.pub_make_move(target_move);
+        let mode = self.factory.get_notation_mode();
+        let result = with_position!(&mut self.position, pos => pos.pub_make_move(target_move, mode));
         if result.flag != MakeMoveResultFlag::IllegalMove {
             self.factory.add_move(target_move, &result);
         }
@@ -98,54 +190,289 @@ impl Engine {
 
     /// Undoes the most recent move on the current board position
     pub fn undo(&mut self) -> wrap_res!() {
-        if !self.position.can_unmake_move() {
-            return Err("There is no move to undo".to_string());
-        }
+        with_position!(&mut self.position, pos => {
+            if !pos.can_unmake_move() {
+                return Err("There is no move to undo".to_string());
+            }
+            pos.unmake_move();
+        });
         self.factory.remove_last_move();
-        self.position.unmake_move();
         Ok(())
     }
     
     /// Returns `0` if it's white's turn, `1` if it's black's turn
     pub fn player_to_move(&self) -> Player {
-        self.position.whos_turn
+        with_position!(&self.position, pos => pos.whos_turn)
+    }
+    
+    /// Sets the opening book used by `get_best_move()` and `get_best_move_timeout()`, or removes it if `None`
+    pub fn set_opening_book(&mut self, book: Option<OpeningBook>) {
+        self.book = book;
+    }
+
+    /// Returns a random move from the opening book (weighted), or `None` if there is no book or the
+    /// current position is not in the book
+    pub fn get_book_move(&mut self) -> Option<MoveInfo> {
+        let book = self.book.as_ref()?;
+        with_position!(&mut self.position, pos => book.pick_move(pos, &mut self.rng))
+    }
+
+    /// Sets the seed of the engine, for reproducible runs. It's used for the zobrist keys (see `set_zobrist_seed()`)
+    /// and for the random choices: the book moves and the moves of the weakened play (see `set_skill()`).
+    /// By default, the random choices are based on the current time and the zobrist seed is 0.
+    /// The search itself has no random tie-breaking: among moves with the same score, the first one in the move
+    /// ordering is kept, so with the same seed `get_best_move()` plays the same moves in deterministic mode
+    /// (see `set_deterministic()`).
+    /// Books in the `Protochess` format only work with the zobrist seed they were built with, so after calling this
+    /// function they must be built with `seed` (see `BookBuilder::new()`). Polyglot books are not affected.
+    pub fn set_seed(&mut self, seed: u64) -> wrap_res!() {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.set_zobrist_seed(seed)
+    }
+
+    /// Sets the seed of the zobrist keys of the pieces (0 by default) and regenerates the current position.
+    /// Unlike `set_seed()`, the seed of the random choices is not changed.
+    /// Books in the `Protochess` format are keyed on the zobrist keys, so they only work with the zobrist seed
+    /// they were built with.
+    pub fn set_zobrist_seed(&mut self, seed: u64) -> wrap_res!() {
+        self.factory.set_zobrist_seed(seed);
+        let state = self.factory.get_state().clone();
+        self.position.set_state(&mut self.factory, state, false)
+    }
+
+    /// Returns the seed of the zobrist keys (see `set_zobrist_seed()`)
+    pub fn get_zobrist_seed(&self) -> u64 {
+        self.factory.get_zobrist_seed()
+    }
+    
+    /// In deterministic mode, the search is always single-threaded (even with the `parallel` feature),
+    /// so `get_best_move()` returns the same result for the same position and seed.
+    /// The result of `get_best_move_timeout()` still depends on the speed of the machine.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
     }
     
-    /// Returns the best move for the current position, along with the evaluation score
+    /// Returns `true` if the engine is in deterministic mode (see `set_deterministic()`)
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Weakens the play of `get_best_move()` and `get_best_move_timeout()`, or plays at full strength if `None`.
+    /// With a skill set, the search is single-threaded and limited by `skill.max_nodes`, so it may not reach the requested depth.
+    pub fn set_skill(&mut self, skill: Option<Skill>) {
+        self.skill = skill;
+    }
+
+    /// Returns the settings of the weakened play, or `None` if the engine plays at full strength
+    pub fn get_skill(&self) -> Option<Skill> {
+        self.skill
+    }
+
+    /// Returns the best move for the current position, along with the evaluation score.
+    /// If the position is in the opening book, a book move is returned with a score of 0.
     pub fn get_best_move(&mut self, depth: Depth) -> wrap_res!(MoveInfo, Centipawns) {
         self.validate_position()?;
         err_assert!(depth != 0, "Depth must be greater than 0");
-        let (pv, score, search_depth) = Searcher::get_best_move(&self.position, depth, self.num_threads);
+        if let Some(mv) = self.get_book_move() {
+            self.nodes_searched = 0;
+            return Ok((mv, 0));
+        }
+        if let Some(skill) = self.skill {
+            let (mv, score, _depth) = self.get_skill_move(skill, depth, 1_000_000)?;
+            return Ok((mv, score));
+        }
+        let (pv, score, search_depth, nodes) = with_position!(&self.position, pos =>
+            Searcher::get_best_move(pos, depth, self.search_threads(), self.tablebases.clone(), self.search_params));
+        self.nodes_searched = nodes;
         err_assert!(search_depth == depth, "Search depth ({search_depth}) != requested depth ({depth})");
         err_assert!(!pv.is_empty(), "No moves found");
         Ok((pv[0].into(), score))
     }
 
-    /// Returns the best move for the current position, along with the evaluation score and the search depth
+    /// Returns the best move for the current position, along with the evaluation score and the search depth.
+    /// If the position is in the opening book, a book move is returned with a score and depth of 0.
     pub fn get_best_move_timeout(&mut self, max_sec: u64) -> wrap_res!(MoveInfo, Centipawns, Depth) {
         self.validate_position()?;
-        let (pv, score, search_depth) = Searcher::get_best_move_timeout(&self.position, max_sec, self.num_threads);
+        if let Some(mv) = self.get_book_move() {
+            self.nodes_searched = 0;
+            return Ok((mv, 0, 0));
+        }
+        if let Some(skill) = self.skill {
+            return self.get_skill_move(skill, Depth::MAX, max_sec);
+        }
+        let (pv, score, search_depth, nodes) = with_position!(&self.position, pos =>
+            Searcher::get_best_move_timeout(pos, max_sec, self.search_threads(), self.tablebases.clone(), self.search_params));
+        self.nodes_searched = nodes;
         err_assert!(!pv.is_empty(), "No moves found");
         Ok((pv[0].into(), score, search_depth))
     }
     
+    /// Returns the best move found by searching about `max_nodes` nodes, along with the evaluation score and the search depth.
+    /// The search is single-threaded and always completes depth 1. With a skill set, the lowest node limit is used.
+    /// If the position is in the opening book, a book move is returned with a score and depth of 0.
+    pub fn get_best_move_nodes(&mut self, max_nodes: u64) -> wrap_res!(MoveInfo, Centipawns, Depth) {
+        self.validate_position()?;
+        if let Some(mv) = self.get_book_move() {
+            self.nodes_searched = 0;
+            return Ok((mv, 0, 0));
+        }
+        if let Some(mut skill) = self.skill {
+            skill.max_nodes = std::cmp::min(skill.max_nodes, max_nodes);
+            return self.get_skill_move(skill, Depth::MAX, 1_000_000);
+        }
+        let (pv, score, search_depth, nodes) = with_position!(&self.position, pos =>
+            Searcher::get_best_move_nodes(pos, max_nodes, self.tablebases.clone(), self.search_params));
+        self.nodes_searched = nodes;
+        err_assert!(!pv.is_empty(), "No moves found");
+        Ok((pv[0].into(), score, search_depth))
+    }
+
+    // Number of threads used by the search
+    fn search_threads(&self) -> u32 {
+        if self.deterministic { 1 } else { self.num_threads }
+    }
+    
+    // Picks one of the best moves according to the skill settings
+    fn get_skill_move(&mut self, skill: Skill, max_depth: Depth, max_sec: u64) -> wrap_res!(MoveInfo, Centipawns, Depth) {
+        let (candidates, nodes) = with_position!(&self.position, pos => Searcher::get_candidate_moves(pos, max_depth, max_sec,
+            skill.max_nodes, skill.candidates, self.tablebases.clone(), self.search_params));
+        self.nodes_searched = nodes;
+        match skill.choose(&candidates, &mut self.rng) {
+            Some((mv, score, depth)) => Ok((mv.into(), score, depth)),
+            None => err!("No moves found"),
+        }
+    }
+    
+    /// Returns the number of nodes searched (by all threads) in the last call to `get_best_move()`,
+    /// `get_best_move_timeout()` or `get_best_move_nodes()`
+    pub fn get_nodes_searched(&self) -> u64 {
+        self.nodes_searched
+    }
+    
+    /// Generates the endgame tables of the current variant for all the materials with up to `max_pieces`
+    /// pieces. The tablebase is not used until it's passed to `set_tablebase()`.
+    pub fn generate_tablebase(&self, max_pieces: u32) -> wrap_res!(Tablebase) {
+        Tablebase::generate_up_to(&self.factory.get_state().initial_state, max_pieces)
+    }
+    
+    /// Sets the endgame tablebase used by `get_best_move()` and `get_best_move_timeout()`, or removes it if `None`.
+    /// The tablebase is ignored if it was generated for a different variant.
+    pub fn set_tablebase(&mut self, tablebase: Option<Tablebase>) {
+        self.tablebases.custom = tablebase.map(Arc::new);
+    }
+    
+    /// Returns the result of the current position according to the tablebase, or `None` if the position
+    /// is not in the tablebase
+    pub fn probe_tablebase(&self) -> Option<ProbeResult> {
+        let tablebase = self.tablebases.custom.as_ref()?;
+        with_position!(&self.position, pos => {
+            if !tablebase.is_compatible(pos) {
+                return None;
+            }
+            tablebase.probe(pos)
+        })
+    }
+    
+    /// Loads the Syzygy tablebase files (.rtbw and .rtbz) in a directory, or removes them if `None`.
+    /// The files are only used in standard chess.
+    #[cfg(feature = "syzygy")]
+    pub fn set_syzygy_path(&mut self, path: Option<&str>) -> wrap_res!() {
+        self.tablebases.syzygy = match path {
+            Some(path) => Some(Arc::new(Syzygy::new(path)?)),
+            None => None,
+        };
+        Ok(())
+    }
+    
+    /// Returns the result of the current position according to the Syzygy tablebase, or `None` if it cannot be probed
+    #[cfg(feature = "syzygy")]
+    pub fn probe_syzygy(&mut self) -> Option<Wdl> {
+        let syzygy = self.tablebases.syzygy.as_ref()?;
+        with_position!(&mut self.position, pos => {
+            if !syzygy.is_compatible(pos) {
+                return None;
+            }
+            syzygy.probe_wdl(pos)
+        })
+    }
+    
+    /// Returns the number of plies until the next capture or pawn move according to the Syzygy tablebase
+    /// (see `Syzygy::probe_dtz()`), or `None` if it cannot be probed
+    #[cfg(feature = "syzygy")]
+    pub fn probe_syzygy_dtz(&mut self) -> Option<i32> {
+        let syzygy = self.tablebases.syzygy.as_ref()?;
+        with_position!(&mut self.position, pos => {
+            if !syzygy.is_compatible(pos) {
+                return None;
+            }
+            syzygy.probe_dtz(pos)
+        })
+    }
+    
+    /// Returns the settings of the search heuristics
+    pub fn get_search_params(&self) -> SearchParams {
+        self.search_params
+    }
+    
+    /// Changes the settings of the search heuristics used by `get_best_move()` and `get_best_move_timeout()`
+    pub fn set_search_params(&mut self, params: SearchParams) {
+        self.search_params = params;
+    }
+    
+    /// Returns the constants used by the evaluation function
+    pub fn get_eval_params(&self) -> EvalParams {
+        *self.factory.get_eval_params()
+    }
+    
+    /// Changes the constants used by the evaluation function (for example, the parameters tuned for the current variant).
+    /// The current position is regenerated using the new parameters.
+    pub fn set_eval_params(&mut self, params: EvalParams) -> wrap_res!() {
+        self.factory.set_eval_params(params);
+        let state = self.factory.get_state().clone();
+        self.position.set_state(&mut self.factory, state, false)
+    }
+    
+    /// Returns the static evaluation of the current position for the player to move (`total`),
+    /// along with the contribution of each piece and evaluation term
+    pub fn explain_eval(&self) -> EvalBreakdown {
+        with_position!(&self.position, pos => searcher::eval::explain(pos))
+    }
+    
+    /// Evaluates the positions with a neural network instead of the handcrafted evaluation, or removes it if `None`.
+    /// The current position is regenerated. The network is ignored in the variants that it's not compatible with.
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Network>) -> wrap_res!() {
+        self.factory.set_network(network.map(Arc::new));
+        let state = self.factory.get_state().clone();
+        self.position.set_state(&mut self.factory, state, false)
+    }
+    
+    /// Returns `true` if the current position is evaluated with the neural network set in `set_network()`
+    #[cfg(feature = "nnue")]
+    pub fn uses_network(&self) -> bool {
+        with_position!(&self.position, pos => pos.get_nnue().is_some())
+    }
+    
     /// Returns an error if the current position is invalid
     pub fn validate_position(&mut self) -> wrap_res!() {
-        let player = self.position.whos_turn;
-        let player_str = if player == 0 { "White" } else { "Black" };
-        if self.position.leader_is_captured() {
-            let has_leader = self.position.pieces[player as usize].get_leader().is_some();
-            let piece_str = if has_leader { "leaders" } else { "pieces" };
-            err!("All the {piece_str} of the player to move ({player_str}) have already been captured");
-        }
-        err_assert!(!MoveGen::get_legal_moves(&mut self.position).is_empty(), "The player to move ({player_str}) has no legal moves");
+        with_position!(&mut self.position, pos => {
+            let player = pos.whos_turn;
+            let player_str = if player == 0 { "White" } else { "Black" };
+            if pos.leader_is_captured() {
+                let has_leader = pos.pieces[player as usize].get_leader().is_some();
+                let piece_str = if has_leader { "leaders" } else { "pieces" };
+                err!("All the {piece_str} of the player to move ({player_str}) have already been captured");
+            }
+            err_assert!(!MoveGen::get_legal_moves(pos).is_empty(), "The player to move ({player_str}) has no legal moves");
+        });
         Ok(())
     }
     
-    /// Returns a list of all squares (x,y) from which the given piece can move, along with the moves themselves
+    /// Returns a list of all squares (x,y) from which the given piece can move, along with the moves themselves.
+    /// The squares are sorted by x, then by y.
     pub fn legal_moves(&mut self) -> Vec<MoveList> {
-        let all_moves = MoveGen::get_legal_moves(&mut self.position);
-        let mut moves_from_map = HashMap::new();
+        let all_moves = with_position!(&mut self.position, pos => MoveGen::get_legal_moves(pos));
+        let mut moves_from_map = BTreeMap::new();
         for mv in all_moves {
             let from = mv.get_from();
             let coords = from_index(from);
@@ -161,7 +488,7 @@ impl Engine {
     
     /// Returns a list of all possible promotions for the given move
     pub fn possible_promotions(&mut self, from: (BCoord, BCoord), to: (BCoord, BCoord)) -> Vec<PieceId> {
-        MoveGen::get_legal_moves(&mut self.position)
+        with_position!(&mut self.position, pos => MoveGen::get_legal_moves(pos))
             .into_iter()
             .filter(|mv| {
                 let mv_from = from_index(mv.get_from());
@@ -203,19 +530,30 @@ impl Engine {
     
     // Debugging functions
     pub fn get_zobrist(&self) -> ZobKey {
-        self.position.get_zobrist()
+        with_position!(&self.position, pos => pos.get_zobrist())
     }
+    /// Counts the leaf nodes of the tree of legal moves up to `depth`. With the `parallel` feature, the moves
+    /// at the first ply are split among the search threads.
     pub fn perft(&mut self, depth: Depth) -> usize {
-        utils::perft::perft(&mut self.position, depth)
+        #[cfg(feature = "parallel")]
+        if self.search_threads() > 1 {
+            return with_position!(&self.position, pos => utils::perft::perft_parallel(pos, depth));
+        }
+        with_position!(&mut self.position, pos => utils::perft::perft(pos, depth))
     }
-    pub fn perft_divide(&mut self, depth: Depth) -> usize {
-        utils::perft::perft_divide(&mut self.position, depth)
+    /// Returns the number of leaf nodes below each legal move, see `utils::perft::perft_divide()`
+    pub fn perft_divide(&mut self, depth: Depth) -> Vec<(String, usize)> {
+        with_position!(&mut self.position, pos => utils::perft::perft_divide(pos, depth))
+    }
+    /// Returns the types of moves found at each depth, see `utils::perft::perft_stats()`
+    pub fn perft_stats(&mut self, depth: Depth) -> Vec<PerftStats> {
+        with_position!(&mut self.position, pos => utils::perft::perft_stats(pos, depth))
     }
 }
 
 impl std::fmt::Display for Engine {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.position)
+        with_position!(&self.position, pos => write!(f, "{pos}"))
     }
 }
 
@@ -224,9 +562,14 @@ impl Default for Engine {
         // Set up position using default GameState
         let state = GameState::default();
         let mut factory = PositionFactory::default();
-        let position = factory.set_state(state, None).unwrap().unwrap();
+        let position = SizedPosition::new(&mut factory, state).unwrap();
         // Use maximum number of threads (usually this is too many, the user should change this later)
         let num_threads = Self::get_max_threads();
-        Engine { position, factory, num_threads }
+        // Seed used for picking the book moves and the moves of the weakened play
+        let rng = StdRng::seed_from_u64(instant::now().to_bits());
+        Engine {
+            position, factory, num_threads, nodes_searched: 0, book: None, rng,
+            tablebases: Tablebases::default(), search_params: SearchParams::default(), skill: None, deterministic: false,
+        }
     }
 }
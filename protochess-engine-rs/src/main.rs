@@ -2,7 +2,7 @@
 
 use std::io::Write;
 
-use protochess_engine_rs::{Engine, MoveInfo, MakeMoveResultFlag, MakeMoveResultWinner, GameState};
+use protochess_engine_rs::{Engine, MakeMoveResultFlag, MakeMoveResultWinner, GameState, NotationMode};
 use protochess_engine_rs::types::GameMode;
 use protochess_engine_rs::utils::debug::split_debug_fen;
 
@@ -34,7 +34,14 @@ pub fn main() {
     
     
     let mut engine = Engine::default();
-    
+    // Use standard SAN so that the PGN can be imported by other tools (e.g. Lichess)
+    engine.set_notation_mode(NotationMode::Standard).unwrap();
+    // Use the Syzygy tablebase in $SYZYGY_PATH, if set
+    #[cfg(feature = "syzygy")]
+    if let Ok(path) = std::env::var("SYZYGY_PATH") {
+        engine.set_syzygy_path(Some(&path)).unwrap_or_else(|e| println!("Cannot load the Syzygy tablebase: {e}"));
+    }
+
     if args.len() > 2 && args[2] != "default" {
         let state = GameState::from_debug_fen(&args[2]);
         print_pgn_header(&args[2], &mut pgn_file);
@@ -69,7 +76,7 @@ pub fn main() {
         println!("PLY: {ply} Engine plays:\n");
         let result = engine.make_move(&mv);
         let move_str = result.move_notation.unwrap_or_else(|| "!!!".to_string());
-        print_pgn(&mut pgn_file, ply, &fix_notation(move_str, mv));
+        print_pgn(&mut pgn_file, ply, &move_str);
         println!("{engine}\n");
         match result.flag {
             MakeMoveResultFlag::Ok => {
@@ -124,23 +131,10 @@ fn print_pgn_header(fen: &str, pgn_file: &mut std::fs::File) {
 }
 
 fn print_pgn(pgn_file: &mut std::fs::File, ply: u32, move_str: &str) {
-    if (ply % 2) == 0 {
+    if ply.is_multiple_of(2) {
         let round = format!("{}. ", ply/2 + 1);
         pgn_file.write_all(round.as_bytes()).expect("write failed");
     }
     pgn_file.write_all(move_str.as_bytes()).expect("write failed");
     pgn_file.write_all(b" ").expect("write failed");
 }
-
-
-// Algebraic notation specifies that a pawn capture includes the file of the capturing pawn,
-// even if the capture is unambiguous without it. This is not generalizable at all, so
-// the engine does not include the file in the notation.
-// If we want Lichess to accept our PGNs, we need to add the file back in.
-fn fix_notation(move_str: String, mv: MoveInfo) -> String {
-    if move_str.starts_with('x') {
-        format!("{}{}", (b'a' + mv.from.0) as char, move_str)
-    } else {
-        move_str
-    }
-}
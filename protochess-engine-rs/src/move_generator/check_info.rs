@@ -0,0 +1,264 @@
+use crate::piece::{Piece, PieceId};
+use crate::position::Position;
+use crate::types::{BCoord, BIndex, Bitboard, Move};
+use crate::utils::{from_index, to_index};
+
+use super::MoveGen;
+use super::legality::{on_same_line, ray};
+
+/// Squares from which the player to move can check the enemy leader, computed once per position to find the moves
+/// that give check without making them.
+///
+/// This only covers the common case: the opponent has a single leader and no piece explodes. In explosive
+/// variants (and for castling and en passant), `gives_check()` makes the move and looks for checks.
+#[derive(Debug, Clone)]
+pub struct CheckInfo<const W: usize = 4> {
+    mode: Mode<W>,
+}
+
+#[derive(Debug, Clone)]
+enum Mode<const W: usize> {
+    /// Every move is tested with make/unmake
+    Fallback,
+    /// The opponent doesn't have exactly one leader. Only capturing one of its leaders can put it in check.
+    NoSingleLeader(Bitboard<W>),
+    Leader(LeaderInfo<W>),
+}
+
+#[derive(Debug, Clone)]
+struct LeaderInfo<const W: usize> {
+    leader: BIndex,
+    /// For each type of piece of the player to move, the squares from which it attacks the enemy leader (direct checks)
+    check_squares: Vec<(PieceId, Bitboard<W>)>,
+    /// Pieces that give a discovered check when they leave the line between the enemy leader and a slider
+    discoverers: Bitboard<W>,
+    /// Line of each discoverer (from the enemy leader to the slider, including the slider)
+    discovery_lines: Vec<(BIndex, Bitboard<W>)>,
+    /// Pieces whose moves must be tested with make/unmake, because they block an attack by a piece that moves
+    /// with sliding deltas
+    unsafe_pieces: Bitboard<W>,
+}
+
+impl<const W: usize> CheckInfo<W> {
+    pub fn new(position: &Position<W>) -> CheckInfo<W> {
+        let mode = {
+            if position.pieces.iter().any(|set| set.iter().any(|p| p.explodes_on_capture())) {
+                Mode::Fallback
+            } else {
+                match position.pieces[1 - position.whos_turn as usize].get_leader() {
+                    Some(leader) if leader.get_num_pieces() == 1 => {
+                        let index = leader.get_bitboard().lowest_one().unwrap();
+                        Mode::Leader(LeaderInfo::new(position, index))
+                    },
+                    Some(leader) => Mode::NoSingleLeader(leader.get_bitboard().clone()),
+                    None => Mode::NoSingleLeader(Bitboard::zero()),
+                }
+            }
+        };
+        CheckInfo { mode }
+    }
+
+    /// Same as `MoveGen::gives_check()`, for a pseudo-legal move of the position used to create this `CheckInfo`
+    pub fn gives_check(&self, mv: Move, position: &mut Position<W>) -> bool {
+        let info = match &self.mode {
+            Mode::Fallback => return make_move_gives_check(mv, position),
+            Mode::NoSingleLeader(leaders) => {
+                // With 2 leaders, capturing one of them leaves the other one in check
+                return leaders.get_bit(mv.get_target()) && make_move_gives_check(mv, position);
+            },
+            Mode::Leader(info) => info,
+        };
+        let from = mv.get_from();
+        let to = mv.get_to();
+        if mv.get_target() == info.leader {
+            // Capturing the last enemy leader ends the game
+            return false;
+        }
+        // Castling moves 2 pieces, and en passant removes a piece from another square
+        if mv.is_castling() || mv.is_en_passant() || info.unsafe_pieces.get_bit(from) {
+            return make_move_gives_check(mv, position);
+        }
+        if info.discoverers.get_bit(from) {
+            let line = &info.discovery_lines.iter().find(|(index, _)| *index == from).unwrap().1;
+            if !line.get_bit(to) {
+                return true;
+            }
+        }
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        match mv.get_promotion_piece() {
+            Some(promoted) => {
+                // The square left by the piece can open a line for the promoted piece
+                let mut occ = position.occ_or_out_bounds.clone();
+                occ.clear_bit(from);
+                occ.set_bit(to);
+                my_pieces.lookup_piece(promoted).is_some_and(|piece| attacks(piece, to, info.leader, &occ))
+            },
+            None => {
+                let piece_id = my_pieces.piece_at(from).unwrap().get_piece_id();
+                info.check_squares.iter().any(|(id, squares)| *id == piece_id && squares.get_bit(to))
+            },
+        }
+    }
+
+    /// Returns false if no move of the piece type can give check, so that its moves don't need to be generated
+    pub fn piece_can_give_check(&self, piece: &Piece<W>) -> bool {
+        let info = match &self.mode {
+            Mode::Fallback => return true,
+            Mode::NoSingleLeader(leaders) => return !leaders.is_zero(),
+            Mode::Leader(info) => info,
+        };
+        // The leader can castle, and promotions can give check with the new piece
+        piece.is_leader() || piece.get_movement().can_promote()
+            || !(piece.get_bitboard() & (&info.discoverers | &info.unsafe_pieces)).is_zero()
+            || info.check_squares.iter().any(|(id, squares)| *id == piece.get_piece_id() && !squares.is_zero())
+    }
+
+    /// Returns false if no quiet move of the piece at `index` can give check, without generating the moves.
+    /// Only direct checks are ruled out this way: the leader (castling), pieces that can promote, discoverers and
+    /// pieces with sliding deltas or double jumps always return true.
+    pub fn piece_at_can_give_quiet_check(&self, piece: &Piece<W>, index: BIndex, position: &Position<W>) -> bool {
+        let info = match &self.mode {
+            Mode::Leader(info) => info,
+            _ => return true,
+        };
+        let movement = piece.get_movement();
+        if piece.is_leader() || movement.can_promote() || !movement.translate_sliding_deltas.is_empty()
+            || !movement.double_jump_squares.is_empty()
+            || info.discoverers.get_bit(index) || info.unsafe_pieces.get_bit(index)
+        {
+            return true;
+        }
+        let check_squares = match info.check_squares.iter().find(|(id, _)| *id == piece.get_piece_id()) {
+            Some((_, squares)) => squares,
+            None => return false,
+        };
+        let occ = &position.occ_or_out_bounds;
+        let slides = MoveGen::attack_tables().get_sliding_moves_bb(
+            index,
+            occ,
+            movement.translate_north,
+            movement.translate_east,
+            movement.translate_south,
+            movement.translate_west,
+            movement.translate_northeast,
+            movement.translate_northwest,
+            movement.translate_southeast,
+            movement.translate_southwest
+        );
+        let targets = (slides | piece.get_translate_jumps(index)) & !occ;
+        !(targets & check_squares).is_zero()
+    }
+}
+
+impl<const W: usize> LeaderInfo<W> {
+    fn new(position: &Position<W>, leader: BIndex) -> LeaderInfo<W> {
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let occ = &position.occ_or_out_bounds;
+        let attack_tables = MoveGen::attack_tables();
+        let (x, y) = from_index(leader);
+        let (inverse_attack, inverse_jumps) = my_pieces.get_inverse_attack(leader);
+
+        // Walk the attacks backwards from the enemy leader. The sliding attacks are reversed by swapping
+        // the opposite directions.
+        let mut check_squares = Vec::new();
+        for piece in my_pieces.iter().filter(|p| p.get_num_pieces() > 0) {
+            let movement = piece.get_movement();
+            let mut squares = attack_tables.get_sliding_moves_bb(
+                leader,
+                occ,
+                movement.attack_south,
+                movement.attack_west,
+                movement.attack_north,
+                movement.attack_east,
+                movement.attack_southwest,
+                movement.attack_southeast,
+                movement.attack_northwest,
+                movement.attack_northeast
+            );
+            let mut jumps = inverse_jumps.clone();
+            while let Some(index) = jumps.lowest_one() {
+                jumps.clear_bit(index);
+                if piece.get_capture_jumps(index).get_bit(leader) {
+                    squares.set_bit(index);
+                }
+            }
+            if !movement.attack_sliding_deltas.is_empty() {
+                for run in &inverse_attack.attack_sliding_deltas {
+                    for (dx, dy) in run {
+                        let (x2, y2) = (x as i8 + *dx, y as i8 + *dy);
+                        if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+                            break;
+                        }
+                        let index = to_index(x2 as BCoord, y2 as BCoord);
+                        if MoveGen::sliding_delta_targets_index(piece, index, leader, occ) {
+                            squares.set_bit(index);
+                        }
+                    }
+                }
+            }
+            check_squares.push((piece.get_piece_id(), squares));
+        }
+
+        // The first piece in each direction from the enemy leader gives a discovered check if the next piece
+        // is a slider of the player to move that attacks in that direction
+        let mut discoverers = Bitboard::zero();
+        let mut discovery_lines = Vec::new();
+        let lines = attack_tables.get_sliding_moves_bb(leader, occ, true, true, true, true, true, true, true, true);
+        let mut blockers = &lines & my_pieces.get_occupied();
+        while let Some(blocker) = blockers.lowest_one() {
+            blockers.clear_bit(blocker);
+            let mut occ_without_blocker = occ.clone();
+            occ_without_blocker.clear_bit(blocker);
+            let line = ray(leader, blocker, &occ_without_blocker);
+            let slider = &line & my_pieces.get_occupied();
+            if let Some(slider_index) = slider.lowest_one() {
+                if MoveGen::slide_targets_coords(x, y, my_pieces.piece_at(slider_index).unwrap(), slider_index) {
+                    discoverers.set_bit(blocker);
+                    discovery_lines.push((blocker, line));
+                }
+            }
+        }
+
+        // Pieces that move with sliding deltas (like the nightrider) can also be blocked, but not along a line.
+        // Walk their paths backwards from the enemy leader, as in `LegalityInfo`.
+        let mut unsafe_pieces = Bitboard::zero();
+        for run in &inverse_attack.attack_sliding_deltas {
+            for (dx, dy) in run {
+                let (x2, y2) = (x as i8 + *dx, y as i8 + *dy);
+                if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+                    break;
+                }
+                let index = to_index(x2 as BCoord, y2 as BCoord);
+                if occ.get_bit(index) {
+                    if my_pieces.get_occupied().get_bit(index) {
+                        unsafe_pieces.set_bit(index);
+                    }
+                    break;
+                }
+            }
+        }
+
+        LeaderInfo { leader, check_squares, discoverers, discovery_lines, unsafe_pieces }
+    }
+}
+
+/// Returns true if `piece`, placed on `index`, attacks `target`
+fn attacks<const W: usize>(piece: &Piece<W>, index: BIndex, target: BIndex, occ: &Bitboard<W>) -> bool {
+    if piece.get_capture_jumps(index).get_bit(target) {
+        return true;
+    }
+    let (x, y) = from_index(target);
+    if on_same_line(index, target) && MoveGen::slide_targets_coords(x, y, piece, index) && ray(index, target, occ).get_bit(target) {
+        return true;
+    }
+    MoveGen::sliding_delta_targets_index(piece, index, target, occ)
+}
+
+/// Makes the move and checks if the opponent is in check
+fn make_move_gives_check<const W: usize>(mv: Move, position: &mut Position<W>) -> bool {
+    position.make_move(mv);
+    // If the move captures the last enemy leader, the enemy is not in check (the game is over)
+    let check = !position.leader_is_captured() && MoveGen::in_check(position);
+    position.unmake_move();
+    check
+}
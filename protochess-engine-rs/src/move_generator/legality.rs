@@ -0,0 +1,226 @@
+use crate::position::Position;
+use crate::types::{BCoord, BIndex, Bitboard, Move};
+use crate::utils::{from_index, to_index};
+
+use super::MoveGen;
+
+/// Pins and checks of the player to move, computed once per position to test the legality of its pseudo-legal
+/// moves without making them.
+///
+/// This only covers the common case: the player to move has a single leader, no piece explodes and checking the
+/// opponent is allowed. In any other position (and for castling, en passant and captures of an enemy leader),
+/// `make_move_if_legal()` falls back to `MoveGen::make_move_if_legal()`, which makes the move and looks for checks.
+#[derive(Debug, Clone)]
+pub struct LegalityInfo<const W: usize = 4> {
+    mode: Mode<W>,
+}
+
+#[derive(Debug, Clone)]
+enum Mode<const W: usize> {
+    /// Every move is tested with make/unmake
+    Fallback,
+    /// The player to move has no leader, so every pseudo-legal move is legal
+    NoLeader,
+    Leader(LeaderInfo<W>),
+}
+
+#[derive(Debug, Clone)]
+struct LeaderInfo<const W: usize> {
+    leader: BIndex,
+    /// `None` if not in check. If the leader is attacked by 2 or more pieces, only the leader can move (empty mask).
+    /// Otherwise, the squares where a piece can move to stop the check (capturing the attacker or blocking it).
+    check_mask: Option<Bitboard<W>>,
+    /// Pieces that cannot leave the line between the leader and an enemy slider
+    pinned: Bitboard<W>,
+    /// Squares where each pinned piece can move (the line between the leader and the pinner, including the pinner)
+    pin_rays: Vec<(BIndex, Bitboard<W>)>,
+    /// Pieces whose moves must be tested with make/unmake, because they block an attack by a piece that moves
+    /// with sliding deltas
+    unsafe_pieces: Bitboard<W>,
+    enemy_leaders: Bitboard<W>,
+}
+
+/// Result of the fast legality test
+enum Legality {
+    Legal,
+    Illegal,
+    Unknown,
+}
+
+impl<const W: usize> LegalityInfo<W> {
+    pub fn new(position: &Position<W>) -> LegalityInfo<W> {
+        let mode = {
+            if position.global_rules.check_is_forbidden
+                || position.pieces.iter().any(|set| set.iter().any(|p| p.explodes_on_capture())) {
+                Mode::Fallback
+            } else {
+                match position.pieces[position.whos_turn as usize].get_leader() {
+                    None => Mode::NoLeader,
+                    Some(leader) if leader.get_num_pieces() == 1 => {
+                        let index = leader.get_bitboard().lowest_one().unwrap();
+                        LeaderInfo::new(position, index).map_or(Mode::Fallback, Mode::Leader)
+                    },
+                    Some(_) => Mode::Fallback,
+                }
+            }
+        };
+        LegalityInfo { mode }
+    }
+
+    /// Same as `MoveGen::make_move_if_legal()`: makes the pseudo-legal move and returns true only if it's legal
+    pub fn make_move_if_legal(&self, mv: Move, position: &mut Position<W>) -> bool {
+        match self.test(mv, position) {
+            Legality::Legal => {
+                position.make_move(mv);
+                true
+            },
+            Legality::Illegal => false,
+            Legality::Unknown => MoveGen::make_move_if_legal(mv, position),
+        }
+    }
+
+    /// Same as `MoveGen::is_move_legal()`, for a pseudo-legal move
+    pub fn is_move_legal(&self, mv: Move, position: &mut Position<W>) -> bool {
+        match self.test(mv, position) {
+            Legality::Legal => true,
+            Legality::Illegal => false,
+            Legality::Unknown => MoveGen::is_move_legal(mv, position),
+        }
+    }
+
+    fn test(&self, mv: Move, position: &mut Position<W>) -> Legality {
+        let info = match &self.mode {
+            Mode::Fallback => return Legality::Unknown,
+            Mode::NoLeader => return Legality::Legal,
+            Mode::Leader(info) => info,
+        };
+        let from = mv.get_from();
+        let to = mv.get_to();
+        // Castling checks the squares the leader crosses, en passant removes a piece from another square, and
+        // capturing the last enemy leader is legal even if the leader is left in check
+        if mv.is_castling() || mv.is_en_passant() || info.enemy_leaders.get_bit(mv.get_target()) || info.unsafe_pieces.get_bit(from) {
+            return Legality::Unknown;
+        }
+        if from == info.leader {
+            // Remove the leader from the board, so that it doesn't hide the squares behind it from the sliders
+            position.occ_or_out_bounds.clear_bit(from);
+            let attacked = MoveGen::index_in_check(to, position);
+            position.occ_or_out_bounds.set_bit(from);
+            return if attacked { Legality::Illegal } else { Legality::Legal };
+        }
+        if let Some(mask) = &info.check_mask {
+            if !mask.get_bit(to) {
+                return Legality::Illegal;
+            }
+        }
+        if info.pinned.get_bit(from) {
+            let ray = &info.pin_rays.iter().find(|(index, _)| *index == from).unwrap().1;
+            if !ray.get_bit(to) {
+                return Legality::Illegal;
+            }
+        }
+        Legality::Legal
+    }
+}
+
+impl<const W: usize> LeaderInfo<W> {
+    // Returns None if the check cannot be described with a mask (the attacker moves with sliding deltas)
+    fn new(position: &Position<W>, leader: BIndex) -> Option<LeaderInfo<W>> {
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let enemy_pieces = &position.pieces[1 - position.whos_turn as usize];
+        let occ = &position.occ_or_out_bounds;
+        let attack_tables = MoveGen::attack_tables();
+        let (x, y) = from_index(leader);
+
+        let enemy_leaders = enemy_pieces.get_leader().map_or(Bitboard::zero(), |l| l.get_bitboard().clone());
+
+        let checkers = MoveGen::attackers(position, leader, 1 - position.whos_turn, occ);
+        let check_mask = match checkers.count_ones() {
+            0 => None,
+            1 => {
+                let checker_index = checkers.lowest_one().unwrap();
+                let checker = enemy_pieces.piece_at(checker_index).unwrap();
+                let mut mask = Bitboard::zero();
+                if !checker.get_capture_jumps(checker_index).get_bit(leader) {
+                    // Sliding attack along a line, it can be blocked
+                    if !checker.get_movement().attack_sliding_deltas.is_empty() || !on_same_line(leader, checker_index) {
+                        return None;
+                    }
+                    mask = ray(leader, checker_index, occ);
+                }
+                mask.set_bit(checker_index);
+                Some(mask)
+            },
+            _ => Some(Bitboard::zero()),
+        };
+
+        // The first piece in each direction from the leader is pinned if the next piece is an enemy slider
+        // that attacks in that direction
+        let mut pinned = Bitboard::zero();
+        let mut pin_rays = Vec::new();
+        let lines = attack_tables.get_sliding_moves_bb(leader, occ, true, true, true, true, true, true, true, true);
+        let mut blockers = &lines & my_pieces.get_occupied();
+        while let Some(blocker) = blockers.lowest_one() {
+            blockers.clear_bit(blocker);
+            let mut occ_without_blocker = occ.clone();
+            occ_without_blocker.clear_bit(blocker);
+            let pin_ray = ray(leader, blocker, &occ_without_blocker);
+            let pinner = &pin_ray & enemy_pieces.get_occupied();
+            if let Some(pinner_index) = pinner.lowest_one() {
+                if MoveGen::slide_targets_coords(x, y, enemy_pieces.piece_at(pinner_index).unwrap(), pinner_index) {
+                    pinned.set_bit(blocker);
+                    pin_rays.push((blocker, pin_ray));
+                }
+            }
+        }
+
+        // Pieces that move with sliding deltas (like the nightrider) can also be blocked, but not along a line.
+        // Walk their paths backwards from the leader, as in `MoveGen::index_in_check()`.
+        let mut unsafe_pieces = Bitboard::zero();
+        let (inverse_attack, _) = enemy_pieces.get_inverse_attack(leader);
+        for run in &inverse_attack.attack_sliding_deltas {
+            for (dx, dy) in run {
+                let (x2, y2) = (x as i8 + *dx, y as i8 + *dy);
+                if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+                    break;
+                }
+                let index = to_index(x2 as BCoord, y2 as BCoord);
+                if occ.get_bit(index) {
+                    if my_pieces.get_occupied().get_bit(index) {
+                        unsafe_pieces.set_bit(index);
+                    }
+                    break;
+                }
+            }
+        }
+
+        Some(LeaderInfo { leader, check_mask, pinned, pin_rays, unsafe_pieces, enemy_leaders })
+    }
+}
+
+pub(super) fn on_same_line(a: BIndex, b: BIndex) -> bool {
+    let (x1, y1) = from_index(a);
+    let (x2, y2) = from_index(b);
+    x1 == x2 || y1 == y2 || (x1 as i8 - x2 as i8).abs() == (y1 as i8 - y2 as i8).abs()
+}
+
+/// Returns the squares from `from` (excluded) in the direction of `towards`, up to the first occupied square
+/// (included). Both squares must be on the same rank, file or diagonal.
+pub(super) fn ray<const W: usize>(from: BIndex, towards: BIndex, occ: &Bitboard<W>) -> Bitboard<W> {
+    let (x1, y1) = from_index(from);
+    let (x2, y2) = from_index(towards);
+    let (east, west) = (x2 > x1, x2 < x1);
+    let (north, south) = (y2 > y1, y2 < y1);
+    MoveGen::attack_tables().get_sliding_moves_bb(
+        from,
+        occ,
+        north && x1 == x2,
+        east && y1 == y2,
+        south && x1 == x2,
+        west && y1 == y2,
+        north && east,
+        north && west,
+        south && east,
+        south && west,
+    )
+}
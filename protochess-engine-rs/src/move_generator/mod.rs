@@ -1,11 +1,16 @@
 use crate::piece::Piece;
 use crate::position::piece_set::PieceSet;
-use crate::types::{BCoord, BIndex, Bitboard, Move, MoveType};
+use crate::types::{BCoord, BIndex, Bitboard, Move, MoveBuffer, MoveSink, MoveType, Player};
 use crate::position::Position;
 use crate::move_generator::attack_tables::AttackTables;
+use crate::move_generator::check_info::CheckInfo;
+use crate::move_generator::legality::LegalityInfo;
+use crate::piece::PieceId;
 use crate::utils::{from_index, to_index};
 
 pub mod attack_tables;
+pub mod check_info;
+pub mod legality;
 
 
 lazy_static! {
@@ -21,40 +26,95 @@ impl MoveGen {
         &ATTACK_TABLES
     }
     
-    pub fn get_legal_moves(position: &mut Position) -> Vec<Move> {
-        let mut legal_moves = Vec::new();
-        for mv in MoveGen::get_pseudo_moves(position, true) {
-            if !MoveGen::is_move_legal(mv, position) {
-                continue;
-            }
-            legal_moves.push(mv);
-        }
+    pub fn get_legal_moves<const W: usize>(position: &mut Position<W>) -> Vec<Move> {
+        let legality = LegalityInfo::new(position);
+        let mut legal_moves = MoveGen::get_pseudo_moves(position, true);
+        legal_moves.retain(|mv| legality.is_move_legal(*mv, position));
         legal_moves
     }
 
     /// Iterator that yields pseudo-legal moves from a positon
-    pub fn get_pseudo_moves(position: &mut Position, output_translations: bool) -> Vec<Move> {
-        let my_pieces = &position.pieces[position.whos_turn as usize];
-
+    pub fn get_pseudo_moves<const W: usize>(position: &mut Position<W>, output_translations: bool) -> Vec<Move> {
         let mut out_moves = Vec::with_capacity(50);
+        MoveGen::output_captures(position, &mut out_moves);
+        let skip_translations = position.global_rules.capturing_is_forced && !out_moves.is_empty();
+        if output_translations && !skip_translations {
+            MoveGen::output_translations(position, &mut out_moves);
+        }
+        out_moves
+    }
 
+    /// Adds the pseudo-legal captures of the player to move to `out_moves`
+    pub fn output_captures<const W: usize>(position: &Position<W>, out_moves: &mut impl MoveSink) {
+        let my_pieces = &position.pieces[position.whos_turn as usize];
         let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
-        let occ_or_not_in_bounds = &position.occ_or_out_bounds;
-        
         for p in my_pieces.iter() {
-            p.output_captures(position, &enemies_or_out_bounds, occ_or_not_in_bounds, &mut out_moves);
+            p.output_captures(position, &enemies_or_out_bounds, &position.occ_or_out_bounds, out_moves);
         }
-        let skip_translations = position.global_rules.capturing_is_forced && !out_moves.is_empty();
-        if output_translations && !skip_translations {
-            for p in my_pieces.iter() {
-                p.output_translations(position, &enemies_or_out_bounds, occ_or_not_in_bounds, &mut out_moves);
+    }
+
+    /// Adds the pseudo-legal non-capture moves of the player to move to `out_moves`.
+    /// Unlike `get_pseudo_moves()`, doesn't check if capturing is forced.
+    pub fn output_translations<const W: usize>(position: &Position<W>, out_moves: &mut impl MoveSink) {
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
+        for p in my_pieces.iter() {
+            p.output_translations(position, &enemies_or_out_bounds, &position.occ_or_out_bounds, out_moves);
+        }
+    }
+
+    /// Checks if a move (for example, from the transposition table) is one of the pseudo-legal moves of the position.
+    /// Only the moves of the piece that moves are generated.
+    pub fn is_pseudo_legal<const W: usize>(mv: Move, position: &Position<W>) -> bool {
+        if mv.is_null() {
+            return false;
+        }
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let piece = match my_pieces.piece_at(mv.get_from()) {
+            Some(piece) => piece,
+            None => return false,
+        };
+        let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
+        let mut moves = MoveBuffer::new();
+        if mv.is_capture() {
+            piece.output_captures_at(mv.get_from(), position, &enemies_or_out_bounds, &position.occ_or_out_bounds, &mut moves);
+        } else {
+            if position.global_rules.capturing_is_forced {
+                MoveGen::output_captures(position, &mut moves);
+                if !moves.is_empty() {
+                    return false;
+                }
             }
+            piece.output_translations_at(mv.get_from(), position, &enemies_or_out_bounds, &position.occ_or_out_bounds, &mut moves);
+        }
+        moves.contains(mv)
+    }
+
+    /// Pseudo-legal moves of the pieces of a given type (of the player to move).
+    /// If capturing is forced, the translations are only included if no piece of the player can capture.
+    pub fn get_pseudo_moves_of_type<const W: usize>(position: &mut Position<W>, piece_id: PieceId) -> Vec<Move> {
+        let mut out_moves = Vec::new();
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        if !my_pieces.iter().any(|p| p.get_piece_id() == piece_id) {
+            return out_moves;
+        }
+        // Another type of piece may have to capture
+        let skip_translations = position.global_rules.capturing_is_forced
+            && !MoveGen::get_pseudo_moves(position, false).is_empty();
+
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let piece = my_pieces.iter().find(|p| p.get_piece_id() == piece_id).unwrap();
+        let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
+        let occ_or_not_in_bounds = &position.occ_or_out_bounds;
+        piece.output_captures(position, &enemies_or_out_bounds, occ_or_not_in_bounds, &mut out_moves);
+        if !skip_translations {
+            piece.output_translations(position, &enemies_or_out_bounds, occ_or_not_in_bounds, &mut out_moves);
         }
         out_moves
     }
 
     /// Checks if the player to move is in check
-    pub fn in_check(position: &mut Position) -> bool {
+    pub fn in_check<const W: usize>(position: &mut Position<W>) -> bool {
         let my_pieces = &position.pieces[position.whos_turn as usize];
         if let Some(my_leader) = my_pieces.get_leader() {
             if my_leader.get_num_pieces() > 1 {
@@ -70,8 +130,76 @@ impl MoveGen {
         }
     }
 
+    /// Checks if a pseudo-legal move puts the opponent in check.
+    /// To test several moves of the same position, create a `CheckInfo` once and use `CheckInfo::gives_check()`.
+    pub fn gives_check<const W: usize>(mv: Move, position: &mut Position<W>) -> bool {
+        CheckInfo::new(position).gives_check(mv, position)
+    }
+
+    /// Returns the pseudo-legal moves that are not captures and put the opponent in check.
+    /// Only the pieces that can give check (directly, by discovery or by promoting) generate their moves.
+    pub fn get_quiet_checks<const W: usize>(position: &mut Position<W>) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let enemy_pieces = &position.pieces[1 - position.whos_turn as usize];
+        // The opponent can only be in check if it has exactly one leader
+        if enemy_pieces.get_leader().is_none_or(|leader| leader.get_num_pieces() != 1) {
+            return moves;
+        }
+        if position.global_rules.capturing_is_forced {
+            MoveGen::output_captures(position, &mut moves);
+            if !moves.is_empty() {
+                return Vec::new();
+            }
+        }
+        let check_info = CheckInfo::new(position);
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
+        for p in my_pieces.iter().filter(|p| check_info.piece_can_give_check(p)) {
+            p.output_translations(position, &enemies_or_out_bounds, &position.occ_or_out_bounds, &mut moves);
+        }
+        moves.retain(|mv| check_info.gives_check(*mv, position));
+        moves
+    }
+
+    /// Returns true if `get_quiet_checks()` would return any move. The moves are generated and tested one piece at
+    /// a time, so it stops at the first piece that can give check and doesn't allocate. The pieces that can only give
+    /// a direct check are skipped if none of their targets attack the enemy leader.
+    pub fn has_quiet_check<const W: usize>(position: &mut Position<W>) -> bool {
+        let enemy_pieces = &position.pieces[1 - position.whos_turn as usize];
+        if enemy_pieces.get_leader().is_none_or(|leader| leader.get_num_pieces() != 1) {
+            return false;
+        }
+        let mut moves = MoveBuffer::new();
+        if position.global_rules.capturing_is_forced {
+            MoveGen::output_captures(position, &mut moves);
+            if !moves.is_empty() {
+                return false;
+            }
+        }
+        let check_info = CheckInfo::new(position);
+        let my_pieces = &position.pieces[position.whos_turn as usize];
+        let enemies_or_out_bounds = &position.occ_or_out_bounds & !my_pieces.get_occupied();
+        let mut candidates = Bitboard::zero();
+        for p in my_pieces.iter().filter(|p| check_info.piece_can_give_check(p)) {
+            candidates |= p.get_bitboard();
+        }
+        while let Some(index) = candidates.lowest_one() {
+            candidates.clear_bit(index);
+            moves.clear();
+            let piece = position.pieces[position.whos_turn as usize].piece_at(index).unwrap();
+            if !check_info.piece_at_can_give_quiet_check(piece, index, position) {
+                continue;
+            }
+            piece.output_translations_at(index, position, &enemies_or_out_bounds, &position.occ_or_out_bounds, &mut moves);
+            if moves.as_slice().iter().any(|m| check_info.gives_check(m.mv, position)) {
+                return true;
+            }
+        }
+        false
+    }
+
     /// Attempts to make a pseudo-legal move, succeeding and returning true only if the move was legal
-    pub fn make_move_if_legal(mv: Move, position: &mut Position) -> bool {
+    pub fn make_move_if_legal<const W: usize>(mv: Move, position: &mut Position<W>) -> bool {
         // Cannot castle while in check or step through check
         if mv.is_castling() {
             let kingside = mv.get_move_type() == MoveType::KingsideCastle;
@@ -116,7 +244,7 @@ impl MoveGen {
     }
     
     /// Checks if a move is legal
-    pub fn is_move_legal(mv: Move, position: &mut Position) -> bool {
+    pub fn is_move_legal<const W: usize>(mv: Move, position: &mut Position<W>) -> bool {
         let legal = Self::make_move_if_legal(mv, position);
         // Restore previous state of the position
         if legal {
@@ -126,7 +254,7 @@ impl MoveGen {
     }
     
     /// Checks if a given square is attacked by the enemy
-    fn index_in_check(index: BIndex, position: &mut Position) -> bool {
+    fn index_in_check<const W: usize>(index: BIndex, position: &mut Position<W>) -> bool {
         let (x, y) = from_index(index);
         let enemy = 1 - position.whos_turn;
         let enemy_pieces = &position.pieces[enemy as usize];
@@ -204,7 +332,65 @@ impl MoveGen {
         }
         false
     }
-    fn slide_targets_coords(x: BCoord, y: BCoord, piece: &Piece, piece_index: BIndex) -> bool {
+    /// Returns the pieces of `player` that attack a given square, using a custom occupancy bitboard.
+    /// Pieces that are not in `occ_or_not_in_bounds` are ignored, which can be used to reveal x-ray attacks.
+    pub fn attackers<const W: usize>(position: &Position<W>, index: BIndex, player: Player, occ_or_not_in_bounds: &Bitboard<W>) -> Bitboard<W> {
+        let (x, y) = from_index(index);
+        let pieces = &position.pieces[player as usize];
+        let candidates = pieces.get_occupied() & occ_or_not_in_bounds;
+        let (inverse_attack, jumps) = pieces.get_inverse_attack(index);
+        let mut attackers = Bitboard::zero();
+
+        let mut slides = MoveGen::attack_tables().get_sliding_moves_bb(
+            index,
+            occ_or_not_in_bounds,
+            inverse_attack.attack_north,
+            inverse_attack.attack_east,
+            inverse_attack.attack_south,
+            inverse_attack.attack_west,
+            inverse_attack.attack_northeast,
+            inverse_attack.attack_northwest,
+            inverse_attack.attack_southeast,
+            inverse_attack.attack_southwest
+        );
+        slides &= &candidates;
+        while let Some(piece_index) = slides.lowest_one() {
+            if MoveGen::slide_targets_coords(x, y, pieces.piece_at(piece_index).unwrap(), piece_index) {
+                attackers.set_bit(piece_index);
+            }
+            slides.clear_bit(piece_index);
+        }
+
+        let mut jump_attacks = jumps & &candidates;
+        while let Some(piece_index) = jump_attacks.lowest_one() {
+            if pieces.piece_at(piece_index).unwrap().get_capture_jumps(piece_index).get_bit(index) {
+                attackers.set_bit(piece_index);
+            }
+            jump_attacks.clear_bit(piece_index);
+        }
+
+        for run in &inverse_attack.attack_sliding_deltas {
+            for (dx, dy) in run {
+                let (x2, y2) = (x as i8 + *dx, y as i8 + *dy);
+                if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+                    break;
+                }
+                let to = to_index(x2 as BCoord, y2 as BCoord);
+                if candidates.get_bit(to) {
+                    let piece = pieces.piece_at(to).unwrap();
+                    if MoveGen::sliding_delta_targets_index(piece, to, index, occ_or_not_in_bounds) {
+                        attackers.set_bit(to);
+                    }
+                }
+                if occ_or_not_in_bounds.get_bit(to) {
+                    break;
+                }
+            }
+        }
+        attackers
+    }
+
+    fn slide_targets_coords<const W: usize>(x: BCoord, y: BCoord, piece: &Piece<W>, piece_index: BIndex) -> bool {
         // We already know that this piece is on the same rank, file, diagonal or antidiagonal as the target (x, y)
         let piece_movement = piece.get_movement();
         let (px, py) = from_index(piece_index);
@@ -228,7 +414,7 @@ impl MoveGen {
             else { piece_movement.attack_southwest }
         }
     }
-    fn sliding_delta_targets_index(piece: &Piece, piece_index: BIndex, target_index: BIndex, occ_or_not_in_bounds: &Bitboard) -> bool {
+    fn sliding_delta_targets_index<const W: usize>(piece: &Piece<W>, piece_index: BIndex, target_index: BIndex, occ_or_not_in_bounds: &Bitboard<W>) -> bool {
         let piece_movement = piece.get_movement();
         let (x, y) = from_index(piece_index);
         for run in &piece_movement.attack_sliding_deltas {
@@ -253,7 +439,7 @@ impl MoveGen {
 
 
 /// Returns true if an explosion in this coordinates would kill all the remaining enemy leaders
-fn explosion_kills_enemy(index: BIndex, enemy_pieces: &PieceSet, enemy_piece: &Piece, enemy_piece_index: u8) -> bool {
+fn explosion_kills_enemy<const W: usize>(index: BIndex, enemy_pieces: &PieceSet<W>, enemy_piece: &Piece<W>, enemy_piece_index: u8) -> bool {
     if let Some(enemy_leader) = enemy_pieces.get_leader() {
         let mut killed_enemy_leaders = 0;
         let affected_squares = enemy_leader.get_bitboard() & enemy_piece.get_explosion(index);
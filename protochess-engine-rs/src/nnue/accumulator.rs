@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use crate::{PieceId, Position};
+use crate::types::{BIndex, Centipawns, Player};
+
+use super::network::{Network, leader_bucket};
+
+/// Hidden values of the network for a position, updated incrementally when pieces are added, removed or moved.
+/// There is one entry per move, so that `unmake_move()` only needs to discard the last entry.
+#[derive(Debug, Clone)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    // Piece type (in the network) of each piece id
+    piece_types: Vec<(PieceId, usize)>,
+    // Each entry contains the hidden values from the point of view of white, followed by the ones of black
+    stack: Vec<f32>,
+    // Leader bucket of each player, for each entry of the stack
+    buckets: Vec<[usize; 2]>,
+}
+
+impl Accumulator {
+    /// Creates the accumulator for a position. The network must be compatible with the position.
+    pub fn new<const W: usize>(network: Arc<Network>, position: &Position<W>) -> Accumulator {
+        let piece_types = position.pieces.iter()
+            .flat_map(|set| set.iter())
+            .map(|piece| (piece.get_piece_id(), network.piece_type(piece.get_piece_id()).unwrap()))
+            .collect();
+        let hidden_size = network.get_hidden_size();
+        let mut accumulator = Accumulator {
+            network,
+            piece_types,
+            stack: vec![0.0; 2 * hidden_size],
+            buckets: vec![[0; 2]],
+        };
+        accumulator.refresh(position);
+        accumulator
+    }
+
+    /// Returns the network used by this accumulator
+    pub fn get_network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Recomputes the current entry from scratch
+    pub fn refresh<const W: usize>(&mut self, position: &Position<W>) {
+        for perspective in 0..2 {
+            self.refresh_perspective(position, perspective);
+        }
+    }
+
+    fn refresh_perspective<const W: usize>(&mut self, position: &Position<W>, perspective: Player) {
+        let network = &self.network;
+        let hidden = Self::top_mut(&mut self.stack, network.get_hidden_size(), perspective);
+        hidden.copy_from_slice(&network.feature_bias);
+        for feature in network.active_features(position, perspective) {
+            network.add_feature(hidden, feature);
+        }
+        self.buckets.last_mut().unwrap()[perspective as usize] = leader_bucket(position, perspective);
+    }
+
+    /// Adds a copy of the current entry to the stack. Call this before modifying the position in `make_move()`.
+    pub fn push(&mut self) {
+        let entry_size = 2 * self.network.get_hidden_size();
+        self.stack.extend_from_within(self.stack.len() - entry_size..);
+        self.buckets.push(*self.buckets.last().unwrap());
+    }
+
+    /// Discards the current entry. Call this when undoing a move.
+    pub fn pop(&mut self) {
+        let entry_size = 2 * self.network.get_hidden_size();
+        self.stack.truncate(self.stack.len() - entry_size);
+        self.buckets.pop();
+    }
+
+    /// Updates the current entry after adding a piece to the board
+    pub fn add_piece(&mut self, owner: Player, piece_id: PieceId, index: BIndex) {
+        self.update_piece(owner, piece_id, index, true);
+    }
+
+    /// Updates the current entry after removing a piece from the board
+    pub fn remove_piece(&mut self, owner: Player, piece_id: PieceId, index: BIndex) {
+        self.update_piece(owner, piece_id, index, false);
+    }
+
+    /// Updates the current entry after moving a piece
+    pub fn move_piece(&mut self, owner: Player, piece_id: PieceId, from: BIndex, to: BIndex) {
+        self.update_piece(owner, piece_id, from, false);
+        self.update_piece(owner, piece_id, to, true);
+    }
+
+    /// Recomputes the hidden values of the players whose leader has changed bucket.
+    /// Call this after all the pieces of a move have been updated.
+    pub fn update_buckets<const W: usize>(&mut self, position: &Position<W>) {
+        for perspective in 0..2 {
+            if leader_bucket(position, perspective) != self.buckets.last().unwrap()[perspective as usize] {
+                self.refresh_perspective(position, perspective);
+            }
+        }
+    }
+
+    /// Returns the evaluation of the current entry, for the player to move
+    pub fn evaluate<const W: usize>(&self, position: &Position<W>) -> Centipawns {
+        let hidden_size = self.network.get_hidden_size();
+        let entry = &self.stack[self.stack.len() - 2 * hidden_size..];
+        let (white, black) = entry.split_at(hidden_size);
+        let output = {
+            if position.whos_turn == 0 { self.network.output(white, black) }
+            else { self.network.output(black, white) }
+        };
+        self.network.output_to_score(output)
+    }
+
+    #[inline]
+    fn update_piece(&mut self, owner: Player, piece_id: PieceId, index: BIndex, add: bool) {
+        let piece_type = self.piece_types.iter().find(|(id, _)| *id == piece_id).unwrap().1;
+        let buckets = *self.buckets.last().unwrap();
+        let network = &self.network;
+        for perspective in 0..2 {
+            let feature = network.feature(buckets[perspective as usize], perspective, owner, piece_type, index);
+            let hidden = Self::top_mut(&mut self.stack, network.get_hidden_size(), perspective);
+            if add {
+                network.add_feature(hidden, feature);
+            } else {
+                network.remove_feature(hidden, feature);
+            }
+        }
+    }
+
+    /// Returns the hidden values of a player in the current entry
+    #[inline]
+    fn top_mut(stack: &mut [f32], hidden_size: usize, perspective: Player) -> &mut [f32] {
+        let start = stack.len() - (2 - perspective as usize) * hidden_size;
+        &mut stack[start..start + hidden_size]
+    }
+}
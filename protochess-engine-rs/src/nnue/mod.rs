@@ -0,0 +1,12 @@
+// Efficiently updatable neural network (NNUE) evaluation for custom variants.
+// The input features are (leader bucket, piece type, owner, square) from the point of view of each player, similar to
+// HalfKP. Instead of the exact square of the king, the leader only selects one of a few buckets (board quadrant), so
+// that the number of features doesn't depend on the board size. The sums of the first layer (accumulators) are
+// updated incrementally in `Position::make_move()`, and the rest of the network is evaluated on each call.
+
+mod network;
+mod accumulator;
+pub mod trainer;
+
+pub use network::{Network, NUM_BUCKETS, SCORE_SCALE};
+pub use accumulator::Accumulator;
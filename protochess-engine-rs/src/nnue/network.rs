@@ -0,0 +1,251 @@
+use std::convert::TryInto;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{InitialState, PieceId, Position, wrap_res, err_assert, err};
+use crate::types::{BCoord, BIndex, Centipawns, Player};
+use crate::utils::{from_index, to_index};
+use crate::utils::variant_hash::variant_hash;
+
+/// Number of leader buckets: one for each quadrant of the board, and one for players without a single leader
+pub const NUM_BUCKETS: usize = 5;
+/// Centipawns per unit of output of the network. The output is trained as the logit of the win probability,
+/// so that `SCORE_SCALE` centipawns correspond to the same probability as in Texel's tuning (`1 / (1 + 10^(-score/400))`).
+pub const SCORE_SCALE: f32 = 400.0 / std::f32::consts::LN_10;
+/// The evaluation is clamped to this value, in order not to be confused with a won position
+const MAX_SCORE: f32 = 50_000.0;
+/// First bytes of a network file. Change it when the format of the file or of `variant_hash()` changes.
+const MAGIC: &[u8; 5] = b"PCNN2";
+
+/// Neural network with a single hidden layer, shared by both players. The hidden values of the player to move and
+/// of the opponent are clipped to [0, 1] and combined by the output layer.
+/// Use `Network::new()` to create an untrained network, and `nnue::trainer::Trainer` to train it.
+#[must_use]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    // Hash of the piece definitions and rules of the variant
+    variant_hash: u64,
+    // Ids (for white and black) of each piece type, in the order used for the features
+    piece_types: Vec<[Option<PieceId>; 2]>,
+    width: BCoord,
+    height: BCoord,
+    hidden_size: usize,
+    // One row of `hidden_size` weights for each feature
+    pub(super) feature_weights: Vec<f32>,
+    pub(super) feature_bias: Vec<f32>,
+    // Weights for the hidden values of the player to move, followed by the ones of the opponent
+    pub(super) output_weights: Vec<f32>,
+    pub(super) output_bias: f32,
+}
+
+impl Network {
+    /// Creates a network for a variant, with random weights
+    pub fn new(variant: &InitialState, hidden_size: usize, seed: u64) -> Network {
+        let piece_types = variant.piece_types.iter().map(|definition| definition.ids).collect::<Vec<_>>();
+        let num_features = NUM_BUCKETS * piece_types.len() * 2 * 256;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut random = |range: f32| rng.gen_range(-range, range);
+        Network {
+            variant_hash: variant_hash(variant.piece_types.iter(), &variant.global_rules, &[]),
+            piece_types,
+            width: variant.board_width,
+            height: variant.board_height,
+            hidden_size,
+            feature_weights: (0..num_features * hidden_size).map(|_| random(0.05)).collect(),
+            feature_bias: vec![0.5; hidden_size],
+            output_weights: (0..2 * hidden_size).map(|_| random(0.1)).collect(),
+            output_bias: 0.0,
+        }
+    }
+
+    /// Returns `true` if the network was created for the variant of the position (same board size, pieces and rules)
+    pub fn is_compatible<const W: usize>(&self, position: &Position<W>) -> bool {
+        let definitions = position.pieces.iter().flat_map(|set| set.iter()).map(|piece| piece.get_movement());
+        position.dimensions.width == self.width && position.dimensions.height == self.height &&
+        variant_hash(definitions, &position.global_rules, &[]) == self.variant_hash
+    }
+
+    /// Returns the number of values in the hidden layer (for each player)
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    /// Returns the number of input features
+    pub fn num_features(&self) -> usize {
+        self.feature_weights.len() / self.hidden_size
+    }
+
+    /// Evaluates the position from scratch, for the player to move. The network must be compatible with the position.
+    /// During the search, use `Accumulator::evaluate()` instead.
+    pub fn evaluate<const W: usize>(&self, position: &Position<W>) -> Centipawns {
+        let mut hidden = [self.feature_bias.clone(), self.feature_bias.clone()];
+        for (perspective, values) in hidden.iter_mut().enumerate() {
+            for feature in self.active_features(position, perspective as Player) {
+                self.add_feature(values, feature);
+            }
+        }
+        let player = position.whos_turn as usize;
+        self.output_to_score(self.output(&hidden[player], &hidden[1 - player]))
+    }
+
+    /// Returns the index of the piece type with the given id, if it exists
+    pub(super) fn piece_type(&self, id: PieceId) -> Option<usize> {
+        self.piece_types.iter().position(|ids| ids.contains(&Some(id)))
+    }
+
+    /// Returns the feature of a piece at `index` owned by `owner`, from the point of view of `perspective`.
+    /// The board is flipped vertically for black, so that both players see their own pieces at the bottom.
+    #[inline]
+    pub(super) fn feature(&self, bucket: usize, perspective: Player, owner: Player, piece_type: usize, index: BIndex) -> usize {
+        let (x, y) = from_index(index);
+        let y = if perspective == 0 { y } else { self.height - 1 - y };
+        let enemy = (owner != perspective) as usize;
+        ((bucket * self.piece_types.len() + piece_type) * 2 + enemy) * 256 + to_index(x, y) as usize
+    }
+
+    /// Returns the features of all the pieces on the board, from the point of view of `perspective`
+    pub(super) fn active_features<const W: usize>(&self, position: &Position<W>, perspective: Player) -> Vec<usize> {
+        let bucket = leader_bucket(position, perspective);
+        let mut features = Vec::with_capacity(64);
+        for (owner, set) in position.pieces.iter().enumerate() {
+            for piece in set.iter() {
+                let piece_type = self.piece_type(piece.get_piece_id()).unwrap();
+                let mut bb = piece.get_bitboard().clone();
+                while let Some(index) = bb.lowest_one() {
+                    bb.clear_bit(index);
+                    features.push(self.feature(bucket, perspective, owner as Player, piece_type, index));
+                }
+            }
+        }
+        features
+    }
+
+    #[inline]
+    pub(super) fn add_feature(&self, hidden: &mut [f32], feature: usize) {
+        let row = &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size];
+        hidden.iter_mut().zip(row).for_each(|(h, w)| *h += w);
+    }
+
+    #[inline]
+    pub(super) fn remove_feature(&self, hidden: &mut [f32], feature: usize) {
+        let row = &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size];
+        hidden.iter_mut().zip(row).for_each(|(h, w)| *h -= w);
+    }
+
+    /// Computes the output of the network from the hidden values of the player to move and of the opponent
+    #[inline]
+    pub(super) fn output(&self, own: &[f32], opponent: &[f32]) -> f32 {
+        let (own_weights, opponent_weights) = self.output_weights.split_at(self.hidden_size);
+        let sum = |hidden: &[f32], weights: &[f32]| -> f32 {
+            hidden.iter().zip(weights).map(|(h, w)| h.clamp(0.0, 1.0) * w).sum()
+        };
+        self.output_bias + sum(own, own_weights) + sum(opponent, opponent_weights)
+    }
+
+    pub(super) fn output_to_score(&self, output: f32) -> Centipawns {
+        (output * SCORE_SCALE).clamp(-MAX_SCORE, MAX_SCORE).round() as Centipawns
+    }
+
+    /// Serializes the network. Use `from_bytes()` to load it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.variant_hash.to_be_bytes());
+        bytes.push(self.width);
+        bytes.push(self.height);
+        bytes.extend_from_slice(&(self.piece_types.len() as u32).to_be_bytes());
+        for ids in &self.piece_types {
+            for id in ids {
+                // 0 is not a valid piece id, use it for pieces that are not available for a player
+                let id = id.map(|id| id as u32).unwrap_or(0);
+                bytes.extend_from_slice(&id.to_be_bytes());
+            }
+        }
+        bytes.extend_from_slice(&(self.hidden_size as u32).to_be_bytes());
+        let values = self.feature_weights.iter().chain(&self.feature_bias).chain(&self.output_weights);
+        for value in values.chain(std::iter::once(&self.output_bias)) {
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Loads a network from the contents of a file created with `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> wrap_res!(Network) {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        err_assert!(reader.take(MAGIC.len())? == MAGIC, "Invalid network file");
+        let variant_hash = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let width = reader.take(1)?[0];
+        let height = reader.take(1)?[0];
+        let num_types = reader.read_u32()? as usize;
+        err_assert!(num_types <= 256, "Invalid network file: too many piece types");
+        let mut piece_types = Vec::with_capacity(num_types);
+        for _ in 0..num_types {
+            let mut ids = [None; 2];
+            for id in &mut ids {
+                *id = match reader.read_u32()? {
+                    0 => None,
+                    code => match char::from_u32(code) {
+                        Some(c) => Some(c),
+                        None => err!("Invalid network file: invalid piece id"),
+                    },
+                };
+            }
+            piece_types.push(ids);
+        }
+        let hidden_size = reader.read_u32()? as usize;
+        err_assert!(hidden_size > 0 && hidden_size <= 4096, "Invalid network file: invalid hidden layer size");
+        let num_features = NUM_BUCKETS * num_types * 2 * 256;
+        let network = Network {
+            variant_hash,
+            piece_types,
+            width,
+            height,
+            hidden_size,
+            feature_weights: reader.read_f32s(num_features * hidden_size)?,
+            feature_bias: reader.read_f32s(hidden_size)?,
+            output_weights: reader.read_f32s(2 * hidden_size)?,
+            output_bias: reader.read_f32s(1)?[0],
+        };
+        err_assert!(reader.pos == bytes.len(), "Invalid network file: unexpected data at the end");
+        Ok(network)
+    }
+}
+
+/// Returns the bucket of the leader of `perspective`: the quadrant of the board where it is (as seen by that player),
+/// or `NUM_BUCKETS - 1` if the player doesn't have exactly 1 leader
+pub(super) fn leader_bucket<const W: usize>(position: &Position<W>, perspective: Player) -> usize {
+    let leader = match position.pieces[perspective as usize].get_leader() {
+        Some(leader) if leader.get_num_pieces() == 1 => leader,
+        _ => return NUM_BUCKETS - 1,
+    };
+    let (x, y) = from_index(leader.get_bitboard().lowest_one().unwrap());
+    let height = position.dimensions.height;
+    let y = if perspective == 0 { y } else { height - 1 - y };
+    (2 * x >= position.dimensions.width) as usize + 2 * (2 * y >= height) as usize
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> wrap_res!(&'a [u8]) {
+        err_assert!(self.pos + n <= self.bytes.len(), "Invalid network file: unexpected end of file");
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+    fn read_u32(&mut self) -> wrap_res!(u32) {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn read_f32s(&mut self, n: usize) -> wrap_res!(Vec<f32>) {
+        let size = match n.checked_mul(4) {
+            Some(size) => size,
+            None => err!("Invalid network file: too many weights"),
+        };
+        let bytes = self.take(size)?;
+        Ok(bytes.chunks_exact(4).map(|chunk| f32::from_be_bytes(chunk.try_into().unwrap())).collect())
+    }
+}
@@ -0,0 +1,144 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+
+use crate::position::create::position_factory::PositionFactory;
+use crate::searcher::eval;
+use crate::utils::texel::LabeledPosition;
+use crate::{wrap_res, err, err_assert, GameState, InitialState};
+
+use super::network::Network;
+use super::SCORE_SCALE;
+
+/// Input features and target of a training position
+struct Sample {
+    // Active features from the point of view of the player to move, and of the opponent
+    features: [Vec<u32>; 2],
+    // Expected win probability of the player to move
+    target: f32,
+}
+
+/// Trains a network with stochastic gradient descent, minimizing the squared error between the win probability
+/// predicted by the network and a target. The target is a mix of the result of the game and the win probability
+/// predicted by the handcrafted evaluation of the engine, which helps when there are few games.
+/// The positions can be generated with `texel::generate_positions()`.
+pub struct Trainer {
+    network: Network,
+    samples: Vec<Sample>,
+    rng: StdRng,
+}
+
+impl Trainer {
+    /// Creates a trainer for a network of the given variant. All the positions must be of that variant.
+    /// `eval_weight` is the weight of the handcrafted evaluation in the target (between 0 and 1).
+    pub fn new(variant: &InitialState, data: &[LabeledPosition], network: Network, eval_weight: f32, seed: u64)
+        -> wrap_res!(Trainer)
+    {
+        err_assert!(!data.is_empty(), "The training set is empty");
+        err_assert!((0.0..=1.0).contains(&eval_weight), "The weight of the evaluation must be between 0 and 1");
+        let mut factory = PositionFactory::default();
+        let mut samples = Vec::with_capacity(data.len());
+        for labeled in data {
+            let state = GameState {
+                initial_state: variant.clone(),
+                initial_fen: Some(labeled.fen.clone()),
+                move_history: vec![],
+            };
+            let position = match factory.set_state(state, None) {
+                Ok(position) => position.unwrap(),
+                Err(e) => err!("Invalid position '{}': {e}", labeled.fen),
+            };
+            err_assert!(network.is_compatible(&position), "The network is not compatible with '{}'", labeled.fen);
+            let player = position.whos_turn;
+            let result = if player == 0 { labeled.result } else { 1.0 - labeled.result } as f32;
+            let eval_probability = sigmoid(eval::evaluate(&position) as f32 / SCORE_SCALE);
+            let features = [player, 1 - player].map(|perspective| {
+                network.active_features(&position, perspective).into_iter().map(|f| f as u32).collect()
+            });
+            samples.push(Sample { features, target: eval_weight * eval_probability + (1.0 - eval_weight) * result });
+        }
+        Ok(Trainer { network, samples, rng: StdRng::seed_from_u64(seed) })
+    }
+
+    /// Returns the number of positions in the training set
+    pub fn num_positions(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns the network being trained
+    pub fn get_network(&self) -> &Network {
+        &self.network
+    }
+
+    /// Returns the mean squared error of the network on the training set
+    pub fn error(&self) -> f32 {
+        let total: f32 = self.samples.iter().map(|sample| {
+            let (_, output) = self.forward(sample);
+            (sigmoid(output) - sample.target).powi(2)
+        }).sum();
+        total / self.samples.len() as f32
+    }
+
+    /// Runs one pass over the training set (in random order), updating the weights after each position.
+    /// Returns the mean squared error of the network during the pass.
+    pub fn train_epoch(&mut self, learning_rate: f32) -> f32 {
+        let mut order: Vec<usize> = (0..self.samples.len()).collect();
+        order.shuffle(&mut self.rng);
+        let mut total = 0.0;
+        for i in order {
+            total += self.train_sample(i, learning_rate);
+        }
+        total / self.samples.len() as f32
+    }
+
+    /// Updates the weights with the gradient of the error for a single position. Returns the error before the update.
+    fn train_sample(&mut self, index: usize, learning_rate: f32) -> f32 {
+        let sample = &self.samples[index];
+        let (hidden, output) = self.forward(sample);
+        let predicted = sigmoid(output);
+        let error = predicted - sample.target;
+        // Derivative of the squared error with respect to the output
+        let gradient = 2.0 * error * predicted * (1.0 - predicted);
+
+        let network = &mut self.network;
+        let hidden_size = network.get_hidden_size();
+        network.output_bias -= learning_rate * gradient;
+        let mut bias_gradient = vec![0.0; hidden_size];
+        for (perspective, values) in hidden.iter().enumerate() {
+            let offset = perspective * hidden_size;
+            let mut hidden_gradient = vec![0.0; hidden_size];
+            for i in 0..hidden_size {
+                let weight = &mut network.output_weights[offset + i];
+                // The gradient only flows through the values that are not clipped
+                if values[i] > 0.0 && values[i] < 1.0 {
+                    hidden_gradient[i] = gradient * *weight;
+                }
+                *weight -= learning_rate * gradient * values[i].clamp(0.0, 1.0);
+            }
+            for &feature in &sample.features[perspective] {
+                let row = &mut network.feature_weights[feature as usize * hidden_size..(feature as usize + 1) * hidden_size];
+                row.iter_mut().zip(&hidden_gradient).for_each(|(w, g)| *w -= learning_rate * g);
+            }
+            bias_gradient.iter_mut().zip(&hidden_gradient).for_each(|(b, g)| *b += g);
+        }
+        network.feature_bias.iter_mut().zip(&bias_gradient).for_each(|(b, g)| *b -= learning_rate * g);
+        error * error
+    }
+
+    /// Returns the hidden values (before clipping) of the player to move and of the opponent, and the output
+    fn forward(&self, sample: &Sample) -> ([Vec<f32>; 2], f32) {
+        let hidden = [0, 1].map(|perspective| {
+            let mut values = self.network.feature_bias.clone();
+            for &feature in &sample.features[perspective] {
+                self.network.add_feature(&mut values, feature as usize);
+            }
+            values
+        });
+        let output = self.network.output(&hidden[0], &hidden[1]);
+        (hidden, output)
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
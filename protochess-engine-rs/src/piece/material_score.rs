@@ -2,14 +2,17 @@ use super::super::PieceDefinition;
 use crate::utils::to_index;
 use crate::MoveGen;
 use crate::types::{Centipawns, BDimensions};
+use crate::searcher::EvalParams;
 
 /// Returns a score value for a piece, given its movement pattern
-pub fn compute_material_score(mp: &PieceDefinition, dims: &BDimensions) -> Centipawns {
+pub fn compute_material_score(mp: &PieceDefinition, dims: &BDimensions, endgame: bool, params: &EvalParams) -> Centipawns {
     // This function is called only once, so it's worth it to implement a more complex scoring system
     // https://www.chessprogramming.org/Point_Value
     
-    const ATTACK_MUL: f32 = 10.0;
-    const TRANSLATE_MUL: f32 = 6.5;
+    let attack_mul = if endgame { params.attack_mul_endgame } else { params.attack_mul };
+    let translate_mul = params.translate_mul;
+    let jump_value = if endgame { params.jump_value_endgame } else { params.jump_value };
+    let promotion_value = if endgame { params.promotion_value_endgame } else { params.promotion_value };
     
     let mut score = 0;
     
@@ -19,63 +22,63 @@ pub fn compute_material_score(mp: &PieceDefinition, dims: &BDimensions) -> Centi
     let antidiag = 1.4 * average_dimension(dims, false, false, false, true);
     
     // 130 centipawns for each direction (Rook is 4*130 = 520 centipawns, Queen is 8*130 = 1040 centipawns)
-    if mp.attack_north { score += (ATTACK_MUL * height) as Centipawns }
-    if mp.attack_south { score += (ATTACK_MUL * height) as Centipawns }
-    if mp.attack_east  { score += (ATTACK_MUL * width) as Centipawns }
-    if mp.attack_west  { score += (ATTACK_MUL * width) as Centipawns }
-    if mp.translate_north { score += (TRANSLATE_MUL * height) as Centipawns }
-    if mp.translate_south { score += (TRANSLATE_MUL * height) as Centipawns }
-    if mp.translate_east  { score += (TRANSLATE_MUL * width) as Centipawns }
-    if mp.translate_west  { score += (TRANSLATE_MUL * width) as Centipawns }
+    if mp.attack_north { score += (attack_mul * height) as Centipawns }
+    if mp.attack_south { score += (attack_mul * height) as Centipawns }
+    if mp.attack_east  { score += (attack_mul * width) as Centipawns }
+    if mp.attack_west  { score += (attack_mul * width) as Centipawns }
+    if mp.translate_north { score += (translate_mul * height) as Centipawns }
+    if mp.translate_south { score += (translate_mul * height) as Centipawns }
+    if mp.translate_east  { score += (translate_mul * width) as Centipawns }
+    if mp.translate_west  { score += (translate_mul * width) as Centipawns }
     
-    if mp.attack_northeast { score += (ATTACK_MUL * diag) as Centipawns }
-    if mp.attack_southwest { score += (ATTACK_MUL * diag) as Centipawns }
-    if mp.attack_northwest { score += (ATTACK_MUL * antidiag) as Centipawns }
-    if mp.attack_southeast { score += (ATTACK_MUL * antidiag) as Centipawns }
-    if mp.translate_northeast { score += (TRANSLATE_MUL * diag) as Centipawns }
-    if mp.translate_southwest { score += (TRANSLATE_MUL * diag) as Centipawns }
-    if mp.translate_northwest { score += (TRANSLATE_MUL * antidiag) as Centipawns }
-    if mp.translate_southeast { score += (TRANSLATE_MUL * antidiag) as Centipawns }
+    if mp.attack_northeast { score += (attack_mul * diag) as Centipawns }
+    if mp.attack_southwest { score += (attack_mul * diag) as Centipawns }
+    if mp.attack_northwest { score += (attack_mul * antidiag) as Centipawns }
+    if mp.attack_southeast { score += (attack_mul * antidiag) as Centipawns }
+    if mp.translate_northeast { score += (translate_mul * diag) as Centipawns }
+    if mp.translate_southwest { score += (translate_mul * diag) as Centipawns }
+    if mp.translate_northwest { score += (translate_mul * antidiag) as Centipawns }
+    if mp.translate_southeast { score += (translate_mul * antidiag) as Centipawns }
     
     let only_able_to_slide = !mp.can_promote() && !mp.can_jump() && !mp.has_sliding_deltas();
     
     // Debuff for being limited to a single color of squares
     if !mp.can_slide_main_direction() && only_able_to_slide {
         // Bishop is 4*130 - 150 = 370 centipawns
-        score -= 150;
+        score -= params.single_color_penalty;
     }
     
     // Debuff for being limited to a single direction
     if mp.can_slide_north_indirectly() && !mp.can_slide_south_indirectly() && only_able_to_slide {
-        score -= 200;
+        score -= params.single_direction_penalty;
     }
     if mp.can_slide_south_indirectly() && !mp.can_slide_north_indirectly() && only_able_to_slide {
-        score -= 200;
+        score -= params.single_direction_penalty;
     }
     if mp.can_slide_east_indirectly() && !mp.can_slide_west_indirectly() && only_able_to_slide {
-        score -= 200;
+        score -= params.single_direction_penalty;
     }
     if mp.can_slide_west_indirectly() && !mp.can_slide_east_indirectly() && only_able_to_slide {
-        score -= 200;
+        score -= params.single_direction_penalty;
     }
     
     // 40 centipawns for each jump (Knight is 8*40 = 320 centipawns)
-    score += (mp.translate_jump_deltas.len() * 20) as Centipawns;
-    score += (mp.attack_jump_deltas.len() * 20) as Centipawns;
+    score += mp.translate_jump_deltas.len() as Centipawns * jump_value;
+    score += mp.attack_jump_deltas.len() as Centipawns * jump_value;
     // 40 centipawns for each delta-based slide group
     for d in mp.translate_sliding_deltas.iter().chain(mp.attack_sliding_deltas.iter()) {
-        score += (d.len() * 20) as Centipawns;
+        score += d.len() as Centipawns * params.sliding_delta_value;
     }
     
     // 40 centipawns for being able to promote
     if mp.can_promote() {
         // Pawn is 20*3 + 40 = 100 centipawns
-        score += 40;
+        score += promotion_value;
     }
     
     if mp.is_leader {
         // Leader piece is 4x the value of the regular piece
-        score *= 4;
+        score *= params.leader_mul;
     }
     
     // Minimum score is 10
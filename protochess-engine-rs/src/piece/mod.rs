@@ -1,7 +1,9 @@
 use rand::rngs::StdRng;
 use rand::{SeedableRng, Rng};
 
-use crate::{types::*, Position};
+use crate::{types::*, Position, MoveGen};
+use crate::utils::{from_index, to_index};
+use crate::searcher::EvalParams;
 
 // Use a unicode character as the id of a piece type
 // Note that in Rust a char is 4 bytes (equivalent to u32)
@@ -24,15 +26,15 @@ use movement::{output_translations, output_captures};
 
 /// Represents a piece type (e.g. 'Rook'). Individual instances of this piece are represented by a 1 in the bitboard
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Piece {
+pub struct Piece<const W: usize = 4> {
     /// Identifier for this piece type for this player (e.g. `'P'` for white pawn)
     id: PieceId,
     /// Info about this piece type
     type_def: PieceDefinition,
     /// Derived from `type_def`
-    precomp: PrecomputedPieceDef,
+    precomp: PrecomputedPieceDef<W>,
     /// Occupancy bitboard
-    bitboard: Bitboard,
+    bitboard: Bitboard<W>,
     /// Player num for the owner of this piece
     player_num: Player,
     /// Zobrist hashes for this piece at each board index
@@ -40,6 +42,8 @@ pub struct Piece {
     
     /// Material score for this piece
     material_score: Centipawns,
+    /// Material score for this piece in the endgame
+    material_score_endgame: Centipawns,
     /// Table of positional scores for this piece
     piece_square_table: Vec<Centipawns>,
     /// Table of positional scores for this piece in the endgame
@@ -49,19 +53,26 @@ pub struct Piece {
     num_pieces: u32,
     /// Result of `num_pieces * material_score`
     total_material_score: Centipawns,
+    /// Result of `num_pieces * material_score_endgame`
+    total_material_score_endgame: Centipawns,
     
     /// Positions at which this piece can castle. Used if `can_castle` or `is_castle_rook` are `true`
-    castle_squares: Bitboard,
+    castle_squares: Bitboard<W>,
 }
 
-impl Piece {
-    pub fn new(definition: PieceDefinition, player_num: Player, dims: &BDimensions) -> Piece {
+impl<const W: usize> Piece<W> {
+    /// Creates a piece type for a player. The zobrist keys of the piece are generated from `zobrist_seed`.
+    pub fn new(definition: PieceDefinition, player_num: Player, dims: &BDimensions<W>, params: &EvalParams, zobrist_seed: u64) -> Piece<W> {
         let id = definition.ids[player_num as usize]
             .expect("Attempted to create piece for a player that does not have access to this piece");
-        let material_score = compute_material_score(&definition, dims);
-        let zobrist_hashes = Piece::random_zobrist(id, player_num);
-        let piece_square_table = compute_piece_square_table(&definition, dims, false);
-        let piece_square_table_endgame = compute_piece_square_table(&definition, dims, true);
+        // The scores count the squares outside the board that block the slides, so they are always computed on
+        // the widest bitboards to be the same for all widths
+        let wide_dims = dims.resize();
+        let material_score = compute_material_score(&definition, &wide_dims, false, params);
+        let material_score_endgame = compute_material_score(&definition, &wide_dims, true, params);
+        let zobrist_hashes = Self::random_zobrist(id, player_num, zobrist_seed);
+        let piece_square_table = compute_piece_square_table(&definition, &wide_dims, false, params);
+        let piece_square_table_endgame = compute_piece_square_table(&definition, &wide_dims, true, params);
         Piece {
             id,
             precomp: PrecomputedPieceDef::from((&definition, dims)),
@@ -69,15 +80,29 @@ impl Piece {
             player_num,
             zobrist_hashes,
             material_score,
+            material_score_endgame,
             piece_square_table,
             piece_square_table_endgame,
             bitboard: Bitboard::zero(),
             num_pieces: 0,
             total_material_score: 0,
+            total_material_score_endgame: 0,
             castle_squares: Bitboard::zero(),
         }
     }
     
+    /// Replaces the material score and piece-square tables with the ones of `other`, which must be
+    /// the same piece type created with different `EvalParams`
+    pub fn copy_scores_from(&mut self, other: &Piece<W>) {
+        debug_assert!(self.id == other.id && self.player_num == other.player_num);
+        self.material_score = other.material_score;
+        self.material_score_endgame = other.material_score_endgame;
+        self.piece_square_table.clone_from(&other.piece_square_table);
+        self.piece_square_table_endgame.clone_from(&other.piece_square_table_endgame);
+        self.total_material_score = self.num_pieces as Centipawns * self.material_score;
+        self.total_material_score_endgame = self.num_pieces as Centipawns * self.material_score_endgame;
+    }
+    
     /// Get the id (char) of this piece type for this player
     pub fn get_piece_id(&self) -> PieceId {
         self.id
@@ -99,7 +124,7 @@ impl Piece {
     }
     
     /// Direct access to the bitboard
-    pub fn get_bitboard(&self) -> &Bitboard {
+    pub fn get_bitboard(&self) -> &Bitboard<W> {
         &self.bitboard
     }
     /// Returns `true` if there is an instance of this piece type at the given index
@@ -138,6 +163,11 @@ impl Piece {
         self.castle_squares.get_bit(index)
     }
     
+    /// Returns `true` if this piece can promote (behaves like a pawn for the halfmove clock)
+    pub fn can_promote(&self) -> bool {
+        self.type_def.can_promote()
+    }
+    
     /// Returns `true` if this piece explodes when it makes a capture
     pub fn explodes_on_capture(&self) -> bool {
         self.type_def.explode_on_capture
@@ -174,6 +204,11 @@ impl Piece {
         self.material_score
     }
     
+    /// Get the material score for 1 unit of this piece in the endgame
+    pub fn get_material_score_endgame(&self) -> Centipawns {
+        self.material_score_endgame
+    }
+    
     /// Returns `true` if this piece is involved in castling (either can castle or is a castle rook)
     pub fn used_in_castling(&self) -> bool {
         self.type_def.can_castle() || self.type_def.is_castle_rook
@@ -207,6 +242,7 @@ impl Piece {
         self.bitboard.set_bit(index);
         self.num_pieces += 1;
         self.total_material_score += self.material_score;
+        self.total_material_score_endgame += self.material_score_endgame;
         
         if set_can_castle && self.used_in_castling() {
             self.castle_squares.set_bit(index);
@@ -224,12 +260,18 @@ impl Piece {
         
         self.num_pieces -= 1;
         self.total_material_score -= self.material_score;
+        self.total_material_score_endgame -= self.material_score_endgame;
         could_castle
     }
     
     /// Get the material score for all current units of this piece
-    pub fn get_material_score_all(&self) -> Centipawns {
-        self.total_material_score
+    #[inline]
+    pub fn get_material_score_all<const ENDGAME: bool>(&self) -> Centipawns {
+        if ENDGAME {
+            self.total_material_score_endgame
+        } else {
+            self.total_material_score
+        }
     }
     
     /// Get the positional score for 1 unit of this piece at the given index
@@ -255,70 +297,135 @@ impl Piece {
     }
     
     /// Get all the non-capture moves that all instances of this piece can make.
-    /// The moves are added to the `out_moves` vector.
-    pub fn output_translations(&self, position: &Position, enemies: &Bitboard,
-        occ_or_not_in_bounds: &Bitboard, out_moves: &mut Vec<Move>)
+    /// The moves are added to `out_moves`.
+    pub fn output_translations(&self, position: &Position<W>, enemies: &Bitboard<W>,
+        occ_or_not_in_bounds: &Bitboard<W>, out_moves: &mut impl MoveSink)
     {
         let mut bb_copy = self.bitboard.clone();
         while let Some(index) = bb_copy.lowest_one() {
-            let can_castle = self.type_def.can_castle() && self.castle_squares.get_bit(index);
-            output_translations(
-                &self.type_def,
-                index,
-                position,
-                enemies,
-                &self.precomp.promotion_squares,
-                occ_or_not_in_bounds,
-                can_castle,
-                &self.precomp.double_jump_squares,
-                &self.precomp.jump_bitboards_translate,
-                &self.type_def.promo_vals[self.player_num as usize],
-                out_moves
-            );
+            self.output_translations_at(index, position, enemies, occ_or_not_in_bounds, out_moves);
             bb_copy.clear_bit(index);
         }
     }
     
+    /// Get the non-capture moves of the instance of this piece at the given index
+    pub fn output_translations_at(&self, index: BIndex, position: &Position<W>, enemies: &Bitboard<W>,
+        occ_or_not_in_bounds: &Bitboard<W>, out_moves: &mut impl MoveSink)
+    {
+        let can_castle = self.type_def.can_castle() && self.castle_squares.get_bit(index);
+        output_translations(
+            &self.type_def,
+            index,
+            position,
+            enemies,
+            &self.precomp.promotion_squares,
+            occ_or_not_in_bounds,
+            can_castle,
+            &self.precomp.double_jump_squares,
+            &self.precomp.jump_bitboards_translate,
+            &self.type_def.promo_vals[self.player_num as usize],
+            out_moves
+        );
+    }
+    
     /// Get all the capture moves that all instances of this piece can make.
-    /// The moves are added to the `out_moves` vector.
-    pub fn output_captures(&self, position: &Position, enemies: &Bitboard,
-        occ_or_not_in_bounds: &Bitboard, out_moves: &mut Vec<Move>)
+    /// The moves are added to `out_moves`.
+    pub fn output_captures(&self, position: &Position<W>, enemies: &Bitboard<W>,
+        occ_or_not_in_bounds: &Bitboard<W>, out_moves: &mut impl MoveSink)
     {
         let mut bb_copy = self.bitboard.clone();
         while let Some(index) = bb_copy.lowest_one() {
-            output_captures(
-                &self.type_def,
-                index,
-                position,
-                enemies,
-                &self.precomp.promotion_squares,
-                occ_or_not_in_bounds,
-                &self.precomp.jump_bitboards_capture[index as usize],
-                &self.type_def.promo_vals[self.player_num as usize],
-                out_moves
-            );
+            self.output_captures_at(index, position, enemies, occ_or_not_in_bounds, out_moves);
             bb_copy.clear_bit(index);
         }
     }
     
+    /// Get the capture moves of the instance of this piece at the given index
+    pub fn output_captures_at(&self, index: BIndex, position: &Position<W>, enemies: &Bitboard<W>,
+        occ_or_not_in_bounds: &Bitboard<W>, out_moves: &mut impl MoveSink)
+    {
+        output_captures(
+            &self.type_def,
+            index,
+            position,
+            enemies,
+            &self.precomp.promotion_squares,
+            occ_or_not_in_bounds,
+            &self.precomp.jump_bitboards_capture[index as usize],
+            &self.type_def.promo_vals[self.player_num as usize],
+            out_moves
+        );
+    }
+    
+    /// Returns the squares attacked by an instance of this piece at the given index,
+    /// including the ones occupied by pieces of the same player
+    pub fn get_attacks(&self, index: BIndex, position: &Position<W>) -> Bitboard<W> {
+        let movement = &self.type_def;
+        let slides = movement.attack_north || movement.attack_east || movement.attack_south || movement.attack_west
+            || movement.attack_northeast || movement.attack_northwest || movement.attack_southeast || movement.attack_southwest;
+        if !slides && movement.attack_sliding_deltas.is_empty() {
+            // Fast path for pieces that only jump (like pawns, knights and kings)
+            return self.precomp.jump_bitboards_capture[index as usize].clone();
+        }
+        let mut attacks = MoveGen::attack_tables().get_sliding_moves_bb(
+            index,
+            &position.occ_or_out_bounds,
+            movement.attack_north,
+            movement.attack_east,
+            movement.attack_south,
+            movement.attack_west,
+            movement.attack_northeast,
+            movement.attack_northwest,
+            movement.attack_southeast,
+            movement.attack_southwest
+        );
+        attacks |= &self.precomp.jump_bitboards_capture[index as usize];
+        let (x, y) = from_index(index);
+        for run in &movement.attack_sliding_deltas {
+            for (dx, dy) in run {
+                let (x2, y2) = (x as i8 + *dx, y as i8 + *dy);
+                if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+                    break;
+                }
+                let to = to_index(x2 as BCoord, y2 as BCoord);
+                attacks.set_bit(to);
+                if position.occ_or_out_bounds.get_bit(to) {
+                    break;
+                }
+            }
+        }
+        attacks &= &position.dimensions.bounds;
+        attacks
+    }
+    
+    /// Returns 1 (north) or -1 (south) if all the moves of this piece advance in that direction (like a pawn), 0 otherwise
+    pub fn get_forward_direction(&self) -> i8 {
+        self.precomp.forward_direction
+    }
+    
     /// Returns the piece definition for this piece type
     pub fn get_movement(&self) -> &PieceDefinition {
         &self.type_def
     }
     /// Look up the precomputed bitboard for the jumps that this piece can make
     /// from the given index.
-    pub fn get_capture_jumps(&self, index: BIndex) -> &Bitboard {
+    pub fn get_capture_jumps(&self, index: BIndex) -> &Bitboard<W> {
         &self.precomp.jump_bitboards_capture[index as usize]
     }
+    /// Look up the precomputed bitboard for the non-capture jumps that this piece can make
+    /// from the given index.
+    pub fn get_translate_jumps(&self, index: BIndex) -> &Bitboard<W> {
+        &self.precomp.jump_bitboards_translate[index as usize]
+    }
     /// Look up the precomputed bitboard for the squares that explode when this
     /// piece explodes at the given index.
-    pub fn get_explosion(&self, index: BIndex) -> &Bitboard {
+    pub fn get_explosion(&self, index: BIndex) -> &Bitboard<W> {
         &self.precomp.explosion_bitboards[index as usize]
     }
     
-    fn random_zobrist(piece_id: PieceId, player: Player) -> Vec<ZobKey> {
-        // Generate a predictable seed for the rng
-        let seed = (player as u64) << 32 | (piece_id as u64);
+    fn random_zobrist(piece_id: PieceId, player: Player, zobrist_seed: u64) -> Vec<ZobKey> {
+        // Generate a predictable seed for the rng. With a zobrist seed of 0, the keys only depend on the piece.
+        let seed = zobrist_seed ^ ((player as u64) << 32 | (piece_id as u64));
         let mut rng = StdRng::seed_from_u64(seed);
         
         let mut zobrist = Vec::with_capacity(256);
@@ -329,7 +436,7 @@ impl Piece {
     }
 }
 
-impl std::fmt::Display for Piece {
+impl<const W: usize> std::fmt::Display for Piece<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}", self.id)
     }
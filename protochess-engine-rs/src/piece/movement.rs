@@ -1,22 +1,22 @@
 use crate::utils::{to_index, from_index};
 use crate::{PieceDefinition, MoveGen, Position, PieceId};
-use crate::types::{Bitboard, Move, MoveType, BCoord, BIndex};
+use crate::types::{Bitboard, Move, MoveSink, MoveType, BCoord, BIndex};
 
 
 /// Outputs all pseudo-legal translation (non-capture) moves for a piece at a given index
 #[allow(clippy::too_many_arguments)]
-pub fn output_translations(
+pub fn output_translations<const W: usize>(
     movement: &PieceDefinition,
     index: BIndex,
-    position: &Position,
-    enemies: &Bitboard,
-    promotion_squares: &Bitboard,
-    occ_or_not_in_bounds: &Bitboard,
+    position: &Position<W>,
+    enemies: &Bitboard<W>,
+    promotion_squares: &Bitboard<W>,
+    occ_or_not_in_bounds: &Bitboard<W>,
     can_castle: bool,
-    double_jump_squares: &Bitboard,
-    jumps_bitboard: &[Bitboard],
+    double_jump_squares: &Bitboard<W>,
+    jumps_bitboard: &[Bitboard<W>],
     promotions: &[PieceId],
-    out_moves: &mut Vec<Move>
+    out_moves: &mut impl MoveSink
 ) {
     let attack_tables = MoveGen::attack_tables();
     
@@ -149,16 +149,16 @@ pub fn output_translations(
 
 /// Outputs all the pseudo-legal capture moves for a piece at a given index
 #[allow(clippy::too_many_arguments)]
-pub fn output_captures(
+pub fn output_captures<const W: usize>(
     movement: &PieceDefinition,
     index: BIndex,
-    position: &Position,
-    enemies: &Bitboard,
-    promotion_squares: &Bitboard,
-    occ_or_not_in_bounds: &Bitboard,
-    jumps_bitboard: &Bitboard,
+    position: &Position<W>,
+    enemies: &Bitboard<W>,
+    promotion_squares: &Bitboard<W>,
+    occ_or_not_in_bounds: &Bitboard<W>,
+    jumps_bitboard: &Bitboard<W>,
     promotions: &[PieceId],
-    out_moves: &mut Vec<Move>
+    out_moves: &mut impl MoveSink
 ) {
     let attack_tables = MoveGen::attack_tables();
     
@@ -231,13 +231,13 @@ pub fn output_captures(
     }
 }
 
-pub fn flatten_bb_moves(
-    enemies: &Bitboard,
-    mut moves: Bitboard,
+pub fn flatten_bb_moves<const W: usize>(
+    enemies: &Bitboard<W>,
+    mut moves: Bitboard<W>,
     from_index: BIndex,
-    promotion_squares: &Bitboard,
+    promotion_squares: &Bitboard<W>,
     promotions: &[PieceId],
-    out_moves: &mut Vec<Move>
+    out_moves: &mut impl MoveSink
 ) {
     while let Some(to) = moves.lowest_one() {
         let promo_here = promotion_squares.get_bit(to);
@@ -263,14 +263,14 @@ pub fn flatten_bb_moves(
         moves.clear_bit(to);
     }
 }
-pub fn flatten_bb_moves_doublejump(
-    mut moves: Bitboard,
+pub fn flatten_bb_moves_doublejump<const W: usize>(
+    mut moves: Bitboard<W>,
     from_index: BIndex,
     ep_square: BIndex,
-    promotion_squares: &Bitboard,
-    double_jump_squares: &Bitboard,
+    promotion_squares: &Bitboard<W>,
+    double_jump_squares: &Bitboard<W>,
     promotions: &[PieceId],
-    out_moves: &mut Vec<Move>
+    out_moves: &mut impl MoveSink
 ) {
     while let Some(to) = moves.lowest_one() {
         if promotion_squares.get_bit(to) {
@@ -1,14 +1,10 @@
 use crate::types::{Centipawns, BIndex, Bitboard, BCoord, BDimensions};
 use crate::utils::{from_index, to_index, distance_to_one};
+use crate::searcher::EvalParams;
 use crate::{MoveGen, PieceDefinition};
 
-const BASE_MULT: Centipawns = 5;
-const EDGE_DIST_MULT: Centipawns = 5;
-const PROMOTION_DIST_MULT: Centipawns = 7;
-const WIN_DIST_MULT: Centipawns = 50;
-
 /// Returns Vec of size 256, each with an integer representing # of moves possible at that location
-pub fn compute_piece_square_table(piece: &PieceDefinition, dims: &BDimensions, endgame: bool) -> Vec<Centipawns> {
+pub fn compute_piece_square_table(piece: &PieceDefinition, dims: &BDimensions, endgame: bool, params: &EvalParams) -> Vec<Centipawns> {
     let mut return_vec = Vec::with_capacity(256);
     let center_squares_bb = get_center_squares(dims.width, dims.height);
     // Keep promotion squares in bounds
@@ -32,30 +28,30 @@ pub fn compute_piece_square_table(piece: &PieceDefinition, dims: &BDimensions, e
         moves &= &center_squares_bb;
         
         // 1 point for each move that lands on a center square
-        inv_score += moves.count_ones() as Centipawns * BASE_MULT;
+        inv_score += moves.count_ones() as Centipawns * params.center_mul;
         
         // 1 point for being 1 square away from the edge (prefer to occupy the center)
         let delta_x = std::cmp::min(x, dims.width - x - 1);
         let delta_y = std::cmp::min(y, dims.height - y - 1);
         
         let distance_from_edge = std::cmp::min(delta_x, delta_y);
-        inv_score += distance_from_edge as Centipawns * EDGE_DIST_MULT;
+        inv_score += distance_from_edge as Centipawns * params.edge_dist_mul;
         
         // Extra points for being close to promotion
         let avg_board = ((dims.width + dims.height) / 2) as isize; 
         let half_board = avg_board / 2; 
-        abs_score += points_for_distance_to_one(x, y, piece, dims, &promotion_squares_bb, half_board, PROMOTION_DIST_MULT);
+        abs_score += points_for_distance_to_one(x, y, piece, dims, &promotion_squares_bb, half_board, params.promotion_dist_mul);
         
         // Extra points for being close to a win square
-        abs_score += points_for_distance_to_one(x, y, piece, dims, &win_squares_bb, avg_board, WIN_DIST_MULT);
+        abs_score += points_for_distance_to_one(x, y, piece, dims, &win_squares_bb, avg_board, params.win_dist_mul);
         
         // Extra points for castling a leader
         if piece.is_leader && !endgame && piece.can_castle() && (y == 0 || y == dims.height - 1) {
             let (queenside_x, kingside_x) = piece.castle_files.unwrap();
             if x == queenside_x || x == kingside_x {
-                abs_score += 40;
+                abs_score += params.castled_bonus;
             } else if x < queenside_x || x > kingside_x {
-                abs_score += 20;
+                abs_score += params.castled_side_bonus;
             }
         }
         
@@ -75,10 +71,10 @@ fn get_center_squares(width: BCoord, height: BCoord) -> Bitboard {
     // For odd dimensions, the center is the 9 squares in the middle
     let x1 = width / 2 - 1;
     let x2 = width / 2;
-    let x3 = (width + 1) / 2;
+    let x3 = width.div_ceil(2);
     let y1 = height / 2 - 1;
     let y2 = height / 2;
-    let y3 = (height + 1) / 2;
+    let y3 = height.div_ceil(2);
     
     center_squares.set_bit_at(x1, y1);
     center_squares.set_bit_at(x1, y2);
@@ -4,24 +4,27 @@ use crate::utils::from_index;
 
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct PrecomputedPieceDef {
+pub struct PrecomputedPieceDef<const W: usize = 4> {
     // Positions at which this piece can promote
-    pub promotion_squares: Bitboard,
+    pub promotion_squares: Bitboard<W>,
     // Positions at which this piece can double jump
-    pub double_jump_squares: Bitboard,
+    pub double_jump_squares: Bitboard<W>,
     // Positions at which the game is won instantly
-    pub instant_win_squares: Bitboard,
+    pub instant_win_squares: Bitboard<W>,
     
     // Jump bitboards for this piece
-    pub jump_bitboards_translate: Vec<Bitboard>,
-    pub jump_bitboards_capture: Vec<Bitboard>,
+    pub jump_bitboards_translate: Vec<Bitboard<W>>,
+    pub jump_bitboards_capture: Vec<Bitboard<W>>,
     
     // Explosion bitboards for this piece
-    pub explosion_bitboards: Vec<Bitboard>,
+    pub explosion_bitboards: Vec<Bitboard<W>>,
+    
+    // 1 (north) or -1 (south) if all the moves of this piece advance in that direction (like a pawn), 0 otherwise
+    pub forward_direction: i8,
 }
 
-impl From<(&PieceDefinition, &BDimensions)> for PrecomputedPieceDef {
-    fn from((definition, dims): (&PieceDefinition, &BDimensions)) -> Self {
+impl<const W: usize> From<(&PieceDefinition, &BDimensions<W>)> for PrecomputedPieceDef<W> {
+    fn from((definition, dims): (&PieceDefinition, &BDimensions<W>)) -> Self {
         PrecomputedPieceDef { 
             promotion_squares: Bitboard::from_coord_list(&definition.promotion_squares) & &dims.bounds,
             double_jump_squares: Bitboard::from_coord_list(&definition.double_jump_squares) & &dims.bounds,
@@ -29,12 +32,28 @@ impl From<(&PieceDefinition, &BDimensions)> for PrecomputedPieceDef {
             jump_bitboards_translate: Self::precompute_jumps(&definition.translate_jump_deltas, dims),
             jump_bitboards_capture: Self::precompute_jumps(&definition.attack_jump_deltas, dims),
             explosion_bitboards: Self::precompute_jumps(&definition.explosion_deltas, dims),
+            forward_direction: Self::forward_direction(definition),
         }
     }
 }
 
-impl PrecomputedPieceDef {
-    fn precompute_jumps(deltas: &Vec<(i8, i8)>, dims: &BDimensions) -> Vec<Bitboard> {
+impl<const W: usize> PrecomputedPieceDef<W> {
+    fn forward_direction(definition: &PieceDefinition) -> i8 {
+        let mut vertical_steps: Vec<i8> = Vec::new();
+        let jumps = definition.translate_jump_deltas.iter().chain(definition.attack_jump_deltas.iter());
+        let slides = definition.translate_sliding_deltas.iter().chain(definition.attack_sliding_deltas.iter()).flatten();
+        vertical_steps.extend(jumps.chain(slides).map(|(_, dy)| dy.signum()));
+        if definition.can_slide_north_indirectly() { vertical_steps.push(1); }
+        if definition.can_slide_south_indirectly() { vertical_steps.push(-1); }
+        if definition.can_slide_east() || definition.can_slide_west() { vertical_steps.push(0); }
+        
+        match vertical_steps.first() {
+            Some(&direction) if direction != 0 && vertical_steps.iter().all(|&d| d == direction) => direction,
+            _ => 0,
+        }
+    }
+    
+    fn precompute_jumps(deltas: &Vec<(i8, i8)>, dims: &BDimensions<W>) -> Vec<Bitboard<W>> {
         let mut jumps = Vec::with_capacity(256);
         for index in 0..=255 {
             let mut jump = Bitboard::zero();
@@ -40,8 +40,10 @@ pub struct FenData {
     /// Number of times each player has been in check. `None` means that this information is not available
     /// in the FEN string (not aplicable to this variant): assume that no player has been in check (`[0,0]`).
     pub times_in_check: Option<[u8; 2]>,
-    
-    // Fullmove and halfmove clocks are not used
+    /// Number of plies since the last capture or pawn move. Defaults to `0`.
+    pub halfmove_clock: u32,
+    /// Number of the current move, starting at 1 and incremented after each move of black. Defaults to `1`.
+    pub fullmove_number: u32,
 }
 
 
@@ -54,48 +56,14 @@ impl FenData {
             err!("Invalid FEN string, it must have at least 1 part");
         }
         
-        // Count the number of ranks
-        let board_height = fen_parts[0].chars().filter(|c| *c == '/').count() + 1;
-        err_assert!(board_height <= 16, "The FEN string has {board_height} ranks, but the limit is 16");
-        
         // Piece placement
-        let mut piece_placements = Vec::new();
-        let mut walls = Vec::new();
-        let mut x = 0;
-        let mut y = board_height as BCoord - 1;
-        let mut skip_x = 0;
-        let mut board_width = 0;
-        for c in fen_parts[0].chars() {
-            if c == '/' {
-                board_width = std::cmp::max(board_width, x + skip_x);
-                x = 0;
-                y -= 1;
-                skip_x = 0;
-                continue;
-            } else if c.is_ascii_digit() {
-                skip_x = 10 * skip_x + c.to_digit(10).unwrap();
-                continue;
-            }
-            x += skip_x;
-            skip_x = 0;
-            if c == '*' {
-                walls.push((x as BCoord, y));
-            } else {
-                piece_placements.push(PiecePlacement { x: x as BCoord, y, piece_id: c });
-            }
-            x += 1;
-        }
-        board_width = std::cmp::max(board_width, x + skip_x);
-        err_assert!(board_width <= 16, "The FEN string has too many files ({board_width} > 16)");
-        
-        let board_width = board_width as BCoord;
-        let board_height = board_height as BCoord;
+        let (board_width, board_height, piece_placements, walls) = parse_piece_placement(fen_parts[0])?;
         
         // Player to move
         let player_to_move = {
             // By default, white moves first
-            if fen_parts.len() <= 1 || fen_parts[1].to_ascii_lowercase() == "w" { 0 }
-            else if fen_parts[1].to_ascii_lowercase() == "b" { 1 }
+            if fen_parts.len() <= 1 || fen_parts[1].eq_ignore_ascii_case("w") { 0 }
+            else if fen_parts[1].eq_ignore_ascii_case("b") { 1 }
             else { err!("The player to move must be 'w' or 'b'") }
         };
         
@@ -113,56 +81,26 @@ impl FenData {
             if fen_parts.len() <= 3 || fen_parts[3] == "-" {
                 None
             } else {
-                // Expected formats: a1, a1(b2)
-                const EXPECTED_REGEX: &str = r"^[a-p][0-9]+(\([a-p][0-9]+\))?$";
-                err_assert!(Regex::new(EXPECTED_REGEX).unwrap().is_match(fen_parts[3]), "Invalid en passant square in FEN string");
-                let (ep_x, ep_y) = match scan_fmt!(fen_parts[3], "{[a-p]}{d}", char, isize) {
-                    Ok(parts) => parts,
-                    Err(_) => err!("Invalid en passant square in FEN string")
-                };
-                let (vic_x, vic_y) = match scan_fmt!(fen_parts[3], "{*[a-p]}{*d}({[a-p]}{d})", char, isize) {
-                    Ok(parts) => parts,
-                    Err(_) => {
-                        // If it's white's turn, the victim is the black pawn that just did a double move
-                        // (victim is 1 square below the EP square).
-                        if player_to_move == 0 { (ep_x, ep_y - 1) }
-                        else { (ep_x, ep_y + 1) }
-                    }
-                };
-                // ep_x and ev_x are guaranteed to be a valid character between 'a' and 'p'
-                let ep_x = ep_x.to_digit(36).unwrap() as BCoord - 10;
-                let ep_y = ep_y - 1;
-                let vic_x = vic_x.to_digit(36).unwrap() as BCoord - 10;
-                let vic_y = vic_y - 1;
-                // Only need to check the y coordinates, since the x coordinates are already guaranteed to be valid
-                err_assert!(ep_y >= 0 && ep_y < board_height as isize, "Invalid en passant square in FEN string");
-                err_assert!(vic_y >= 0 && vic_y < board_height as isize, "Invalid en passant victim in FEN string");
-                
-                Some(((ep_x, ep_y as BCoord), (vic_x, vic_y as BCoord)))
+                Some(parse_ep_square(fen_parts[3], player_to_move, board_height)?)
             }
         };
         
         // Times in check: search all remaining parts for a +W+B format
         let mut times_in_check = None;
+        let mut clocks = Vec::new();
+        let wrong_format_regex = Regex::new(r"^([0-9]+)\+([0-9]+)$").unwrap();
         for part in fen_parts.iter().skip(4) {
-            const TIMES_IN_CHECK_REGEX: &str = r"^\+([0-9]+)\+([0-9]+)$";
-            const WRONG_FORMAT_REGEX: &str = r"^([0-9]+)\+([0-9]+)$";
-            if !Regex::new(TIMES_IN_CHECK_REGEX).unwrap().is_match(part) {
+            if let Some(checks) = parse_check_count(part)? {
+                times_in_check = Some(checks);
+            } else if let Some(clock) = parse_clock(part)? {
+                clocks.push(clock);
+            } else if wrong_format_regex.is_match(part) {
                 // Check if this is an alternative check count format
-                if Regex::new(WRONG_FORMAT_REGEX).unwrap().is_match(part) {
-                    err!("Invalid check count format, use +W+B, where W is the number of times White put Black in check.
-                    In 3-Check, '3+1' is equivalent to '+0+2'");
-                }
-                continue;
+                err!("Invalid check count format, use +W+B, where W is the number of times White put Black in check.
+                In 3-Check, '3+1' is equivalent to '+0+2'");
             }
-            // Some of the parts match the check count format
-            let (white_checks, black_checks) = match scan_fmt!(fen_parts[6], "+{d}+{d}", u8, u8) {
-                Ok(parts) => parts,
-                Err(_) => err!("Invalid check format, make sure it's between +0+0 and +255+255"),
-            };
-            // Important: Note that the order is reversed
-            times_in_check = Some([black_checks, white_checks]);
         }
+        let (halfmove_clock, fullmove_number) = clocks_or_default(&clocks)?;
         
         Ok(Self {
             width: board_width,
@@ -173,11 +111,118 @@ impl FenData {
             player_to_move,
             castling_availability,
             ep_square_and_victim,
+            halfmove_clock,
+            fullmove_number,
         })
     }
 }
 
 
+/// Parses the piece placement field of a FEN string.
+/// Returns the width and height of the board, the pieces and the walls (`'*'`).
+pub(super) fn parse_piece_placement(placement: &str) -> wrap_res!(BCoord, BCoord, Vec<PiecePlacement>, Vec<(BCoord, BCoord)>) {
+    // Count the number of ranks
+    let board_height = placement.chars().filter(|c| *c == '/').count() + 1;
+    err_assert!(board_height <= 16, "The FEN string has {board_height} ranks, but the limit is 16");
+    
+    let mut piece_placements = Vec::new();
+    let mut walls = Vec::new();
+    let mut x = 0;
+    let mut y = board_height as BCoord - 1;
+    let mut skip_x = 0;
+    let mut board_width = 0;
+    for c in placement.chars() {
+        if c == '/' {
+            board_width = std::cmp::max(board_width, x + skip_x);
+            x = 0;
+            y -= 1;
+            skip_x = 0;
+            continue;
+        } else if c.is_ascii_digit() {
+            skip_x = 10 * skip_x + c.to_digit(10).unwrap();
+            continue;
+        }
+        x += skip_x;
+        skip_x = 0;
+        if c == '*' {
+            walls.push((x as BCoord, y));
+        } else {
+            piece_placements.push(PiecePlacement { x: x as BCoord, y, piece_id: c });
+        }
+        x += 1;
+    }
+    board_width = std::cmp::max(board_width, x + skip_x);
+    err_assert!(board_width <= 16, "The FEN string has too many files ({board_width} > 16)");
+    
+    Ok((board_width as BCoord, board_height as BCoord, piece_placements, walls))
+}
+
+/// Parses the en passant field of a FEN string (`a1` or `a1(b2)`).
+/// Returns the EP square and the square of the EP victim.
+pub(super) fn parse_ep_square(ep: &str, player_to_move: Player, board_height: BCoord) -> wrap_res!(((BCoord, BCoord), (BCoord, BCoord))) {
+    // Expected formats: a1, a1(b2)
+    const EXPECTED_REGEX: &str = r"^[a-p][0-9]+(\([a-p][0-9]+\))?$";
+    err_assert!(Regex::new(EXPECTED_REGEX).unwrap().is_match(ep), "Invalid en passant square in FEN string");
+    let (ep_x, ep_y) = match scan_fmt!(ep, "{[a-p]}{d}", char, isize) {
+        Ok(parts) => parts,
+        Err(_) => err!("Invalid en passant square in FEN string")
+    };
+    let (vic_x, vic_y) = match scan_fmt!(ep, "{*[a-p]}{*d}({[a-p]}{d})", char, isize) {
+        Ok(parts) => parts,
+        Err(_) => {
+            // If it's white's turn, the victim is the black pawn that just did a double move
+            // (victim is 1 square below the EP square).
+            if player_to_move == 0 { (ep_x, ep_y - 1) }
+            else { (ep_x, ep_y + 1) }
+        }
+    };
+    // ep_x and ev_x are guaranteed to be a valid character between 'a' and 'p'
+    let ep_x = ep_x.to_digit(36).unwrap() as BCoord - 10;
+    let ep_y = ep_y - 1;
+    let vic_x = vic_x.to_digit(36).unwrap() as BCoord - 10;
+    let vic_y = vic_y - 1;
+    // Only need to check the y coordinates, since the x coordinates are already guaranteed to be valid
+    err_assert!(ep_y >= 0 && ep_y < board_height as isize, "Invalid en passant square in FEN string");
+    err_assert!(vic_y >= 0 && vic_y < board_height as isize, "Invalid en passant victim in FEN string");
+    
+    Ok(((ep_x, ep_y as BCoord), (vic_x, vic_y as BCoord)))
+}
+
+/// Parses a check count in the `+W+B` format, where `W` is the number of times White put Black in check.
+/// Returns `None` if the string is not in this format.
+pub(super) fn parse_check_count(part: &str) -> wrap_res!(Option<[u8; 2]>) {
+    const TIMES_IN_CHECK_REGEX: &str = r"^\+([0-9]+)\+([0-9]+)$";
+    if !Regex::new(TIMES_IN_CHECK_REGEX).unwrap().is_match(part) {
+        return Ok(None);
+    }
+    let (white_checks, black_checks) = match scan_fmt!(part, "+{d}+{d}", u8, u8) {
+        Ok(parts) => parts,
+        Err(_) => err!("Invalid check format, make sure it's between +0+0 and +255+255"),
+    };
+    // Important: Note that the order is reversed
+    Ok(Some([black_checks, white_checks]))
+}
+
+/// Parses a halfmove or fullmove clock. Returns `None` if the string is not a number.
+pub(super) fn parse_clock(part: &str) -> wrap_res!(Option<u32>) {
+    if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    match part.parse::<u32>() {
+        Ok(clock) => Ok(Some(clock)),
+        Err(_) => err!("Invalid move clock in FEN string: '{part}'"),
+    }
+}
+
+/// Converts the parsed clocks (in order: halfmove, fullmove) to the halfmove clock and the fullmove number.
+/// Some FEN strings use `0` as the fullmove number, it's treated as `1`.
+pub(super) fn clocks_or_default(clocks: &[u32]) -> wrap_res!(u32, u32) {
+    err_assert!(clocks.len() <= 2, "The FEN string has too many move clocks");
+    let halfmove_clock = clocks.first().copied().unwrap_or(0);
+    let fullmove_number = clocks.get(1).copied().unwrap_or(1).max(1);
+    Ok((halfmove_clock, fullmove_number))
+}
+
 /// Returns a list of the squares that have not moved
 fn parse_castling(castling: &str, board_height: BCoord, board_width: BCoord) -> wrap_res!(Vec<(BCoord, BCoord)>) {
     if castling.starts_with('(') {
@@ -239,8 +284,8 @@ fn parse_traditional_castling(castling: &str, height: BCoord, width: BCoord) ->
 
 
 /// Extracts the FEN data from a Position, so that it can be converted to a string
-impl From<&Position> for FenData {
-    fn from(pos: &Position) -> Self {
+impl<const W: usize> From<&Position<W>> for FenData {
+    fn from(pos: &Position<W>) -> Self {
         let mut piece_placements = Vec::new();
         let mut castling = Vec::new();
         let mut walls = Vec::new();
@@ -281,16 +326,17 @@ impl From<&Position> for FenData {
             player_to_move: pos.whos_turn,
             castling_availability: Some(castling),
             ep_square_and_victim,
+            halfmove_clock: pos.get_properties().halfmove_clock,
+            fullmove_number: pos.get_properties().fullmove_number,
         }
     }
 }
 
 
 
-/// Outputs the FEN data as a FEN-formatted string
-impl std::fmt::Display for FenData {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // STEP 1: Piece placements
+impl FenData {
+    /// Writes the piece placement field of the FEN string. Walls are written as `'*'`.
+    pub(super) fn write_piece_placement<W: std::fmt::Write>(&self, f: &mut W) -> std::fmt::Result {
         let mut empty_count = 0;
         // For each square in the board
         for y in (0..self.height).rev() {
@@ -331,6 +377,15 @@ impl std::fmt::Display for FenData {
                 write!(f, "/")?;
             }
         }
+        Ok(())
+    }
+}
+
+/// Outputs the FEN data as a FEN-formatted string
+impl std::fmt::Display for FenData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // STEP 1: Piece placements
+        self.write_piece_placement(f)?;
         
         // STEP 2: Player to move
         let player_char = if self.player_to_move == 0 { 'w' } else { 'b' };
@@ -360,7 +415,12 @@ impl std::fmt::Display for FenData {
             write!(f, " -")?;
         }
         
-        // STEP 5: Times in check
+        // STEP 5: Move clocks, only if they are not the default values
+        if self.halfmove_clock != 0 || self.fullmove_number != 1 {
+            write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)?;
+        }
+        
+        // STEP 6: Times in check
         // Important: Note that the order is reversed
         if let Some(times_in_check) = self.times_in_check {
             write!(f, " +{}+{}", times_in_check[1], times_in_check[0])?;
@@ -0,0 +1,318 @@
+use std::convert::TryFrom;
+
+use scan_fmt::scan_fmt;
+
+use crate::utils::notation::tuple_to_rank_file;
+use crate::{wrap_res, err_assert, err, InitialState, PieceId};
+use crate::types::{BCoord, Player};
+
+use super::fen::{FenData, PiecePlacement, parse_piece_placement, parse_ep_square, parse_check_count, parse_clock, clocks_or_default};
+
+
+/// FEN formats that can be converted to and from `FenData`.
+///
+/// Apart from the custom format used by the engine, all dialects store the castling rights and en passant
+/// square in the traditional way. Positions that need the extensions of the custom format (for example,
+/// castling outside the back rank or an en passant victim that is not in front of the en passant square)
+/// cannot be represented and return an error.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenDialect {
+    /// Custom format used by the engine (see `docs/FEN.md`)
+    Protochess,
+    /// Castling rights use `KQkq` for the outermost rooks, and the file of the rook (`Cc`) otherwise
+    XFen,
+    /// Castling rights always use the file of the rook (`HAha`)
+    ShredderFen,
+    /// Same as X-FEN, with walls (`*`), holdings (`[...]`), promoted pieces (`+P`) and remaining checks (`3+3`)
+    FairyStockfish,
+}
+
+impl TryFrom<&str> for FenDialect {
+    type Error = String;
+    fn try_from(value: &str) -> wrap_res!(Self) {
+        match value.to_lowercase().as_str() {
+            "protochess" | "custom" => Ok(FenDialect::Protochess),
+            "xfen" | "x-fen" => Ok(FenDialect::XFen),
+            "shredder" | "shredder-fen" => Ok(FenDialect::ShredderFen),
+            "fairy" | "fairy-stockfish" | "fsf" => Ok(FenDialect::FairyStockfish),
+            _ => err!("Invalid FEN dialect: '{value}'"),
+        }
+    }
+}
+
+impl std::fmt::Display for FenDialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenDialect::Protochess => write!(f, "Protochess FEN"),
+            FenDialect::XFen => write!(f, "X-FEN"),
+            FenDialect::ShredderFen => write!(f, "Shredder-FEN"),
+            FenDialect::FairyStockfish => write!(f, "Fairy-Stockfish FEN"),
+        }
+    }
+}
+
+
+impl FenData {
+    /// Parses a FEN string written in the given dialect.
+    /// The variant (`state`) is used to find the pieces involved in castling and the number of checks to lose.
+    pub fn parse_fen_dialect(fen: &str, dialect: FenDialect, state: &InitialState) -> wrap_res!(Self) {
+        if dialect == FenDialect::Protochess {
+            return Self::parse_fen(fen);
+        }
+        let fen_parts: Vec<&str> = fen.split_whitespace().collect();
+        err_assert!(!fen_parts.is_empty(), "Invalid FEN string, it must have at least 1 part");
+
+        // Piece placement
+        let placement = strip_placement_extensions(fen_parts[0], dialect)?;
+        let (width, height, piece_placements, walls) = parse_piece_placement(&placement)?;
+        err_assert!(walls.is_empty() || dialect == FenDialect::FairyStockfish, "{dialect} does not support walls ('*')");
+
+        // Player to move
+        let player_to_move = match fen_parts.get(1) {
+            None | Some(&"w") => 0,
+            Some(&"b") => 1,
+            Some(_) => err!("The player to move must be 'w' or 'b'"),
+        };
+
+        // Castling rights
+        let castling_availability = match fen_parts.get(2) {
+            None => None,
+            Some(&"-") => Some(vec![]),
+            Some(castling) => Some(parse_dialect_castling(castling, dialect, &piece_placements, height, state)?),
+        };
+
+        // En passant square, the victim is always in front of it
+        let ep_square_and_victim = match fen_parts.get(3) {
+            None | Some(&"-") => None,
+            Some(ep) => {
+                err_assert!(!ep.contains('('), "{dialect} does not support en passant victims: '{ep}'");
+                Some(parse_ep_square(ep, player_to_move, height)?)
+            }
+        };
+
+        // Fairy-Stockfish stores the remaining checks right after the en passant square
+        let mut times_in_check = None;
+        let mut remaining_parts = fen_parts.iter().skip(4).peekable();
+        if dialect == FenDialect::FairyStockfish {
+            if let Some(part) = remaining_parts.peek() {
+                if let Ok((white_left, black_left)) = scan_fmt!(part, "{d}+{d}", u8, u8) {
+                    times_in_check = Some(remaining_to_times_in_check(white_left, black_left, state)?);
+                    remaining_parts.next();
+                }
+            }
+        }
+        // Halfmove and fullmove clocks, the check count can also be given in the +W+B format
+        let mut clocks = Vec::new();
+        for part in remaining_parts {
+            if let Some(checks) = parse_check_count(part)? {
+                times_in_check = Some(checks);
+            } else if let Some(clock) = parse_clock(part)? {
+                clocks.push(clock);
+            } else {
+                err!("Unexpected field in {dialect} string: '{part}'");
+            }
+        }
+        let (halfmove_clock, fullmove_number) = clocks_or_default(&clocks)?;
+
+        Ok(Self {
+            width,
+            height,
+            piece_placements,
+            walls,
+            player_to_move,
+            castling_availability,
+            ep_square_and_victim,
+            times_in_check,
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+
+    /// Converts the FEN data to a FEN string in the given dialect.
+    /// The variant (`state`) is used to find the pieces involved in castling and the number of checks to lose.
+    /// Returns an error if the position cannot be represented in the dialect.
+    pub fn to_fen_dialect(&self, dialect: FenDialect, state: &InitialState) -> wrap_res!(String) {
+        if dialect == FenDialect::Protochess {
+            return Ok(self.to_string());
+        }
+        for p in &self.piece_placements {
+            err_assert!(p.piece_id.is_ascii_alphabetic(), "{dialect} only supports ASCII letters as piece ids, found '{}'", p.piece_id);
+        }
+        err_assert!(self.walls.is_empty() || dialect == FenDialect::FairyStockfish, "{dialect} does not support walls ('*')");
+
+        let mut fen = String::new();
+        self.write_piece_placement(&mut fen).unwrap();
+
+        let player_char = if self.player_to_move == 0 { 'w' } else { 'b' };
+        fen.push(' ');
+        fen.push(player_char);
+
+        fen.push(' ');
+        fen.push_str(&self.dialect_castling(dialect, state)?);
+
+        if let Some((ep_square, ep_victim)) = self.ep_square_and_victim {
+            let expected_y = if self.player_to_move == 0 { ep_square.1.checked_sub(1) } else { Some(ep_square.1 + 1) };
+            err_assert!(ep_victim.0 == ep_square.0 && Some(ep_victim.1) == expected_y,
+                "{dialect} cannot represent an en passant victim that is not in front of the en passant square");
+            fen.push(' ');
+            fen.push_str(&tuple_to_rank_file(ep_square));
+        } else {
+            fen.push_str(" -");
+        }
+
+        // Important: Note that the order of times_in_check is reversed
+        let checks_to_lose = state.global_rules.checks_to_lose;
+        let [black_checks, white_checks] = self.times_in_check.unwrap_or([0, 0]);
+        if dialect == FenDialect::FairyStockfish && checks_to_lose != 0 {
+            let white_left = checks_to_lose.saturating_sub(white_checks);
+            let black_left = checks_to_lose.saturating_sub(black_checks);
+            fen.push_str(&format!(" {white_left}+{black_left}"));
+        }
+
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.fullmove_number));
+
+        if dialect != FenDialect::FairyStockfish && checks_to_lose != 0 {
+            fen.push_str(&format!(" +{white_checks}+{black_checks}"));
+        }
+        Ok(fen)
+    }
+
+    /// Returns the castling rights in the format of the dialect (`KQkq` or `HAha`)
+    fn dialect_castling(&self, dialect: FenDialect, state: &InitialState) -> wrap_res!(String) {
+        let has_not_moved = |p: &PiecePlacement| {
+            match &self.castling_availability {
+                None => true,
+                Some(castling) => castling.contains(&(p.x, p.y)),
+            }
+        };
+        let mut result = String::new();
+        for player in 0..2 {
+            let (castler_ids, rook_ids) = castling_piece_ids(state, player);
+            let back_rank = if player == 0 { 0 } else { self.height - 1 };
+            let castlers: Vec<&PiecePlacement> = self.piece_placements.iter()
+                .filter(|p| castler_ids.contains(&p.piece_id) && has_not_moved(p))
+                .collect();
+            let rooks: Vec<&PiecePlacement> = self.piece_placements.iter()
+                .filter(|p| rook_ids.contains(&p.piece_id))
+                .collect();
+
+            // A castling piece can only castle with an unmoved rook in the same rank
+            let can_castle = |k: &PiecePlacement| rooks.iter().any(|r| r.y == k.y && has_not_moved(r));
+            let mut castlers = castlers.into_iter().filter(|k| can_castle(k));
+            let king = match castlers.next() {
+                Some(king) => king,
+                None => continue,
+            };
+            err_assert!(king.y == back_rank, "{dialect} cannot represent castling rights outside the back rank");
+            err_assert!(castlers.next().is_none(), "{dialect} cannot represent castling rights for multiple kings");
+
+            // Outermost rooks of the back rank, at each side of the king
+            let outermost_east = rooks.iter().filter(|r| r.y == back_rank && r.x > king.x).map(|r| r.x).max();
+            let outermost_west = rooks.iter().filter(|r| r.y == back_rank && r.x < king.x).map(|r| r.x).min();
+            let mut castling_rooks: Vec<BCoord> = rooks.iter()
+                .filter(|r| r.y == back_rank && has_not_moved(r))
+                .map(|r| r.x)
+                .collect();
+            // Kingside first
+            castling_rooks.sort_unstable_by(|a, b| b.cmp(a));
+            for x in castling_rooks {
+                let file_char = (b'a' + x) as char;
+                let c = match dialect {
+                    FenDialect::ShredderFen => file_char,
+                    _ if Some(x) == outermost_east => 'k',
+                    _ if Some(x) == outermost_west => 'q',
+                    _ => file_char,
+                };
+                result.push(if player == 0 { c.to_ascii_uppercase() } else { c });
+            }
+        }
+        if result.is_empty() {
+            result.push('-');
+        }
+        Ok(result)
+    }
+}
+
+
+/// Removes the Fairy-Stockfish extensions from the piece placement field, or returns an error if
+/// they cannot be represented (pieces in hand and promoted pieces)
+fn strip_placement_extensions(placement: &str, dialect: FenDialect) -> wrap_res!(String) {
+    if dialect != FenDialect::FairyStockfish {
+        err_assert!(!placement.contains(['[', '+', '~']), "Invalid piece placement for {dialect}: '{placement}'");
+        return Ok(placement.to_string());
+    }
+    let mut placement = placement;
+    if let Some(start) = placement.find('[') {
+        err_assert!(placement.ends_with(']'), "Invalid holdings in {dialect} string: '{placement}'");
+        let holdings = &placement[start+1 .. placement.len()-1];
+        err_assert!(holdings.is_empty() || holdings == "-", "Pieces in hand are not supported: '{holdings}'");
+        placement = &placement[..start];
+    }
+    err_assert!(!placement.contains('+'), "Promoted pieces ('+') are not supported: '{placement}'");
+    // The '~' suffix marks promoted pieces that demote when captured, which only matters for drops
+    Ok(placement.replace('~', ""))
+}
+
+/// Returns the ids of the pieces that can castle (kings) and the ids of the pieces they castle with (rooks)
+fn castling_piece_ids(state: &InitialState, player: Player) -> (Vec<PieceId>, Vec<PieceId>) {
+    let mut castler_ids = Vec::new();
+    let mut rook_ids = Vec::new();
+    for def in &state.piece_types {
+        if let Some(id) = def.ids[player as usize] {
+            if def.can_castle() { castler_ids.push(id); }
+            if def.is_castle_rook { rook_ids.push(id); }
+        }
+    }
+    (castler_ids, rook_ids)
+}
+
+/// Parses castling rights in the `KQkq` or `HAha` formats.
+/// Returns a list of the squares that have not moved (kings and rooks).
+fn parse_dialect_castling(castling: &str, dialect: FenDialect, pieces: &[PiecePlacement], height: BCoord, state: &InitialState) -> wrap_res!(Vec<(BCoord, BCoord)>) {
+    let mut result = Vec::new();
+    for c in castling.chars() {
+        err_assert!(c.is_ascii_alphabetic(), "Invalid castling rights for {dialect}: '{castling}'");
+        let player = if c.is_ascii_uppercase() { 0 } else { 1 };
+        let back_rank = if player == 0 { 0 } else { height - 1 };
+        let (castler_ids, rook_ids) = castling_piece_ids(state, player);
+
+        let mut kings = pieces.iter().filter(|p| p.y == back_rank && castler_ids.contains(&p.piece_id));
+        let king_x = match (kings.next(), kings.next()) {
+            (Some(king), None) => king.x,
+            (None, _) => err!("Invalid castling rights '{c}': there is no king in the back rank"),
+            (Some(_), Some(_)) => err!("Invalid castling rights '{c}': there are multiple kings in the back rank"),
+        };
+        let rooks = pieces.iter().filter(|p| p.y == back_rank && rook_ids.contains(&p.piece_id)).map(|p| p.x);
+
+        let rook_x = match c.to_ascii_lowercase() {
+            'k' if dialect != FenDialect::ShredderFen => rooks.filter(|x| *x > king_x).max(),
+            'q' if dialect != FenDialect::ShredderFen => rooks.filter(|x| *x < king_x).min(),
+            file @ 'a'..='p' => {
+                let file_x = file as BCoord - b'a';
+                rooks.into_iter().find(|x| *x == file_x)
+            },
+            _ => err!("Invalid castling rights for {dialect}: '{castling}'"),
+        };
+        let rook_x = match rook_x {
+            Some(x) => x,
+            None => err!("Invalid castling rights '{c}': there is no rook to castle with"),
+        };
+        for square in [(king_x, back_rank), (rook_x, back_rank)] {
+            if !result.contains(&square) {
+                result.push(square);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Converts the remaining checks of each player (`W+B`) to the number of times each player has been in check
+fn remaining_to_times_in_check(white_left: u8, black_left: u8, state: &InitialState) -> wrap_res!([u8; 2]) {
+    let checks_to_lose = state.global_rules.checks_to_lose;
+    err_assert!(checks_to_lose != 0, "The check count '{white_left}+{black_left}' is only supported in variants that count checks");
+    err_assert!(white_left <= checks_to_lose && black_left <= checks_to_lose,
+        "The remaining checks '{white_left}+{black_left}' cannot exceed the limit of {checks_to_lose} checks");
+    // Important: Note that the order is reversed
+    Ok([checks_to_lose - black_left, checks_to_lose - white_left])
+}
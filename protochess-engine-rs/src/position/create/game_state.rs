@@ -9,8 +9,8 @@ use super::variant_factory::VariantFactory;
 /// Full state of the game, including:
 /// - **Initial state:** Defines the rules of the game and starting position.
 /// - **Initial fen (optional):** User-provided fen that further defines the starting position, overriding 
-/// the initial state. It's applied to the initial state before playing the moves in `move_history`.
-/// See [this document](https://github.com/p-rivero/protochess-engine/tree/master/docs/FEN.md) for the custom FEN format.
+///   the initial state. It's applied to the initial state before playing the moves in `move_history`.
+///   See [this document](https://github.com/p-rivero/protochess-engine/tree/master/docs/FEN.md) for the custom FEN format.
 /// - **Move history:** Defines the current position and allows enforcing the repetition rules.
 /// 
 /// All games of a given chess variant have the same initial state, but can have different initial FENs.
@@ -72,8 +72,8 @@ pub struct StateDiff {
     pub player_to_move: Player,
 }
 
-impl From<&mut Position> for StateDiff {
-    fn from(pos: &mut Position) -> Self {
+impl<const W: usize> From<&mut Position<W>> for StateDiff {
+    fn from(pos: &mut Position<W>) -> Self {
         let fen = FenData::from(&*pos).to_string();
         let in_check = {
             if pos.leader_is_captured() { false }
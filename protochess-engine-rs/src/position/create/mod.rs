@@ -1,6 +1,7 @@
 // This directory contains the code for creating new Position objects, as well as exporting them to external formats.
 
 pub mod fen;
+pub mod fen_dialect;
 pub mod game_state;
 pub mod variant_factory;
 pub mod position_factory;
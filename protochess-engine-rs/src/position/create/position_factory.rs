@@ -1,7 +1,13 @@
 use crate::position::position_properties::PositionProperties;
 use crate::{InitialState, Position, wrap_res, err_assert, MakeMoveResultFlag, MoveInfo, MakeMoveResult};
 use crate::utils::to_index;
+use crate::utils::notation::NotationMode;
 use crate::types::BDimensions;
+use crate::searcher::EvalParams;
+#[cfg(feature = "nnue")]
+use crate::nnue::Network;
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
 
 use super::fen::FenData;
 use super::game_state::GameState;
@@ -15,6 +21,11 @@ pub struct PositionFactory {
     current_state: Option<GameState>,
     last_result: Option<MakeMoveResult>,
     move_notation: Vec<String>,
+    notation_mode: NotationMode,
+    eval_params: EvalParams,
+    zobrist_seed: u64,
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
 }
 
 impl PositionFactory {    
@@ -23,9 +34,15 @@ impl PositionFactory {
     /// Returns:
     /// - `Err` If the state is invalid
     /// - `Ok(None)` If the current position can be reused. In this case, `reused_position` parameter
-    /// will be updated.
+    ///   will be updated.
     /// - `Ok(Some(position))` If a new position was created.
     pub fn set_state(&mut self, new_state: GameState, reused_position: Option<&mut Position>) -> wrap_res!(Option<Position>) {
+        self.set_state_sized(new_state, reused_position)
+    }
+
+    /// Same as `set_state()`, for a position with bitboards of `W` words (see `Bitboard`).
+    /// Returns an error if the board doesn't fit in them.
+    pub fn set_state_sized<const W: usize>(&mut self, new_state: GameState, reused_position: Option<&mut Position<W>>) -> wrap_res!(Option<Position<W>>) {
         // No current state, just create a new position
         if self.current_state.is_none() || reused_position.is_none() {
             let position = self.set_state_impl(new_state)?;
@@ -57,7 +74,7 @@ impl PositionFactory {
         
         // Apply the new moves
         for (i, mv) in new_state.move_history[reuse_count..].iter().enumerate() {
-            let result = reused_position.pub_make_move(mv);
+            let result = reused_position.pub_make_move(mv, self.notation_mode);
             
             // If an illegal move is encountered, roll back and return error
             // In order to roll back, we need to make the moves that were undone earlier
@@ -69,7 +86,7 @@ impl PositionFactory {
                 }
                 // Redo the moves that were undone earlier
                 for mv2 in &current_state.move_history[reuse_count..] {
-                    let result2 = reused_position.pub_make_move(mv2);
+                    let result2 = reused_position.pub_make_move(mv2, self.notation_mode);
                     err_assert!(result2.flag != MakeMoveResultFlag::IllegalMove, 
                         "Invalid move when attempting to rollback: {}", mv2);
                     self.move_notation.push(result2.move_notation.clone().unwrap());
@@ -87,6 +104,11 @@ impl PositionFactory {
     
     /// Creates a `Position` from a fen string, using the previous `GameState`'s variant
     pub fn load_fen(&mut self, fen: &str) -> wrap_res!(Position) {
+        self.load_fen_sized(fen)
+    }
+
+    /// Same as `load_fen()`, for a position with bitboards of `W` words (see `Bitboard`)
+    pub fn load_fen_sized<const W: usize>(&mut self, fen: &str) -> wrap_res!(Position<W>) {
         if self.current_state.is_none() {
             panic!("No current state, call make_position() first");
         }
@@ -98,7 +120,7 @@ impl PositionFactory {
         self.set_state_impl(new_state)
     }
     
-    fn set_state_impl(&mut self, state: GameState) -> wrap_res!(Position) {
+    fn set_state_impl<const W: usize>(&mut self, state: GameState) -> wrap_res!(Position<W>) {
         // Parse the variant's default starting position
         let mut fen_data = FenData::parse_fen(&state.initial_state.fen)?;
         fen_data.player_to_move = state.initial_state.player_to_move;
@@ -109,12 +131,15 @@ impl PositionFactory {
             // Don't allow the user to override the walls
             fen_data.walls = old_fen.walls;
         }
-        let mut pos = Self::create_new_position(&state.initial_state, fen_data)?;
+        let mut pos = Self::create_new_position(&state.initial_state, fen_data, &self.eval_params, self.zobrist_seed)?;
+        // If the network is not compatible with the variant, the handcrafted evaluation is used
+        #[cfg(feature = "nnue")]
+        pos.set_network(self.network.clone());
         
         // Apply the move history
         self.move_notation.clear();
         for m in &state.move_history {
-            let result = pos.pub_make_move(m);
+            let result = pos.pub_make_move(m, self.notation_mode);
             err_assert!(result.flag != MakeMoveResultFlag::IllegalMove, "Invalid move: {}", m);
             self.move_notation.push(result.move_notation.clone().unwrap());
             self.last_result = Some(result);
@@ -139,6 +164,52 @@ impl PositionFactory {
         &self.move_notation
     }
     
+    /// Returns the style of algebraic notation used for new moves
+    pub fn get_notation_mode(&self) -> NotationMode {
+        self.notation_mode
+    }
+    
+    /// Changes the style of algebraic notation used for new moves.
+    /// The notation of moves that have already been played is not updated.
+    pub fn set_notation_mode(&mut self, mode: NotationMode) {
+        self.notation_mode = mode;
+    }
+    
+    /// Returns the evaluation parameters used for new positions
+    pub fn get_eval_params(&self) -> &EvalParams {
+        &self.eval_params
+    }
+    
+    /// Changes the evaluation parameters used for new positions.
+    /// Positions that have already been created are not updated.
+    pub fn set_eval_params(&mut self, params: EvalParams) {
+        self.eval_params = params;
+    }
+    
+    /// Returns the seed of the zobrist keys of new positions
+    pub fn get_zobrist_seed(&self) -> u64 {
+        self.zobrist_seed
+    }
+    
+    /// Changes the seed of the zobrist keys of new positions (by default 0).
+    /// Positions that have already been created are not updated.
+    pub fn set_zobrist_seed(&mut self, seed: u64) {
+        self.zobrist_seed = seed;
+    }
+    
+    /// Returns the neural network used to evaluate new positions, if any
+    #[cfg(feature = "nnue")]
+    pub fn get_network(&self) -> Option<&Arc<Network>> {
+        self.network.as_ref()
+    }
+    
+    /// Changes the neural network used to evaluate new positions.
+    /// Positions that have already been created are not updated.
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.network = network;
+    }
+    
     /// Returns the result of the last move in `state.move_history`, or `Ok` if
     /// this information is not known
     pub fn get_last_result(&self) -> MakeMoveResult {
@@ -178,12 +249,16 @@ impl PositionFactory {
     /// - **Board height and width:** From `InitialState`
     /// - **Piece definitions:** From `InitialState`
     /// - **Global rules:** From `InitialState`
+    /// - **Evaluation parameters:** From `eval_params`
+    /// - **Zobrist keys:** From `zobrist_seed`
+    /// - **Initial material:** From the FEN of `InitialState`
     /// - **Piece placements and Walls:** From `FenData`
     /// - **Player to move:** From `FenData`
     /// - **Castling availability:** From `FenData`
     /// - **EP square and victim:** From `FenData`
     /// - **Times in check:** From `FenData`
-    fn create_new_position(state: &InitialState, fen: FenData) -> wrap_res!(Position) {
+    /// - **Move clocks:** From `FenData`
+    fn create_new_position<const W: usize>(state: &InitialState, fen: FenData, eval_params: &EvalParams, zobrist_seed: u64) -> wrap_res!(Position<W>) {
         
         let dims = BDimensions::from_walls(state.board_width, state.board_height, &fen.walls)?;
     
@@ -204,19 +279,24 @@ impl PositionFactory {
             props.zobrist_key ^= 1;
         }
         props.times_in_check = fen.times_in_check.unwrap_or([0,0]);
+        props.halfmove_clock = fen.halfmove_clock;
+        props.fullmove_number = fen.fullmove_number;
 
         // Instantiate position and register piecetypes
-        let mut pos = Position::new(dims, fen.player_to_move, props, state.global_rules.clone());
+        let mut pos = Position::new(dims, fen.player_to_move, props, state.global_rules.clone(), *eval_params, zobrist_seed);
         for definition in &state.piece_types {
             pos.register_piecetype(definition)?;
         }
         pos.assert_promotion_consistency()?;
+        // The game phase is relative to the material of the variant's starting position, not the user-provided FEN
+        let variant_fen = FenData::parse_fen(&state.fen)?;
+        pos.set_initial_material(variant_fen.piece_placements.iter().map(|p| p.piece_id));
         
         // Add pieces
         for p in fen.piece_placements {
-            let can_castle = {
-                if fen.castling_availability.is_none() { true }
-                else { fen.castling_availability.as_ref().unwrap().contains(&(p.x, p.y)) }
+            let can_castle = match &fen.castling_availability {
+                None => true,
+                Some(castling) => castling.contains(&(p.x, p.y)),
             };
             pos.public_add_piece(p.piece_id, to_index(p.x, p.y), can_castle)?;
         }
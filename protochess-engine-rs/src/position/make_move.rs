@@ -1,21 +1,29 @@
 use crate::{Position, MoveInfo, MoveGen, MakeMoveResult};
-use crate::types::{Move, MoveType};
-use crate::utils::notation::{get_algebraic_notation, add_suffix};
+use crate::PieceId;
+use crate::types::{Move, MoveType, BIndex, Player};
+use crate::utils::notation::{get_algebraic_notation, add_suffix, game_over_suffix, NotationMode};
+use crate::utils::to_index;
 
 use super::position_properties::PositionProperties;
 
-impl Position {
+impl<const W: usize> Position<W> {
     
     /// Public interface for making a move. Checks if the move is legal, and if so, makes it.
-    pub fn pub_make_move(&mut self, target_move: &MoveInfo) -> MakeMoveResult {
-        let moves = MoveGen::get_pseudo_moves(self, true);
+    /// The notation of the move in the result is generated using `notation_mode`.
+    pub fn pub_make_move(&mut self, target_move: &MoveInfo, notation_mode: NotationMode) -> MakeMoveResult {
+        // Only the moves of the same type of piece are needed to write the notation
+        let piece_id = match self.piece_at(to_index(target_move.from.0, target_move.from.1)) {
+            Some(piece) => piece.get_piece_id(),
+            None => return MakeMoveResult::illegal_move(),
+        };
+        let moves = MoveGen::get_pseudo_moves_of_type(self, piece_id);
         for mv in &moves {
             if target_move != mv {
                 continue;
             }
             // Found the move, try to play it
             let exploded = mv.get_potential_explosion(self);
-            let mut move_notation = get_algebraic_notation(self, *mv, &moves);
+            let mut move_notation = get_algebraic_notation(self, *mv, &moves, notation_mode);
             if !MoveGen::make_move_if_legal(*mv, self) {
                 continue;
             }
@@ -30,7 +38,8 @@ impl Position {
             
             // Leader captured (atomic chess, or playing without a king)
             if self.leader_is_captured() {
-                move_notation = add_suffix(move_notation, "#");
+                // The opponent has no leader left, so it cannot be in check
+                move_notation = add_suffix(move_notation, game_over_suffix(notation_mode, false));
                 if self.pieces[self.whos_turn as usize].get_leader().is_none() {
                     return MakeMoveResult::all_pieces_captured(winner, exploded, move_notation);
                 }
@@ -38,7 +47,8 @@ impl Position {
             }
             // Piece moved to winning square (king of the hill, racing kings)
             if self.piece_is_on_winning_square() {
-                move_notation = add_suffix(move_notation, "#");
+                let in_check = MoveGen::in_check(self);
+                move_notation = add_suffix(move_notation, game_over_suffix(notation_mode, in_check));
                 return MakeMoveResult::piece_in_win_square(winner, exploded, move_notation);
             }
             let in_check = MoveGen::in_check(self);
@@ -49,7 +59,7 @@ impl Position {
                     return MakeMoveResult::checkmate(winner, exploded, move_notation);
                 }
                 if self.global_rules.stalemated_player_loses {
-                    move_notation = add_suffix(move_notation, "#");
+                    move_notation = add_suffix(move_notation, game_over_suffix(notation_mode, false));
                     return MakeMoveResult::stalemate(Some(winner), exploded, move_notation);
                 }
                 // Don't add "#" since it's a draw
@@ -57,7 +67,7 @@ impl Position {
             }
             // Checked N times (N=3 in 3-check)
             if in_check && self.increment_num_checks() {
-                move_notation = add_suffix(move_notation, "#");
+                move_notation = add_suffix(move_notation, game_over_suffix(notation_mode, true));
                 return MakeMoveResult::check_limit(winner, exploded, move_notation);
             }
             // Threefold Repetition
@@ -79,8 +89,17 @@ impl Position {
         let my_player_num = self.whos_turn;
         let mut new_props = *self.get_properties(); // Copy the current properties
         new_props.num_captures = 0;
+        new_props.halfmove_clock += 1;
+        if my_player_num == 1 {
+            new_props.fullmove_number += 1;
+        }
         let move_type = mv.get_move_type();
         
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push();
+        }
+        
         // Update the player
         self.whos_turn = 1 - self.whos_turn;
         // Update the player zobrist key
@@ -107,7 +126,7 @@ impl Position {
             let castling_zob = captured_piece.get_castle_zobrist(capt_index);
             new_props.zobrist_key ^= captured_piece.get_zobrist(capt_index);
     
-            let could_castle = self.pieces[capt_player as usize].remove_piece(capt_index);
+            let could_castle = self.remove_piece_incremental(capt_player, capt_index);
             if could_castle {
                 new_props.zobrist_key ^= castling_zob;
             }
@@ -127,7 +146,7 @@ impl Position {
             new_props.zobrist_key ^= rook_piece.get_zobrist(rook_from);
             new_props.zobrist_key ^= rook_piece.get_castle_zobrist(rook_from);
             rook_id = Some(rook_piece.get_piece_id());
-            self.pieces[my_player_num as usize].remove_piece(rook_from);
+            self.remove_piece_incremental(my_player_num, rook_from);
         }
 
         let from = mv.get_from();
@@ -135,8 +154,11 @@ impl Position {
         // Move the piece (only if it hasn't exploded)
         if self.pieces[my_player_num as usize].index_has_piece(from) {
             // Move piece to location
-            new_props.moved_piece_castle = self.pieces[my_player_num as usize].move_piece(from, to, false);
+            new_props.moved_piece_castle = self.move_piece_incremental(my_player_num, from, to);
             let moved_piece = self.player_piece_at(my_player_num, to).unwrap();
+            if moved_piece.can_promote() {
+                new_props.halfmove_clock = 0;
+            }
             new_props.zobrist_key ^= moved_piece.get_zobrist(from);
             new_props.zobrist_key ^= moved_piece.get_zobrist(to);
             if new_props.moved_piece_castle {
@@ -151,9 +173,9 @@ impl Position {
                 new_props.zobrist_key ^= moved_piece.get_zobrist(to);
                 new_props.promote_from = moved_piece.get_piece_id();
                 // Remove old piece
-                self.pieces[my_player_num as usize].remove_piece(to);
+                self.remove_piece_incremental(my_player_num, to);
                 // Add new piece
-                self.pieces[my_player_num as usize].add_piece(promo, to, false);
+                self.add_piece_incremental(my_player_num, promo, to);
                 let piece = self.player_piece_at(my_player_num, to).unwrap();
                 new_props.zobrist_key ^= piece.get_zobrist(to);
             }
@@ -165,7 +187,7 @@ impl Position {
                 if move_type == MoveType::KingsideCastle { to - 1 }
                 else { to + 1 }
             };
-            self.pieces[my_player_num as usize].add_piece(rook_id.unwrap(), rook_to, false);
+            self.add_piece_incremental(my_player_num, rook_id.unwrap(), rook_to);
             let rook_piece = self.player_piece_at(my_player_num, rook_to).unwrap();
             new_props.zobrist_key ^= rook_piece.get_zobrist(rook_to);
         }
@@ -179,11 +201,21 @@ impl Position {
         }
         
         // Update props
+        if new_props.num_captures > 0 {
+            new_props.halfmove_clock = 0;
+        }
         new_props.move_played = mv;
         self.properties_stack.push(new_props);
         
         // Update occupied bbs for future calculations
         self.update_occupied();
+        
+        // The hidden values of a player must be recomputed if its leader has moved to another bucket
+        #[cfg(feature = "nnue")]
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.update_buckets(self);
+            self.nnue = Some(nnue);
+        }
     }
 
     #[inline]
@@ -200,7 +232,7 @@ impl Position {
         let moved_piece_castle_zob = moved_piece.get_castle_zobrist(from);
         let moved_piece_id = moved_piece.get_piece_id();
         // Remove the capturing piece
-        let capturing_could_castle = self.pieces[my_player_num as usize].remove_piece(from);
+        let capturing_could_castle = self.remove_piece_incremental(my_player_num, from);
         if capturing_could_castle {
             new_props.zobrist_key ^= moved_piece_castle_zob;
         }
@@ -217,7 +249,7 @@ impl Position {
                 let exploded_id = exploded_piece.get_piece_id();
                 let exploded_player = exploded_piece.get_player();
                 let exploded_castle_zob = exploded_piece.get_castle_zobrist(nindex);
-                let could_castle = self.pieces[exploded_player as usize].remove_piece(nindex);
+                let could_castle = self.remove_piece_incremental(exploded_player, nindex);
                 if could_castle {
                     new_props.zobrist_key ^= exploded_castle_zob;
                 }
@@ -229,6 +261,41 @@ impl Position {
 
 
 
+    /// Adds a piece in `make_move()`, updating the neural network (if any).
+    /// `unmake_move()` doesn't need to update the network, since it restores the previous hidden values.
+    #[inline]
+    fn add_piece_incremental(&mut self, player: Player, piece_id: PieceId, index: BIndex) {
+        self.pieces[player as usize].add_piece(piece_id, index, false);
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            nnue.add_piece(player, piece_id, index);
+        }
+    }
+    
+    /// Removes a piece in `make_move()`, updating the neural network (if any).
+    /// Returns true if the piece could castle before this move.
+    #[inline]
+    fn remove_piece_incremental(&mut self, player: Player, index: BIndex) -> bool {
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            let piece_id = self.pieces[player as usize].piece_at(index).unwrap().get_piece_id();
+            nnue.remove_piece(player, piece_id, index);
+        }
+        self.pieces[player as usize].remove_piece(index)
+    }
+    
+    /// Moves a piece in `make_move()`, updating the neural network (if any).
+    /// Returns true if the piece could castle before this move.
+    #[inline]
+    fn move_piece_incremental(&mut self, player: Player, from: BIndex, to: BIndex) -> bool {
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            let piece_id = self.pieces[player as usize].piece_at(from).unwrap().get_piece_id();
+            nnue.move_piece(player, piece_id, from, to);
+        }
+        self.pieces[player as usize].move_piece(from, to, false)
+    }
+
     /// Returns true if there is a move to undo
     pub fn can_unmake_move(&self) -> bool {
         // We always have at least one move in the stack
@@ -240,6 +307,10 @@ impl Position {
         // Update props
         // Consume prev props; never to return again
         let props = self.properties_stack.pop().expect("No move to undo");
+        #[cfg(feature = "nnue")]
+        if let Some(nnue) = &mut self.nnue {
+            nnue.pop();
+        }
         
         // Update player turn
         self.whos_turn = 1 - self.whos_turn;
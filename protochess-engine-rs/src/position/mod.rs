@@ -1,8 +1,13 @@
 use std::fmt;
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
 
 use crate::{types::*, PieceDefinition, err_assert, wrap_res, err};
 use crate::utils::to_index;
 use crate::piece::{Piece, PieceId};
+use crate::searcher::EvalParams;
+#[cfg(feature = "nnue")]
+use crate::nnue::{Accumulator, Network};
 
 mod position_properties;
 mod make_move;
@@ -16,12 +21,12 @@ use piece_set::PieceSet;
 
 /// Represents a single position in chess
 #[derive(Clone, Debug)]
-pub struct Position {
-    pub dimensions: BDimensions,
+pub struct Position<const W: usize = 4> {
+    pub dimensions: BDimensions<W>,
     pub whos_turn: Player,
-    pub pieces: [PieceSet; 2], // pieces[0] = white, pieces[1] = black
+    pub pieces: [PieceSet<W>; 2], // pieces[0] = white, pieces[1] = black
     // Bitboard squares, that are occupied by a piece or out of bounds
-    pub occ_or_out_bounds: Bitboard,
+    pub occ_or_out_bounds: Bitboard<W>,
     // Stack of properties relating only to the current position
     // Typically hard-to-recover properties, like castling
     // Similar to state in stockfish
@@ -32,10 +37,20 @@ pub struct Position {
     captures_stack: Vec<(PieceId, Player, bool, BIndex)>,
     // Global rules of the game
     pub global_rules: GlobalRules,
+    // Constants used to compute the material and positional scores of the pieces
+    pub eval_params: EvalParams,
+    // Material score (without leaders) of both players in the starting position of the variant.
+    // The game phase is computed relative to this value.
+    pub initial_material: Centipawns,
+    // Seed of the zobrist keys of the pieces
+    zobrist_seed: u64,
+    // Hidden values of the neural network, if the position is evaluated with a network
+    #[cfg(feature = "nnue")]
+    nnue: Option<Accumulator>,
 }
 
-impl Position {
-    fn new(dimensions: BDimensions, whos_turn: Player, props: PositionProperties, rules: GlobalRules) -> Position {
+impl<const W: usize> Position<W> {
+    fn new(dimensions: BDimensions<W>, whos_turn: Player, props: PositionProperties, rules: GlobalRules, eval_params: EvalParams, zobrist_seed: u64) -> Position<W> {
         let mut properties_stack = Vec::with_capacity(128);
         properties_stack.push(props);
         let occ_or_out_bounds = !&dimensions.bounds;
@@ -48,6 +63,11 @@ impl Position {
             properties_stack,
             captures_stack: Vec::with_capacity(128),
             global_rules: rules,
+            eval_params,
+            initial_material: 0,
+            zobrist_seed,
+            #[cfg(feature = "nnue")]
+            nnue: None,
         }
     }
 
@@ -66,10 +86,68 @@ impl Position {
             for set in &self.pieces {
                 err_assert!(!set.contains_piece(id), "Piece id {id} already exists");
             }
-            self.pieces[player].register_piecetype(definition, &self.dimensions)?;
+            self.pieces[player].register_piecetype(definition, &self.dimensions, &self.eval_params, self.zobrist_seed)?;
         }
         Ok(())
     }
+    
+    /// Computes `initial_material` from the ids of the pieces in the starting position of the variant.
+    /// The piece types must have been registered before calling this function.
+    pub fn set_initial_material(&mut self, piece_ids: impl Iterator<Item=PieceId>) {
+        self.initial_material = piece_ids
+            .filter_map(|id| self.lookup_piece(id))
+            .filter(|piece| !piece.is_leader())
+            .map(|piece| piece.get_material_score())
+            .sum();
+    }
+    
+    /// Uses the material and positional scores of `template`, a position of the same variant that was created
+    /// with different `EvalParams`. This is much faster than recomputing the piece-square tables.
+    pub fn copy_eval_params_from(&mut self, template: &Position<W>) -> wrap_res!() {
+        for (set, template_set) in self.pieces.iter_mut().zip(template.pieces.iter()) {
+            let ids = set.iter().map(|p| p.get_piece_id());
+            err_assert!(ids.eq(template_set.iter().map(|p| p.get_piece_id())), "The template position has different piece types");
+            for (piece, template_piece) in set.iter_mut().zip(template_set.iter()) {
+                piece.copy_scores_from(template_piece);
+            }
+            set.update_scores_from_scratch();
+        }
+        self.eval_params = template.eval_params;
+        self.initial_material = template.initial_material;
+        Ok(())
+    }
+    
+    /// Evaluates this position with a neural network, or with the handcrafted evaluation if `None`.
+    /// Returns `false` if the network is not compatible with the position, in which case the handcrafted
+    /// evaluation is used.
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) -> bool {
+        self.nnue = None;
+        match network {
+            Some(network) if network.is_compatible(self) => {
+                self.nnue = Some(Accumulator::new(network, self));
+                true
+            },
+            Some(_) => false,
+            None => true,
+        }
+    }
+    
+    /// Returns the accumulator of the neural network, if the position is evaluated with a network
+    #[cfg(feature = "nnue")]
+    pub fn get_nnue(&self) -> Option<&Accumulator> {
+        self.nnue.as_ref()
+    }
+    
+    /// Recomputes the hidden values of the neural network (if any) from scratch
+    #[cfg(feature = "nnue")]
+    fn refresh_nnue(&mut self) {
+        if let Some(mut nnue) = self.nnue.take() {
+            nnue.refresh(self);
+            self.nnue = Some(nnue);
+        }
+    }
+    
     pub fn assert_promotion_consistency(&self) -> wrap_res!() {
         for player in 0..self.pieces.len() {
             self.pieces[player].assert_promotion_consistency()?;
@@ -158,7 +236,7 @@ impl Position {
         }
     }
 
-    pub fn piece_at(&self, index: BIndex) -> Option<&Piece> {
+    pub fn piece_at(&self, index: BIndex) -> Option<&Piece<W>> {
         for ps in &self.pieces {
             if let Some(piece) = ps.piece_at(index) {
                 return Some(piece);
@@ -166,7 +244,7 @@ impl Position {
         }
         None
     }
-    pub fn piece_at_mut(&mut self, index: BIndex) -> Option<&mut Piece> {
+    pub fn piece_at_mut(&mut self, index: BIndex) -> Option<&mut Piece<W>> {
         for ps in &mut self.pieces {
             if let Some(piece) = ps.piece_at_mut(index) {
                 return Some(piece);
@@ -174,10 +252,10 @@ impl Position {
         }
         None
     }
-    pub fn player_piece_at(&self, player: Player, index: BIndex) -> Option<&Piece> {
+    pub fn player_piece_at(&self, player: Player, index: BIndex) -> Option<&Piece<W>> {
         self.pieces[player as usize].piece_at(index)
     }
-    pub fn lookup_piece(&self, piece_id: PieceId) -> Option<&Piece> {
+    pub fn lookup_piece(&self, piece_id: PieceId) -> Option<&Piece<W>> {
         for ps in &self.pieces {
             if let Some(piece) = ps.lookup_piece(piece_id) {
                 return Some(piece);
@@ -214,6 +292,8 @@ impl Position {
             zob ^= piece.get_castle_zobrist(index);
         }
         self.update_occupied();
+        #[cfg(feature = "nnue")]
+        self.refresh_nnue();
         let stack_len = self.properties_stack.len();
         self.properties_stack[stack_len - 1].zobrist_key = zob;
         Ok(())
@@ -237,6 +317,8 @@ impl Position {
             zob ^= castle_zob;
         }
         self.update_occupied();
+        #[cfg(feature = "nnue")]
+        self.refresh_nnue();
         let stack_len = self.properties_stack.len();
         self.properties_stack[stack_len - 1].zobrist_key = zob;
         Ok(())
@@ -266,14 +348,14 @@ impl Position {
     /// Must be called after every position update/modification
     fn update_occupied(&mut self) {
         self.occ_or_out_bounds = !&self.dimensions.bounds;
-        for (_i, ps) in self.pieces.iter_mut().enumerate() {
+        for ps in &mut self.pieces {
             ps.update_occupied();
             self.occ_or_out_bounds |= ps.get_occupied();
         }
     }
 }
 
-impl fmt::Display for Position {
+impl<const W: usize> fmt::Display for Position<W> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for y in (0..self.dimensions.height).rev() {
             write!(f, "{:2} ", y+1)?;
@@ -302,8 +384,8 @@ impl fmt::Display for Position {
     }
 }
 
-impl PartialEq for Position {
-    fn eq(&self, other: &Position) -> bool {
+impl<const W: usize> PartialEq for Position<W> {
+    fn eq(&self, other: &Position<W>) -> bool {
         self.dimensions == other.dimensions &&
         self.whos_turn == other.whos_turn &&
         self.pieces == other.pieces &&
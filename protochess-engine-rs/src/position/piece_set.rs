@@ -5,25 +5,31 @@ use crate::{PieceDefinition, wrap_res, err_assert};
 //Pieces that a player has
 use crate::types::{Bitboard, BIndex, Player, BDimensions, Centipawns, BCoord};
 use crate::piece::{Piece, PieceId};
+use crate::searcher::EvalParams;
 use crate::utils::from_index;
 
 /// Represents a set of pieces for a player
 /// custom is a vec of custom piece
 #[derive(Clone, Debug)]
-pub struct PieceSet {
-    pieces: Vec<Piece>,
+pub struct PieceSet<const W: usize = 4> {
+    pieces: Vec<Piece<W>>,
     leader_piece_index: isize,
-    occupied: Bitboard,
+    occupied: Bitboard<W>,
     player_num: Player,
     // Inverse attack pattern of all the pieces in the set
     inverse_attack: PieceDefinition,
-    inverse_attack_jumps: Vec<Bitboard>,
+    inverse_attack_jumps: Vec<Bitboard<W>>,
     
     piece_at_index: [isize; 256],
+    
+    // Incrementally updated scores of all the pieces in the set (index 0 is the middlegame, 1 is the endgame)
+    material_score: [Centipawns; 2],
+    leader_material_score: [Centipawns; 2],
+    positional_score: [Centipawns; 2],
 }
 
-impl PieceSet {
-    pub fn new(player_num: Player) -> PieceSet {
+impl<const W: usize> PieceSet<W> {
+    pub fn new(player_num: Player) -> PieceSet<W> {
         let mut inverse_attack_jumps = Vec::with_capacity(256);
         for _ in 0..256 {
             inverse_attack_jumps.push(Bitboard::zero());
@@ -36,20 +42,23 @@ impl PieceSet {
             inverse_attack: PieceDefinition::default(),
             inverse_attack_jumps,
             piece_at_index: [-1; 256],
+            material_score: [0; 2],
+            leader_material_score: [0; 2],
+            positional_score: [0; 2],
         }
     }
     
     /// Add a new piece definition to the set. The following conditions must have been checked before calling this function:
     /// - Both ids (white and black) are unique among all pieces in all sets (so that it is possible to uniquely identify a piece)
     /// - This piece is available for the player (i.e. `ids[player_num]` is not `None`)
-    pub fn register_piecetype(&mut self, definition: &PieceDefinition, dims: &BDimensions) -> wrap_res!() {
+    pub fn register_piecetype(&mut self, definition: &PieceDefinition, dims: &BDimensions<W>, params: &EvalParams, zobrist_seed: u64) -> wrap_res!() {
         if definition.is_leader {
             let player = if self.player_num == 0 { "White" } else { "Black" };
             err_assert!(self.leader_piece_index == -1, "{player} has more than 1 leader piece");
             self.leader_piece_index = self.pieces.len() as isize;
         }
         
-        let piece = Piece::new(definition.clone(), self.player_num, dims);
+        let piece = Piece::new(definition.clone(), self.player_num, dims, params, zobrist_seed);
         
         let reserved_id_regex = regex::Regex::new(r"[\s/0-9*]").unwrap();
         err_assert!(!reserved_id_regex.is_match(&piece.get_piece_id().to_string()),
@@ -72,21 +81,21 @@ impl PieceSet {
         Ok(())
     }
     
-    pub fn lookup_piece(&self, piece_id: PieceId) -> Option<&Piece> {
+    pub fn lookup_piece(&self, piece_id: PieceId) -> Option<&Piece<W>> {
         self.pieces.iter().find(|p| p.get_piece_id() == piece_id)
     }
     pub fn contains_piece(&self, piece_id: PieceId) -> bool {
         self.lookup_piece(piece_id).is_some()
     }
     
-    pub fn iter(&self) -> Iter<Piece> {
+    pub fn iter(&self) -> Iter<'_, Piece<W>> {
         self.pieces.iter()
     }
-    pub fn iter_mut(&mut self) -> IterMut<Piece> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, Piece<W>> {
         self.pieces.iter_mut()
     }
     
-    pub fn get_occupied(&self) -> &Bitboard {
+    pub fn get_occupied(&self) -> &Bitboard<W> {
         &self.occupied
     }
     
@@ -98,7 +107,7 @@ impl PieceSet {
         self.piece_at_index[index as usize] != -1
     }
     
-    pub fn piece_at(&self, index: BIndex) -> Option<&Piece> {
+    pub fn piece_at(&self, index: BIndex) -> Option<&Piece<W>> {
         let piece_index = self.piece_at_index[index as usize];
         if piece_index == -1 {
             None
@@ -106,7 +115,7 @@ impl PieceSet {
             Some(&self.pieces[piece_index as usize])
         }
     }
-    pub fn piece_at_mut(&mut self, index: BIndex) -> Option<&mut Piece> {
+    pub fn piece_at_mut(&mut self, index: BIndex) -> Option<&mut Piece<W>> {
         let piece_index = self.piece_at_index[index as usize];
         if piece_index == -1 {
             None
@@ -115,14 +124,14 @@ impl PieceSet {
         }
     }
     
-    pub fn get_leader(&self) -> Option<&Piece> {
+    pub fn get_leader(&self) -> Option<&Piece<W>> {
         if self.leader_piece_index == -1 {
             return None;
         }
         self.pieces.get(self.leader_piece_index as usize)
     }
     
-    pub fn get_inverse_attack(&self, index: BIndex) -> (&PieceDefinition, &Bitboard) {
+    pub fn get_inverse_attack(&self, index: BIndex) -> (&PieceDefinition, &Bitboard<W>) {
         (&self.inverse_attack, &self.inverse_attack_jumps[index as usize])
     }
 
@@ -142,6 +151,8 @@ impl PieceSet {
         let piece_index = self.piece_at_index[from as usize];
         let piece = &mut self.pieces[piece_index as usize];
         let could_castle = piece.move_piece_(from, to, set_can_castle);
+        self.positional_score[0] += piece.get_positional_score::<false>(to) - piece.get_positional_score::<false>(from);
+        self.positional_score[1] += piece.get_positional_score::<true>(to) - piece.get_positional_score::<true>(from);
         self.piece_at_index[from as usize] = -1;
         self.piece_at_index[to as usize] = piece_index;
         could_castle
@@ -154,6 +165,7 @@ impl PieceSet {
         let piece = &mut self.pieces[piece_index];
         piece.add_piece_(index, set_can_castle);
         self.piece_at_index[index as usize] = piece_index as isize;
+        self.update_scores(piece_index, index, 1);
     }
     
     /// Remove a piece from a given index (assuming there is a piece there).
@@ -164,34 +176,53 @@ impl PieceSet {
         let piece = &mut self.pieces[piece_index as usize];
         let could_castle = piece.remove_piece_(index);
         self.piece_at_index[index as usize] = -1;
+        self.update_scores(piece_index as usize, index, -1);
         could_castle
     }
     
-    /// Returns the material score of all pieces in the set, and of only the leader pieces
-    pub fn get_material_score(&self) -> (Centipawns, Centipawns) {
-        let mut score = 0;
-        let mut leader_score = 0;
-        for piece in &self.pieces {
-            let piece_total_score = piece.get_material_score_all();
-            score += piece_total_score;
-            if piece.is_leader() {
-                leader_score += piece_total_score;
+    /// Adds (`sign = 1`) or subtracts (`sign = -1`) the scores of a piece at a given index
+    #[inline]
+    fn update_scores(&mut self, piece_index: usize, index: BIndex, sign: Centipawns) {
+        let piece = &self.pieces[piece_index];
+        let material = [sign * piece.get_material_score(), sign * piece.get_material_score_endgame()];
+        self.material_score[0] += material[0];
+        self.material_score[1] += material[1];
+        if piece.is_leader() {
+            self.leader_material_score[0] += material[0];
+            self.leader_material_score[1] += material[1];
+        }
+        self.positional_score[0] += sign * piece.get_positional_score::<false>(index);
+        self.positional_score[1] += sign * piece.get_positional_score::<true>(index);
+    }
+    
+    /// Recomputes the incrementally updated scores from scratch.
+    /// Call this after changing the scores of the pieces (see `Piece::copy_scores_from()`).
+    pub fn update_scores_from_scratch(&mut self) {
+        self.material_score = [0; 2];
+        self.leader_material_score = [0; 2];
+        self.positional_score = [0; 2];
+        for piece_index in 0..self.pieces.len() {
+            for index in self.pieces[piece_index].get_indexes() {
+                self.update_scores(piece_index, index, 1);
             }
         }
-        (score, leader_score)
     }
     
+    /// Returns the material score of all pieces in the set, and of only the leader pieces
+    #[inline]
+    pub fn get_material_score<const ENDGAME: bool>(&self) -> (Centipawns, Centipawns) {
+        let phase = ENDGAME as usize;
+        (self.material_score[phase], self.leader_material_score[phase])
+    }
+    
+    /// Returns the positional score of all pieces in the set
     #[inline]
     pub fn get_positional_score<const ENDGAME: bool>(&self) -> Centipawns {
-        let mut score = 0;
-        for piece in &self.pieces {
-            score += piece.get_positional_score_all::<ENDGAME>();
-        }
-        score
+        self.positional_score[ENDGAME as usize]
     }
     
     
-    fn update_inverse_attack(&mut self, other: &PieceDefinition, dims: &BDimensions) {
+    fn update_inverse_attack(&mut self, other: &PieceDefinition, dims: &BDimensions<W>) {
         self.inverse_attack.attack_north |= other.attack_south;
         self.inverse_attack.attack_south |= other.attack_north;
         self.inverse_attack.attack_east |= other.attack_west;
@@ -225,7 +256,7 @@ impl PieceSet {
     }
 }
 
-impl PartialEq for PieceSet {
+impl<const W: usize> PartialEq for PieceSet<W> {
     fn eq(&self, other: &Self) -> bool {
         eq_anyorder(&self.pieces, &other.pieces) &&
         self.occupied == other.occupied &&
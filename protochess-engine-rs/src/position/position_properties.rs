@@ -17,6 +17,10 @@ pub struct PositionProperties {
     pub num_captures: u8,
     // Number of times that each player has been in check
     pub times_in_check: [u8; 2],
+    // Plies since the last capture or move of a piece that can promote (pawn)
+    pub halfmove_clock: u32,
+    // Starts at 1 and is incremented after each move of black
+    pub fullmove_number: u32,
 }
 
 impl PositionProperties {
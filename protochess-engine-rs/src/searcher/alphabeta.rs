@@ -3,16 +3,59 @@ use instant::Instant;
 #[cfg(feature = "parallel")]
 use std::sync::atomic::Ordering;
 
-use crate::MoveGen;
+use crate::{MoveGen, ProbeResult};
+use crate::move_generator::legality::LegalityInfo;
+#[cfg(feature = "syzygy")]
+use crate::tablebase::syzygy::Wdl;
 use crate::types::{Move, Depth, Centipawns, SearchTimeout, ZobKey};
 
 use super::Searcher;
 use super::eval;
+use super::move_picker::MovePicker;
+use super::see;
 use super::transposition_table::{Entry, EntryFlag};
 
 pub const GAME_OVER_SCORE: Centipawns = -1_000_000;
+/// Score of a position won according to the Syzygy tablebase, lower than any checkmate score
+#[cfg(feature = "syzygy")]
+const SYZYGY_WIN_SCORE: Centipawns = 500_000;
+/// Score of a cursed win (a win that would be a draw under the 50-move rule)
+#[cfg(feature = "syzygy")]
+const SYZYGY_CURSED_WIN_SCORE: Centipawns = 400_000;
+/// Scores above this value (in absolute value) are wins or losses, not static evaluations
+const DECISIVE_SCORE: Centipawns = 100_000;
+/// Aspiration windows are not used below this depth, since the score is not stable yet
+const ASPIRATION_MIN_DEPTH: Depth = 4;
+/// Maximum remaining depth at which reverse futility pruning, razoring and late move pruning are used
+const REVERSE_FUTILITY_MAX_DEPTH: Depth = 5;
+const RAZORING_MAX_DEPTH: Depth = 2;
+const LATE_MOVE_PRUNING_MAX_DEPTH: Depth = 3;
+/// Check evasions are only searched in the first plies of the quiescence search, deeper positions in check are
+/// evaluated like the others. Without a limit, checks and evasions could go on forever if repetitions are not draws.
+const QSEARCH_MAX_EVASION_PLY: usize = 8;
 
-impl Searcher {
+/// Converts a tablebase result to a score, using the same scale as checkmates
+fn tablebase_score(result: ProbeResult, pv_index: usize) -> Centipawns {
+    match result {
+        ProbeResult::Win(dtm) => -(GAME_OVER_SCORE + (pv_index + dtm as usize) as Centipawns),
+        ProbeResult::Draw => 0,
+        ProbeResult::Loss(dtm) => GAME_OVER_SCORE + (pv_index + dtm as usize) as Centipawns,
+    }
+}
+
+/// Converts a Syzygy WDL result to a score. Closer wins are preferred.
+#[cfg(feature = "syzygy")]
+fn syzygy_score(wdl: Wdl, pv_index: usize) -> Centipawns {
+    match wdl {
+        Wdl::Win => SYZYGY_WIN_SCORE - pv_index as Centipawns,
+        Wdl::CursedWin => SYZYGY_CURSED_WIN_SCORE - pv_index as Centipawns,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -SYZYGY_CURSED_WIN_SCORE + pv_index as Centipawns,
+        Wdl::Loss => -SYZYGY_WIN_SCORE + pv_index as Centipawns,
+    }
+}
+
+impl<const W: usize> Searcher<W> {
     /// Search for the best move to play at the current position.
     /// Populates the principal variation vector and returns the score of the position.
     /// Use the previous PV as a hint: begin the search by playing the PV, and the rest of the search attempts to refute it.
@@ -23,6 +66,34 @@ impl Searcher {
         self.alphabeta::<true,true>(depth, depth, 0, -Centipawns::MAX, Centipawns::MAX, true, Some(hint))
     }
     
+    /// Same as `start_alphabeta()`, but starts with a narrow window around the score of the previous iteration.
+    /// If the score falls outside of the window, the window is widened and the position is searched again.
+    /// # Errors
+    /// Returns `Err(SearchTimeout)` if the search timed out.
+    pub fn start_aspiration(&mut self, depth: Depth, hint: &Vec<Move>, previous_score: Centipawns) -> Result<Centipawns, SearchTimeout> {
+        let mut delta = match self.params.aspiration_window {
+            // A window that is not positive would never contain the score, don't use aspiration at all
+            Some(delta) if delta > 0 && depth >= ASPIRATION_MIN_DEPTH && previous_score.abs() < DECISIVE_SCORE => delta,
+            _ => return self.start_alphabeta(depth, hint),
+        };
+        loop {
+            let alpha = previous_score - delta;
+            let beta = previous_score + delta;
+            let score = self.alphabeta::<true,true>(depth, depth, 0, alpha, beta, true, Some(hint))?;
+            if score > alpha && score < beta {
+                return Ok(score);
+            }
+            // The score is outside of the window, discard the incomplete PV and search again
+            for mv in self.principal_variation.iter_mut() {
+                *mv = Move::null();
+            }
+            delta *= 2;
+            if delta >= DECISIVE_SCORE {
+                return self.start_alphabeta(depth, hint);
+            }
+        }
+    }
+    
     // alpha is the best score that I can currently guarantee at this level or above.
     // beta is the worst score for me that the opponent can currently guarantee at this level or above.
     #[allow(clippy::too_many_lines)]
@@ -56,9 +127,36 @@ impl Searcher {
                 return Ok(alpha);
             }
         }
+        
+        // Probe endgame tablebase
+        if IS_ROOT {
+            if let Some((mv, score)) = self.probe_tablebase_root() {
+                self.principal_variation[0] = mv;
+                self.end_pv::<IS_PV>(1);
+                self.tablebase_root_hit = true;
+                return Ok(score);
+            }
+        } else if let Some(result) = self.tablebase.as_ref().and_then(|tb| tb.probe(&self.pos)) {
+            let score = tablebase_score(result, pv_index);
+            self.end_pv::<IS_PV>(pv_index);
+            return Ok(score.clamp(alpha, beta));
+        }
+        #[cfg(feature = "syzygy")]
+        if !IS_ROOT {
+            let wdl = match &self.syzygy {
+                Some(tb) => tb.probe_wdl(&mut self.pos),
+                None => None,
+            };
+            if let Some(wdl) = wdl {
+                let score = syzygy_score(wdl, pv_index);
+                self.end_pv::<IS_PV>(pv_index);
+                return Ok(score.clamp(alpha, beta));
+            }
+        }
 
-        // Probe transposition table
-        if let Some(entry) = self.transposition_table.retrieve(self.zobrist()) {
+        // Probe transposition table (the score of the root is not valid if some moves are excluded)
+        let can_probe = !IS_ROOT || self.excluded_root_moves.is_empty();
+        if let Some(entry) = self.transposition_table.retrieve(self.zobrist()).filter(|_| can_probe) {
             if entry.depth >= depth {
                 match entry.flag {
                     EntryFlag::Exact => {
@@ -90,7 +188,7 @@ impl Searcher {
         }
         
         if depth == 0 {
-            let quiesce_score = self.quiesce(alpha, beta, pv_index)?;
+            let quiesce_score = self.quiesce(alpha, beta, pv_index, 0)?;
             let flag = {
                 if quiesce_score <= alpha { EntryFlag::Alpha }
                 else if quiesce_score >= beta { EntryFlag::Beta }
@@ -142,6 +240,47 @@ impl Searcher {
             self.known_checks.insert(self.zobrist());
         }
         
+        let can_prune = !IS_PV && !in_check && self.can_prune();
+        // Margins of reverse futility pruning and razoring, if they can be used at this depth
+        let futility_margin = self.params.reverse_futility_margin
+            .filter(|_| depth <= REVERSE_FUTILITY_MAX_DEPTH && beta.abs() < DECISIVE_SCORE)
+            .map(|margin| margin * depth as Centipawns);
+        let razoring_margin = self.params.razoring_margin
+            .filter(|_| depth <= RAZORING_MAX_DEPTH && alpha.abs() < DECISIVE_SCORE)
+            .map(|margin| margin * depth as Centipawns);
+        if can_prune && (futility_margin.is_some() || razoring_margin.is_some()) {
+            // Only the comparisons with the pruning thresholds matter, the slow terms are skipped far from them
+            let lower = razoring_margin.map_or(-Centipawns::MAX, |margin| alpha - margin);
+            let upper = futility_margin.map_or(Centipawns::MAX, |margin| beta + margin);
+            let static_eval = eval::evaluate_lazy(&self.pos, lower, upper);
+            // Reverse futility pruning: the position is so good that the opponent will avoid it
+            if let Some(margin) = futility_margin {
+                if static_eval - margin >= beta {
+                    return Ok(beta);
+                }
+            }
+            // Razoring: the position is so bad that only a capture could save it. A check could also lead to a mate,
+            // so if the quiescence search doesn't try the quiet checks, razoring is skipped when there are any.
+            if let Some(margin) = razoring_margin {
+                if static_eval + margin <= alpha
+                    && (self.params.qsearch_quiet_checks || !MoveGen::has_quiet_check(&mut self.pos))
+                {
+                    let score = self.quiesce(alpha, alpha + 1, pv_index, 0)?;
+                    if score <= alpha {
+                        return Ok(alpha);
+                    }
+                }
+            }
+        }
+        // Late move pruning: close to the leaves, only the first quiet moves are searched
+        let late_move_count = {
+            if can_prune && self.params.late_move_pruning && depth <= LATE_MOVE_PRUNING_MAX_DEPTH {
+                3 + depth as u32 * depth as u32
+            } else {
+                u32::MAX
+            }
+        };
+        
         // If a hint is available, try the hinted move as the leftmost child
         if let Some(pv_hint) = hint {
             if IS_PV && pv_hint.len() > pv_index {
@@ -157,21 +296,36 @@ impl Searcher {
                     score = -self.alphabeta::<IS_PV,false>(depth-1, search_depth, pv_index+1, -beta, -alpha, true, hint)?;
                 }
                 self.pos.unmake_move();
-                // This is the leftmost branch, we know that best_score = -INF && alpha = -INF
-                assert!(score > best_score);
-                assert!(score > alpha);
+                // This is the leftmost branch, we know that best_score = -INF.
+                // However, alpha and beta can be finite when using aspiration windows
                 best_score = score;
                 best_move = mv;
-                alpha = score;
-                self.update_history_heuristic(depth, mv);
+                if score > alpha {
+                    if score >= beta {
+                        return Ok(self.beta_cutoff::<IS_PV>(depth, pv_index, mv, beta));
+                    }
+                    alpha = score;
+                    self.update_history_heuristic(depth, mv);
+                }
             }
         }
         
-        // Get potential moves, sorted by move ordering heuristics (try the most promising moves first)
-        let moves = MoveGen::get_pseudo_moves(&mut self.pos, true);
-        for (_move_score, mv) in self.sort_moves_by_score(moves, depth) {
+        // Get potential moves in move ordering order (try the most promising moves first)
+        let mut picker = MovePicker::new(self.hash_move(), self.killers(depth), true);
+        // Computed when the first move is tried, since the node may be cut off before
+        let mut legality = None;
+        while let Some(mv) = picker.next(self) {
             
-            if !MoveGen::make_move_if_legal(mv, &mut self.pos) {
+            if IS_ROOT && self.root_moves.as_ref().is_some_and(|root_moves| !root_moves.contains(&mv)) {
+                continue;
+            }
+            if IS_ROOT && self.excluded_root_moves.contains(&mv) {
+                continue;
+            }
+            if num_legal_moves >= late_move_count && mv.is_quiet() && best_score > -DECISIVE_SCORE {
+                continue;
+            }
+            if !legality.get_or_insert_with(|| LegalityInfo::new(&self.pos)).make_move_if_legal(mv, &mut self.pos) {
                 continue;
             }
 
@@ -216,18 +370,7 @@ impl Searcher {
 
                 if score > alpha {
                     if score >= beta {
-                        // Record new killer moves
-                        self.update_killers(depth, mv);
-                        // Beta cutoff, store in transpositon table
-                        self.transposition_table.insert(Entry::new(
-                            self.zobrist(),
-                            EntryFlag::Beta,
-                            beta,
-                            mv,
-                            depth,
-                        ));
-                        self.end_pv::<IS_PV>(pv_index);
-                        return Ok(beta);
+                        return Ok(self.beta_cutoff::<IS_PV>(depth, pv_index, mv, beta));
                     }
                     alpha = score;
 
@@ -274,31 +417,103 @@ impl Searcher {
     }
 
 
-    // Keep seaching, but only consider capture moves (avoid horizon effect)
-    fn quiesce(&mut self, mut alpha: Centipawns, beta: Centipawns, pv_index: usize) -> Result<Centipawns, SearchTimeout> {
+    // Keep seaching, but only consider capture moves (avoid horizon effect). `qply` is the number of plies since the
+    // start of the quiescence search.
+    // When in check, all the evasions are searched (up to QSEARCH_MAX_EVASION_PLY). If the `qsearch_quiet_checks`
+    // parameter is set, the first ply also searches the quiet moves that give check.
+    fn quiesce(&mut self, mut alpha: Centipawns, beta: Centipawns, pv_index: usize, qply: usize) -> Result<Centipawns, SearchTimeout> {
         
         if self.pos.leader_is_captured() {
             return Ok(self.checkmate_score(pv_index));
         }
         self.increment_num_nodes()?;
 
-        let score = eval::evaluate(&self.pos);
+        let in_check = MoveGen::in_check(&mut self.pos);
+        if in_check {
+            if self.pos.increment_num_checks() {
+                // If the player has been checked N times, the game is over
+                return Ok(self.checkmate_score(pv_index));
+            }
+            if qply < QSEARCH_MAX_EVASION_PLY {
+                return self.quiesce_evasions(alpha, beta, pv_index, qply);
+            }
+        }
+
+        let stand_pat = eval::evaluate_lazy(&self.pos, alpha, beta);
         
-        if score >= beta {
+        if stand_pat >= beta {
             return Ok(beta);
         }
-        if score > alpha {
-            alpha = score;
+        if stand_pat > alpha {
+            alpha = stand_pat;
         }
 
-        // Get only captures, sorted by move ordering heuristics (try the most promising moves first)
-        let moves = MoveGen::get_pseudo_moves(&mut self.pos, false);
-        for (_move_score, mv) in self.sort_moves_by_score(moves, 0) {
+        let futility_margin = self.params.qsearch_futility_margin.filter(|_| self.can_prune());
+        // Get only captures, in move ordering order (try the most promising moves first)
+        let mut picker = MovePicker::new(self.hash_move(), [Move::null(); 2], false);
+        let mut legality = None;
+        while let Some(mv) = picker.next(self) {
+            // Skip the captures that lose material (unless trying to lose)
+            if !self.pos.global_rules.invert_win_conditions && see::is_losing_capture(&self.pos, mv) {
+                continue;
+            }
+            // Futility pruning: skip the captures that cannot raise the score above alpha
+            if let Some(margin) = futility_margin {
+                if !mv.is_promotion() && stand_pat + self.capture_gain(mv) + margin <= alpha {
+                    continue;
+                }
+            }
             // This is a capture move, so there is no need to check for repetition
-            if !MoveGen::make_move_if_legal(mv, &mut self.pos) {
+            if !legality.get_or_insert_with(|| LegalityInfo::new(&self.pos)).make_move_if_legal(mv, &mut self.pos) {
+                continue;
+            }
+            let score = -self.quiesce(-beta, -alpha, pv_index+1, qply+1)?;
+            self.pos.unmake_move();
+
+            if score >= beta {
+                return Ok(beta);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        if qply == 0 && self.params.qsearch_quiet_checks {
+            for mv in MoveGen::get_quiet_checks(&mut self.pos) {
+                if !legality.get_or_insert_with(|| LegalityInfo::new(&self.pos)).make_move_if_legal(mv, &mut self.pos) {
+                    continue;
+                }
+                let score = {
+                    if let Some(end_score) = self.is_game_over(mv, pv_index+1) { -end_score }
+                    else { -self.quiesce(-beta, -alpha, pv_index+1, qply+1)? }
+                };
+                self.pos.unmake_move();
+
+                if score >= beta {
+                    return Ok(beta);
+                }
+                if score > alpha {
+                    alpha = score;
+                }
+            }
+        }
+        Ok(alpha)
+    }
+
+    // Search all the legal moves when in check (no stand pat), detecting checkmates
+    fn quiesce_evasions(&mut self, mut alpha: Centipawns, beta: Centipawns, pv_index: usize, qply: usize) -> Result<Centipawns, SearchTimeout> {
+        let mut num_legal_moves = 0;
+        let mut picker = MovePicker::new(self.hash_move(), self.killers(0), true);
+        let mut legality = None;
+        while let Some(mv) = picker.next(self) {
+            if !legality.get_or_insert_with(|| LegalityInfo::new(&self.pos)).make_move_if_legal(mv, &mut self.pos) {
                 continue;
             }
-            let score = -self.quiesce(-beta, -alpha, pv_index+1)?;
+            num_legal_moves += 1;
+            let score = {
+                if let Some(end_score) = self.is_game_over(mv, pv_index+1) { -end_score }
+                else { -self.quiesce(-beta, -alpha, pv_index+1, qply+1)? }
+            };
             self.pos.unmake_move();
 
             if score >= beta {
@@ -308,6 +523,10 @@ impl Searcher {
                 alpha = score;
             }
         }
+        if num_legal_moves == 0 {
+            // No legal moves and in check: Checkmate
+            return Ok(self.checkmate_score(pv_index));
+        }
         Ok(alpha)
     }
     
@@ -319,6 +538,10 @@ impl Searcher {
     #[inline]
     fn increment_num_nodes(&mut self) -> Result<(), SearchTimeout> {
         self.nodes_searched += 1;
+        // Node limit, ignored in the first search like the time limit
+        if self.total_nodes_searched + self.nodes_searched > self.max_nodes && self.max_searching_depth > 2 {
+            return Err(SearchTimeout);
+        }
         // Check for timeout periodically (every 2^19 nodes)
         #[allow(clippy::collapsible_if)]
         if self.nodes_searched.trailing_zeros() >= 19 {
@@ -360,6 +583,68 @@ impl Searcher {
         None
     }
 
+    /// If the root position is in the tablebase, returns the best move and its score
+    fn probe_tablebase_root(&mut self) -> Option<(Move, Centipawns)> {
+        let tablebase = self.tablebase.as_ref()?;
+        // Only use the tablebase if the root position is in it
+        let _root_result = tablebase.probe(&self.pos)?;
+        let mut best: Option<(Move, ProbeResult)> = None;
+        for mv in MoveGen::get_legal_moves(&mut self.pos) {
+            self.pos.make_move(mv);
+            let result = {
+                if self.pos.leader_is_captured() || self.pos.piece_is_on_winning_square() {
+                    // Game over, the opponent loses (or wins if the win conditions are inverted)
+                    if self.pos.global_rules.invert_win_conditions { Some(ProbeResult::Win(0)) }
+                    else { Some(ProbeResult::Loss(0)) }
+                } else {
+                    tablebase.probe(&self.pos)
+                }
+            };
+            self.pos.unmake_move();
+            // The position after the move is not in the tablebase (e.g. en passant is possible), search normally
+            let result = result?.parent();
+            if best.is_none_or(|(_, best_result)| result.is_better_than(best_result)) {
+                best = Some((mv, result));
+            }
+        }
+        best.map(|(mv, result)| (mv, tablebase_score(result, 0)))
+    }
+
+    // Record the move that caused a beta cutoff and return the score of the node
+    fn beta_cutoff<const IS_PV: bool>(&mut self, depth: Depth, pv_index: usize, mv: Move, beta: Centipawns) -> Centipawns {
+        // Record new killer moves
+        self.update_killers(depth, mv);
+        // Beta cutoff, store in transpositon table
+        self.transposition_table.insert(Entry::new(
+            self.zobrist(),
+            EntryFlag::Beta,
+            beta,
+            mv,
+            depth,
+        ));
+        self.end_pv::<IS_PV>(pv_index);
+        beta
+    }
+
+    // Upper bound of the material won by a capture (a large value if it cannot be estimated)
+    fn capture_gain(&self, mv: Move) -> Centipawns {
+        let player = self.pos.whos_turn;
+        match self.pos.player_piece_at(player, mv.get_from()) {
+            // The explosion can capture more pieces
+            Some(attacker) if !attacker.explodes_on_capture() => {
+                self.pos.player_piece_at(1 - player, mv.get_target()).map_or(0, |p| p.get_material_score())
+            },
+            _ => Centipawns::MAX / 2,
+        }
+    }
+
+    #[inline]
+    // The pruning heuristics based on the static evaluation are not reliable if the player must
+    // capture or wants to lose material
+    fn can_prune(&self) -> bool {
+        !self.pos.global_rules.invert_win_conditions && !self.pos.global_rules.capturing_is_forced
+    }
+
     #[inline]
     fn update_killers(&mut self, depth: Depth, mv: Move) {
         if !mv.is_capture() && mv != self.killer_moves[depth as usize][0] && mv != self.killer_moves[depth as usize][1] {
@@ -378,33 +663,15 @@ impl Searcher {
     }
 
     #[inline]
-    fn sort_moves_by_score(&self, moves: Vec<Move>, depth: Depth) -> Vec<(Centipawns, Move)> {
+    // Best move stored in the transposition table (search it first), or the null move
+    fn hash_move(&self) -> Move {
+        self.transposition_table.retrieve(self.zobrist()).map_or(Move::null(), |entry| entry.mv)
+    }
+
+    #[inline]
+    fn killers(&self, depth: Depth) -> [Move; 2] {
         // Limit depth to the size of the killer moves array
-        let depth = std::cmp::min(self.killer_moves.len() - 1, depth as usize);
-        let mut moves_and_score = Vec::with_capacity(moves.len());
-        for mv in moves {
-            let score = eval::score_move(self, depth, mv);
-            moves_and_score.push((score, mv));
-        }
-
-        // Assign PV/hash moves to Centipawns::MAX (search first in the PV)
-        if let Some(entry) = self.transposition_table.retrieve(self.zobrist()) {
-            let best_move = &entry.mv;
-            for (score, mv) in &mut moves_and_score {
-                if mv == best_move {
-                    *score = Centipawns::MAX;
-                }
-            }
-        }
-        
-        // Sort moves by decreasing score
-        moves_and_score.sort_unstable_by(|a, b| b.0.cmp(&a.0));
-        
-        if self.pos.global_rules.invert_win_conditions {
-            moves_and_score.reverse();
-        }
-        
-        moves_and_score
+        self.killer_moves[std::cmp::min(self.killer_moves.len() - 1, depth as usize)]
     }
     
     #[inline]
@@ -1,102 +1,249 @@
 use crate::position::Position;
-use crate::types::{Move, Centipawns};
+use crate::types::{Move, Centipawns, EvalBreakdown, SideEvalBreakdown, PieceMaterial, PiecePositional};
+use crate::utils::from_index;
 
-use super::Searcher;
+use super::see;
+use super::eval_terms;
 
-/// Retrieves the score for the player to move (`position.whos_turn`)
-pub fn evaluate(position: &Position) -> Centipawns {
-    // Material score (without leaders) of both players combined, below which the game is considered to be in the endgame
-    // Arbitrary threshold of roughly 2 queens and 2 rooks, feel free to experiment
-    const ENDGAME_THRESHOLD: Centipawns = 3000;
+/// Value of `game_phase()` in the middlegame. The endgame is 0.
+pub const MIDGAME_PHASE: Centipawns = 256;
+/// Largest expected contribution of the terms in `eval_terms`, see `evaluate_lazy()`
+pub const LAZY_EVAL_MARGIN: Centipawns = 300;
+
+/// Retrieves the score for the player to move (`position.whos_turn`).
+/// If the position has a neural network (see `Position::set_network()`), it's used instead of the handcrafted evaluation.
+pub fn evaluate<const W: usize>(position: &Position<W>) -> Centipawns {
+    evaluate_in_window(position, None)
+}
+
+/// Like `evaluate()`, but the terms in `eval_terms` are skipped when the rest of the handcrafted evaluation is more
+/// than `LAZY_EVAL_MARGIN` below `alpha` or above `beta`. Used in the quiescence search and for the pruning decisions
+/// in the main search, where only the comparison with the window matters.
+pub fn evaluate_lazy<const W: usize>(position: &Position<W>, alpha: Centipawns, beta: Centipawns) -> Centipawns {
+    evaluate_in_window(position, Some((alpha, beta)))
+}
+
+fn evaluate_in_window<const W: usize>(position: &Position<W>, window: Option<(Centipawns, Centipawns)>) -> Centipawns {
+    let params = &position.eval_params;
+    let player_num = position.whos_turn;
     
+    let mut check_score = 0;
+    if let Some(times_checked) = position.get_times_checked() {
+        check_score -= params.check_penalty * times_checked[player_num as usize] as Centipawns;
+        check_score += params.check_penalty * times_checked[1-player_num as usize] as Centipawns;
+    }
+    let window = window.map(|(alpha, beta)| (alpha.saturating_sub(check_score), beta.saturating_sub(check_score)));
+    
+    // The network is trained with the results of the games, so it already takes the win conditions into account
+    #[cfg(feature = "nnue")]
+    let score = match position.get_nnue() {
+        Some(nnue) => nnue.evaluate(position),
+        None => handcrafted_eval(position, window),
+    };
+    #[cfg(not(feature = "nnue"))]
+    let score = handcrafted_eval(position, window);
+    
+    score + check_score
+}
+
+/// Evaluation based on the material, the piece-square tables and the terms in `eval_terms`,
+/// for the player to move (without the check penalty). See `evaluate_lazy()` for the window.
+fn handcrafted_eval<const W: usize>(position: &Position<W>, window: Option<(Centipawns, Centipawns)>) -> Centipawns {
     let player_num = position.whos_turn;
-    // Material score (opponent pieces are negative)
-    let mut score = 0;
+    // Middlegame and endgame scores (opponent pieces are negative)
+    let mut score_midgame = 0;
+    let mut score_endgame = 0;
     //Material score of both players (opponent pieces are positive), without the leaders
     let mut total_leaderless_score = 0;
     
     for ps in &position.pieces {
-        let (material_score, leaders_score) = ps.get_material_score();
+        let (material_score, leaders_score) = ps.get_material_score::<false>();
+        let (material_score_endgame, _) = ps.get_material_score::<true>();
+        let midgame = material_score + ps.get_positional_score::<false>();
+        let endgame = material_score_endgame + ps.get_positional_score::<true>();
         
         if ps.get_player_num() == player_num {
-            score += material_score;
+            score_midgame += midgame;
+            score_endgame += endgame;
         } else {
-            score -= material_score;
+            score_midgame -= midgame;
+            score_endgame -= endgame;
         }
         
         total_leaderless_score += material_score - leaders_score;
     }
-
-    // Positional score
-    let is_endgame = total_leaderless_score < ENDGAME_THRESHOLD;
-    for ps in &position.pieces {
-        let ps_score = {
-            if is_endgame {
-                ps.get_positional_score::<true>()
-            } else {
-                ps.get_positional_score::<false>()
-            }
-        };
-        if ps.get_player_num() == player_num {
-            score += ps_score;
-        } else {
-            score -= ps_score;
+    let phase = game_phase(position, total_leaderless_score);
+    
+    // The remaining terms are much slower than the incremental scores above, don't compute them if they
+    // are unlikely to bring the score back inside the window
+    if let Some((alpha, beta)) = window {
+        let score = interpolate(position, score_midgame, score_endgame, phase);
+        if score.saturating_add(LAZY_EVAL_MARGIN) <= alpha || score.saturating_sub(LAZY_EVAL_MARGIN) >= beta {
+            return score;
         }
     }
+    
+    // Mobility, leader safety, pawn structure and hanging pieces (from the point of view of white)
+    let (terms_midgame, terms_endgame) = eval_terms::evaluate_terms(position);
+    let sign = if player_num == 0 { 1 } else { -1 };
+    score_midgame += sign * terms_midgame;
+    score_endgame += sign * terms_endgame;
+    
+    interpolate(position, score_midgame, score_endgame, phase)
+}
 
+/// Interpolates between the middlegame and endgame scores, in order to avoid sudden jumps in the evaluation
+fn interpolate<const W: usize>(position: &Position<W>, midgame: Centipawns, endgame: Centipawns, phase: Centipawns) -> Centipawns {
+    let score = (midgame * phase + endgame * (MIDGAME_PHASE - phase)) / MIDGAME_PHASE;
     // When trying to lose, minimize own score
     if position.global_rules.invert_win_conditions {
-        score = -score;
+        -score
+    } else {
+        score
     }
-    
-    if let Some(times_checked) = position.get_times_checked() {
-        const CHECK_PENALTY: Centipawns = 512;
-        score -= CHECK_PENALTY * times_checked[player_num as usize] as Centipawns;
-        score += CHECK_PENALTY * times_checked[1-player_num as usize] as Centipawns;
+}
+
+/// Returns the phase of the game, from 0 (endgame) to `MIDGAME_PHASE` (middlegame), given the material score
+/// of both players without the leaders. The phase depends on the fraction of the initial material that remains.
+pub fn game_phase<const W: usize>(position: &Position<W>, leaderless_material: Centipawns) -> Centipawns {
+    let params = &position.eval_params;
+    if position.initial_material <= 0 {
+        return 0;
+    }
+    let remaining = leaderless_material as f32 / position.initial_material as f32;
+    if remaining >= params.midgame_phase_limit {
+        MIDGAME_PHASE
+    } else if remaining <= params.endgame_phase_limit {
+        0
+    } else {
+        let fraction = (remaining - params.endgame_phase_limit) / (params.midgame_phase_limit - params.endgame_phase_limit);
+        (fraction * MIDGAME_PHASE as f32) as Centipawns
     }
+}
+
+
+/// Explains the result of `evaluate()`, listing the contribution of each piece and evaluation term
+pub fn explain<const W: usize>(position: &Position<W>) -> EvalBreakdown {
+    let params = &position.eval_params;
+    let player_num = position.whos_turn;
+    let (terms_midgame, terms_endgame) = eval_terms::evaluate_terms_by_player(position);
+    let mut total_leaderless_score = 0;
     
-    score
+    let sides = [0, 1].map(|player| {
+        let mut side = SideEvalBreakdown {
+            material: Vec::new(),
+            positional: Vec::new(),
+            terms_midgame: terms_midgame[player],
+            terms_endgame: terms_endgame[player],
+            midgame: terms_midgame[player],
+            endgame: terms_endgame[player],
+            check_penalty: 0,
+        };
+        for piece in position.pieces[player].iter() {
+            if piece.get_num_pieces() == 0 {
+                continue;
+            }
+            let material = PieceMaterial {
+                id: piece.get_piece_id(),
+                count: piece.get_num_pieces(),
+                midgame: piece.get_material_score_all::<false>(),
+                endgame: piece.get_material_score_all::<true>(),
+            };
+            side.midgame += material.midgame;
+            side.endgame += material.endgame;
+            if !piece.is_leader() {
+                total_leaderless_score += material.midgame;
+            }
+            side.material.push(material);
+            
+            let mut bb = piece.get_bitboard().clone();
+            while let Some(index) = bb.lowest_one() {
+                bb.clear_bit(index);
+                let (x, y) = from_index(index);
+                let positional = PiecePositional {
+                    id: piece.get_piece_id(),
+                    x,
+                    y,
+                    midgame: piece.get_positional_score::<false>(index),
+                    endgame: piece.get_positional_score::<true>(index),
+                };
+                side.midgame += positional.midgame;
+                side.endgame += positional.endgame;
+                side.positional.push(positional);
+            }
+        }
+        if let Some(times_checked) = position.get_times_checked() {
+            side.check_penalty = params.check_penalty * times_checked[player] as Centipawns;
+        }
+        side
+    });
+    
+    // Same computation as in handcrafted_eval()
+    let (own, opponent) = (&sides[player_num as usize], &sides[1 - player_num as usize]);
+    let phase = game_phase(position, total_leaderless_score);
+    let score_midgame = own.midgame - opponent.midgame;
+    let score_endgame = own.endgame - opponent.endgame;
+    let handcrafted = (score_midgame * phase + score_endgame * (MIDGAME_PHASE - phase)) / MIDGAME_PHASE;
+    let inverted = position.global_rules.invert_win_conditions;
+    
+    #[cfg(feature = "nnue")]
+    let network = position.get_nnue().map(|nnue| nnue.evaluate(position));
+    #[cfg(not(feature = "nnue"))]
+    let network = None;
+    
+    let score = match network {
+        Some(score) => score,
+        None if inverted => -handcrafted,
+        None => handcrafted,
+    };
+    let total = score - own.check_penalty + opponent.check_penalty;
+    
+    let [white, black] = sides;
+    EvalBreakdown { white, black, phase, handcrafted, inverted, network, player_to_move: player_num, total }
 }
 
 
-/// Scores a move on a position
-/// This is used for move ordering in order to search the moves with the most potential first
-pub fn score_move(search: &Searcher, depth: usize, mv: Move) -> Centipawns {
+/// Scores a move on a position, given the history score of the move (only used for non-captures).
+/// This is used for move ordering in order to search the moves with the most potential first.
+/// Captures that lose material get a negative score, even if they promote. The killer moves are ordered by the `MovePicker`.
+pub fn score_move<const W: usize>(position: &Position<W>, history_score: Centipawns, mv: Move) -> Centipawns {
     const CAPTURE_BASE_SCORE: Centipawns = 10000;
-    const KILLERMOVE_SCORE: Centipawns = 9000;
     const PROMOTION_SCORE: Centipawns = 1000;
     let mut score = 0;
     if mv.is_capture() {
-        let current_player = search.pos.whos_turn;
-        let attacker = search.pos.player_piece_at(current_player, mv.get_from()).unwrap();
-        let victim = search.pos.player_piece_at(1-current_player, mv.get_target()).unwrap();
+        let current_player = position.whos_turn;
+        let attacker = position.player_piece_at(current_player, mv.get_from()).unwrap();
+        let victim = position.player_piece_at(1-current_player, mv.get_target()).unwrap();
 
         let attacker_score = attacker.get_material_score();
         let victim_score = victim.get_material_score();
 
+        // Losing captures are searched after the quiet moves (when trying to lose, use MVV-LVA only).
+        // The exchange already includes the value gained by promoting.
+        let trying_to_lose = position.global_rules.invert_win_conditions;
+        let can_lose_material = attacker.explodes_on_capture() || attacker_score > victim_score;
+        let exchange = if can_lose_material && !trying_to_lose { see::see(position, mv) } else { 0 };
+        if exchange < 0 {
+            return exchange;
+        }
         score += CAPTURE_BASE_SCORE;
         score += 8 * victim_score - attacker_score;
-    } else if mv == search.killer_moves[depth][0] || mv == search.killer_moves[depth][1] {
-        score += KILLERMOVE_SCORE;
     } else {
-        score += search.history_moves[mv.get_from() as usize][mv.get_to() as usize];
+        score += history_score;
     }
     if mv.is_promotion() {
         score += PROMOTION_SCORE;
     }
-    if search.pos.global_rules.invert_win_conditions {
-        score = -score;
-    }
     score
 }
 
 /// Determines whether or not null move pruning can be performed for a Position
-pub fn can_do_null_move(position: &Position) -> bool {
+pub fn can_do_null_move<const W: usize>(position: &Position<W>) -> bool {
     // Material score (without leaders) of current player, below which null move pruning is NOT performed
     // Arbitrary threshold of roughly 1 rook, feel free to experiment
     const NULL_MOVE_THRESHOLD: Centipawns = 500;
     
     let piece_set = &position.pieces[position.whos_turn as usize];
-    let (total_score, leader_score) = piece_set.get_material_score();
+    let (total_score, leader_score) = piece_set.get_material_score::<false>();
     total_score - leader_score > NULL_MOVE_THRESHOLD
 }
@@ -0,0 +1,151 @@
+use crate::types::Centipawns;
+use crate::{wrap_res, err, err_assert};
+
+/// Value of a parameter, which can be converted to a `f64` for tuning
+trait ParamValue: Copy {
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+}
+impl ParamValue for Centipawns {
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(value: f64) -> Self { value.round() as Centipawns }
+}
+impl ParamValue for f32 {
+    fn to_f64(self) -> f64 { self as f64 }
+    fn from_f64(value: f64) -> Self { value as f32 }
+}
+
+macro_rules! eval_params {
+    ($($(#[doc = $doc:expr])* $field:ident: $type:ty = $default:expr, step $step:expr;)*) => {
+        /// Constants used by the evaluation function. The material values and piece-square tables are computed
+        /// from these parameters when the pieces are registered, so changing them requires creating a new position
+        /// (see `Engine::set_eval_params()`).
+        ///
+        /// The parameters can be stored as text (see `EvalParams::parse()`), with one `name = value` line per parameter.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct EvalParams {
+            $($(#[doc = $doc])* pub $field: $type,)*
+        }
+
+        impl Default for EvalParams {
+            fn default() -> Self {
+                EvalParams { $($field: $default,)* }
+            }
+        }
+
+        impl EvalParams {
+            /// Names of the parameters, in the same order as `to_vec()`
+            pub const NAMES: &'static [&'static str] = &[$(stringify!($field),)*];
+            /// Smallest meaningful change of each parameter, used by the tuner
+            pub const STEPS: &'static [f64] = &[$($step,)*];
+
+            /// Returns the value of all the parameters
+            pub fn to_vec(&self) -> Vec<f64> {
+                vec![$(self.$field.to_f64(),)*]
+            }
+
+            /// Creates the parameters from the values returned by `to_vec()`.
+            /// Integer parameters are rounded to the nearest value.
+            pub fn from_vec(values: &[f64]) -> wrap_res!(EvalParams) {
+                err_assert!(values.len() == Self::NAMES.len(), "Expected {} values, got {}", Self::NAMES.len(), values.len());
+                let mut values = values.iter();
+                Ok(EvalParams { $($field: <$type>::from_f64(*values.next().unwrap()),)* })
+            }
+        }
+
+        impl std::fmt::Display for EvalParams {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                $(writeln!(f, "{} = {}", stringify!($field), self.$field)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+eval_params! {
+    /// Fraction of the initial material (without leaders) below which the position is evaluated as a pure endgame
+    endgame_phase_limit: f32 = 0.2, step 0.02;
+    /// Fraction of the initial material (without leaders) above which the position is evaluated as a pure middlegame.
+    /// Between both limits, the middlegame and endgame scores are interpolated.
+    midgame_phase_limit: f32 = 0.85, step 0.02;
+    /// Penalty for each time that a player has been checked (only if `checks_to_lose` is set)
+    check_penalty: Centipawns = 512, step 8.0;
+    /// Material value of a sliding capture direction, multiplied by the average length of the direction
+    attack_mul: f32 = 10.0, step 0.25;
+    /// Same as `attack_mul`, in the endgame
+    attack_mul_endgame: f32 = 11.0, step 0.25;
+    /// Material value of a sliding non-capture direction, multiplied by the average length of the direction
+    translate_mul: f32 = 6.5, step 0.25;
+    /// Penalty for sliding pieces that are limited to a single color of squares
+    single_color_penalty: Centipawns = 150, step 5.0;
+    /// Penalty for sliding pieces that can only move in one direction along an axis
+    single_direction_penalty: Centipawns = 200, step 5.0;
+    /// Material value of each jump (capture or non-capture)
+    jump_value: Centipawns = 20, step 1.0;
+    /// Same as `jump_value`, in the endgame
+    jump_value_endgame: Centipawns = 18, step 1.0;
+    /// Material value of each step of the delta-based sliding moves
+    sliding_delta_value: Centipawns = 20, step 1.0;
+    /// Material value of being able to promote
+    promotion_value: Centipawns = 40, step 2.0;
+    /// Same as `promotion_value`, in the endgame
+    promotion_value_endgame: Centipawns = 70, step 2.0;
+    /// The material value of the leader is multiplied by this factor
+    leader_mul: Centipawns = 4, step 1.0;
+    /// Positional score for each move that lands on a center square
+    center_mul: Centipawns = 5, step 1.0;
+    /// Positional score for each square of distance to the edge of the board
+    edge_dist_mul: Centipawns = 5, step 1.0;
+    /// Positional score for being close to a promotion square, multiplied by the number of moves saved
+    promotion_dist_mul: Centipawns = 7, step 1.0;
+    /// Positional score for being close to a win square, multiplied by the number of moves saved
+    win_dist_mul: Centipawns = 50, step 2.0;
+    /// Positional score of the leader on the squares where it lands after castling (not in the endgame)
+    castled_bonus: Centipawns = 40, step 2.0;
+    /// Positional score of the leader beyond the castling squares (not in the endgame)
+    castled_side_bonus: Centipawns = 20, step 2.0;
+    /// Score for each square attacked by a piece (other than the leader), excluding the squares occupied by
+    /// pieces of the same player
+    mobility_mul: Centipawns = 2, step 1.0;
+    /// Penalty for each attack of an enemy piece on the squares around the leader (not in the endgame)
+    leader_attack_penalty: Centipawns = 6, step 1.0;
+    /// Score for each piece with forward-only movement (like a pawn) right in front of the leader (not in the endgame)
+    leader_shield_bonus: Centipawns = 12, step 1.0;
+    /// Score for promoting pieces with forward-only movement that cannot be stopped by an enemy piece with
+    /// forward-only movement, multiplied by the number of ranks advanced
+    passed_bonus: Centipawns = 4, step 1.0;
+    /// Same as `passed_bonus`, in the endgame
+    passed_bonus_endgame: Centipawns = 10, step 1.0;
+    /// Penalty for promoting pieces with forward-only movement whose next square is occupied
+    blocked_penalty: Centipawns = 10, step 1.0;
+    /// Penalty for each piece (other than the leader) that is attacked by the opponent and not defended
+    hanging_penalty: Centipawns = 25, step 2.0;
+}
+
+impl EvalParams {
+    /// Parses the parameters written as `name = value` lines. Empty lines and lines starting with `#` are ignored,
+    /// and the parameters that are not present keep their default value.
+    pub fn parse(contents: &str) -> wrap_res!(EvalParams) {
+        let mut values = EvalParams::default().to_vec();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = match line.split_once('=') {
+                Some(parts) => parts,
+                None => err!("Line {}: expected 'name = value', got '{line}'", i + 1),
+            };
+            let (name, value) = (name.trim(), value.trim());
+            let index = match EvalParams::NAMES.iter().position(|n| *n == name) {
+                Some(index) => index,
+                None => err!("Line {}: unknown parameter '{name}'", i + 1),
+            };
+            values[index] = match value.parse::<f64>() {
+                Ok(value) if value.is_finite() => value,
+                _ => err!("Line {}: invalid value '{value}'", i + 1),
+            };
+        }
+        EvalParams::from_vec(&values)
+    }
+}
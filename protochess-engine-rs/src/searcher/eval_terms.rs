@@ -0,0 +1,170 @@
+use crate::Position;
+use crate::types::{Bitboard, BCoord, BIndex, Centipawns, Player};
+use crate::utils::{from_index, to_index};
+
+/// Evaluation terms that depend on the interaction between the pieces: mobility, leader safety,
+/// passed and blocked promoting pieces, and hanging pieces. They work with any piece definition.
+/// Unlike the material and positional scores, they are computed from scratch on every call
+/// (the terms whose weights are 0 are skipped). Returns the middlegame and endgame scores, from the point of view of white.
+pub fn evaluate_terms<const W: usize>(position: &Position<W>) -> (Centipawns, Centipawns) {
+    let (midgame, endgame) = evaluate_terms_by_player(position);
+    (midgame[0] - midgame[1], endgame[0] - endgame[1])
+}
+
+/// Same as `evaluate_terms()`, but returns the middlegame and endgame scores of each player separately
+pub fn evaluate_terms_by_player<const W: usize>(position: &Position<W>) -> ([Centipawns; 2], [Centipawns; 2]) {
+    let mut midgame = [0; 2];
+    let mut endgame = [0; 2];
+    let mut attacked = [Bitboard::zero(), Bitboard::zero()];
+    let leader_zones = [leader_zone(position, 0), leader_zone(position, 1)];
+    let params = &position.eval_params;
+    let uses_attacks = params.mobility_mul != 0 || params.leader_attack_penalty != 0 || params.hanging_penalty != 0;
+    let uses_forward_pieces = params.leader_shield_bonus != 0 || params.passed_bonus != 0
+        || params.passed_bonus_endgame != 0 || params.blocked_penalty != 0;
+
+    for player in (0..2).filter(|_| uses_attacks) {
+        let own_pieces = position.pieces[player].get_occupied();
+        for piece in position.pieces[player].iter() {
+            let mut bb = piece.get_bitboard().clone();
+            while let Some(index) = bb.lowest_one() {
+                bb.clear_bit(index);
+                let attacks = piece.get_attacks(index, position);
+                if !piece.is_leader() {
+                    let mobility = (&attacks & !own_pieces).count_ones() as Centipawns * params.mobility_mul;
+                    midgame[player] += mobility;
+                    endgame[player] += mobility;
+                }
+                // Attacks on the enemy leader are penalized for the enemy
+                let leader_attacks = (&attacks & &leader_zones[1 - player]).count_ones() as Centipawns;
+                midgame[1 - player] -= leader_attacks * params.leader_attack_penalty;
+                attacked[player] |= &attacks;
+            }
+        }
+    }
+
+    for player in 0..2 {
+        // Pieces that are attacked and not defended
+        if params.hanging_penalty != 0 {
+            let mut non_leaders = position.pieces[player].get_occupied().clone();
+            if let Some(leader) = position.pieces[player].get_leader() {
+                non_leaders ^= leader.get_bitboard();
+            }
+            let hanging = non_leaders & &attacked[1 - player] & !&attacked[player];
+            midgame[player] -= hanging.count_ones() as Centipawns * params.hanging_penalty;
+            endgame[player] -= hanging.count_ones() as Centipawns * params.hanging_penalty;
+        }
+
+        if !uses_forward_pieces {
+            continue;
+        }
+        let (midgame_score, endgame_score) = forward_pieces_score(position, player as Player);
+        midgame[player] += midgame_score;
+        endgame[player] += endgame_score;
+    }
+
+    (midgame, endgame)
+}
+
+/// Returns the squares around the leader of a player, or an empty bitboard if the player doesn't have a single leader
+fn leader_zone<const W: usize>(position: &Position<W>, player: Player) -> Bitboard<W> {
+    let mut zone = Bitboard::zero();
+    let leader_square = match single_leader(position, player) {
+        Some(index) => index,
+        None => return zone,
+    };
+    let (x, y) = from_index(leader_square);
+    for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 0), (0, 1), (1, -1), (1, 0), (1, 1)] {
+        if let Some(index) = offset_index(position, x, y, dx, dy) {
+            zone.set_bit(index);
+        }
+    }
+    zone
+}
+
+/// Returns the scores of the pieces that can only move forward (like pawns): passed and blocked promoting pieces,
+/// and pieces that shield the leader
+fn forward_pieces_score<const W: usize>(position: &Position<W>, player: Player) -> (Centipawns, Centipawns) {
+    let params = &position.eval_params;
+    // Lowest and highest rank of the enemy forward-only pieces on each file
+    let mut enemy_min_rank = [BCoord::MAX; 16];
+    let mut enemy_max_rank = [0; 16];
+    let mut enemy_on_file = [false; 16];
+    for piece in position.pieces[1 - player as usize].iter() {
+        if piece.get_forward_direction() == 0 {
+            continue;
+        }
+        let mut bb = piece.get_bitboard().clone();
+        while let Some(index) = bb.lowest_one() {
+            bb.clear_bit(index);
+            let (x, y) = from_index(index);
+            enemy_min_rank[x as usize] = enemy_min_rank[x as usize].min(y);
+            enemy_max_rank[x as usize] = enemy_max_rank[x as usize].max(y);
+            enemy_on_file[x as usize] = true;
+        }
+    }
+    let leader = single_leader(position, player).map(from_index);
+
+    let mut midgame = 0;
+    let mut endgame = 0;
+    for piece in position.pieces[player as usize].iter() {
+        let direction = piece.get_forward_direction();
+        if direction == 0 {
+            continue;
+        }
+        let can_promote = piece.get_movement().can_promote();
+        let mut bb = piece.get_bitboard().clone();
+        while let Some(index) = bb.lowest_one() {
+            bb.clear_bit(index);
+            let (x, y) = from_index(index);
+
+            // Pieces right in front of the leader protect it
+            if let Some((leader_x, leader_y)) = leader {
+                if (x as i8 - leader_x as i8).abs() <= 1 && y as i8 == leader_y as i8 + direction {
+                    midgame += params.leader_shield_bonus;
+                }
+            }
+            if !can_promote {
+                continue;
+            }
+
+            // Blocked: the square in front is occupied
+            if let Some(next) = offset_index(position, x, y, 0, direction) {
+                if position.occ_or_out_bounds.get_bit(next) {
+                    midgame -= params.blocked_penalty;
+                    endgame -= params.blocked_penalty;
+                }
+            }
+
+            // Passed: no enemy forward-only pieces in front, on the same or adjacent files
+            let first_file = x.saturating_sub(1) as usize;
+            let last_file = (x as usize + 1).min(15);
+            let is_passed = (first_file..=last_file).all(|file| {
+                !enemy_on_file[file] || if direction > 0 { enemy_max_rank[file] <= y } else { enemy_min_rank[file] >= y }
+            });
+            if is_passed {
+                let ranks_advanced = if direction > 0 { y } else { position.dimensions.height - 1 - y } as Centipawns;
+                midgame += ranks_advanced * params.passed_bonus;
+                endgame += ranks_advanced * params.passed_bonus_endgame;
+            }
+        }
+    }
+    (midgame, endgame)
+}
+
+/// Returns the index of the leader of a player, if the player has exactly 1 leader
+fn single_leader<const W: usize>(position: &Position<W>, player: Player) -> Option<BIndex> {
+    let leader = position.pieces[player as usize].get_leader()?;
+    if leader.get_num_pieces() != 1 {
+        return None;
+    }
+    leader.get_bitboard().lowest_one()
+}
+
+/// Returns the index at `(x + dx, y + dy)`, if it's in bounds
+fn offset_index<const W: usize>(position: &Position<W>, x: BCoord, y: BCoord, dx: i8, dy: i8) -> Option<BIndex> {
+    let (x2, y2) = (x as i8 + dx, y as i8 + dy);
+    if x2 < 0 || y2 < 0 || !position.in_bounds(x2 as BCoord, y2 as BCoord) {
+        return None;
+    }
+    Some(to_index(x2 as BCoord, y2 as BCoord))
+}
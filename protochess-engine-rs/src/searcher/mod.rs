@@ -1,25 +1,38 @@
 use std::collections::BTreeSet;
 
+use std::sync::Arc;
 #[cfg(feature = "parallel")]
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 #[cfg(feature = "parallel")]
 use std::sync::atomic::{Ordering, AtomicBool, AtomicU8};
 
 use instant::{Instant, Duration};
 
 use crate::types::{Move, Depth, Centipawns, SearchTimeout, ZobKey};
-use crate::Position;
+use crate::{Position, Tablebase};
+use crate::tablebase::Tablebases;
+#[cfg(feature = "syzygy")]
+use crate::tablebase::syzygy::Syzygy;
 
 mod alphabeta;
+mod move_picker;
 pub mod transposition_table;
 pub mod eval;
+pub mod see;
+pub mod params;
+pub mod eval_params;
+pub mod eval_terms;
+pub mod skill;
 
 use transposition_table::{TranspositionTable, TranspositionHandle};
+pub use params::SearchParams;
+pub use eval_params::EvalParams;
+pub use skill::Skill;
 
 #[derive(Debug, Clone)]
-pub struct Searcher {
+pub struct Searcher<const W: usize = 4> {
     // The position we are currently searching
-    pos: Position,
+    pos: Position<W>,
     //We store two killer moves per ply,
     //indexed by killer_moves[depth][0/1]
     killer_moves: [[Move;2];256],
@@ -28,10 +41,25 @@ pub struct Searcher {
     transposition_table: TranspositionHandle,
     // Stats
     nodes_searched: u64,
+    total_nodes_searched: u64,
     max_searching_depth: Depth,
     end_time: Instant,
     principal_variation: [Move; Depth::MAX as usize + 1],
     known_checks: BTreeSet<ZobKey>,
+    // Endgame tablebase, only if it's compatible with the position
+    tablebase: Option<Arc<Tablebase>>,
+    // True if the result of the root node was found in the tablebase
+    tablebase_root_hit: bool,
+    // Syzygy tablebase, only if the position is from standard chess
+    #[cfg(feature = "syzygy")]
+    syzygy: Option<Arc<Syzygy>>,
+    // If set, only these moves are searched at the root
+    root_moves: Option<Vec<Move>>,
+    // Moves that are not searched at the root (used to find the next best moves)
+    excluded_root_moves: Vec<Move>,
+    // The search is stopped after this number of nodes (except at depth 1)
+    max_nodes: u64,
+    params: SearchParams,
     
     // Attributes for parallel search
     #[cfg(feature = "parallel")]
@@ -42,20 +70,44 @@ pub struct Searcher {
     current_searched_depth: Arc<AtomicU8>,
 }
 
-type SearchRes = (Vec<Move>, Centipawns, Depth);
+/// Principal variation, score, depth reached and total number of nodes searched
+type SearchRes = (Vec<Move>, Centipawns, Depth, u64);
 
-impl Searcher {
-    fn new(position: &Position, transposition_table: TranspositionHandle) -> Searcher {
+/// Returns the number of moves until checkmate if the score is a checkmate score, or `None` otherwise.
+/// The result is positive if the player to move is winning, and negative if it's losing.
+pub fn mate_in(score: Centipawns) -> Option<i32> {
+    // Number of plies until checkmate
+    let diff = -(score.abs() + alphabeta::GAME_OVER_SCORE);
+    if diff < 200 {
+        let moves = (diff + 1) / 2;
+        Some(if score > 0 { moves } else { -moves })
+    } else {
+        None
+    }
+}
+
+impl<const W: usize> Searcher<W> {
+    fn new(position: &Position<W>, transposition_table: TranspositionHandle, tablebases: Tablebases, params: SearchParams) -> Searcher<W> {
+        let tablebase = tablebases.custom.filter(|tb| tb.is_compatible(position));
         Searcher{
             pos: position.clone(),
             killer_moves: [[Move::null(); 2];256],
             history_moves: [[0;256];256],
             transposition_table,
             nodes_searched: 0,
+            total_nodes_searched: 0,
             max_searching_depth: 0,
             end_time: Instant::now(),
             principal_variation: [Move::null(); Depth::MAX as usize + 1],
             known_checks: BTreeSet::new(),
+            tablebase,
+            tablebase_root_hit: false,
+            #[cfg(feature = "syzygy")]
+            syzygy: tablebases.syzygy.filter(|tb| tb.is_compatible(position)),
+            root_moves: None,
+            excluded_root_moves: Vec::new(),
+            max_nodes: u64::MAX,
+            params,
             
             #[cfg(feature = "parallel")]
             thread_num: 0,
@@ -66,33 +118,50 @@ impl Searcher {
         }
     }
     
-    pub fn get_best_move(position: &Position, depth: Depth, num_threads: u32) -> SearchRes {
+    pub fn get_best_move(position: &Position<W>, depth: Depth, num_threads: u32, tablebases: Tablebases, params: SearchParams) -> SearchRes {
         // Create a new copy of the heuristics for each search
         // Cannot use u64::MAX due to overflow, 1_000_000 seconds is 11.5 days
-        Searcher::get_best_move_impl(position, depth, 1_000_000, num_threads)
+        Searcher::get_best_move_impl(position, depth, 1_000_000, num_threads, tablebases, params)
     }
 
-    pub fn get_best_move_timeout(position: &Position, time_sec: u64, num_threads: u32) -> SearchRes {
+    pub fn get_best_move_timeout(position: &Position<W>, time_sec: u64, num_threads: u32, tablebases: Tablebases, params: SearchParams) -> SearchRes {
         // Create a new copy of the heuristics for each search
-        Searcher::get_best_move_impl(position, Depth::MAX, time_sec, num_threads)
+        Searcher::get_best_move_impl(position, Depth::MAX, time_sec, num_threads, tablebases, params)
+    }
+
+    /// Searches until `max_nodes` nodes have been searched (the first iteration is always completed).
+    /// The search is single-threaded, so the result doesn't depend on the speed of the machine.
+    pub fn get_best_move_nodes(position: &Position<W>, max_nodes: u64, tablebases: Tablebases, params: SearchParams) -> SearchRes {
+        #[cfg(not(feature = "parallel"))]
+        let table = TranspositionTable::default();
+        #[cfg(feature = "parallel")]
+        let table = Arc::new(TranspositionTable::default());
+        let mut searcher = Searcher::new(position, table.into(), tablebases, params);
+        searcher.max_nodes = max_nodes;
+        searcher.search(127, 1_000_000)
     }
     
     // Run for some time, then return the PV, the position score, and the depth
-    fn get_best_move_impl(position: &Position, max_depth: Depth, time_sec: u64, num_threads: u32) -> SearchRes {
+    fn get_best_move_impl(position: &Position<W>, max_depth: Depth, time_sec: u64, num_threads: u32, tablebases: Tablebases, params: SearchParams) -> SearchRes {
         // Limit the max depth to 127 to avoid overflow when doubling
         let max_depth = std::cmp::min(max_depth, 127);
         #[cfg(not(feature = "parallel"))] {
             assert!(num_threads == 1);
             let table = TranspositionTable::default();
-            Searcher::new(position, table.into()).search(max_depth, time_sec)
+            Searcher::new(position, table.into(), tablebases, params).search(max_depth, time_sec)
         }
         #[cfg(feature = "parallel")] {
-            Self::search_multi_thread(position, max_depth, time_sec, num_threads)
+            if num_threads == 1 {
+                // Search in the current thread, without using the thread pool
+                let table = Arc::new(TranspositionTable::default());
+                return Searcher::new(position, table.into(), tablebases, params).search(max_depth, time_sec);
+            }
+            Self::search_multi_thread(position, max_depth, time_sec, num_threads, tablebases, params)
         }
     }
     
     #[cfg(feature = "parallel")]
-    fn search_multi_thread(position: &Position, max_depth: Depth, time_sec: u64, num_threads: u32) -> SearchRes {
+    fn search_multi_thread(position: &Position<W>, max_depth: Depth, time_sec: u64, num_threads: u32, tablebases: Tablebases, params: SearchParams) -> SearchRes {
         // Arc pointer to a vector of results
         let res = vec![Default::default(); num_threads as usize];
         let results_arc = Arc::new(Mutex::new(res));
@@ -108,10 +177,11 @@ impl Searcher {
                 let stop_arc = stop_arc.clone();
                 let depth_arc = depth_arc.clone();
                 let table = table.clone();
+                let tablebases = tablebases.clone();
                 // Spawn a new task in the thread pool, take ownership of the pointers
                 scope.spawn(move |_scope| {
                     // Create a new searcher (with cloned position) for each thread
-                    let mut searcher = Searcher::new(position, table.into());
+                    let mut searcher = Searcher::new(position, table.into(), tablebases, params);
                     searcher.thread_num = thread_num;
                     searcher.stop_flag = stop_arc;
                     searcher.current_searched_depth = depth_arc;
@@ -125,10 +195,12 @@ impl Searcher {
         let mut best_pv = Vec::new();
         let mut best_score = -Centipawns::MAX;
         let mut best_depth = 0;
+        let mut total_nodes = 0;
         // Consume the results vector, return the best result (prefer higher depth, then higher score, then longer PV)
         let results_mutex = Arc::try_unwrap(results_arc).expect("Arc still has owners");
         let results_vec = results_mutex.into_inner().expect("Mutex is poisoned");
-        for (pv, score, depth) in results_vec {
+        for (pv, score, depth, nodes) in results_vec {
+            total_nodes += nodes;
             if depth > best_depth ||
                 (depth == best_depth && score > best_score) ||
                 (depth == best_depth && score == best_score && pv.len() > best_pv.len())
@@ -138,7 +210,7 @@ impl Searcher {
                 best_pv = pv;
             }
         }
-        (best_pv, best_score, best_depth)
+        (best_pv, best_score, best_depth, total_nodes)
     }
     
     fn search(&mut self, max_depth: Depth, time_sec: u64) -> SearchRes {
@@ -146,6 +218,12 @@ impl Searcher {
         let mut pv_score: Centipawns = 0;
         let mut pv_depth: Depth = 0;
         self.known_checks.clear();
+        self.tablebase_root_hit = false;
+        self.total_nodes_searched = 0;
+        // Only search the moves that keep the best result according to the Syzygy tablebase
+        #[cfg(feature = "syzygy")] {
+            self.root_moves = self.syzygy.clone().and_then(|tb| tb.root_moves(&mut self.pos));
+        }
         self.end_time = Instant::now() + Duration::from_secs(time_sec);
         
         let mut search_depth;
@@ -165,7 +243,12 @@ impl Searcher {
         loop {
             self.nodes_searched = 0;
             self.max_searching_depth = 2 * search_depth;
-            match self.start_alphabeta(search_depth, &pv) {
+            let result = {
+                if pv.is_empty() { self.start_alphabeta(search_depth, &pv) }
+                else { self.start_aspiration(search_depth, &pv, pv_score) }
+            };
+            self.total_nodes_searched += self.nodes_searched;
+            match result {
                 Ok(score) => {
                     // Update the current searched depth
                     #[cfg(feature = "parallel")] {
@@ -196,7 +279,8 @@ impl Searcher {
                     for i in 0..self.max_searching_depth {
                         self.principal_variation[i as usize] = Move::null();
                     }
-                    pv_depth = search_depth;
+                    // The tablebase result is exact, equivalent to searching until the end of the game
+                    pv_depth = if self.tablebase_root_hit { max_depth } else { search_depth };
                     pv_score = score;
                     // Print PV info
                     println!("{}", self.format_result(score, &pv, search_depth));
@@ -213,7 +297,9 @@ impl Searcher {
                 break;
             }
             
-            if Instant::now() >= self.end_time || search_depth == max_depth {
+            // If the root is in the tablebase, searching deeper won't change the result
+            let out_of_nodes = self.total_nodes_searched >= self.max_nodes;
+            if Instant::now() >= self.end_time || search_depth == max_depth || self.tablebase_root_hit || out_of_nodes {
                 // Set stop flag to stop other threads
                 #[cfg(feature = "parallel")] {
                     self.stop_flag.store(true, Ordering::Relaxed);
@@ -230,7 +316,7 @@ impl Searcher {
                 search_depth = std::cmp::min(next_depth, max_depth);
             }
         }
-        (pv, pv_score, pv_depth)
+        (pv, pv_score, pv_depth, self.total_nodes_searched)
     }
     
     // Format the result as a string in order to print it all at once. This prevents 2 threads from printing at the same time.
@@ -240,11 +326,9 @@ impl Searcher {
         #[cfg(not(feature = "parallel"))]
         let thread_str = String::new();
         
-        let diff = -(score.abs() + alphabeta::GAME_OVER_SCORE);
         let score_str = {
-            if diff < 200 {
-                let sign = if score > 0 { "" } else { "-" };
-                format!("MATE {}{}", sign, (diff+1) / 2)
+            if let Some(moves) = mate_in(score) {
+                format!("MATE {moves}")
             } else {
                 format!("cp {:<4}", score)
             }
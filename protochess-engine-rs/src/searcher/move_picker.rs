@@ -0,0 +1,159 @@
+use crate::MoveGen;
+use crate::types::{Move, MoveBuffer, ScoredMove};
+
+use super::Searcher;
+use super::eval;
+
+/// Stages of the `MovePicker`, in the order in which the moves are returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    HashMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// Returns the pseudo-legal moves of the position in move ordering order: the hash move, the captures that don't
+/// lose material, the killer moves, the rest of the quiet moves and the losing captures.
+///
+/// Each group of moves is only generated and scored when the previous one is exhausted, so a node that is cut off
+/// by the hash move or a capture never generates its quiet moves. The moves are stored in a `MoveBuffer` on the
+/// stack and selected one by one instead of sorting the whole list.
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: Move,
+    killers: [Move; 2],
+    include_quiets: bool,
+    moves: MoveBuffer,
+    // Index of the next move to select in `moves`
+    current: usize,
+    // The captures are stored in moves[0..num_captures], the quiet moves after them
+    num_captures: usize,
+    // Index of the first losing capture, once the good captures are exhausted
+    bad_captures: usize,
+    killer_index: usize,
+}
+
+impl MovePicker {
+    /// If `include_quiets` is false, only the captures are returned (the killers and non-capture hash moves are ignored)
+    pub fn new(hash_move: Move, killers: [Move; 2], include_quiets: bool) -> MovePicker {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            killers,
+            include_quiets,
+            moves: MoveBuffer::new(),
+            current: 0,
+            num_captures: 0,
+            bad_captures: 0,
+            killer_index: 0,
+        }
+    }
+
+    /// Returns the next pseudo-legal move to search, or `None` if all the moves have been returned
+    pub fn next<const W: usize>(&mut self, searcher: &Searcher<W>) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    let hash_move = self.hash_move;
+                    if (self.include_quiets || hash_move.is_capture()) && MoveGen::is_pseudo_legal(hash_move, &searcher.pos) {
+                        return Some(hash_move);
+                    }
+                    self.hash_move = Move::null();
+                },
+                Stage::GenerateCaptures => {
+                    MoveGen::output_captures(&searcher.pos, &mut self.moves);
+                    for scored in self.moves.as_mut_slice() {
+                        // The history score is only used for quiet moves
+                        scored.score = eval::score_move(&searcher.pos, 0, scored.mv);
+                    }
+                    self.num_captures = self.moves.len();
+                    self.stage = Stage::GoodCaptures;
+                },
+                Stage::GoodCaptures => {
+                    match self.select(self.num_captures) {
+                        Some(scored) if scored.score < 0 => {
+                            // The rest of the captures lose material, search them after the quiet moves
+                            self.current -= 1;
+                            self.bad_captures = self.current;
+                            self.stage = Stage::Killers;
+                        },
+                        Some(scored) if scored.mv == self.hash_move => {},
+                        Some(scored) => return Some(scored.mv),
+                        None => {
+                            self.bad_captures = self.num_captures;
+                            self.stage = Stage::Killers;
+                        },
+                    }
+                },
+                Stage::Killers => {
+                    // If capturing is forced, the quiet moves are only legal when there are no captures
+                    let forced_capture = searcher.pos.global_rules.capturing_is_forced && self.num_captures > 0;
+                    if !self.include_quiets || forced_capture {
+                        self.current = self.bad_captures;
+                        self.stage = Stage::BadCaptures;
+                        continue;
+                    }
+                    if self.killer_index == self.killers.len() {
+                        self.stage = Stage::GenerateQuiets;
+                        continue;
+                    }
+                    let killer = self.killers[self.killer_index];
+                    self.killer_index += 1;
+                    if !killer.is_capture() && killer != self.hash_move && MoveGen::is_pseudo_legal(killer, &searcher.pos) {
+                        return Some(killer);
+                    }
+                },
+                Stage::GenerateQuiets => {
+                    MoveGen::output_translations(&searcher.pos, &mut self.moves);
+                    for scored in &mut self.moves.as_mut_slice()[self.num_captures..] {
+                        let history = searcher.history_moves[scored.mv.get_from() as usize][scored.mv.get_to() as usize];
+                        scored.score = eval::score_move(&searcher.pos, history, scored.mv);
+                    }
+                    self.current = self.num_captures;
+                    self.stage = Stage::Quiets;
+                },
+                Stage::Quiets => {
+                    match self.select(self.moves.len()) {
+                        Some(scored) if scored.mv == self.hash_move || self.killers.contains(&scored.mv) => {},
+                        Some(scored) => return Some(scored.mv),
+                        None => {
+                            self.current = self.bad_captures;
+                            self.stage = Stage::BadCaptures;
+                        },
+                    }
+                },
+                Stage::BadCaptures => {
+                    match self.select(self.num_captures) {
+                        Some(scored) if scored.mv == self.hash_move => {},
+                        Some(scored) => return Some(scored.mv),
+                        None => self.stage = Stage::Done,
+                    }
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    // Moves the best move in moves[current..end] to the current index and returns it
+    fn select(&mut self, end: usize) -> Option<ScoredMove> {
+        if self.current >= end {
+            return None;
+        }
+        let moves = &mut self.moves.as_mut_slice()[..end];
+        let mut best = self.current;
+        for i in self.current + 1..end {
+            if moves[i].score > moves[best].score {
+                best = i;
+            }
+        }
+        moves.swap(self.current, best);
+        self.current += 1;
+        Some(moves[self.current - 1])
+    }
+}
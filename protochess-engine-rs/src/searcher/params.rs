@@ -0,0 +1,52 @@
+use crate::types::Centipawns;
+
+/// Settings of the search heuristics. Each heuristic can be disabled by setting it to `None` (or `false`).
+/// The pruning heuristics rely on the static evaluation, so they are never used in variants where
+/// `invert_win_conditions` or `capturing_is_forced` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchParams {
+    /// Initial half-width of the aspiration window around the score of the previous iteration.
+    /// The window is doubled every time the score falls outside of it. Values that are not positive disable it.
+    pub aspiration_window: Option<Centipawns>,
+    /// Reverse futility pruning: margin (per ply of remaining depth) by which the static evaluation must
+    /// exceed beta in order to skip the search of the position
+    pub reverse_futility_margin: Option<Centipawns>,
+    /// Futility pruning in quiescence: captures that cannot raise the score above alpha, even with this
+    /// extra margin, are not searched
+    pub qsearch_futility_margin: Option<Centipawns>,
+    /// Late move pruning: skip the last quiet moves of the nodes close to the leaves
+    pub late_move_pruning: bool,
+    /// Razoring: margin (per ply of remaining depth) by which the static evaluation must be below alpha in
+    /// order to drop directly into quiescence
+    pub razoring_margin: Option<Centipawns>,
+    /// Also search the quiet moves that give check at the first ply of the quiescence search (off by default).
+    /// The check evasions are always searched.
+    pub qsearch_quiet_checks: bool,
+}
+
+impl SearchParams {
+    /// Parameters with all the optional heuristics disabled
+    pub fn disabled() -> SearchParams {
+        SearchParams {
+            aspiration_window: None,
+            reverse_futility_margin: None,
+            qsearch_futility_margin: None,
+            late_move_pruning: false,
+            razoring_margin: None,
+            qsearch_quiet_checks: false,
+        }
+    }
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            aspiration_window: Some(50),
+            reverse_futility_margin: Some(150),
+            qsearch_futility_margin: Some(200),
+            late_move_pruning: true,
+            razoring_margin: Some(300),
+            qsearch_quiet_checks: false,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::position::Position;
+use crate::types::{BIndex, Bitboard, Move, Centipawns};
+use crate::{MoveGen, Piece};
+
+/// Static exchange evaluation: returns the material balance (for the player to move) of the sequence of captures
+/// on the target square of a move, assuming that each player always recaptures with its least valuable piece
+/// and can stop capturing when it's not favorable.
+/// Returns 0 for moves that are not captures.
+pub fn see<const W: usize>(position: &Position<W>, mv: Move) -> Centipawns {
+    if !mv.is_capture() {
+        return 0;
+    }
+    let player = position.whos_turn;
+    let from = mv.get_from();
+    let to = mv.get_to();
+    let attacker = match position.player_piece_at(player, from) {
+        Some(piece) => piece,
+        None => return 0,
+    };
+    let victim_value = position.player_piece_at(1 - player, mv.get_target()).map_or(0, |p| p.get_material_score());
+    let promoted_value = mv.get_promotion_piece().and_then(|id| position.lookup_piece(id)).map(|p| p.get_material_score());
+
+    // In atomic variants the capture explodes, so there cannot be any recaptures
+    if attacker.explodes_on_capture() {
+        return explosion_balance(position, mv) + victim_value;
+    }
+
+    let mut occupied = position.occ_or_out_bounds.clone();
+    occupied.clear_bit(from);
+    occupied.clear_bit(mv.get_target());
+    // Value of the piece that will be captured next
+    let mut on_square_value = promoted_value.unwrap_or_else(|| attacker.get_material_score());
+    // A capture that promotes also wins the difference between the promoted piece and the capturing piece
+    let promotion_gain = on_square_value - attacker.get_material_score();
+    // gains[i] is the balance after the i-th capture, from the point of view of the player that made it
+    let mut gains = vec![victim_value + promotion_gain];
+    let mut side = 1 - player;
+    loop {
+        let attackers = MoveGen::attackers(position, to, side, &occupied);
+        let (index, piece) = match least_valuable(position, &attackers) {
+            Some(least_valuable) => least_valuable,
+            None => break,
+        };
+        occupied.clear_bit(index);
+        // The leader can only recapture if the square is no longer defended
+        if piece.is_leader() && !MoveGen::attackers(position, to, 1 - side, &occupied).is_zero() {
+            break;
+        }
+        gains.push(on_square_value - gains.last().unwrap());
+        on_square_value = piece.get_material_score();
+        side = 1 - side;
+    }
+    // Each player can choose to stop capturing
+    while gains.len() > 1 {
+        let gain = gains.pop().unwrap();
+        let previous = gains.last_mut().unwrap();
+        *previous = -std::cmp::max(-*previous, gain);
+    }
+    gains[0]
+}
+
+/// Returns `true` if the move is a capture that loses material according to the static exchange evaluation
+pub fn is_losing_capture<const W: usize>(position: &Position<W>, mv: Move) -> bool {
+    if !mv.is_capture() {
+        return false;
+    }
+    let player = position.whos_turn;
+    let attacker = match position.player_piece_at(player, mv.get_from()) {
+        Some(piece) => piece,
+        None => return false,
+    };
+    let victim_value = position.player_piece_at(1 - player, mv.get_target()).map_or(0, |p| p.get_material_score());
+    // Capturing a more valuable piece never loses material, unless the capturing piece explodes
+    if !attacker.explodes_on_capture() && attacker.get_material_score() <= victim_value {
+        return false;
+    }
+    see(position, mv) < 0
+}
+
+/// Returns the piece of the bitboard with the lowest material score
+fn least_valuable<'a, const W: usize>(position: &'a Position<W>, pieces: &Bitboard<W>) -> Option<(BIndex, &'a Piece<W>)> {
+    let mut pieces = pieces.clone();
+    let mut best: Option<(BIndex, &Piece<W>)> = None;
+    while let Some(index) = pieces.lowest_one() {
+        pieces.clear_bit(index);
+        let piece = match position.piece_at(index) {
+            Some(piece) => piece,
+            None => continue,
+        };
+        if best.is_none_or(|(_, best_piece)| piece.get_material_score() < best_piece.get_material_score()) {
+            best = Some((index, piece));
+        }
+    }
+    best
+}
+
+/// Material won by the player to move due to the explosion (excluding the captured piece)
+fn explosion_balance<const W: usize>(position: &Position<W>, mv: Move) -> Centipawns {
+    let player = position.whos_turn;
+    let attacker = position.player_piece_at(player, mv.get_from()).unwrap();
+    // The capturing piece always explodes
+    let mut balance = -attacker.get_material_score();
+    let mut explosion = attacker.get_explosion(mv.get_to()).clone();
+    explosion.clear_bit(mv.get_from());
+    explosion.clear_bit(mv.get_target());
+    while let Some(index) = explosion.lowest_one() {
+        explosion.clear_bit(index);
+        if let Some(piece) = position.piece_at(index) {
+            if piece.immune_to_explosion() {
+                continue;
+            }
+            if piece.get_player() == player {
+                balance -= piece.get_material_score();
+            } else {
+                balance += piece.get_material_score();
+            }
+        }
+    }
+    balance
+}
@@ -0,0 +1,148 @@
+use instant::{Instant, Duration};
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::{MoveGen, Position};
+use crate::tablebase::Tablebases;
+use crate::types::{Centipawns, Depth, Move, SearchTimeout};
+
+use super::{Searcher, SearchParams};
+use super::transposition_table::TranspositionTable;
+
+/// Candidate moves at each completed depth (sorted from best to worst), and total number of nodes searched
+pub type CandidatesRes = (Vec<Vec<(Move, Centipawns)>>, u64);
+
+/// Settings of the weakened play, used in `Engine::set_skill()`.
+/// Instead of always playing the best move, the engine searches the best few moves (MultiPV) with a limited
+/// number of nodes and picks one of them at random, preferring the ones with a better score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Skill {
+    /// Number of best moves that can be chosen
+    pub candidates: u32,
+    /// Maximum number of nodes searched for each move. The first iteration (depth 1) is always completed.
+    pub max_nodes: u64,
+    /// The probability of a candidate is proportional to `exp((score - best_score) / temperature)`,
+    /// so higher temperatures choose worse moves more often
+    pub temperature: f32,
+    /// Probability of choosing among the candidates of the shallower search at `miss_depth`,
+    /// which misses the tactics that are only found by the deeper searches
+    pub miss_chance: f32,
+    pub miss_depth: Depth,
+}
+
+impl Skill {
+    /// Highest skill level. Use `Engine::set_skill(None)` to play at full strength.
+    pub const MAX_LEVEL: u8 = 19;
+    /// Rough strength of level 0 and of each additional level (not calibrated against other engines)
+    const MIN_ELO: u32 = 800;
+    const ELO_PER_LEVEL: u32 = 80;
+
+    /// Returns the settings for a skill level from 0 (weakest) to `MAX_LEVEL` (strongest)
+    pub fn from_level(level: u8) -> Skill {
+        let level = std::cmp::min(level, Skill::MAX_LEVEL);
+        // Fraction of the levels below this one, from 0 to 0.95
+        let fraction = level as f32 / (Skill::MAX_LEVEL + 1) as f32;
+        Skill {
+            candidates: 6 - level as u32 / 5,
+            max_nodes: (1000.0 * 2f64.powf(level as f64 / 2.0)) as u64,
+            temperature: 200.0 * (1.0 - fraction),
+            miss_chance: 0.5 * (1.0 - fraction),
+            miss_depth: 1 + level / 7,
+        }
+    }
+
+    /// Returns the settings of the skill level closest to an approximate Elo rating
+    pub fn from_elo(elo: u32) -> Skill {
+        let level = elo.saturating_sub(Skill::MIN_ELO) / Skill::ELO_PER_LEVEL;
+        Skill::from_level(std::cmp::min(level, Skill::MAX_LEVEL as u32) as u8)
+    }
+
+    /// Picks one of the candidates returned by `Searcher::get_candidate_moves()`
+    pub fn choose(&self, candidates: &[Vec<(Move, Centipawns)>], rng: &mut StdRng) -> Option<(Move, Centipawns, Depth)> {
+        // Each entry corresponds to a depth
+        let mut depth = candidates.len();
+        if depth > self.miss_depth as usize && rng.gen::<f32>() < self.miss_chance {
+            depth = self.miss_depth as usize;
+        }
+        let moves = candidates.get(depth.checked_sub(1)?)?;
+        let best_score = moves.first()?.1;
+        let temperature = self.temperature.max(1.0);
+        let weights: Vec<f32> = moves.iter()
+            .map(|&(_, score)| ((score - best_score) as f32 / temperature).exp())
+            .collect();
+        let mut target = rng.gen::<f32>() * weights.iter().sum::<f32>();
+        for (&(mv, score), weight) in moves.iter().zip(&weights) {
+            if target < *weight {
+                return Some((mv, score, depth as Depth));
+            }
+            target -= weight;
+        }
+        // Rounding errors
+        moves.last().map(|&(mv, score)| (mv, score, depth as Depth))
+    }
+}
+
+impl<const W: usize> Searcher<W> {
+    /// Iterative deepening search that finds the best `num_candidates` moves at each depth (MultiPV), by searching
+    /// the position again with the moves already found excluded at the root. The search is single-threaded and
+    /// stops when `max_depth`, `time_sec` or `max_nodes` is reached. The depth where it stops is discarded.
+    pub fn get_candidate_moves(position: &Position<W>, max_depth: Depth, time_sec: u64, max_nodes: u64, num_candidates: u32,
+        tablebases: Tablebases, params: SearchParams) -> CandidatesRes
+    {
+        #[cfg(not(feature = "parallel"))]
+        let table = TranspositionTable::default();
+        #[cfg(feature = "parallel")]
+        let table = std::sync::Arc::new(TranspositionTable::default());
+        let mut searcher = Searcher::new(position, table.into(), tablebases, params);
+        searcher.max_nodes = max_nodes;
+        searcher.end_time = Instant::now() + Duration::from_secs(time_sec);
+        #[cfg(feature = "syzygy")] {
+            searcher.root_moves = searcher.syzygy.clone().and_then(|tb| tb.root_moves(&mut searcher.pos));
+        }
+        let num_legal_moves = MoveGen::get_legal_moves(&mut searcher.pos).len();
+        let num_candidates = std::cmp::min(num_candidates as usize, num_legal_moves);
+        let max_depth = std::cmp::min(max_depth, 127);
+
+        let mut results: Vec<Vec<(Move, Centipawns)>> = Vec::new();
+        'deepening: for depth in 1..=max_depth {
+            searcher.max_searching_depth = 2 * depth;
+            searcher.excluded_root_moves.clear();
+            let mut candidates = Vec::with_capacity(num_candidates);
+            for _ in 0..num_candidates {
+                // Start with the best move of the previous depth. The hint is not checked against the excluded moves.
+                let hint = match results.last() {
+                    Some(previous) if candidates.is_empty() => vec![previous[0].0],
+                    _ => vec![],
+                };
+                searcher.nodes_searched = 0;
+                let result = searcher.start_alphabeta(depth, &hint);
+                searcher.total_nodes_searched += searcher.nodes_searched;
+                let mv = searcher.principal_variation[0];
+                for i in 0..searcher.max_searching_depth {
+                    searcher.principal_variation[i as usize] = Move::null();
+                }
+                let score = match result {
+                    Ok(score) => score,
+                    Err(SearchTimeout) => break 'deepening,
+                };
+                // All the remaining moves are forbidden by the tablebase
+                if mv.is_null() {
+                    break;
+                }
+                candidates.push((mv, score));
+                searcher.excluded_root_moves.push(mv);
+                // The tablebase only returns the best move
+                if searcher.tablebase_root_hit {
+                    break;
+                }
+            }
+            candidates.sort_by_key(|&(_, score)| -score);
+            results.push(candidates);
+            let out_of_nodes = searcher.total_nodes_searched >= searcher.max_nodes;
+            if searcher.tablebase_root_hit || out_of_nodes || Instant::now() >= searcher.end_time {
+                break;
+            }
+        }
+        (results, searcher.total_nodes_searched)
+    }
+}
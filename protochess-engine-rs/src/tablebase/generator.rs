@@ -0,0 +1,353 @@
+use crate::{GameState, InitialState, MoveGen, PieceId, Position, wrap_res, err_assert, err};
+use crate::position::create::fen::FenData;
+use crate::position::create::position_factory::PositionFactory;
+use crate::types::Player;
+
+use super::{Tablebase, ProbeResult, board_squares, variant_hash, INVALID, MAX_DTM};
+
+/// Maximum number of positions in a single table
+const MAX_TABLE_SIZE: usize = 1 << 24;
+/// Marks a position whose result is not known yet
+const UNKNOWN: u16 = INVALID - 1;
+
+/// Position reached after making a legal move
+enum Successor {
+    /// Position in the table that is being generated, with its index
+    Internal(usize),
+    /// Position in another table, with its material and index
+    Table(String, usize),
+    /// The game has ended (leader captured or piece on a winning square)
+    GameOver,
+}
+
+impl Tablebase {
+    /// Generates the tables for the given materials of a variant, along with all the tables that can be reached
+    /// from them (after captures or promotions).
+    /// Each material is a list of piece ids, for example `["KRk", "KQk"]` in standard chess.
+    pub fn generate(state: &InitialState, materials: &[&str]) -> wrap_res!(Tablebase) {
+        let template = empty_position(state)?;
+        let squares = board_squares(&template);
+        let mut tb = Tablebase::new(variant_hash(&template, &squares), squares);
+        let mut generator = Generator { template, in_progress: Vec::new() };
+        for material in materials {
+            let material = generator.check_material(material)?;
+            generator.generate_table(&mut tb, &material)?;
+        }
+        Ok(tb)
+    }
+
+    /// Generates the tables for all the materials with up to `max_pieces` pieces (including the leaders) in a variant
+    pub fn generate_up_to(state: &InitialState, max_pieces: u32) -> wrap_res!(Tablebase) {
+        let template = empty_position(state)?;
+        let generator = Generator { template, in_progress: Vec::new() };
+        let mut ids: Vec<PieceId> = generator.template.pieces.iter()
+            .flat_map(|piece_set| piece_set.iter().map(|piece| piece.get_piece_id()))
+            .collect();
+        ids.sort_unstable();
+        let mut materials = Vec::new();
+        generator.find_materials(&ids, max_pieces as usize, &mut String::new(), &mut materials);
+        let materials: Vec<&str> = materials.iter().map(|m| m.as_str()).collect();
+        Self::generate(state, &materials)
+    }
+}
+
+
+struct Generator {
+    // Position of the variant without any pieces
+    template: Position,
+    // Tables that are being generated, used to detect circular dependencies
+    in_progress: Vec<String>,
+}
+
+impl Generator {
+    /// Returns the sorted material, or an error if the material is not valid for this variant
+    fn check_material(&self, material: &str) -> wrap_res!(String) {
+        let mut ids: Vec<PieceId> = material.chars().collect();
+        ids.sort_unstable();
+        for id in &ids {
+            err_assert!(self.template.lookup_piece(*id).is_some(), "Invalid material '{material}': piece '{id}' doesn't exist");
+        }
+        for (player, piece_set) in self.template.pieces.iter().enumerate() {
+            let count = |leaders: bool| ids.iter()
+                .filter(|id| piece_set.lookup_piece(**id).is_some_and(|p| !leaders || p.is_leader()))
+                .count();
+            let player = if player == 0 { "White" } else { "Black" };
+            if piece_set.get_leader().is_some() {
+                err_assert!(count(true) == 1, "Invalid material '{material}': {player} must have exactly 1 leader");
+            } else {
+                err_assert!(count(false) > 0, "Invalid material '{material}': {player} has no pieces");
+            }
+        }
+        Ok(ids.into_iter().collect())
+    }
+
+    /// Adds all the valid materials with up to `max_pieces` pieces to `result`
+    fn find_materials(&self, ids: &[PieceId], max_pieces: usize, current: &mut String, result: &mut Vec<String>) {
+        if self.check_material(current).is_ok() {
+            result.push(current.clone());
+        }
+        if current.chars().count() == max_pieces {
+            return;
+        }
+        for (i, id) in ids.iter().enumerate() {
+            current.push(*id);
+            // Only add pieces in order, to avoid generating the same material twice
+            self.find_materials(&ids[i..], max_pieces, current, result);
+            current.pop();
+        }
+    }
+
+    /// Generates a table (and the tables it depends on) using retrograde analysis
+    fn generate_table(&mut self, tb: &mut Tablebase, material: &str) -> wrap_res!() {
+        if tb.tables.contains_key(material) {
+            return Ok(());
+        }
+        err_assert!(!self.in_progress.iter().any(|m| m == material), "Cannot generate table {material}: circular dependency");
+        self.in_progress.push(material.to_string());
+
+        let pieces: Vec<PieceId> = material.chars().collect();
+        let size = tb.squares.len().checked_pow(pieces.len() as u32)
+            .and_then(|size| size.checked_mul(2))
+            .filter(|size| *size <= MAX_TABLE_SIZE);
+        let size = match size {
+            Some(size) => size,
+            None => err!("Cannot generate table {material}: too many positions"),
+        };
+        let mut pos = self.template.clone();
+        // Result for the player to move when the game has ended
+        let game_over = {
+            if pos.global_rules.invert_win_conditions { ProbeResult::Win(0) }
+            else { ProbeResult::Loss(0) }
+        };
+
+        // Pass 1: find the results that don't depend on this table, and count the moves between positions of this table
+        let mut values = vec![UNKNOWN; size];
+        // Number of moves to positions of this table whose result is not yet known to be a win for the opponent
+        let mut remaining = vec![0u32; size];
+        // Best result that can be reached with the rest of the moves (or the result if there are no legal moves)
+        let mut external: Vec<Option<ProbeResult>> = vec![None; size];
+        let mut num_predecessors = vec![0u32; size];
+        for index in 0..size {
+            if !place_pieces(tb, &mut pos, &pieces, index)? {
+                values[index] = INVALID;
+                continue;
+            }
+            let successors = get_successors(tb, &mut pos, material);
+            if successors.is_empty() {
+                let loses = pos.global_rules.stalemated_player_loses || MoveGen::in_check(&mut pos);
+                external[index] = Some(if loses { game_over } else { ProbeResult::Draw });
+            }
+            remove_pieces(tb, &mut pos, &pieces, index)?;
+            for successor in successors {
+                let result = match successor {
+                    Successor::Internal(next) => {
+                        remaining[index] += 1;
+                        num_predecessors[next] += 1;
+                        continue;
+                    },
+                    Successor::GameOver => game_over,
+                    Successor::Table(next_material, next) => {
+                        self.generate_table(tb, &next_material)?;
+                        match ProbeResult::decode(tb.tables[&next_material][next]) {
+                            Some(result) => result,
+                            None => err!("Table {next_material} contains an invalid position reached from {material}"),
+                        }
+                    },
+                };
+                let result = result.parent();
+                if external[index].is_none_or(|best| result.is_better_than(best)) {
+                    external[index] = Some(result);
+                }
+            }
+        }
+
+        // Pass 2: store the predecessors of each position
+        let mut offsets = Vec::with_capacity(size + 1);
+        offsets.push(0);
+        for n in &num_predecessors {
+            let total = offsets[offsets.len() - 1] + *n as usize;
+            offsets.push(total);
+        }
+        let mut predecessors = vec![0u32; offsets[size]];
+        let mut next_free = offsets.clone();
+        for index in 0..size {
+            if values[index] == INVALID || remaining[index] == 0 {
+                continue;
+            }
+            place_pieces(tb, &mut pos, &pieces, index)?;
+            for successor in get_successors(tb, &mut pos, material) {
+                if let Successor::Internal(next) = successor {
+                    predecessors[next_free[next]] = index as u32;
+                    next_free[next] += 1;
+                }
+            }
+            remove_pieces(tb, &mut pos, &pieces, index)?;
+        }
+
+        // Pass 3: starting from the known results, visit the positions in order of distance to mate
+        let mut buckets: Vec<Vec<(usize, ProbeResult)>> = Vec::new();
+        for index in 0..size {
+            if values[index] == INVALID {
+                continue;
+            }
+            match external[index] {
+                Some(ProbeResult::Draw) if remaining[index] == 0 => values[index] = ProbeResult::Draw.encode(),
+                Some(result @ ProbeResult::Loss(_)) if remaining[index] == 0 => push_result(&mut buckets, index, result)?,
+                Some(result @ ProbeResult::Win(_)) => push_result(&mut buckets, index, result)?,
+                _ => {},
+            }
+        }
+        let mut dtm = 0;
+        while dtm < buckets.len() {
+            for (index, result) in std::mem::take(&mut buckets[dtm]) {
+                if values[index] != UNKNOWN {
+                    // Already found a faster win
+                    continue;
+                }
+                values[index] = result.encode();
+                for prev in &predecessors[offsets[index]..offsets[index + 1]] {
+                    let prev = *prev as usize;
+                    if values[prev] != UNKNOWN {
+                        continue;
+                    }
+                    match result {
+                        ProbeResult::Loss(_) => push_result(&mut buckets, prev, result.parent())?,
+                        ProbeResult::Win(_) => {
+                            remaining[prev] -= 1;
+                            if remaining[prev] > 0 {
+                                continue;
+                            }
+                            // All the moves to this table lose, this is the longest loss among them
+                            let mut best = result.parent();
+                            if let Some(ext) = external[prev] {
+                                if ext.is_better_than(best) { best = ext; }
+                            }
+                            match best {
+                                ProbeResult::Draw => values[prev] = ProbeResult::Draw.encode(),
+                                ProbeResult::Loss(_) => push_result(&mut buckets, prev, best)?,
+                                // Already added to a bucket
+                                ProbeResult::Win(_) => {},
+                            }
+                        },
+                        ProbeResult::Draw => unreachable!("Draws are not added to the buckets"),
+                    }
+                }
+            }
+            dtm += 1;
+        }
+        // The rest of the positions can't be won by either player
+        for value in &mut values {
+            if *value == UNKNOWN {
+                *value = ProbeResult::Draw.encode();
+            }
+        }
+
+        tb.max_pieces = std::cmp::max(tb.max_pieces, pieces.len() as u32);
+        tb.tables.insert(material.to_string(), values);
+        self.in_progress.pop();
+        Ok(())
+    }
+}
+
+
+/// Adds a win or loss to the bucket of its distance to mate
+fn push_result(buckets: &mut Vec<Vec<(usize, ProbeResult)>>, index: usize, result: ProbeResult) -> wrap_res!() {
+    let dtm = match result {
+        ProbeResult::Win(dtm) | ProbeResult::Loss(dtm) => dtm,
+        ProbeResult::Draw => unreachable!("Draws are not added to the buckets"),
+    };
+    err_assert!(dtm <= MAX_DTM, "Distance to mate is too large");
+    if dtm as usize >= buckets.len() {
+        buckets.resize_with(dtm as usize + 1, Vec::new);
+    }
+    buckets[dtm as usize].push((index, result));
+    Ok(())
+}
+
+/// Returns the squares of each piece and the player to move of a position in a table
+fn decode_index(tb: &Tablebase, num_pieces: usize, index: usize) -> (Vec<usize>, Player) {
+    let whos_turn = (index % 2) as Player;
+    let mut rest = index / 2;
+    let mut squares = Vec::with_capacity(num_pieces);
+    for _ in 0..num_pieces {
+        squares.push(rest % tb.squares.len());
+        rest /= tb.squares.len();
+    }
+    (squares, whos_turn)
+}
+
+/// Sets up the position with the given index on an empty board. Returns `false` (and leaves the board empty)
+/// if the position is not legal.
+fn place_pieces(tb: &Tablebase, pos: &mut Position, pieces: &[PieceId], index: usize) -> wrap_res!(bool) {
+    let (squares, whos_turn) = decode_index(tb, pieces.len(), index);
+    for (i, sq) in squares.iter().enumerate() {
+        if squares[..i].contains(sq) {
+            return Ok(false);
+        }
+    }
+    for (id, sq) in pieces.iter().zip(&squares) {
+        pos.public_add_piece(*id, tb.squares[*sq], false)?;
+    }
+    pos.whos_turn = whos_turn;
+    // The game has already ended
+    let mut valid = !pos.piece_is_on_winning_square();
+    // The player that just moved cannot be in check
+    pos.whos_turn = 1 - whos_turn;
+    valid &= !MoveGen::in_check(pos);
+    pos.whos_turn = whos_turn;
+    if pos.global_rules.check_is_forbidden {
+        valid &= !MoveGen::in_check(pos);
+    }
+    if !valid {
+        remove_pieces(tb, pos, pieces, index)?;
+    }
+    Ok(valid)
+}
+
+/// Removes the pieces placed with `place_pieces()`
+fn remove_pieces(tb: &Tablebase, pos: &mut Position, pieces: &[PieceId], index: usize) -> wrap_res!() {
+    let (squares, _) = decode_index(tb, pieces.len(), index);
+    for sq in squares {
+        pos.public_remove_piece(tb.squares[sq])?;
+    }
+    Ok(())
+}
+
+/// Returns the positions reached with each legal move
+fn get_successors(tb: &Tablebase, pos: &mut Position, material: &str) -> Vec<Successor> {
+    let mut successors = Vec::new();
+    for mv in MoveGen::get_legal_moves(pos) {
+        pos.make_move(mv);
+        if pos.leader_is_captured() || pos.piece_is_on_winning_square() {
+            successors.push(Successor::GameOver);
+        } else {
+            let (next_material, next) = tb.material_and_index(pos);
+            if next_material == material {
+                successors.push(Successor::Internal(next));
+            } else {
+                successors.push(Successor::Table(next_material, next));
+            }
+        }
+        pos.unmake_move();
+    }
+    successors
+}
+
+/// Creates a position of the variant with no pieces
+pub(super) fn empty_position(state: &InitialState) -> wrap_res!(Position) {
+    err_assert!(state.global_rules.checks_to_lose == 0, "Tablebases are not supported in variants that count checks");
+    let mut fen = FenData::parse_fen(&state.fen)?;
+    fen.piece_placements.clear();
+    fen.castling_availability = Some(vec![]);
+    fen.ep_square_and_victim = None;
+    fen.times_in_check = None;
+    let game_state = GameState {
+        initial_state: state.clone(),
+        initial_fen: Some(fen.to_string()),
+        move_history: vec![],
+    };
+    match PositionFactory::default().set_state(game_state, None)? {
+        Some(pos) => Ok(pos),
+        None => err!("Could not create the position"),
+    }
+}
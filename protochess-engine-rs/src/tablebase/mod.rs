@@ -0,0 +1,336 @@
+// This directory contains the endgame tablebases for custom variants, generated with retrograde analysis.
+// Each table stores the result (win/draw/loss and distance to mate) of all the positions with a given set of pieces.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use crate::{Position, PieceId, wrap_res, err_assert, err};
+use crate::types::{BIndex, Player};
+use crate::utils::{self, to_index};
+
+mod generator;
+#[cfg(feature = "syzygy")]
+pub mod syzygy;
+
+/// Marks a square that is out of bounds in `Tablebase::square_index`
+const NO_SQUARE: u16 = u16::MAX;
+/// Marks an illegal position in a table
+const INVALID: u16 = u16::MAX;
+const WIN_FLAG: u16 = 0x8000;
+/// Maximum number of plies until the end of the game that can be stored
+const MAX_DTM: u16 = 0x7FFC;
+/// First bytes of a tablebase file. Change it when the format of the file or of `variant_hash()` changes.
+const MAGIC: &[u8; 5] = b"PCTB3";
+
+/// Result of a position stored in the tablebase, from the point of view of the player to move.
+/// Wins and losses contain the number of plies until the end of the game (distance to mate), assuming
+/// that the winner plays the fastest win and the loser delays it as much as possible.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeResult {
+    Win(u16),
+    Draw,
+    Loss(u16),
+}
+
+impl ProbeResult {
+    /// Returns the result for the player who made the move that led to this position
+    pub fn parent(self) -> ProbeResult {
+        match self {
+            ProbeResult::Win(dtm) => ProbeResult::Loss(dtm + 1),
+            ProbeResult::Draw => ProbeResult::Draw,
+            ProbeResult::Loss(dtm) => ProbeResult::Win(dtm + 1),
+        }
+    }
+
+    /// Returns `true` if this result is better than `other` for the player to move
+    pub fn is_better_than(self, other: ProbeResult) -> bool {
+        self.rank() > other.rank()
+    }
+
+    // Faster wins are better, slower losses are better
+    fn rank(self) -> (u8, i32) {
+        match self {
+            ProbeResult::Win(dtm) => (2, -(dtm as i32)),
+            ProbeResult::Draw => (1, 0),
+            ProbeResult::Loss(dtm) => (0, dtm as i32),
+        }
+    }
+
+    fn encode(self) -> u16 {
+        match self {
+            ProbeResult::Win(dtm) => WIN_FLAG | (dtm + 1),
+            ProbeResult::Draw => 0,
+            ProbeResult::Loss(dtm) => dtm + 1,
+        }
+    }
+
+    fn decode(value: u16) -> Option<ProbeResult> {
+        match value {
+            INVALID => None,
+            0 => Some(ProbeResult::Draw),
+            v if v & WIN_FLAG != 0 => Some(ProbeResult::Win((v & !WIN_FLAG) - 1)),
+            v => Some(ProbeResult::Loss(v - 1)),
+        }
+    }
+}
+
+/// Converts a table value to the code stored in the file, which is small for short distances to mate
+fn value_to_code(value: u16) -> u32 {
+    match ProbeResult::decode(value) {
+        None => 0,
+        Some(ProbeResult::Draw) => 1,
+        Some(ProbeResult::Win(dtm)) => 2 * dtm as u32 + 2,
+        Some(ProbeResult::Loss(dtm)) => 2 * dtm as u32 + 3,
+    }
+}
+
+fn code_to_value(code: u32) -> wrap_res!(u16) {
+    let dtm = (code.saturating_sub(2) / 2) as u16;
+    err_assert!(dtm <= MAX_DTM, "Invalid tablebase file: distance to mate is too large");
+    Ok(match code {
+        0 => INVALID,
+        1 => ProbeResult::Draw.encode(),
+        c if c % 2 == 0 => ProbeResult::Win(dtm).encode(),
+        _ => ProbeResult::Loss(dtm).encode(),
+    })
+}
+
+
+/// Tablebases available to the search. Each one is only used if it's compatible with the position.
+#[derive(Debug, Clone, Default)]
+pub struct Tablebases {
+    pub custom: Option<Arc<Tablebase>>,
+    #[cfg(feature = "syzygy")]
+    pub syzygy: Option<Arc<syzygy::Syzygy>>,
+}
+
+/// Set of endgame tables for a variant. Use `Tablebase::generate()` to create it, or `Tablebase::from_bytes()`
+/// to load a file created with `Tablebase::to_bytes()`.
+///
+/// The tables don't store castling rights, en passant squares or check counts. Positions where castling or
+/// en passant is possible are not probed, and variants that count checks are not supported.
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tablebase {
+    // Hash of the piece definitions, rules and board shape of the variant
+    variant_hash: u64,
+    // In-bounds squares of the board, in the order used for indexing
+    squares: Vec<BIndex>,
+    // Position of each board index in `squares`, or NO_SQUARE
+    square_index: Vec<u16>,
+    max_pieces: u32,
+    // Each table is identified by the ids of its pieces, sorted. Stores 1 value per (squares, player to move).
+    tables: HashMap<String, Vec<u16>>,
+}
+
+impl Tablebase {
+    fn new(variant_hash: u64, squares: Vec<BIndex>) -> Tablebase {
+        let mut square_index = vec![NO_SQUARE; 256];
+        for (i, sq) in squares.iter().enumerate() {
+            square_index[*sq as usize] = i as u16;
+        }
+        Tablebase { variant_hash, squares, square_index, max_pieces: 0, tables: HashMap::new() }
+    }
+
+    /// Returns `true` if this tablebase was generated for the variant of the given position
+    pub fn is_compatible<const W: usize>(&self, pos: &Position<W>) -> bool {
+        variant_hash(pos, &self.squares) == self.variant_hash && board_squares(pos) == self.squares
+    }
+
+    /// Returns the maximum number of pieces (of both players) in any of the tables
+    pub fn get_max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    /// Returns the ids of the pieces of each table (for example `"Kkq"`), sorted
+    pub fn get_materials(&self) -> Vec<&str> {
+        let mut materials: Vec<&str> = self.tables.keys().map(|s| s.as_str()).collect();
+        materials.sort_unstable();
+        materials
+    }
+
+    /// Returns the result of the position, or `None` if the position is not in the tablebase.
+    /// The position must be from the variant used to create the tablebase (see `is_compatible()`).
+    pub fn probe<const W: usize>(&self, pos: &Position<W>) -> Option<ProbeResult> {
+        let num_pieces = pos.pieces[0].get_occupied().count_ones() + pos.pieces[1].get_occupied().count_ones();
+        if num_pieces > self.max_pieces || pos.get_ep_square().is_some() || can_castle(pos) {
+            return None;
+        }
+        let (material, index) = self.material_and_index(pos);
+        let table = self.tables.get(&material)?;
+        ProbeResult::decode(table[index])
+    }
+
+    /// Returns the material string and the index in the table of a position
+    fn material_and_index<const W: usize>(&self, pos: &Position<W>) -> (String, usize) {
+        let mut pieces: Vec<(PieceId, BIndex)> = Vec::new();
+        for piece_set in &pos.pieces {
+            for piece in piece_set.iter() {
+                for index in piece.get_indexes() {
+                    pieces.push((piece.get_piece_id(), index));
+                }
+            }
+        }
+        pieces.sort_unstable();
+        let material = pieces.iter().map(|(id, _)| *id).collect();
+        let squares: Vec<u16> = pieces.iter().map(|(_, index)| self.square_index[*index as usize]).collect();
+        (material, self.index_of(&squares, pos.whos_turn))
+    }
+
+    /// Index in a table of the position with the given squares (one for each piece in the material string)
+    fn index_of(&self, squares: &[u16], whos_turn: Player) -> usize {
+        let mut index = 0;
+        for sq in squares.iter().rev() {
+            index = index * self.squares.len() + *sq as usize;
+        }
+        2 * index + whos_turn as usize
+    }
+
+    /// Returns the contents of the tablebase file. The tables are run-length encoded, and the runs are stored as
+    /// variable-length integers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.variant_hash.to_be_bytes());
+        bytes.extend_from_slice(&(self.squares.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.squares);
+        bytes.extend_from_slice(&(self.tables.len() as u32).to_be_bytes());
+        for material in self.get_materials() {
+            bytes.push(material.chars().count() as u8);
+            for id in material.chars() {
+                bytes.extend_from_slice(&(id as u32).to_be_bytes());
+            }
+            // Runs of (value, length). The positions with white to move go first, since they have similar values.
+            let table = &self.tables[material];
+            let mut runs: Vec<(u16, u32)> = Vec::new();
+            for value in table.iter().step_by(2).chain(table.iter().skip(1).step_by(2)) {
+                match runs.last_mut() {
+                    Some((v, len)) if v == value => *len += 1,
+                    _ => runs.push((*value, 1)),
+                }
+            }
+            bytes.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+            for (value, len) in runs {
+                write_varint(&mut bytes, value_to_code(value));
+                write_varint(&mut bytes, len);
+            }
+        }
+        bytes
+    }
+
+    /// Loads a tablebase from the contents of a file created with `to_bytes()`
+    pub fn from_bytes(bytes: &[u8]) -> wrap_res!(Tablebase) {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        err_assert!(reader.take(MAGIC.len())? == MAGIC, "Invalid tablebase file");
+        let variant_hash = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let num_squares = reader.read_u16()? as usize;
+        let squares = reader.take(num_squares)?.to_vec();
+        let mut tb = Tablebase::new(variant_hash, squares);
+        let num_tables = reader.read_u32()?;
+        for _ in 0..num_tables {
+            let num_pieces = reader.take(1)?[0] as u32;
+            let mut material = String::new();
+            for _ in 0..num_pieces {
+                match char::from_u32(reader.read_u32()?) {
+                    Some(id) => material.push(id),
+                    None => err!("Invalid tablebase file: invalid piece id"),
+                }
+            }
+            let size = match num_squares.checked_pow(num_pieces).and_then(|n| n.checked_mul(2)) {
+                Some(size) => size,
+                None => err!("Invalid tablebase file: table {material} is too large"),
+            };
+            let mut table = Vec::with_capacity(size);
+            let num_runs = reader.read_u32()?;
+            for _ in 0..num_runs {
+                let value = code_to_value(reader.read_varint()?)?;
+                let len = reader.read_varint()? as usize;
+                err_assert!(table.len() + len <= size, "Invalid tablebase file: table {material} has the wrong size");
+                table.extend(std::iter::repeat_n(value, len));
+            }
+            err_assert!(table.len() == size, "Invalid tablebase file: table {material} has the wrong size");
+            // Undo the reordering done in to_bytes()
+            let (white, black) = table.split_at(size / 2);
+            let table = white.iter().zip(black).flat_map(|(w, b)| [*w, *b]).collect();
+            tb.max_pieces = std::cmp::max(tb.max_pieces, num_pieces);
+            tb.tables.insert(material, table);
+        }
+        err_assert!(reader.pos == bytes.len(), "Invalid tablebase file: unexpected data at the end");
+        Ok(tb)
+    }
+}
+
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> wrap_res!(&'a [u8]) {
+        err_assert!(self.pos + n <= self.bytes.len(), "Invalid tablebase file: unexpected end of file");
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+    fn read_u16(&mut self) -> wrap_res!(u16) {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn read_u32(&mut self) -> wrap_res!(u32) {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    // LEB128: 7 bits per byte, the highest bit is set in all bytes except the last one
+    fn read_varint(&mut self) -> wrap_res!(u32) {
+        let mut result: u32 = 0;
+        for shift in (0..32).step_by(7) {
+            let byte = self.take(1)?[0];
+            result |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        err!("Invalid tablebase file: integer is too large")
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+
+/// Returns the in-bounds squares of the board
+pub(super) fn board_squares<const W: usize>(pos: &Position<W>) -> Vec<BIndex> {
+    let mut squares = Vec::new();
+    for y in 0..pos.dimensions.height {
+        for x in 0..pos.dimensions.width {
+            if pos.in_bounds(x, y) {
+                squares.push(to_index(x, y));
+            }
+        }
+    }
+    squares
+}
+
+/// Hash of the piece definitions, the rules of the variant and the squares of the board, used to detect tablebases
+/// from other variants
+pub(super) fn variant_hash<const W: usize>(pos: &Position<W>, squares: &[BIndex]) -> u64 {
+    let definitions = pos.pieces.iter().flat_map(|set| set.iter()).map(|piece| piece.get_movement());
+    utils::variant_hash::variant_hash(definitions, &pos.global_rules, squares)
+}
+
+/// Returns `true` if any player can still castle
+pub(super) fn can_castle<const W: usize>(pos: &Position<W>) -> bool {
+    pos.pieces.iter().any(|piece_set| {
+        let unmoved = |castler: bool| piece_set.iter().any(|piece| {
+            let is_castler = if castler { piece.get_movement().can_castle() } else { piece.is_rook() };
+            is_castler && piece.get_indexes().iter().any(|index| piece.has_not_moved(*index))
+        });
+        unmoved(true) && unmoved(false)
+    })
+}
@@ -0,0 +1,367 @@
+// Probing of Syzygy endgame tablebases for standard chess.
+// WDL files (.rtbw) store the result of each position, DTZ files (.rtbz) store the number of plies until the next
+// capture or pawn move (zeroing move) when playing optimally. The files are loaded the first time they are used.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use crate::{MoveGen, Position, wrap_res, err_assert, err};
+use crate::position::create::variant_factory::VariantFactory;
+use crate::types::{GameMode, Move};
+use crate::utils::from_index;
+
+use super::{board_squares, variant_hash, can_castle};
+use super::generator::empty_position;
+
+mod table;
+use table::{Table, TableKind, TableValue, TbPosition, MAX_PIECES, BLACK};
+
+/// Ranks of the root moves, a win with DTZ `n` is ranked as `MAX_DTZ - n`
+const MAX_DTZ: i32 = 1 << 18;
+
+/// Result of a position according to the WDL tables, from the point of view of the player to move.
+/// A cursed win is a win that would be a draw under the 50-move rule, and a blessed loss is the opposite.
+/// The engine doesn't implement the 50-move rule, so they are still wins and losses.
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_i32(value: i32) -> Option<Wdl> {
+        match value {
+            -2 => Some(Wdl::Loss),
+            -1 => Some(Wdl::BlessedLoss),
+            0 => Some(Wdl::Draw),
+            1 => Some(Wdl::CursedWin),
+            2 => Some(Wdl::Win),
+            _ => None,
+        }
+    }
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl std::ops::Neg for Wdl {
+    type Output = Wdl;
+    fn neg(self) -> Wdl {
+        Wdl::from_i32(-(self as i32)).unwrap()
+    }
+}
+
+
+/// Table file that is loaded the first time it's probed
+#[derive(Debug)]
+struct LazyTable {
+    key: String,
+    path: PathBuf,
+    kind: TableKind,
+    // None if the file could not be loaded
+    table: OnceLock<Option<Table>>,
+}
+
+impl LazyTable {
+    fn get(&self) -> Option<&Table> {
+        self.table.get_or_init(|| {
+            let bytes = std::fs::read(&self.path).ok()?;
+            Table::new(&self.key, self.kind, bytes).ok()
+        }).as_ref()
+    }
+}
+
+
+/// Set of Syzygy tablebase files in a directory. Only used in standard chess.
+///
+/// Positions where castling is possible are not probed.
+#[derive(Debug)]
+pub struct Syzygy {
+    path: PathBuf,
+    // Hash of the standard chess variant (see `Tablebase::is_compatible()`)
+    variant_hash: u64,
+    max_pieces: u32,
+    // Tables indexed by material (for example "KRvK"). Both "KRvK" and "KvKR" point to the same table.
+    wdl: HashMap<String, Arc<LazyTable>>,
+    dtz: HashMap<String, Arc<LazyTable>>,
+}
+
+impl Syzygy {
+    /// Finds the WDL and DTZ files in a directory. The files are named after their material, for example `KRvK.rtbw`.
+    pub fn new(path: &str) -> wrap_res!(Syzygy) {
+        let template = empty_position(&VariantFactory::new(GameMode::Standard).make_initial_state())?;
+        let mut syzygy = Syzygy {
+            path: PathBuf::from(path),
+            variant_hash: variant_hash(&template, &board_squares(&template)),
+            max_pieces: 0,
+            wdl: HashMap::new(),
+            dtz: HashMap::new(),
+        };
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => err!("Cannot read the Syzygy directory {path}: {e}"),
+        };
+        for entry in entries.flatten() {
+            syzygy.add_file(&entry.path());
+        }
+        err_assert!(!syzygy.wdl.is_empty(), "No Syzygy WDL files (.rtbw) found in {path}");
+        Ok(syzygy)
+    }
+
+    fn add_file(&mut self, path: &Path) {
+        let (kind, tables) = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("rtbw") => (TableKind::Wdl, &mut self.wdl),
+            Some("rtbz") => (TableKind::Dtz, &mut self.dtz),
+            _ => return,
+        };
+        let key = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(key) => key.to_string(),
+            None => return,
+        };
+        let valid_side = |side: &str| side.starts_with('K') && side[1..].chars().all(|c| "QRBNP".contains(c));
+        let (white, black) = match key.split_once('v') {
+            Some((white, black)) if valid_side(white) && valid_side(black) => (white, black),
+            _ => return,
+        };
+        let num_pieces = white.len() + black.len();
+        if num_pieces > MAX_PIECES {
+            return;
+        }
+        if kind == TableKind::Wdl {
+            self.max_pieces = std::cmp::max(self.max_pieces, num_pieces as u32);
+        }
+        let mirrored = format!("{black}v{white}");
+        let table = Arc::new(LazyTable { key: key.clone(), path: path.to_path_buf(), kind, table: OnceLock::new() });
+        tables.insert(mirrored, table.clone());
+        tables.insert(key, table);
+    }
+
+    /// Returns the directory of the files
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the maximum number of pieces (including kings) of the WDL files
+    pub fn get_max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    /// Returns `true` if the position is from standard chess
+    pub fn is_compatible<const W: usize>(&self, pos: &Position<W>) -> bool {
+        variant_hash(pos, &board_squares(pos)) == self.variant_hash
+    }
+
+    /// Returns `true` if the position has few enough pieces and no castling rights.
+    /// The position must be from standard chess (see `is_compatible()`).
+    pub fn can_probe<const W: usize>(&self, pos: &Position<W>) -> bool {
+        let num_pieces = pos.pieces[0].get_occupied().count_ones() + pos.pieces[1].get_occupied().count_ones();
+        num_pieces <= self.max_pieces && !can_castle(pos)
+    }
+
+    /// Returns the result of the position, or `None` if it cannot be probed or some file is missing.
+    /// The position must be from standard chess (see `is_compatible()`).
+    pub fn probe_wdl<const W: usize>(&self, pos: &mut Position<W>) -> Option<Wdl> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+        self.search(pos, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the number of plies until the next zeroing move (positive if winning, negative if losing,
+    /// 0 if it's a draw), or `None` if the position cannot be probed or some file is missing.
+    /// Cursed wins and blessed losses are returned with an extra 100 plies, like in the files.
+    /// The position must be from standard chess (see `is_compatible()`).
+    pub fn probe_dtz<const W: usize>(&self, pos: &mut Position<W>) -> Option<i32> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+        self.dtz_impl(pos)
+    }
+
+    /// Returns the legal moves that keep the best result, choosing the fastest progress (lowest DTZ) when winning and
+    /// the slowest one when losing. Returns `None` if the position cannot be probed or some file is missing.
+    /// The position must be from standard chess (see `is_compatible()`).
+    pub fn root_moves<const W: usize>(&self, pos: &mut Position<W>) -> Option<Vec<Move>> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+        let mut ranked_moves = Vec::new();
+        for mv in MoveGen::get_legal_moves(pos) {
+            let zeroing = is_zeroing(pos, mv);
+            pos.make_move(mv);
+            let dtz = {
+                if zeroing {
+                    // After a zeroing move, the DTZ only depends on the result
+                    self.search(pos, false).map(|(wdl, _)| dtz_before_zeroing(-wdl))
+                } else {
+                    self.dtz_impl(pos).map(|dtz| -dtz + (-dtz).signum())
+                }
+            };
+            // A checkmate has a DTZ of 1
+            let mates = dtz == Some(2) && is_checkmate(pos);
+            pos.unmake_move();
+            let dtz = if mates { 1 } else { dtz? };
+            let rank = {
+                if dtz > 0 { MAX_DTZ - dtz }
+                else if dtz < 0 { -MAX_DTZ - dtz }
+                else { 0 }
+            };
+            ranked_moves.push((mv, rank));
+        }
+        let best_rank = ranked_moves.iter().map(|(_, rank)| *rank).max()?;
+        Some(ranked_moves.into_iter().filter(|(_, rank)| *rank == best_rank).map(|(mv, _)| mv).collect())
+    }
+
+    /// Searches the captures (and pawn moves if `check_zeroing_moves`), since the files don't store the positions
+    /// where en passant is possible and some positions where capturing is the best move.
+    /// Also returns `true` if the best move is a zeroing move.
+    fn search<const W: usize>(&self, pos: &mut Position<W>, check_zeroing_moves: bool) -> Option<(Wdl, bool)> {
+        let moves = MoveGen::get_legal_moves(pos);
+        let total_moves = moves.len();
+        let mut move_count = 0;
+        let mut best = Wdl::Loss;
+        for mv in moves {
+            if !(mv.is_capture() || check_zeroing_moves && is_zeroing(pos, mv)) {
+                continue;
+            }
+            move_count += 1;
+            pos.make_move(mv);
+            let result = self.search(pos, false);
+            pos.unmake_move();
+            let value = -result?.0;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+        // If all the legal moves have been searched, don't probe the table (the stored value could be wrong)
+        let no_more_moves = move_count > 0 && move_count == total_moves;
+        let value = {
+            if no_more_moves { best }
+            else {
+                match self.probe_table(pos, TableKind::Wdl, Wdl::Draw)? {
+                    TableValue::Value(value) => Wdl::from_i32(value)?,
+                    TableValue::ChangeStm => return None,
+                }
+            }
+        };
+        // The files store a "don't care" value if the best move is a winning capture
+        if best >= value {
+            return Some((best, best > Wdl::Draw || no_more_moves));
+        }
+        Some((value, false))
+    }
+
+    fn dtz_impl<const W: usize>(&self, pos: &mut Position<W>) -> Option<i32> {
+        let (wdl, zeroing_best_move) = self.search(pos, true)?;
+        if wdl == Wdl::Draw {
+            // DTZ files don't store draws
+            return Some(0);
+        }
+        if zeroing_best_move {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        match self.probe_table(pos, TableKind::Dtz, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed = if wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss { 100 } else { 0 };
+                Some((dtz + cursed) * wdl.signum())
+            },
+            TableValue::ChangeStm => {
+                // The file stores the positions with the other side to move, do a 1-ply search
+                let mut min_dtz = 0xFFFF;
+                for mv in MoveGen::get_legal_moves(pos) {
+                    let zeroing = is_zeroing(pos, mv);
+                    pos.make_move(mv);
+                    let dtz = {
+                        if zeroing { self.search(pos, false).map(|(wdl, _)| -dtz_before_zeroing(wdl)) }
+                        else { self.dtz_impl(pos).map(|dtz| -dtz) }
+                    };
+                    let mates = dtz == Some(1) && is_checkmate(pos);
+                    pos.unmake_move();
+                    let mut dtz = dtz?;
+                    if mates {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                        min_dtz = dtz;
+                    }
+                }
+                // No legal moves: checkmate
+                Some(if min_dtz == 0xFFFF { -1 } else { min_dtz })
+            },
+        }
+    }
+
+    /// Looks up the position in the file of its material
+    fn probe_table<const W: usize>(&self, pos: &Position<W>, kind: TableKind, wdl: Wdl) -> Option<TableValue> {
+        let (tb_pos, material) = to_tb_position(pos)?;
+        if material == "KvK" {
+            return Some(TableValue::Value(0));
+        }
+        let tables = if kind == TableKind::Wdl { &self.wdl } else { &self.dtz };
+        let table = tables.get(&material)?.get()?;
+        table.probe(&tb_pos, &material, wdl as i32)
+    }
+}
+
+
+/// Converts a position to the square numbering of the files, and returns its material (for example "KRvK")
+fn to_tb_position<const W: usize>(pos: &Position<W>) -> Option<(TbPosition, String)> {
+    const PIECES: &str = "KQRBNP";
+    let mut board = [0; 64];
+    let mut counts = [[0; 6]; 2];
+    for (player, piece_set) in pos.pieces.iter().enumerate() {
+        for piece in piece_set.iter() {
+            let id = piece.get_piece_id().to_ascii_uppercase();
+            let piece_index = PIECES.find(id)?;
+            // K = 6, Q = 5, ..., P = 1
+            let code = (6 - piece_index) as u8 + if player == 1 { BLACK } else { 0 };
+            for index in piece.get_indexes() {
+                let (x, y) = from_index(index);
+                board[y as usize * 8 + x as usize] = code;
+                counts[player][piece_index] += 1;
+            }
+        }
+    }
+    let mut material = String::new();
+    for (player, player_counts) in counts.iter().enumerate() {
+        if player == 1 {
+            material.push('v');
+        }
+        for (piece_index, count) in player_counts.iter().enumerate() {
+            let id = PIECES.chars().nth(piece_index).unwrap();
+            material.extend(std::iter::repeat_n(id, *count));
+        }
+    }
+    Some((TbPosition { board, stm: pos.whos_turn }, material))
+}
+
+/// Returns `true` if the move is a capture or a pawn move
+fn is_zeroing<const W: usize>(pos: &Position<W>, mv: Move) -> bool {
+    mv.is_capture() || pos.piece_at(mv.get_from()).is_some_and(|piece| piece.get_piece_id().eq_ignore_ascii_case(&'P'))
+}
+
+fn is_checkmate<const W: usize>(pos: &mut Position<W>) -> bool {
+    MoveGen::in_check(pos) && MoveGen::get_legal_moves(pos).is_empty()
+}
+
+/// DTZ of the position before a zeroing move that leads to the given result
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
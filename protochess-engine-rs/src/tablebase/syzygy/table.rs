@@ -0,0 +1,739 @@
+// Decoder for a single Syzygy file (.rtbw or .rtbz). The tables are indexed using the symmetries of the board, and
+// compressed with recursive pairing followed by canonical Huffman coding.
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::{wrap_res, err_assert, err};
+
+/// First bytes of a WDL file
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+/// First bytes of a DTZ file
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+/// Maximum number of pieces supported by the file format
+pub(super) const MAX_PIECES: usize = 7;
+
+// Piece codes used in the files (white pieces, add BLACK for black pieces)
+pub(super) const PAWN: u8 = 1;
+pub(super) const BLACK: u8 = 8;
+const PIECE_CHARS: &str = " PNBRQK";
+
+// Flags of each compressed table
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// Result of looking up a position in a table
+pub(super) enum TableValue {
+    /// WDL score (from -2 to 2) or DTZ value
+    Value(i32),
+    /// DTZ tables only store one side to move, and the position has the other one
+    ChangeStm,
+}
+
+/// Position to look up, using the square numbering of the files (a1 = 0, b1 = 1, ..., h8 = 63)
+pub(super) struct TbPosition {
+    /// Piece code on each square, or 0 if the square is empty
+    pub board: [u8; 64],
+    /// 0 if white is to move, 1 if black is to move
+    pub stm: u8,
+}
+
+
+/// Tables used to compute the index of a position
+struct Encoding {
+    // Squares a2-h7 to 0..47, the leading pawn is the one with the highest value
+    map_pawns: [usize; 64],
+    // Squares below the a1-h8 diagonal to 0..27
+    map_b1h1h7: [usize; 64],
+    // Squares in the a1-d1-d4 triangle to 0..9, the ones on the diagonal go last
+    map_a1d1d4: [usize; 64],
+    // The 462 legal positions of 2 kings, with the first one in the a1-d1-d4 triangle
+    map_kk: [[usize; 64]; 10],
+    // binomial[k][n]: number of ways to choose k elements from a set of n elements
+    binomial: [[u64; 64]; MAX_PIECES - 1],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES - 1],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES - 1],
+}
+
+lazy_static! {
+    static ref ENCODING: Encoding = Encoding::new();
+}
+
+fn file_of(sq: usize) -> usize { sq & 7 }
+fn rank_of(sq: usize) -> usize { sq >> 3 }
+/// Negative below the a1-h8 diagonal, 0 on the diagonal and positive above it
+fn off_a1h8(sq: usize) -> i32 { rank_of(sq) as i32 - file_of(sq) as i32 }
+
+impl Encoding {
+    fn new() -> Encoding {
+        let mut enc = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES - 1],
+            lead_pawn_idx: [[0; 64]; MAX_PIECES - 1],
+            lead_pawns_size: [[0; 4]; MAX_PIECES - 1],
+        };
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_a1h8(sq) < 0 {
+                enc.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+        // a1, b1, c1, d1, b2, c2, d2, c3, d3, d4
+        let triangle = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+        code = 0;
+        for sq in triangle.iter().filter(|sq| off_a1h8(**sq) < 0) {
+            enc.map_a1d1d4[*sq] = code;
+            code += 1;
+        }
+        for sq in triangle.iter().filter(|sq| off_a1h8(**sq) == 0) {
+            enc.map_a1d1d4[*sq] = code;
+            code += 1;
+        }
+
+        let map_a1d1d4 = enc.map_a1d1d4;
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            // b1 is mapped to 0, the squares outside the triangle are also 0
+            for s1 in (0..28).filter(|s1| map_a1d1d4[*s1] == idx && (idx != 0 || *s1 == 1)) {
+                for s2 in 0..64 {
+                    let adjacent = file_of(s1).abs_diff(file_of(s2)) <= 1 && rank_of(s1).abs_diff(rank_of(s2)) <= 1;
+                    if adjacent || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        // Illegal position, or first king on the diagonal and second above it
+                        continue;
+                    }
+                    if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        enc.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            enc.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        enc.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..std::cmp::min(n + 1, MAX_PIECES - 1) {
+                let with = if k > 0 { enc.binomial[k - 1][n - 1] } else { 0 };
+                let without = if k < n { enc.binomial[k][n - 1] } else { 0 };
+                enc.binomial[k][n] = with + without;
+            }
+        }
+
+        let mut available_squares = 47;
+        for lead_pawns_cnt in 1..MAX_PIECES - 1 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = rank * 8 + file;
+                    if lead_pawns_cnt == 1 {
+                        enc.map_pawns[sq] = available_squares;
+                        enc.map_pawns[sq ^ 7] = available_squares - 1;
+                        available_squares = available_squares.saturating_sub(2);
+                    }
+                    enc.lead_pawn_idx[lead_pawns_cnt][sq] = idx;
+                    idx += enc.binomial[lead_pawns_cnt - 1][enc.map_pawns[sq]];
+                }
+                enc.lead_pawns_size[lead_pawns_cnt][file] = idx;
+            }
+        }
+        enc
+    }
+}
+
+
+/// Compressed table for one side to move (and one file of the leading pawn, if there are pawns)
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    // Piece codes in the order used for indexing
+    pieces: [u8; MAX_PIECES],
+    // Number of pieces of each group, zero-terminated
+    group_len: [usize; MAX_PIECES + 1],
+    // Multiplier of the index of each group. The last one is the size of the table.
+    group_idx: [u64; MAX_PIECES + 1],
+    size_of_block: u64,
+    span: u64,
+    num_blocks: u64,
+    block_length_size: u64,
+    min_sym_len: u8,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    // Offsets in the file
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    sparse_index_size: u64,
+    block_length: usize,
+    data: usize,
+    // Offsets in the DTZ map for each WDL result
+    map_idx: [usize; 4],
+}
+
+/// Syzygy table, loaded in memory
+pub(super) struct Table {
+    kind: TableKind,
+    bytes: Vec<u8>,
+    // Material of the file name (for example "KRvK"), white is the stronger side
+    key: String,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+    // Number of pawns of the leading color and the other color
+    pawn_count: [usize; 2],
+    // Indexed by [side to move][file of the leading pawn]
+    pairs: Vec<Vec<PairsData>>,
+    // Offset of the DTZ map
+    map: usize,
+}
+
+impl std::fmt::Debug for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Table({}, {:?}, {} bytes)", self.key, self.kind, self.bytes.len())
+    }
+}
+
+impl Table {
+    /// Parses the contents of the file `<key>.rtbw` or `<key>.rtbz`
+    pub fn new(key: &str, kind: TableKind, bytes: Vec<u8>) -> wrap_res!(Table) {
+        let (white, black) = match key.split_once('v') {
+            Some(sides) => sides,
+            None => err!("Invalid Syzygy table name: {key}"),
+        };
+        let count = |side: &str, piece: u8| side.chars().filter(|c| PIECE_CHARS.find(*c) == Some(piece as usize)).count();
+        let piece_count = white.len() + black.len();
+        err_assert!(piece_count <= MAX_PIECES, "Syzygy table {key} has too many pieces");
+        let has_pawns = count(white, PAWN) + count(black, PAWN) > 0;
+        let has_unique_pieces = (PAWN..6).any(|piece| count(white, piece) == 1 || count(black, piece) == 1);
+        // The leading color is the one with less pawns (but at least 1)
+        let (white_pawns, black_pawns) = (count(white, PAWN), count(black, PAWN));
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] };
+
+        let mut table = Table {
+            kind,
+            bytes,
+            key: key.to_string(),
+            symmetric: white == black,
+            has_pawns,
+            has_unique_pieces,
+            piece_count,
+            pawn_count,
+            pairs: Vec::new(),
+            map: 0,
+        };
+        match table.parse() {
+            Some(()) => Ok(table),
+            None => err!("Syzygy table {key} is corrupted"),
+        }
+    }
+
+    /// Reads the headers of the file. Returns `None` if the file is invalid.
+    fn parse(&mut self) -> Option<()> {
+        let magic = if self.kind == TableKind::Wdl { WDL_MAGIC } else { DTZ_MAGIC };
+        if self.bytes.get(0..4)? != magic {
+            return None;
+        }
+        let mut pos = 4;
+        let flags = self.byte(pos)?;
+        if (flags & 2 != 0) != self.has_pawns || (flags & 1 != 0) == self.symmetric {
+            return None;
+        }
+        pos += 1;
+
+        let sides = if self.kind == TableKind::Wdl && !self.symmetric { 2 } else { 1 };
+        let num_files = if self.has_pawns { 4 } else { 1 };
+        // Pawns on both sides
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        self.pairs = vec![vec![PairsData::default(); num_files]; sides];
+
+        for file in 0..num_files {
+            let order0 = self.byte(pos)?;
+            let order1 = if pp { self.byte(pos + 1)? } else { 0xFF };
+            let order = [[order0 & 0xF, order1 & 0xF], [order0 >> 4, order1 >> 4]];
+            pos += 1 + pp as usize;
+            for k in 0..self.piece_count {
+                let b = self.byte(pos)?;
+                for side in 0..sides {
+                    self.pairs[side][file].pieces[k] = if side == 1 { b >> 4 } else { b & 0xF };
+                }
+                pos += 1;
+            }
+            for (side, side_order) in order.iter().enumerate().take(sides) {
+                // The pieces must match the file name
+                let mut pieces = self.pairs[side][file].pieces[..self.piece_count].to_vec();
+                pieces.sort_unstable();
+                if pieces != self.material_codes() {
+                    return None;
+                }
+                self.set_groups(side, file, *side_order);
+            }
+        }
+        // Word alignment
+        pos += pos & 1;
+
+        for file in 0..num_files {
+            for side in 0..sides {
+                pos = self.set_sizes(side, file, pos)?;
+            }
+        }
+        if self.kind == TableKind::Dtz {
+            pos = self.set_dtz_map(pos, num_files)?;
+        }
+        for file in 0..num_files {
+            for side in 0..sides {
+                let d = &mut self.pairs[side][file];
+                d.sparse_index = pos;
+                pos = pos.checked_add(usize::try_from(d.sparse_index_size.checked_mul(6)?).ok()?)?;
+            }
+        }
+        for file in 0..num_files {
+            for side in 0..sides {
+                let d = &mut self.pairs[side][file];
+                d.block_length = pos;
+                pos = pos.checked_add(usize::try_from(d.block_length_size.checked_mul(2)?).ok()?)?;
+            }
+        }
+        for file in 0..num_files {
+            for side in 0..sides {
+                // 64 byte alignment
+                pos = (pos + 0x3F) & !0x3F;
+                let d = &mut self.pairs[side][file];
+                d.data = pos;
+                pos = pos.checked_add(usize::try_from(d.num_blocks.checked_mul(d.size_of_block)?).ok()?)?;
+            }
+        }
+        if pos > self.bytes.len() {
+            return None;
+        }
+        Some(())
+    }
+
+    /// Returns the piece codes of the material in the file name, sorted
+    fn material_codes(&self) -> Vec<u8> {
+        let (white, black) = self.key.split_once('v').unwrap_or_default();
+        let mut codes: Vec<u8> = white.chars().map(|c| PIECE_CHARS.find(c).unwrap_or(0) as u8)
+            .chain(black.chars().map(|c| PIECE_CHARS.find(c).unwrap_or(0) as u8 | BLACK))
+            .collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// Groups together the pieces that are encoded together, and computes the multiplier of each group
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) {
+        let enc = &*ENCODING;
+        let d = &mut self.pairs[side][file];
+        // The leading group contains 3 different pieces, 2 kings or the pawns of the leading color
+        let mut first_len: i32 = if self.has_pawns { 0 } else if self.has_unique_pieces { 3 } else { 2 };
+        let mut n = 0;
+        d.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        // The groups are not encoded in order: the first group is at order[0] and the remaining pawns at order[1]
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= {
+                    if self.has_pawns { enc.lead_pawns_size[d.group_len[0]][file] }
+                    else if self.has_unique_pieces { 31332 }
+                    else { 462 }
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= enc.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= enc.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    /// Reads the parameters of the Huffman code, returns the offset after them
+    fn set_sizes(&mut self, side: usize, file: usize, mut pos: usize) -> Option<usize> {
+        let flags = self.byte(pos)?;
+        let mut d = std::mem::take(&mut self.pairs[side][file]);
+        d.flags = flags;
+        pos += 1;
+        if flags & FLAG_SINGLE_VALUE != 0 {
+            // All the positions have the same value, stored in min_sym_len
+            d.min_sym_len = self.byte(pos)?;
+            self.pairs[side][file] = d;
+            return Some(pos + 1);
+        }
+        let table_size = d.group_idx[d.group_len.iter().position(|len| *len == 0)?];
+        d.size_of_block = 1u64.checked_shl(self.byte(pos)? as u32)?;
+        d.span = 1u64.checked_shl(self.byte(pos + 1)? as u32)?;
+        d.sparse_index_size = table_size.div_ceil(d.span);
+        let padding = self.byte(pos + 2)? as u64;
+        d.num_blocks = self.u32_le(pos + 3)? as u64;
+        d.block_length_size = d.num_blocks + padding;
+        let max_sym_len = self.byte(pos + 7)?;
+        d.min_sym_len = self.byte(pos + 8)?;
+        pos += 9;
+        if max_sym_len < d.min_sym_len || d.min_sym_len == 0 {
+            return None;
+        }
+        d.lowest_sym = pos;
+
+        // Canonical Huffman code: base64[i] is the lowest code of length i + min_sym_len, left-aligned to 64 bits
+        let num_lengths = (max_sym_len - d.min_sym_len) as usize + 1;
+        d.base64 = vec![0; num_lengths];
+        for i in (0..num_lengths - 1).rev() {
+            let lowest = self.u16_le(d.lowest_sym + 2 * i)? as u64;
+            let next_lowest = self.u16_le(d.lowest_sym + 2 * (i + 1))? as u64;
+            d.base64[i] = d.base64[i + 1].wrapping_add(lowest).wrapping_sub(next_lowest) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            *base = base.checked_shl(64 - i as u32 - d.min_sym_len as u32).unwrap_or(0);
+        }
+        pos += 2 * num_lengths;
+
+        // Each symbol is a leaf or a pair of symbols (recursive pairing)
+        let num_symbols = self.u16_le(pos)? as usize;
+        pos += 2;
+        d.btree = pos;
+        self.byte(pos + 3 * num_symbols)?;
+        d.symlen = vec![0; num_symbols];
+        let mut visited = vec![false; num_symbols];
+        for sym in 0..num_symbols {
+            if !visited[sym] {
+                d.symlen[sym] = self.set_symlen(&mut d, sym, &mut visited)?;
+            }
+        }
+        self.pairs[side][file] = d;
+        Some(pos + 3 * num_symbols + (num_symbols & 1))
+    }
+
+    /// Returns the number of values represented by a symbol, minus 1
+    fn set_symlen(&self, d: &mut PairsData, sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let (left, right) = self.btree(d, sym)?;
+        if right == 0xFFF {
+            return Some(0);
+        }
+        for child in [left, right] {
+            if !*visited.get(child)? {
+                d.symlen[child] = self.set_symlen(d, child, visited)?;
+            }
+        }
+        Some(d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1))
+    }
+
+    /// Reads the maps from stored values to DTZ values, returns the offset after them
+    fn set_dtz_map(&mut self, mut pos: usize, num_files: usize) -> Option<usize> {
+        self.map = pos;
+        for file in 0..num_files {
+            let flags = self.pairs[0][file].flags;
+            if flags & FLAG_MAPPED == 0 {
+                continue;
+            }
+            for i in 0..4 {
+                if flags & FLAG_WIDE != 0 {
+                    // Word alignment
+                    pos += pos & 1;
+                    self.pairs[0][file].map_idx[i] = (pos - self.map) / 2 + 1;
+                    pos += 2 * self.u16_le(pos)? as usize + 2;
+                } else {
+                    self.pairs[0][file].map_idx[i] = pos - self.map + 1;
+                    pos += self.byte(pos)? as usize + 1;
+                }
+            }
+        }
+        Some(pos + (pos & 1))
+    }
+
+    /// Returns the value of a position. For WDL tables, this is the score from -2 (loss) to 2 (win) for the side
+    /// to move. For DTZ tables, `wdl` must be the WDL score of the position.
+    /// `material` is the material of the position, with white pieces first (for example `"KvKR"`).
+    pub fn probe(&self, pos: &TbPosition, material: &str, wdl: i32) -> Option<TableValue> {
+        let enc = &*ENCODING;
+        // The files only store the positions where white is the stronger side, and only the positions with
+        // white to move if both sides have the same pieces. Otherwise, swap the colors and flip the board.
+        let flip = (self.symmetric && pos.stm == 1) || material != self.key;
+        let flip_color = if flip { BLACK } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip as u8 ^ pos.stm) as usize;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_cnt = 0;
+        let mut lead_pawn = 0;
+        let mut file = 0;
+        if self.has_pawns {
+            // The pawns of the leading color are first in all the tables
+            lead_pawn = self.pairs[0][0].pieces[0] ^ flip_color;
+            for sq in (0..64).filter(|sq| pos.board[*sq] == lead_pawn) {
+                squares[size] = sq ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns_cnt = size;
+            // The leading pawn is the one closest to the edge and, among them, with the lowest rank
+            let lead = (0..lead_pawns_cnt).fold(0, |best, i| {
+                if enc.map_pawns[squares[i]] > enc.map_pawns[squares[best]] { i } else { best }
+            });
+            squares.swap(0, lead);
+            file = std::cmp::min(file_of(squares[0]), 7 - file_of(squares[0]));
+        }
+
+        let d = &self.pairs[stm % self.pairs.len()][file];
+        if self.kind == TableKind::Dtz && (d.flags & FLAG_STM) as usize != stm && (!self.symmetric || self.has_pawns) {
+            return Some(TableValue::ChangeStm);
+        }
+
+        for sq in 0..64 {
+            let piece = pos.board[sq];
+            if piece != 0 && !(self.has_pawns && piece == lead_pawn) {
+                squares[size] = sq ^ flip_squares;
+                pieces[size] = piece ^ flip_color;
+                size += 1;
+            }
+        }
+        if size != self.piece_count {
+            return None;
+        }
+        // Sort the pieces in the same order as the table
+        for i in lead_pawns_cnt..size - 1 {
+            if let Some(j) = (i + 1..size).find(|j| d.pieces[i] == pieces[*j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+        // Mirror the board so that the leading piece is on files a-d
+        if file_of(squares[0]) > 3 {
+            for sq in &mut squares[..size] {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+        if self.has_pawns {
+            idx = enc.lead_pawn_idx[lead_pawns_cnt][squares[0]];
+            squares[1..lead_pawns_cnt].sort_by_key(|sq| enc.map_pawns[*sq]);
+            for (i, sq) in squares.iter().enumerate().take(lead_pawns_cnt).skip(1) {
+                idx += enc.binomial[i][enc.map_pawns[*sq]];
+            }
+        } else {
+            // Mirror the board so that the leading piece is on ranks 1-4
+            if rank_of(squares[0]) > 3 {
+                for sq in &mut squares[..size] {
+                    *sq ^= 56;
+                }
+            }
+            // The first piece of the leading group that is not on the a1-h8 diagonal must be below it
+            for i in 0..d.group_len[0] {
+                if off_a1h8(squares[i]) == 0 {
+                    continue;
+                }
+                if off_a1h8(squares[i]) > 0 {
+                    for sq in &mut squares[i..size] {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            idx = if self.has_unique_pieces {
+                let s = &squares;
+                let adjust1 = (s[1] > s[0]) as usize;
+                let adjust2 = (s[2] > s[0]) as usize + (s[2] > s[1]) as usize;
+                let idx = {
+                    if off_a1h8(s[0]) != 0 {
+                        (enc.map_a1d1d4[s[0]] * 63 + (s[1] - adjust1)) * 62 + s[2] - adjust2
+                    } else if off_a1h8(s[1]) != 0 {
+                        (6 * 63 + rank_of(s[0]) * 28 + enc.map_b1h1h7[s[1]]) * 62 + s[2] - adjust2
+                    } else if off_a1h8(s[2]) != 0 {
+                        6 * 63 * 62 + 4 * 28 * 62 + rank_of(s[0]) * 7 * 28
+                            + (rank_of(s[1]) - adjust1) * 28 + enc.map_b1h1h7[s[2]]
+                    } else {
+                        6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank_of(s[0]) * 7 * 6
+                            + (rank_of(s[1]) - adjust1) * 6 + (rank_of(s[2]) - adjust2)
+                    }
+                };
+                idx as u64
+            } else {
+                enc.map_kk[enc.map_a1d1d4[squares[0]]][squares[1]] as u64
+            };
+        }
+        idx *= d.group_idx[0];
+
+        // Encode the rest of the groups, sorting the squares of each group
+        let mut group_start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let group_end = group_start + d.group_len[next];
+            squares[group_start..group_end].sort_unstable();
+            let mut n = 0;
+            for i in 0..d.group_len[next] {
+                let sq = squares[group_start + i];
+                // Skip the squares used by the previous groups
+                let adjust = squares[..group_start].iter().filter(|s| sq > **s).count();
+                n += enc.binomial[i + 1][sq - adjust - if remaining_pawns { 8 } else { 0 }];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = self.decompress(d, idx)?;
+        Some(TableValue::Value(self.map_score(file, value, wdl)?))
+    }
+
+    /// Converts a stored value to a WDL score or a DTZ value (in plies)
+    fn map_score(&self, file: usize, value: i32, wdl: i32) -> Option<i32> {
+        if self.kind == TableKind::Wdl {
+            return Some(value - 2);
+        }
+        let d = &self.pairs[0][file];
+        let mut value = value;
+        if d.flags & FLAG_MAPPED != 0 {
+            // Index of the map of each WDL score (loss, blessed loss, draw, cursed win, win)
+            const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+            let map_idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]];
+            value = {
+                if d.flags & FLAG_WIDE != 0 { self.u16_le(self.map + 2 * (map_idx + value as usize))? as i32 }
+                else { self.byte(self.map + map_idx + value as usize)? as i32 }
+            };
+        }
+        // Some tables store the distance in moves instead of plies
+        let in_moves = match wdl {
+            2 => d.flags & FLAG_WIN_PLIES == 0,
+            -2 => d.flags & FLAG_LOSS_PLIES == 0,
+            1 | -1 => true,
+            _ => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(value + 1)
+    }
+
+    /// Returns the value stored at the given index
+    fn decompress(&self, d: &PairsData, idx: u64) -> Option<i32> {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as i32);
+        }
+        // Find the block that contains the index. The sparse index stores the block and offset of
+        // some indexes (k * span + span / 2), then move forward or backward through the blocks.
+        let k = (idx / d.span) as usize;
+        let mut block = self.u32_le(d.sparse_index + 6 * k)? as usize;
+        let mut offset = self.u16_le(d.sparse_index + 6 * k + 4)? as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += self.u16_le(d.block_length + 2 * block)? as i64 + 1;
+        }
+        loop {
+            let block_length = self.u16_le(d.block_length + 2 * block)? as i64;
+            if offset <= block_length {
+                break;
+            }
+            offset -= block_length + 1;
+            block += 1;
+        }
+
+        // Decode the Huffman symbols of the block until reaching the one that contains the offset
+        let mut ptr = d.data + block * d.size_of_block as usize;
+        let mut buf64 = self.u64_be(ptr)?;
+        ptr += 8;
+        let mut buf64_size = 64;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf64 < *d.base64.get(len)? {
+                len += 1;
+            }
+            sym = ((buf64 - d.base64[len]) >> (64 - len - d.min_sym_len as usize)) as u16;
+            sym = sym.wrapping_add(self.u16_le(d.lowest_sym + 2 * len)?);
+            let sym_len = *d.symlen.get(sym as usize)? as i64;
+            if offset <= sym_len {
+                break;
+            }
+            offset -= sym_len + 1;
+            len += d.min_sym_len as usize;
+            buf64 <<= len;
+            buf64_size -= len;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (self.u32_be(ptr)? as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+        // Expand the symbol (its pairs are adjacent) until reaching a leaf
+        let mut sym = sym as usize;
+        while d.symlen[sym] != 0 {
+            let (left, right) = self.btree(d, sym)?;
+            let left_len = *d.symlen.get(left)? as i64;
+            if offset <= left_len {
+                sym = left;
+            } else {
+                offset -= left_len + 1;
+                sym = right;
+            }
+        }
+        Some(self.btree(d, sym)?.0 as i32)
+    }
+
+    /// Returns the left and right children of a symbol (the right child is 0xFFF for leaves)
+    fn btree(&self, d: &PairsData, sym: usize) -> Option<(usize, usize)> {
+        let lr = self.bytes.get(d.btree + 3 * sym..d.btree + 3 * sym + 3)?;
+        let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+        let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+        Some((left, right))
+    }
+
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.bytes.get(pos).copied()
+    }
+    fn u16_le(&self, pos: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes.get(pos..pos + 2)?.try_into().unwrap()))
+    }
+    fn u32_le(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes.get(pos..pos + 4)?.try_into().unwrap()))
+    }
+    fn u32_be(&self, pos: usize) -> Option<u32> {
+        Some(u32::from_be_bytes(self.bytes.get(pos..pos + 4)?.try_into().unwrap()))
+    }
+    fn u64_be(&self, pos: usize) -> Option<u64> {
+        Some(u64::from_be_bytes(self.bytes.get(pos..pos + 8)?.try_into().unwrap()))
+    }
+}
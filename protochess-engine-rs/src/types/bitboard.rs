@@ -1,5 +1,4 @@
 use std::ops;
-use impl_ops::*;
 
 use crate::{utils::to_index, wrap_res, err_assert};
 
@@ -10,16 +9,17 @@ pub type BCoord = u8; // Coordinate the board: [0..15]
 // Store bounds of the board (bit set to 1 for valid positions) and dimensions (width and height)
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[must_use]
-pub struct BDimensions {
+pub struct BDimensions<const W: usize = 4> {
     pub width: BCoord,
     pub height: BCoord,
-    pub bounds: Bitboard,
+    pub bounds: Bitboard<W>,
 }
 
-impl BDimensions {
+impl<const W: usize> BDimensions<W> {
     // Create a BDimensions object of a given width and height, with all squares valid
-    pub fn new_without_walls(width: BCoord, height: BCoord) -> wrap_res!(BDimensions) {
+    pub fn new_without_walls(width: BCoord, height: BCoord) -> wrap_res!(Self) {
         err_assert!(width <= 16 && height <= 16, "Board dimensions ({width}x{height}) must be <= 16x16");
+        err_assert!(height as usize <= 4 * W, "A board with {height} ranks doesn't fit in a bitboard of {W} words");
         let mut bounds = Bitboard::zero();
         for x in 0..width {
             for y in 0..height {
@@ -28,9 +28,14 @@ impl BDimensions {
         }
         Ok(BDimensions { width, height, bounds })
     }
+    // Converts the bounds to a bitboard of a different width (see `Bitboard::resize()`)
+    pub fn resize<const N: usize>(&self) -> BDimensions<N> {
+        BDimensions { width: self.width, height: self.height, bounds: self.bounds.resize() }
+    }
     // Given a list of invalid squares (coordinates of walls), return a BDimensions object
-    pub fn from_walls(width: BCoord, height: BCoord, walls: &[(BCoord, BCoord)]) -> wrap_res!(BDimensions) {
+    pub fn from_walls(width: BCoord, height: BCoord, walls: &[(BCoord, BCoord)]) -> wrap_res!(Self) {
         err_assert!(width <= 16 && height <= 16, "Board dimensions must be <= 16x16");
+        err_assert!(height as usize <= 4 * W, "A board with {height} ranks doesn't fit in a bitboard of {W} words");
         let mut bounds = Bitboard::zero();
         for x in 0..width {
             for y in 0..height {
@@ -50,33 +55,59 @@ impl BDimensions {
     }
 }
 
+/// Set of squares of a 16x16 board (bit `x + 16*y` is the square at `(x, y)`).
+/// Stored as `W` words of 64 bits, with the least significant word first.
+///
+/// A board with `h` ranks only uses the lowest `16*h` bits, so smaller boards can use fewer words: 1 word for up to
+/// 4 ranks, 2 words for up to 8 ranks (like standard chess) and 4 words for the rest (see `words_for_height()`).
+/// The bits above `64*W` are always 0: setting them is ignored, and reading them returns `false`.
+/// `Engine` chooses the width when the position is created, the rest of the crate is generic over it. `Bitboard`
+/// without parameters is the 256-bit bitboard, which works with any board.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[must_use]
-pub struct Bitboard {
-    board_internal: numext_fixed_uint::U256,
+pub struct Bitboard<const W: usize = 4> {
+    words: [u64; W],
+}
+
+/// Returns the number of 64-bit words needed by a bitboard of a board with `height` ranks (1, 2 or 4)
+pub fn words_for_height(height: BCoord) -> usize {
+    match height {
+        0..=4 => 1,
+        5..=8 => 2,
+        _ => 4,
+    }
 }
 
-impl Bitboard {
+impl<const W: usize> Bitboard<W> {
     #[inline]
-    pub fn zero() -> Bitboard {
-        Bitboard { board_internal: numext_fixed_uint::U256::zero() }
+    pub fn zero() -> Self {
+        Bitboard { words: [0; W] }
     }
     #[inline]
-    pub fn one() -> Bitboard {
-        Bitboard { board_internal: numext_fixed_uint::U256::one() }
+    pub fn one() -> Self {
+        Bitboard { words: std::array::from_fn(|i| (i == 0) as u64) }
     }
-    pub fn from_coord_list(squares: &[(BCoord, BCoord)]) -> Bitboard {
-        let mut board = Bitboard::zero();
+    pub fn from_coord_list(squares: &[(BCoord, BCoord)]) -> Self {
+        let mut board = Self::zero();
         squares.iter().for_each(|(x,y)| board.set_bit_at(*x, *y));
         board
     }
+    /// Converts the bitboard to a different width. When narrowing, the bits that don't fit are discarded.
+    #[inline]
+    pub fn resize<const N: usize>(&self) -> Bitboard<N> {
+        Bitboard { words: std::array::from_fn(|i| if i < W { self.words[i] } else { 0 }) }
+    }
     #[inline]
     pub fn set_bit(&mut self, index: BIndex) {
-        self.board_internal.set_bit(index as usize, true);
+        if let Some(word) = self.words.get_mut(index as usize / 64) {
+            *word |= 1 << (index % 64);
+        }
     }
     #[inline]
     pub fn clear_bit(&mut self, index: BIndex) {
-        self.board_internal.set_bit(index as usize, false);
+        if let Some(word) = self.words.get_mut(index as usize / 64) {
+            *word &= !(1 << (index % 64));
+        }
     }
     #[inline]
     pub fn set_bit_at(&mut self, x: BCoord, y: BCoord) {
@@ -88,7 +119,10 @@ impl Bitboard {
     }
     #[inline]
     pub fn get_bit(&self, index: BIndex) -> bool {
-        self.board_internal.bit(index as usize).unwrap()
+        match self.words.get(index as usize / 64) {
+            Some(word) => (word >> (index % 64)) & 1 != 0,
+            None => false,
+        }
     }
     #[inline]
     pub fn get_bit_at(&self, x: BCoord, y: BCoord) -> bool {
@@ -96,34 +130,104 @@ impl Bitboard {
     }
     #[inline]
     pub fn is_zero(&self) -> bool {
-        self.board_internal.is_zero()
+        self.words.iter().fold(0, |acc, word| acc | word) == 0
     }
     #[inline]
     pub fn lowest_one(&self) -> Option<BIndex> {
-        self.board_internal.lowest_one().map(|x| x as BIndex)
+        for (i, word) in self.words.iter().enumerate() {
+            if *word != 0 {
+                return Some((i as u32 * 64 + word.trailing_zeros()) as BIndex);
+            }
+        }
+        None
     }
     #[inline]
     pub fn highest_one(&self) -> Option<BIndex> {
-        self.board_internal.highest_one().map(|x| x as BIndex)
+        for (i, word) in self.words.iter().enumerate().rev() {
+            if *word != 0 {
+                return Some((i as u32 * 64 + 63 - word.leading_zeros()) as BIndex);
+            }
+        }
+        None
     }
     #[inline]
     pub fn count_ones(&self) -> u32 {
-        self.board_internal.count_ones()
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+    /// Product modulo 2^(64*W)
+    #[inline]
+    pub fn overflowing_mul(self, rhs: &Self) -> Self {
+        let mut words = [0u64; W];
+        for i in 0..W {
+            let mut carry = 0u128;
+            for j in 0..W - i {
+                let product = self.words[i] as u128 * rhs.words[j] as u128 + words[i + j] as u128 + carry;
+                words[i + j] = product as u64;
+                carry = product >> 64;
+            }
+        }
+        Bitboard { words }
+    }
+    #[inline]
+    pub fn get_inner(&self) -> &[u64; W] {
+        &self.words
+    }
+    #[inline]
+    pub fn get_inner_mut(&mut self) -> &mut [u64; W] {
+        &mut self.words
+    }
+
+    #[inline]
+    fn wrapping_add(&self, rhs: &Self) -> Self {
+        let mut words = [0u64; W];
+        let mut carry = false;
+        for (i, word) in words.iter_mut().enumerate() {
+            let (sum, carry1) = self.words[i].overflowing_add(rhs.words[i]);
+            let (sum, carry2) = sum.overflowing_add(carry as u64);
+            *word = sum;
+            carry = carry1 || carry2;
+        }
+        Bitboard { words }
     }
     #[inline]
-    pub fn overflowing_mul(self, rhs: &Bitboard) -> Bitboard {
-        Bitboard { board_internal: self.board_internal.overflowing_mul(&rhs.board_internal).0 }
+    fn wrapping_sub(&self, rhs: &Self) -> Self {
+        let mut words = [0u64; W];
+        let mut borrow = false;
+        for (i, word) in words.iter_mut().enumerate() {
+            let (diff, borrow1) = self.words[i].overflowing_sub(rhs.words[i]);
+            let (diff, borrow2) = diff.overflowing_sub(borrow as u64);
+            *word = diff;
+            borrow = borrow1 || borrow2;
+        }
+        Bitboard { words }
     }
     #[inline]
-    pub fn get_inner(&self) -> &[u64; 4] {
-        self.board_internal.get_inner()
+    fn map2(&self, rhs: &Self, op: impl Fn(u64, u64) -> u64) -> Self {
+        Bitboard { words: std::array::from_fn(|i| op(self.words[i], rhs.words[i])) }
     }
     #[inline]
-    pub fn get_inner_mut(&mut self) -> &mut [u64; 4] {
-        self.board_internal.get_inner_mut()
+    fn shl(&self, shift: BCoord) -> Self {
+        let w = &self.words;
+        if shift < 64 {
+            // Most shifts move the pieces by less than a word. (w >> 1) >> (63 - s) avoids shifting by 64 when s == 0
+            let s = shift as u32;
+            let carry = |i: usize| if i == 0 { 0 } else { (w[i - 1] >> 1) >> (63 - s) };
+            return Bitboard { words: std::array::from_fn(|i| (w[i] << s) | carry(i)) };
+        }
+        Bitboard { words: std::array::from_fn(|i| if i == 0 { 0 } else { w[i - 1] }) }.shl(shift - 64)
+    }
+    #[inline]
+    fn shr(&self, shift: BCoord) -> Self {
+        let w = &self.words;
+        if shift < 64 {
+            let s = shift as u32;
+            let carry = |i: usize| if i + 1 == W { 0 } else { (w[i + 1] << 1) << (63 - s) };
+            return Bitboard { words: std::array::from_fn(|i| (w[i] >> s) | carry(i)) };
+        }
+        Bitboard { words: std::array::from_fn(|i| if i + 1 == W { 0 } else { w[i + 1] }) }.shr(shift - 64)
     }
 }
-impl std::fmt::Display for Bitboard {
+impl<const W: usize> std::fmt::Display for Bitboard<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for y in (0..16).rev() {
             for x in 0..16 {
@@ -139,44 +243,85 @@ impl std::fmt::Display for Bitboard {
         Ok(())
     }
 }
-impl std::fmt::LowerHex for Bitboard {
+impl<const W: usize> std::fmt::LowerHex for Bitboard<W> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:x}", self.board_internal)
+        // Skip the leading zero words
+        let highest = self.words.iter().rposition(|word| *word != 0).unwrap_or(0);
+        write!(f, "{:x}", self.words[highest])?;
+        for word in self.words[..highest].iter().rev() {
+            write!(f, "{:016x}", word)?;
+        }
+        Ok(())
     }
 }
-impl Default for Bitboard {
+impl<const W: usize> Default for Bitboard<W> {
     fn default() -> Self {
         Bitboard::zero()
     }
 }
 
-impl_op_ex!(+ |a: &Bitboard, b: &Bitboard| -> Bitboard { Bitboard{board_internal: &a.board_internal + &b.board_internal} });
-impl_op_ex!(- |a: &Bitboard, b: &Bitboard| -> Bitboard { Bitboard{board_internal: &a.board_internal - &b.board_internal} });
-impl_op_ex!(& |a: &Bitboard, b: &Bitboard| -> Bitboard { Bitboard{board_internal: &a.board_internal & &b.board_internal} });
-impl_op_ex!(| |a: &Bitboard, b: &Bitboard| -> Bitboard { Bitboard{board_internal: &a.board_internal | &b.board_internal} });
-impl_op_ex!(^ |a: &Bitboard, b: &Bitboard| -> Bitboard { Bitboard{board_internal: &a.board_internal ^ &b.board_internal} });
-impl_op_ex!(<< |a: &Bitboard, b: BCoord| -> Bitboard { Bitboard{board_internal: &a.board_internal << b} });
-impl_op_ex!(>> |a: &Bitboard, b: BCoord| -> Bitboard { Bitboard{board_internal: &a.board_internal >> b} });
-impl_op_ex!(! |a: &Bitboard| -> Bitboard { Bitboard{board_internal: ! &a.board_internal} });
+// Implements a binary operator for all the combinations of owned and borrowed operands
+macro_rules! impl_binary_op {
+    ($op_trait:ident, $op_fn:ident, |$a:ident, $b:ident: $rhs:ty| $body:expr) => {
+        impl<const W: usize> ops::$op_trait<$rhs> for &Bitboard<W> {
+            type Output = Bitboard<W>;
+            #[inline]
+            fn $op_fn(self, $b: $rhs) -> Bitboard<W> { let $a = self; $body }
+        }
+        impl<const W: usize> ops::$op_trait<$rhs> for Bitboard<W> {
+            type Output = Bitboard<W>;
+            #[inline]
+            fn $op_fn(self, $b: $rhs) -> Bitboard<W> { let $a = &self; $body }
+        }
+    };
+}
+// Same as `impl_binary_op!`, when the right operand is also a bitboard
+macro_rules! impl_bitboard_op {
+    ($op_trait:ident, $op_fn:ident, |$a:ident, $b:ident| $body:expr) => {
+        impl_binary_op!($op_trait, $op_fn, |$a, $b: &Bitboard<W>| $body);
+        impl_binary_op!($op_trait, $op_fn, |$a, b: Bitboard<W>| { let $b = &b; $body });
+    };
+}
+// Implements an assignment operator for owned and borrowed right operands
+macro_rules! impl_assign_op {
+    ($op_trait:ident, $op_fn:ident, |$a:ident, $b:ident: $rhs:ty| $body:expr) => {
+        impl<const W: usize> ops::$op_trait<$rhs> for Bitboard<W> {
+            #[inline]
+            fn $op_fn(&mut self, $b: $rhs) { let $a = self; $body }
+        }
+    };
+}
+macro_rules! impl_bitboard_assign_op {
+    ($op_trait:ident, $op_fn:ident, |$a:ident, $b:ident| $body:expr) => {
+        impl_assign_op!($op_trait, $op_fn, |$a, $b: &Bitboard<W>| $body);
+        impl_assign_op!($op_trait, $op_fn, |$a, b: Bitboard<W>| { let $b = &b; $body });
+    };
+}
 
-impl_op_ex!(+= |a: &mut Bitboard, b: &Bitboard| { a.board_internal += &b.board_internal });
-impl_op_ex!(-= |a: &mut Bitboard, b: &Bitboard| { a.board_internal -= &b.board_internal });
-impl_op_ex!(&= |a: &mut Bitboard, b: &Bitboard| { a.board_internal &= &b.board_internal });
-impl_op_ex!(|= |a: &mut Bitboard, b: &Bitboard| { a.board_internal |= &b.board_internal });
-impl_op_ex!(^= |a: &mut Bitboard, b: &Bitboard| { a.board_internal ^= &b.board_internal });
-impl_op_ex!(^= |a: &mut Bitboard, b: u16| { a.board_internal ^= numext_fixed_uint::U256::from(b) });
-impl_op_ex!(<<= |a: &mut Bitboard, b: BCoord| { a.board_internal <<= b });
-impl_op_ex!(>>= |a: &mut Bitboard, b: BCoord| { a.board_internal >>= b });
+impl_bitboard_op!(Add, add, |a, b| a.wrapping_add(b));
+impl_bitboard_op!(Sub, sub, |a, b| a.wrapping_sub(b));
+impl_bitboard_op!(BitAnd, bitand, |a, b| a.map2(b, |x, y| x & y));
+impl_bitboard_op!(BitOr, bitor, |a, b| a.map2(b, |x, y| x | y));
+impl_bitboard_op!(BitXor, bitxor, |a, b| a.map2(b, |x, y| x ^ y));
+impl_binary_op!(Shl, shl, |a, b: BCoord| a.shl(b));
+impl_binary_op!(Shr, shr, |a, b: BCoord| a.shr(b));
 
-trait GetBitboardInner {
-    fn get_inner(&self) -> &[u64; 4];
-    fn get_inner_mut(&mut self) -> &mut [u64; 4];
+impl<const W: usize> ops::Not for &Bitboard<W> {
+    type Output = Bitboard<W>;
+    #[inline]
+    fn not(self) -> Bitboard<W> { self.map2(self, |x, _| !x) }
 }
-impl GetBitboardInner for numext_fixed_uint::U256 {
-    fn get_inner(&self) -> &[u64; 4] {
-        &self.0
-    }
-    fn get_inner_mut(&mut self) -> &mut [u64; 4] {
-        &mut self.0
-    }
+impl<const W: usize> ops::Not for Bitboard<W> {
+    type Output = Bitboard<W>;
+    #[inline]
+    fn not(self) -> Bitboard<W> { !&self }
 }
+
+impl_bitboard_assign_op!(AddAssign, add_assign, |a, b| *a = a.wrapping_add(b));
+impl_bitboard_assign_op!(SubAssign, sub_assign, |a, b| *a = a.wrapping_sub(b));
+impl_bitboard_assign_op!(BitAndAssign, bitand_assign, |a, b| a.words.iter_mut().zip(b.words.iter()).for_each(|(x, y)| *x &= y));
+impl_bitboard_assign_op!(BitOrAssign, bitor_assign, |a, b| a.words.iter_mut().zip(b.words.iter()).for_each(|(x, y)| *x |= y));
+impl_bitboard_assign_op!(BitXorAssign, bitxor_assign, |a, b| a.words.iter_mut().zip(b.words.iter()).for_each(|(x, y)| *x ^= y));
+impl_assign_op!(BitXorAssign, bitxor_assign, |a, b: u16| a.words[0] ^= b as u64);
+impl_assign_op!(ShlAssign, shl_assign, |a, b: BCoord| *a = a.shl(b));
+impl_assign_op!(ShrAssign, shr_assign, |a, b: BCoord| *a = a.shr(b));
@@ -10,8 +10,10 @@ use super::{BIndex, BCoord};
 mod move_list;
 mod move_info;
 mod make_move_result;
+mod move_buffer;
 
 pub use move_list::*;
+pub use move_buffer::*;
 pub use move_info::*;
 pub use make_move_result::*;
 
@@ -128,7 +130,7 @@ impl Move {
 
     /// Returns the squares that would explode if this move was made.
     /// **Important**: Call this function **before** making the move
-    pub fn get_potential_explosion(&self, pos: &Position) -> Vec<(BCoord, BCoord)> {
+    pub fn get_potential_explosion<const W: usize>(&self, pos: &Position<W>) -> Vec<(BCoord, BCoord)> {
         let piece = pos.piece_at(self.get_from()).unwrap();
         if !piece.explodes_on_capture() || !self.is_capture() {
             return Vec::new();
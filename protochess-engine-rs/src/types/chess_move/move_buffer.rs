@@ -0,0 +1,93 @@
+use crate::types::Centipawns;
+
+use super::Move;
+
+/// Number of moves that a `MoveBuffer` can hold without allocating (enough for any position of standard chess)
+pub const MOVE_BUFFER_CAPACITY: usize = 256;
+
+/// Destination of the moves generated by `MoveGen` and `Piece`
+pub trait MoveSink {
+    fn push(&mut self, mv: Move);
+}
+
+impl MoveSink for Vec<Move> {
+    #[inline]
+    fn push(&mut self, mv: Move) {
+        Vec::push(self, mv);
+    }
+}
+
+/// A move with its move ordering score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScoredMove {
+    pub mv: Move,
+    pub score: Centipawns,
+}
+
+/// List of moves stored on the stack, used to generate moves without allocating memory in each node.
+/// If more than `MOVE_BUFFER_CAPACITY` moves are added (for example, on a large board with many pieces),
+/// the moves are moved to the heap.
+#[derive(Debug, Clone)]
+pub struct MoveBuffer {
+    inline: [ScoredMove; MOVE_BUFFER_CAPACITY],
+    len: usize,
+    // Only used after the inline storage is full
+    heap: Vec<ScoredMove>,
+}
+
+impl MoveBuffer {
+    pub fn new() -> MoveBuffer {
+        MoveBuffer { inline: [ScoredMove::default(); MOVE_BUFFER_CAPACITY], len: 0, heap: Vec::new() }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        if self.heap.is_empty() { self.len } else { self.heap.len() }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.heap.clear();
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[ScoredMove] {
+        if self.heap.is_empty() { &self.inline[..self.len] } else { &self.heap }
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [ScoredMove] {
+        if self.heap.is_empty() { &mut self.inline[..self.len] } else { &mut self.heap }
+    }
+
+    pub fn contains(&self, mv: Move) -> bool {
+        self.as_slice().iter().any(|m| m.mv == mv)
+    }
+}
+
+impl Default for MoveBuffer {
+    fn default() -> Self {
+        MoveBuffer::new()
+    }
+}
+
+impl MoveSink for MoveBuffer {
+    #[inline]
+    fn push(&mut self, mv: Move) {
+        let scored = ScoredMove { mv, score: 0 };
+        if self.heap.is_empty() && self.len < MOVE_BUFFER_CAPACITY {
+            self.inline[self.len] = scored;
+            self.len += 1;
+        } else {
+            if self.heap.is_empty() {
+                self.heap.extend_from_slice(&self.inline[..self.len]);
+            }
+            self.heap.push(scored);
+        }
+    }
+}
@@ -10,7 +10,7 @@ use crate::utils::notation::tuple_to_rank_file;
 
 use super::Move;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[must_use]
 pub struct MoveInfo {
     pub from: (BCoord, BCoord),
@@ -44,10 +44,7 @@ impl TryFrom<&str> for MoveInfo {
             Ok(parts) => parts,
             Err(_) => err!("Invalid move format: '{s}'"),
         };
-        let promotion = match scan_fmt!(s, "{*[a-p]}{*d}{*[a-p]}{*d}={}", PieceId) {
-            Ok(promo) => Some(promo),
-            Err(_) => None,
-        };
+        let promotion = scan_fmt!(s, "{*[a-p]}{*d}{*[a-p]}{*d}={}", PieceId).ok();
         // from_x, to_x are guaranteed to be between 'a' and 'p' (inclusive)
         let from_x = from_x.to_digit(36).unwrap() as BCoord - 10;
         let to_x = to_x.to_digit(36).unwrap() as BCoord - 10;
@@ -0,0 +1,57 @@
+use crate::PieceId;
+use crate::types::{BCoord, Centipawns, Player};
+
+/// Material of all the units of a piece type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceMaterial {
+    pub id: PieceId,
+    pub count: u32,
+    pub midgame: Centipawns,
+    pub endgame: Centipawns,
+}
+
+/// Positional score (from the piece-square tables) of a single piece on the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiecePositional {
+    pub id: PieceId,
+    pub x: BCoord,
+    pub y: BCoord,
+    pub midgame: Centipawns,
+    pub endgame: Centipawns,
+}
+
+/// Evaluation of the pieces of one player, from the point of view of that player
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideEvalBreakdown {
+    /// Material of each piece type, including the leaders
+    pub material: Vec<PieceMaterial>,
+    /// Positional score of each piece
+    pub positional: Vec<PiecePositional>,
+    /// Mobility, leader safety, pawn structure and hanging pieces (see `searcher::eval_terms`)
+    pub terms_midgame: Centipawns,
+    pub terms_endgame: Centipawns,
+    /// Sum of the material, positional and terms scores
+    pub midgame: Centipawns,
+    pub endgame: Centipawns,
+    /// Penalty for the checks received by this player (only in variants with a check limit)
+    pub check_penalty: Centipawns,
+}
+
+/// Breakdown of the evaluation of a position, returned by `Engine::explain_eval()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct EvalBreakdown {
+    pub white: SideEvalBreakdown,
+    pub black: SideEvalBreakdown,
+    /// Weight of the middlegame scores, from 0 (endgame) to `eval::MIDGAME_PHASE` (middlegame)
+    pub phase: Centipawns,
+    /// Interpolation of the middlegame and endgame scores, for the player to move
+    pub handcrafted: Centipawns,
+    /// `true` if the goal of the variant is to lose (e.g. antichess), in which case the handcrafted score is negated
+    pub inverted: bool,
+    /// Score of the neural network for the player to move, if it's used instead of the handcrafted evaluation
+    pub network: Option<Centipawns>,
+    pub player_to_move: Player,
+    /// Final evaluation for the player to move, after the inversion and the check penalties
+    pub total: Centipawns,
+}
@@ -1,12 +1,14 @@
 mod bitboard;
 mod chess_move;
 mod searcher;
+mod eval_breakdown;
 
 use std::convert::TryFrom;
 
 pub use bitboard::*;
 pub use chess_move::*;
 pub use searcher::*;
+pub use eval_breakdown::*;
 
 use crate::{wrap_res, err};
 
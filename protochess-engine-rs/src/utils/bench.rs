@@ -0,0 +1,166 @@
+use instant::{Duration, Instant};
+
+use crate::piece::{PieceDefinition, PieceFactory};
+use crate::types::{Depth, GameMode};
+use crate::{wrap_res, err_assert, Engine, GameState, GlobalRules, InitialState, MakeMoveResultFlag};
+
+/// Default depth of `run_bench()`
+pub const DEFAULT_BENCH_DEPTH: Depth = 7;
+
+// Positions searched by the benchmark, in the format of `GameState::from_debug_fen()`
+const BENCH_FENS: [&str; 12] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP2BPPP/R1BQK2R w KQ - 4 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ATOMIC",
+    "rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR w KQkq - 2 3 ATOMIC",
+    "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1 HORDE",
+    "rnbqkbnr/ppp1pppp/8/1PPpPPP1/PPPP1PPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 2 HORDE",
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1 ANTICHESS",
+    "rnbqkbnr/pp2pppp/8/2pp4/3P4/4P3/PPP2PPP/RNBQKBNR w - - 0 3 ANTICHESS",
+    "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1 RACINGKINGS",
+    "8/8/8/8/1k4K1/8/1rbnNBR1/qrbnNBRQ w - - 4 3 RACINGKINGS",
+];
+
+// Positions of the 10x10 variant (see `custom_variant()`)
+const CUSTOM_FENS: [&str; 2] = [
+    "rnbqkcabnr/pppppppppp/10/10/10/10/10/10/PPPPPPPPPP/RNBQKCABNR w - - 0 1",
+    "r1bqkc1bnr/pppp1ppppp/2n2a4/4p5/10/10/4PP4/2N7/PPPP2PPPP/R1BQKCABNR w - - 1 4",
+];
+
+/// A position searched by the benchmark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchPosition {
+    /// FEN of the position followed by the name of the variant
+    pub name: String,
+    pub state: GameState,
+}
+
+/// Result of searching a position of the benchmark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchEntry {
+    pub name: String,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+/// Result of `run_bench()`
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub depth: Depth,
+    pub entries: Vec<BenchEntry>,
+}
+
+impl BenchResult {
+    pub fn total_nodes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.nodes).sum()
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.entries.iter().map(|entry| entry.time).sum()
+    }
+
+    /// Nodes searched per second
+    pub fn nps(&self) -> f64 {
+        self.total_nodes() as f64 / self.total_time().as_secs_f64().max(1e-9)
+    }
+
+    /// The search is deterministic, so the number of nodes only changes when the search or the evaluation
+    /// change. Comparing it with a previous run detects unintended changes (for example, in a refactoring).
+    pub fn signature(&self) -> u64 {
+        self.total_nodes()
+    }
+}
+
+impl std::fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}: {} nodes [time: {:?}]", entry.name, entry.nodes, entry.time)?;
+        }
+        writeln!(f, "\nDepth: {}", self.depth)?;
+        writeln!(f, "Total time: {:?}", self.total_time())?;
+        writeln!(f, "Nodes searched: {}", self.total_nodes())?;
+        writeln!(f, "Nodes/second: {:.0}", self.nps())?;
+        write!(f, "Signature: {}", self.signature())
+    }
+}
+
+/// Positions of standard chess, atomic, horde, antichess, racing kings and a 10x10 variant with custom pieces
+pub fn bench_positions() -> Vec<BenchPosition> {
+    let mut positions: Vec<BenchPosition> = BENCH_FENS.iter().map(|fen| BenchPosition {
+        name: fen.to_string(),
+        state: GameState::from_debug_fen(fen),
+    }).collect();
+    for fen in CUSTOM_FENS {
+        positions.push(BenchPosition {
+            name: format!("{fen} 10x10"),
+            state: GameState { initial_state: custom_variant(), initial_fen: Some(fen.to_string()), move_history: vec![] },
+        });
+    }
+    positions
+}
+
+/// Searches each position of `bench_positions()` up to `depth`, in a single thread and with a new transposition
+/// table for each position, so that the number of nodes doesn't depend on the machine or the previous searches
+pub fn run_bench(depth: Depth) -> wrap_res!(BenchResult) {
+    let mut entries = Vec::new();
+    for position in bench_positions() {
+        let mut engine = Engine::default();
+        engine.set_deterministic(true);
+        let result = engine.set_state(position.state)?;
+        err_assert!(result.flag == MakeMoveResultFlag::Ok, "The game is over in bench position {}", position.name);
+        let start = Instant::now();
+        let (_mv, _score) = engine.get_best_move(depth)?;
+        entries.push(BenchEntry { name: position.name, nodes: engine.get_nodes_searched(), time: start.elapsed() });
+    }
+    Ok(BenchResult { depth, entries })
+}
+
+// Standard pieces plus the archbishop (bishop + knight) and the chancellor (rook + knight) on a 10x10 board
+fn custom_variant() -> InitialState {
+    let factory = PieceFactory::new(GameMode::Standard);
+    let mut piece_types = factory.make_piece_set(10, 10);
+    for pawn in piece_types.iter_mut().filter(|p| p.ids == [Some('P'), None] || p.ids == [None, Some('p')]) {
+        pawn.promo_vals[0].extend(['A', 'C']);
+        pawn.promo_vals[1].extend(['a', 'c']);
+    }
+    let knight = factory.make_knight();
+    let archbishop = PieceDefinition {
+        ids: [Some('A'), Some('a')],
+        notation_prefix: [Some("A".to_string()), Some("A".to_string())],
+        attack_northeast: true,
+        attack_northwest: true,
+        attack_southeast: true,
+        attack_southwest: true,
+        translate_northeast: true,
+        translate_northwest: true,
+        translate_southeast: true,
+        translate_southwest: true,
+        ..knight.clone()
+    };
+    let chancellor = PieceDefinition {
+        ids: [Some('C'), Some('c')],
+        notation_prefix: [Some("C".to_string()), Some("C".to_string())],
+        attack_north: true,
+        attack_south: true,
+        attack_east: true,
+        attack_west: true,
+        translate_north: true,
+        translate_south: true,
+        translate_east: true,
+        translate_west: true,
+        ..knight
+    };
+    piece_types.push(archbishop);
+    piece_types.push(chancellor);
+    InitialState {
+        fen: CUSTOM_FENS[0].to_string(),
+        player_to_move: 0,
+        piece_types,
+        board_width: 10,
+        board_height: 10,
+        global_rules: GlobalRules::default(),
+    }
+}
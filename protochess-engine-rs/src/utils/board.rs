@@ -15,10 +15,10 @@ pub fn from_index(index: BIndex) -> (BCoord, BCoord) {
 }
 
 // BFS to find distance to nearest 1, using a callback function to get neighbors
-pub fn distance_to_one<F>(x_start: BCoord, y_start: BCoord, board: &Bitboard, get_neighbors: F) -> isize 
+pub fn distance_to_one<F, const W: usize>(x_start: BCoord, y_start: BCoord, board: &Bitboard<W>, get_neighbors: F) -> isize 
     where F: Fn(BCoord, BCoord) -> Vec<(BCoord, BCoord)>
 {
-    let mut visited = Bitboard::zero();
+    let mut visited: Bitboard = Bitboard::zero();
     let mut queue = VecDeque::new();
     queue.push_back((x_start, y_start, 0));
     while !queue.is_empty() {
@@ -0,0 +1,175 @@
+use std::convert::TryFrom;
+
+use regex::Regex;
+
+use crate::position::create::variant_factory::VariantFactory;
+use crate::types::GameMode;
+use crate::utils::notation::normalize_san;
+use crate::{wrap_res, err_assert, err, GameState, MoveInfo};
+
+/// A test position from an EPD (Extended Position Description) file.
+///
+/// Each line contains the first 4 fields of a FEN string (piece placement, player to move, castling rights
+/// and en passant square) followed by a list of operations separated by `;`. The position can use the
+/// custom FEN format (see `docs/FEN.md`), including the check count (`+W+B`) after the 4 fields.
+/// The following opcodes are supported, the rest are ignored:
+/// - `bm`: Best moves, the position is solved if the engine plays one of them
+/// - `am`: Avoid moves, the position is solved if the engine doesn't play any of them
+/// - `dm`: Direct mate, the position is solved if the engine finds a checkmate in this many moves (or fewer)
+/// - `id`: Name of the position
+/// - `variant`: Name of the variant (see `GameMode`), defaults to `Standard`
+///
+/// Moves can be written in SAN (`Qxf7+`) or in the format used by `MoveInfo` (`e2e4`, `e7e8=Q`).
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpdEntry {
+    pub fen: String,
+    pub variant: GameMode,
+    pub id: Option<String>,
+    pub best_moves: Vec<String>,
+    pub avoid_moves: Vec<String>,
+    pub mate_in: Option<i32>,
+}
+
+impl EpdEntry {
+    /// Returns a `GameState` that can be loaded into the engine with `Engine::set_state()`
+    pub fn game_state(&self) -> GameState {
+        let initial_state = VariantFactory::new(self.variant).make_initial_state();
+        GameState { initial_state, initial_fen: Some(self.fen.clone()), move_history: vec![] }
+    }
+
+    /// Returns `true` if the move found by the engine solves the position.
+    /// - `notation`: SAN of the move (see `NotationMode::Standard`)
+    /// - `mv`: Move in the format of `MoveInfo`
+    /// - `mate_in`: Number of moves until checkmate found by the engine, if any (see `searcher::mate_in()`)
+    pub fn is_solved(&self, notation: &str, mv: &MoveInfo, mate_in: Option<i32>) -> bool {
+        let played = normalize_san(notation);
+        let coords = mv.to_string();
+        let matches = |expected: &String| {
+            let expected = normalize_san(expected);
+            expected == played || expected == coords
+        };
+        if !self.best_moves.is_empty() && !self.best_moves.iter().any(matches) {
+            return false;
+        }
+        if self.avoid_moves.iter().any(matches) {
+            return false;
+        }
+        if let Some(expected_mate) = self.mate_in {
+            match mate_in {
+                Some(found) if found > 0 && found <= expected_mate => (),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl TryFrom<&str> for EpdEntry {
+    type Error = String;
+    fn try_from(line: &str) -> wrap_res!(Self) {
+        // The position is stored in the first 4 fields
+        let mut rest = line.trim();
+        let mut fen_parts = Vec::new();
+        for _ in 0..4 {
+            let (part, remaining) = match rest.find(char::is_whitespace) {
+                Some(i) => (&rest[..i], rest[i..].trim_start()),
+                None => (rest, ""),
+            };
+            err_assert!(!part.is_empty(), "Invalid EPD line, it must start with 4 FEN fields: '{line}'");
+            fen_parts.push(part);
+            rest = remaining;
+        }
+        // Optional check count
+        let check_count_regex = Regex::new(r"^\+[0-9]+\+[0-9]+(\s|$)").unwrap();
+        if let Some(m) = check_count_regex.find(rest) {
+            fen_parts.push(m.as_str().trim_end());
+            rest = rest[m.end()..].trim_start();
+        }
+
+        let mut entry = EpdEntry {
+            fen: fen_parts.join(" "),
+            variant: GameMode::Standard,
+            id: None,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            mate_in: None,
+        };
+        for operation in split_operations(rest)? {
+            let (opcode, operands) = match operation.split_first() {
+                Some(op) => op,
+                None => continue,
+            };
+            match opcode.as_str() {
+                "bm" => entry.best_moves.extend(operands.iter().cloned()),
+                "am" => entry.avoid_moves.extend(operands.iter().cloned()),
+                "id" => entry.id = Some(operands.join(" ")),
+                "dm" => match operands.first().map(|n| n.parse::<i32>()) {
+                    Some(Ok(n)) if n > 0 => entry.mate_in = Some(n),
+                    _ => err!("Invalid 'dm' operation in EPD line: '{line}'"),
+                },
+                "variant" => match operands.first() {
+                    Some(variant) => entry.variant = GameMode::try_from(variant.as_str())?,
+                    None => err!("Missing variant name in EPD line: '{line}'"),
+                },
+                // Other opcodes (hmvc, fmvn, c0, ...) are ignored
+                _ => (),
+            }
+        }
+        Ok(entry)
+    }
+}
+
+/// Parses the contents of an EPD file. Empty lines and lines starting with `#` are ignored.
+pub fn parse_epd(contents: &str) -> wrap_res!(Vec<EpdEntry>) {
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match EpdEntry::try_from(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => err!("Line {}: {e}", i + 1),
+        }
+    }
+    Ok(entries)
+}
+
+/// Splits the operations of an EPD line (separated by `;`) into opcodes and operands.
+/// Operands can be quoted strings, which may contain spaces and `;`.
+pub(super) fn split_operations(operations: &str) -> wrap_res!(Vec<Vec<String>>) {
+    let mut result = Vec::new();
+    let mut current_op = Vec::new();
+    let mut current_token = String::new();
+    let mut in_quotes = false;
+    for c in operations.chars() {
+        match c {
+            '"' => {
+                if in_quotes {
+                    // Push the quoted string even if it's empty
+                    current_op.push(std::mem::take(&mut current_token));
+                }
+                in_quotes = !in_quotes;
+            },
+            _ if in_quotes => current_token.push(c),
+            ';' | ' ' | '\t' => {
+                if !current_token.is_empty() {
+                    current_op.push(std::mem::take(&mut current_token));
+                }
+                if c == ';' && !current_op.is_empty() {
+                    result.push(std::mem::take(&mut current_op));
+                }
+            },
+            _ => current_token.push(c),
+        }
+    }
+    err_assert!(!in_quotes, "Unterminated string in EPD operations: '{operations}'");
+    if !current_token.is_empty() {
+        current_op.push(current_token);
+    }
+    if !current_op.is_empty() {
+        result.push(current_op);
+    }
+    Ok(result)
+}
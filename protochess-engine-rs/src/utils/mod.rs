@@ -1,7 +1,14 @@
 mod board;
 pub mod perft;
+pub mod perft_suite;
 pub mod debug;
 pub mod notation;
+pub mod epd;
+pub mod pgn;
+pub mod self_play;
+pub mod texel;
+pub mod bench;
+pub mod variant_hash;
 
 pub use board::*;
 
@@ -1,8 +1,35 @@
+use std::convert::TryFrom;
+
 use crate::types::{BCoord, Move, MoveType};
-use crate::Position;
+use crate::{Position, MoveGen, wrap_res, err};
 
 use super::from_index;
 
+/// Style of the algebraic notation generated for each move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[must_use]
+pub enum NotationMode {
+    /// Shortest unambiguous notation. Pawn captures omit the file (`xd5`), en passant captures
+    /// are marked with ` e.p.` and any move that ends the game is marked with `#`.
+    #[default]
+    Compact,
+    /// Strict Standard Algebraic Notation, as accepted by PGN readers. Captures by pieces without
+    /// a notation prefix include the file of origin (`exd5`), en passant is not marked, `+` is
+    /// only used for checks and `#` only for checkmates.
+    Standard,
+}
+
+impl TryFrom<&str> for NotationMode {
+    type Error = String;
+    fn try_from(value: &str) -> wrap_res!(Self) {
+        match value.to_lowercase().as_str() {
+            "compact" => Ok(NotationMode::Compact),
+            "standard" | "san" => Ok(NotationMode::Standard),
+            _ => err!("Invalid notation mode '{value}'"),
+        }
+    }
+}
+
 /// Converts an (x, y) location to chess rank-file notation
 /// Ex: `to_rank_file(0, 1)` = a2
 pub fn to_rank_file(x: BCoord, y: BCoord) -> String {
@@ -15,13 +42,15 @@ pub fn tuple_to_rank_file((x, y): (BCoord, BCoord)) -> String {
 
 /// Converts the move to user-friendly algebraic notation
 /// **IMPORTANT:** Call this **before** making the move
-pub fn get_algebraic_notation(pos: &mut Position, mv: Move, all_moves: &[Move]) -> String {
+pub fn get_algebraic_notation<const W: usize>(pos: &mut Position<W>, mv: Move, all_moves: &[Move], mode: NotationMode) -> String {
     if mv.is_castling() {
         return castling_notation(mv, all_moves);
     }
     let piece = pos.piece_at(mv.get_from()).unwrap();
     let prefix = piece.get_notation_prefix();
-    let disamb = disambiguate(pos, mv, all_moves);
+    // In SAN, a capture by a piece without prefix (pawn) always includes the file of origin
+    let force_file = mode == NotationMode::Standard && mv.is_capture() && prefix.is_empty();
+    let disamb = disambiguate(pos, mv, all_moves, mode, force_file);
     
     let capture = if mv.is_capture() { "x" } else { "" };
     
@@ -37,10 +66,20 @@ pub fn get_algebraic_notation(pos: &mut Position, mv: Move, all_moves: &[Move])
         }
     };
     
-    let ep = if mv.is_en_passant() { " e.p." } else { "" };
+    let ep = if mv.is_en_passant() && mode == NotationMode::Compact { " e.p." } else { "" };
     
     format!("{prefix}{disamb}{capture}{to}{promo}{ep}")
 }
+
+/// Returns the suffix for a move that ends the game without checkmating the opponent
+/// (leader captured, piece in win square, check limit, etc.)
+pub fn game_over_suffix(mode: NotationMode, in_check: bool) -> &'static str {
+    match mode {
+        NotationMode::Compact => "#",
+        // In SAN the suffix only describes the check status of the opponent
+        NotationMode::Standard => if in_check { "+" } else { "" },
+    }
+}
 pub fn add_suffix(mv: String, suf: &str) -> String {
     if mv.ends_with(" e.p.") {
         mv.replace(" e.p.", format!("{suf} e.p.").as_str())
@@ -49,6 +88,12 @@ pub fn add_suffix(mv: String, suf: &str) -> String {
     }
 }
 
+/// Removes the check, checkmate and annotation symbols from a move in SAN, and writes castling with
+/// letters (`O-O`), so that moves written by different tools can be compared
+pub fn normalize_san(san: &str) -> String {
+    san.trim_end_matches(['+', '#', '!', '?']).replace("0-0", "O-O")
+}
+
 fn castling_notation(mv: Move, all_moves: &[Move]) -> String {
     let mut kingside_castles = 0;
     let mut queenside_castles = 0;
@@ -73,16 +118,19 @@ fn castling_notation(mv: Move, all_moves: &[Move]) -> String {
     }
 }
 
-/// Returns the necessary disambiguation for the move
-fn disambiguate(pos: &mut Position, mv: Move, all_moves: &[Move]) -> String {
+/// Returns the necessary disambiguation for the move.
+/// If `force_file` is true, the file is always included (and the rank only if the file is not enough).
+fn disambiguate<const W: usize>(pos: &mut Position<W>, mv: Move, all_moves: &[Move], mode: NotationMode, force_file: bool) -> String {
     let from = from_index(mv.get_from());
     let mv_piece = pos.piece_at(mv.get_from()).unwrap().get_piece_id();
     let mut print_rank = false;
-    let mut print_file = false;
+    let mut print_file = force_file;
     
     for m in all_moves {
         if m.get_to() == mv.get_to() && m.get_from() != mv.get_from() 
-        && pos.piece_at(m.get_from()).unwrap().get_piece_id() == mv_piece {
+        && pos.piece_at(m.get_from()).unwrap().get_piece_id() == mv_piece
+        // SAN only disambiguates between legal moves (e.g. ignore pinned pieces)
+        && (mode == NotationMode::Compact || MoveGen::is_move_legal(*m, pos)) {
             // Got a match, determine if we need to disambiguate rank or file
             let m_from = from_index(m.get_from());
             if m_from.0 == from.0 {
@@ -1,69 +1,258 @@
 use crate::{MoveGen, Position};
-use crate::types::Depth;
+use crate::move_generator::legality::LegalityInfo;
+use crate::searcher::eval;
+use crate::types::{Depth, Move, ZobKey};
 
 use super::from_index;
 use super::notation::to_rank_file;
 
 /// Returns the number of possible moves from a board position up to a given depth
 /// See <https://www.chessprogramming.org/Perft>
-pub fn perft(position: &mut Position, depth: Depth) -> usize {
+pub fn perft<const W: usize>(position: &mut Position<W>, depth: Depth) -> usize {
     let mut nodes = 0;
 
+    // Bulk counting: the moves of the last ply don't need to be made
     if depth == 1 {
         return MoveGen::get_legal_moves(position).len();
     }
+    let legality = LegalityInfo::new(position);
     for mv in MoveGen::get_pseudo_moves(position, true) {
-        if !MoveGen::make_move_if_legal(mv, position) {
+        if !make_move_if_game_continues(mv, position, &legality) {
             continue;
         }
-        // Check for game over
-        if position.leader_is_captured() || position.piece_is_on_winning_square() {
-            position.unmake_move();
+        nodes += perft(position, depth - 1);
+        position.unmake_move();
+    }
+    nodes
+}
+
+/// Makes the move if it's legal and the game is not over after it. Otherwise, returns `false` and leaves the position
+/// unchanged: the moves that end the game (capturing the leader, reaching a winning square or giving the last check)
+/// are counted, but the positions after them are not expanded.
+pub(super) fn make_move_if_game_continues<const W: usize>(mv: Move, position: &mut Position<W>, legality: &LegalityInfo<W>) -> bool {
+    if !legality.make_move_if_legal(mv, position) {
+        return false;
+    }
+    if position.leader_is_captured() || position.piece_is_on_winning_square() {
+        position.unmake_move();
+        return false;
+    }
+    if position.global_rules.checks_to_lose != 0 && MoveGen::in_check(position) && position.increment_num_checks() {
+        position.unmake_move();
+        return false;
+    }
+    true
+}
+
+/// Types of the moves found at a given depth of the perft tree, as in the tables of
+/// <https://www.chessprogramming.org/Perft_Results>. A move can be counted in several categories.
+#[must_use]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: usize,
+    /// Including en passant captures
+    pub captures: usize,
+    pub en_passant: usize,
+    pub castles: usize,
+    pub promotions: usize,
+    pub checks: usize,
+    /// Captures made with a piece that explodes (atomic chess)
+    pub explosions: usize,
+    pub checkmates: usize,
+}
+
+impl std::ops::AddAssign for PerftStats {
+    fn add_assign(&mut self, other: PerftStats) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.en_passant += other.en_passant;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.explosions += other.explosions;
+        self.checkmates += other.checkmates;
+    }
+}
+
+impl std::fmt::Display for PerftStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "nodes: {}, captures: {}, e.p.: {}, castles: {}, promotions: {}, checks: {}, explosions: {}, checkmates: {}",
+            self.nodes, self.captures, self.en_passant, self.castles, self.promotions, self.checks, self.explosions, self.checkmates)
+    }
+}
+
+/// Like perft, but returns the statistics of each depth from 1 to `depth` (the `nodes` of the element `i`
+/// are equal to `perft(position, i + 1)`). Much slower than perft, since every move needs to be made.
+pub fn perft_stats<const W: usize>(position: &mut Position<W>, depth: Depth) -> Vec<PerftStats> {
+    let mut stats = vec![PerftStats::default(); depth as usize];
+    if depth > 0 {
+        perft_stats_impl(position, &mut stats, 0);
+    }
+    stats
+}
+
+fn perft_stats_impl<const W: usize>(position: &mut Position<W>, stats: &mut [PerftStats], ply: usize) {
+    let legality = LegalityInfo::new(position);
+    for mv in MoveGen::get_pseudo_moves(position, true) {
+        let explodes = mv.is_capture() && position.piece_at(mv.get_from()).is_some_and(|p| p.explodes_on_capture());
+        if !legality.make_move_if_legal(mv, position) {
             continue;
         }
-        if position.global_rules.checks_to_lose != 0 && MoveGen::in_check(position) && position.increment_num_checks() {
+        let entry = &mut stats[ply];
+        entry.nodes += 1;
+        entry.captures += mv.is_capture() as usize;
+        entry.en_passant += mv.is_en_passant() as usize;
+        entry.castles += mv.is_castling() as usize;
+        entry.promotions += mv.is_promotion() as usize;
+        entry.explosions += explodes as usize;
+        // If the leader has been captured, the opponent cannot be in check
+        let in_check = !position.leader_is_captured() && MoveGen::in_check(position);
+        if in_check {
+            entry.checks += 1;
+            if MoveGen::get_legal_moves(position).is_empty() {
+                entry.checkmates += 1;
+            }
+        }
+        position.unmake_move();
+        if ply + 1 < stats.len() && make_move_if_game_continues(mv, position, &legality) {
+            perft_stats_impl(position, stats, ply + 1);
             position.unmake_move();
+        }
+    }
+}
+
+/// Transposition table for `perft_hashed()`, which stores the number of nodes below each position and depth.
+/// When 2 entries use the same slot, the newest one is kept.
+#[derive(Debug, Clone)]
+pub struct PerftTable {
+    entries: Vec<Option<(ZobKey, Depth, usize)>>,
+}
+
+impl PerftTable {
+    /// Creates a table with `num_entries` slots
+    pub fn new(num_entries: usize) -> PerftTable {
+        PerftTable { entries: vec![None; std::cmp::max(num_entries, 1)] }
+    }
+
+    fn probe(&self, key: ZobKey, depth: Depth) -> Option<usize> {
+        match self.entries[key as usize % self.entries.len()] {
+            Some((entry_key, entry_depth, nodes)) if entry_key == key && entry_depth == depth => Some(nodes),
+            _ => None,
+        }
+    }
+
+    fn store(&mut self, key: ZobKey, depth: Depth, nodes: usize) {
+        let len = self.entries.len();
+        self.entries[key as usize % len] = Some((key, depth, nodes));
+    }
+}
+
+impl Default for PerftTable {
+    fn default() -> Self {
+        // 1M entries of 32 bytes, 32 MiB
+        PerftTable::new(1 << 20)
+    }
+}
+
+/// Same result as perft, but the counts of the positions that are reached by different move orders are only
+/// computed once. The result may be wrong in case of a zobrist key collision.
+pub fn perft_hashed<const W: usize>(position: &mut Position<W>, depth: Depth, table: &mut PerftTable) -> usize {
+    if depth == 1 {
+        return MoveGen::get_legal_moves(position).len();
+    }
+    let key = position.get_zobrist();
+    if let Some(nodes) = table.probe(key, depth) {
+        return nodes;
+    }
+    let mut nodes = 0;
+    let legality = LegalityInfo::new(position);
+    for mv in MoveGen::get_pseudo_moves(position, true) {
+        if !make_move_if_game_continues(mv, position, &legality) {
             continue;
         }
-        nodes += perft(position, depth - 1);
+        nodes += perft_hashed(position, depth - 1, table);
         position.unmake_move();
     }
+    table.store(key, depth, nodes);
     nodes
 }
 
-/// Like perft, but prints the moves at the first ply
-pub fn perft_divide(position: &mut Position, depth: Depth) -> usize {
-    let mut nodes = 0;
+/// Same result as perft, but the subtree of each move at the first ply is counted in a different thread
+#[cfg(feature = "parallel")]
+pub fn perft_parallel<const W: usize>(position: &Position<W>, depth: Depth) -> usize {
+    use rayon::prelude::*;
+    if depth <= 1 {
+        return perft(&mut position.clone(), depth);
+    }
+    let moves = MoveGen::get_pseudo_moves(&mut position.clone(), true);
+    let legality = LegalityInfo::new(position);
+    moves.par_iter().map(|&mv| {
+        let mut position = position.clone();
+        if !make_move_if_game_continues(mv, &mut position, &legality) {
+            return 0;
+        }
+        perft(&mut position, depth - 1)
+    }).sum()
+}
 
-    let mut printing = Vec::new();
+/// Like perft, but also evaluates every leaf node. Used for benchmarking the evaluation function.
+/// Returns the number of leaf nodes and the sum of their evaluations.
+pub fn eval_perft<const W: usize>(position: &mut Position<W>, depth: Depth) -> (usize, i64) {
+    if depth == 0 {
+        return (1, eval::evaluate(position) as i64);
+    }
+    let mut nodes = 0;
+    let mut total_score = 0;
+    let legality = LegalityInfo::new(position);
     for mv in MoveGen::get_pseudo_moves(position, true) {
-        if !MoveGen::is_move_legal(mv, position) {
+        if !legality.make_move_if_legal(mv, position) {
             continue;
         }
-
-        let (x,y) = from_index(mv.get_from());
-        let (x2,y2) = from_index(mv.get_to());
-        let promo = mv.get_promotion_piece();
-        let promo_str = {
-            if let Some(p) = promo { p.to_string() }
-            else { "".to_string() }
-        };
-        if depth == 1 {
-            nodes += 1;
-            printing.push(format!("{}{}{}: 1", to_rank_file(x,y), to_rank_file(x2,y2), promo_str));
-        } else {
-            position.make_move(mv);
-            let plus = perft(position, depth - 1);
-            nodes += plus;
+        if position.leader_is_captured() || position.piece_is_on_winning_square() {
             position.unmake_move();
-            //Print nodes
-            printing.push(format!("{}{}{}: {}", to_rank_file(x,y), to_rank_file(x2,y2), promo_str, plus));
+            continue;
         }
+        let (child_nodes, child_score) = eval_perft(position, depth - 1);
+        nodes += child_nodes;
+        total_score += child_score;
+        position.unmake_move();
+    }
+    (nodes, total_score)
+}
+
+/// Like perft, but returns the number of nodes below each legal move at the first ply, sorted by move.
+/// The moves are written in coordinate notation (`e2e4`, `e7e8Q` for promotions and `e1h1` for castling).
+/// The moves that end the game have 0 nodes below them (unless `depth` is 1).
+pub fn perft_divide<const W: usize>(position: &mut Position<W>, depth: Depth) -> Vec<(String, usize)> {
+    let mut result = Vec::new();
+    let legality = LegalityInfo::new(position);
+    for (name, mv) in legal_moves_by_name(position) {
+        let nodes = {
+            if depth <= 1 {
+                1
+            } else if make_move_if_game_continues(mv, position, &legality) {
+                let nodes = perft(position, depth - 1);
+                position.unmake_move();
+                nodes
+            } else {
+                0
+            }
+        };
+        result.push((name, nodes));
     }
-    printing.sort();
-    for s in printing {
-        println!("{s}");
+    result.sort();
+    result
+}
+
+/// Returns the legal moves in the coordinate notation of `perft_divide()`
+pub(super) fn legal_moves_by_name<const W: usize>(position: &mut Position<W>) -> Vec<(String, Move)> {
+    let mut result = Vec::new();
+    for mv in MoveGen::get_legal_moves(position) {
+        let (x, y) = from_index(mv.get_from());
+        // Castling moves are written as if the king moves to the rook square, like in `MoveInfo`
+        let (x2, y2) = from_index(if mv.is_castling() { mv.get_target() } else { mv.get_to() });
+        let promo_str = mv.get_promotion_piece().map(|p| p.to_string()).unwrap_or_default();
+        result.push((format!("{}{}{promo_str}", to_rank_file(x, y), to_rank_file(x2, y2)), mv));
     }
-    nodes
+    result
 }
-  
\ No newline at end of file
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::move_generator::legality::LegalityInfo;
+use crate::position::create::position_factory::PositionFactory;
+use crate::position::create::variant_factory::VariantFactory;
+use crate::types::{Depth, GameMode};
+use crate::{wrap_res, err_assert, err, Engine, GameState, InitialState, MakeMoveResultFlag, Position};
+
+use super::epd::split_operations;
+use super::perft::{legal_moves_by_name, make_move_if_game_continues, perft};
+
+/// A position with its expected perft results, from a perft suite file.
+///
+/// Each line contains a FEN string (in the custom format, see `docs/FEN.md`) followed by a list of operations
+/// separated by `;`, as in `perftsuite.epd`:
+///
+/// `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902`
+///
+/// The following opcodes are supported:
+/// - `D<n>`: Expected number of leaf nodes at depth `n`
+/// - `variant`: Name of the variant, either one of `GameMode` or a custom variant passed to `game_state()`.
+///   Defaults to `standard`.
+/// - `id`: Name of the position
+#[must_use]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftSuiteEntry {
+    pub fen: String,
+    pub variant: String,
+    pub id: Option<String>,
+    /// Expected results, sorted by depth
+    pub depths: Vec<(Depth, usize)>,
+}
+
+/// Result of comparing the perft of a position with its expected value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftCheck {
+    pub fen: String,
+    pub variant: String,
+    pub depth: Depth,
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl PerftCheck {
+    pub fn passed(&self) -> bool {
+        self.expected == self.found
+    }
+}
+
+impl std::fmt::Display for PerftCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let status = if self.passed() { "OK" } else { "FAILED" };
+        write!(f, "{status} [{}] {} depth {}: expected {}, found {}",
+            self.variant, self.fen, self.depth, self.expected, self.found)
+    }
+}
+
+impl PerftSuiteEntry {
+    /// Returns the `GameState` of the position. The variant is looked up in `custom_variants` first, so that
+    /// the move generation of new piece types can be tested without adding them to `GameMode`.
+    pub fn game_state(&self, custom_variants: &[(&str, InitialState)]) -> wrap_res!(GameState) {
+        let initial_state = match custom_variants.iter().find(|(name, _)| name.eq_ignore_ascii_case(&self.variant)) {
+            Some((_, state)) => state.clone(),
+            None => VariantFactory::new(GameMode::try_from(self.variant.as_str())?).make_initial_state(),
+        };
+        Ok(GameState { initial_state, initial_fen: Some(self.fen.clone()), move_history: vec![] })
+    }
+
+    /// Runs perft at each expected depth, up to `max_depth`
+    pub fn run(&self, custom_variants: &[(&str, InitialState)], max_depth: Depth) -> wrap_res!(Vec<PerftCheck>) {
+        let mut engine = Engine::default();
+        let result = engine.set_state(self.game_state(custom_variants)?)?;
+        err_assert!(result.flag == MakeMoveResultFlag::Ok, "The game is over in position {}", self.fen);
+        let mut checks = Vec::new();
+        for &(depth, expected) in self.depths.iter().filter(|(depth, _)| *depth <= max_depth) {
+            checks.push(PerftCheck {
+                fen: self.fen.clone(),
+                variant: self.variant.clone(),
+                depth,
+                expected,
+                found: engine.perft(depth),
+            });
+        }
+        Ok(checks)
+    }
+
+    /// Returns the position, for `find_divide_mismatch()`
+    pub fn position(&self, custom_variants: &[(&str, InitialState)]) -> wrap_res!(Position) {
+        match PositionFactory::default().set_state(self.game_state(custom_variants)?, None)? {
+            Some(position) => Ok(position),
+            None => err!("Cannot create the position {}", self.fen),
+        }
+    }
+}
+
+impl TryFrom<&str> for PerftSuiteEntry {
+    type Error = String;
+    fn try_from(line: &str) -> wrap_res!(Self) {
+        let (fen, operations) = line.split_once(';').unwrap_or((line, ""));
+        err_assert!(!fen.trim().is_empty(), "Missing FEN in perft suite line: '{line}'");
+        let mut entry = PerftSuiteEntry {
+            fen: fen.trim().to_string(),
+            variant: "standard".to_string(),
+            id: None,
+            depths: Vec::new(),
+        };
+        for operation in split_operations(operations)? {
+            let (opcode, operands) = match operation.split_first() {
+                Some(op) => op,
+                None => continue,
+            };
+            match opcode.as_str() {
+                "variant" => match operands.first() {
+                    Some(variant) => entry.variant = variant.to_lowercase(),
+                    None => err!("Missing variant name in perft suite line: '{line}'"),
+                },
+                "id" => entry.id = Some(operands.join(" ")),
+                _ => {
+                    let depth = opcode.strip_prefix('D').and_then(|d| d.parse::<Depth>().ok());
+                    let nodes = operands.first().and_then(|n| n.parse::<usize>().ok());
+                    match (depth, nodes) {
+                        (Some(depth), Some(nodes)) if depth > 0 => entry.depths.push((depth, nodes)),
+                        _ => err!("Invalid operation '{}' in perft suite line: '{line}'", operation.join(" ")),
+                    }
+                },
+            }
+        }
+        err_assert!(!entry.depths.is_empty(), "No expected results in perft suite line: '{line}'");
+        entry.depths.sort_unstable();
+        Ok(entry)
+    }
+}
+
+/// Parses the contents of a perft suite file. Empty lines and lines starting with `#` are ignored.
+pub fn parse_perft_suite(contents: &str) -> wrap_res!(Vec<PerftSuiteEntry>) {
+    let mut entries = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match PerftSuiteEntry::try_from(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => err!("Line {}: {e}", i + 1),
+        }
+    }
+    Ok(entries)
+}
+
+
+/// Expected divide results (number of leaf nodes below each move) of a position and of some positions after it.
+///
+/// Each line contains a move in the coordinate notation of `perft_divide()` and its number of nodes (`e2e4: 20`).
+/// A line `moves: <m1> <m2> ...` starts the results of the position reached after playing those moves from the
+/// root (with 1 less depth per move), and `moves:` goes back to the root. Moves are case-insensitive, castling
+/// moves are written from the king to the rook (`e1h1`). Empty lines, lines starting with `#` and the summary
+/// lines printed by other engines (`Nodes searched: ...`) are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DivideReference {
+    results: HashMap<Vec<String>, Vec<(String, usize)>>,
+}
+
+impl DivideReference {
+    pub fn parse(contents: &str) -> wrap_res!(Self) {
+        let mut results: HashMap<Vec<String>, Vec<(String, usize)>> = HashMap::new();
+        let mut path = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            let lowercase = line.to_lowercase();
+            if line.is_empty() || line.starts_with('#') || lowercase.starts_with("nodes") || lowercase.starts_with("total") {
+                continue;
+            }
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => err!("Line {}: expected '<move>: <nodes>' or 'moves: <moves>', got '{line}'", i + 1),
+            };
+            if key == "moves" {
+                path = value.split_whitespace().map(|mv| mv.to_lowercase()).collect();
+                continue;
+            }
+            match value.parse::<usize>() {
+                Ok(nodes) => results.entry(path.clone()).or_default().push((key, nodes)),
+                Err(_) => err!("Line {}: invalid number of nodes '{value}'", i + 1),
+            }
+        }
+        Ok(DivideReference { results })
+    }
+
+    /// Adds the results of the position reached after `path`, in the format returned by `perft_divide()`
+    pub fn insert(&mut self, path: &[&str], divide: &[(String, usize)]) {
+        let path = path.iter().map(|mv| mv.to_lowercase()).collect();
+        let divide = divide.iter().map(|(mv, nodes)| (mv.to_lowercase(), *nodes)).collect();
+        self.results.insert(path, divide);
+    }
+
+    fn get(&self, path: &[String]) -> Option<&Vec<(String, usize)>> {
+        self.results.get(path)
+    }
+}
+
+/// First difference found between the divide results of the engine and a `DivideReference`.
+/// `path` contains the moves played from the root to reach the position where the difference was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivideMismatch {
+    /// The engine generates a move that is not in the reference
+    ExtraMove { path: Vec<String>, mv: String },
+    /// The engine doesn't generate a move of the reference
+    MissingMove { path: Vec<String>, mv: String },
+    /// Both have the move, but the number of nodes below it is different and the reference doesn't contain
+    /// the results after it (or they match, which means that the reference is inconsistent)
+    WrongCount { path: Vec<String>, mv: String, expected: usize, found: usize },
+}
+
+impl std::fmt::Display for DivideMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let position = |path: &Vec<String>| if path.is_empty() { "at the root".to_string() } else { format!("after {}", path.join(" ")) };
+        match self {
+            DivideMismatch::ExtraMove { path, mv } => write!(f, "Illegal move {mv} generated {}", position(path)),
+            DivideMismatch::MissingMove { path, mv } => write!(f, "Legal move {mv} not generated {}", position(path)),
+            DivideMismatch::WrongCount { path, mv, expected, found } => {
+                write!(f, "Wrong count for {mv} {}: expected {expected}, found {found}", position(path))
+            },
+        }
+    }
+}
+
+/// Compares the divide results of the position at the given depth with a reference, and returns the deepest
+/// difference that can be found. When the number of nodes below a move is wrong, the comparison continues in the
+/// position after that move, as long as the reference contains its results.
+/// Returns an error if the reference doesn't contain the results of the root position.
+pub fn find_divide_mismatch<const W: usize>(position: &mut Position<W>, depth: Depth, reference: &DivideReference) -> wrap_res!(Option<DivideMismatch>) {
+    err_assert!(depth > 0, "The depth must be at least 1");
+    err_assert!(reference.get(&[]).is_some(), "The reference doesn't contain the results of the root position");
+    Ok(find_mismatch_impl(position, depth, reference, &mut Vec::new()))
+}
+
+fn find_mismatch_impl<const W: usize>(position: &mut Position<W>, depth: Depth, reference: &DivideReference, path: &mut Vec<String>) -> Option<DivideMismatch> {
+    let expected: HashMap<&str, usize> = reference.get(path)?.iter().map(|(mv, nodes)| (mv.as_str(), *nodes)).collect();
+    let mut moves = legal_moves_by_name(position);
+    for (name, _) in &mut moves {
+        *name = name.to_lowercase();
+    }
+    moves.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if let Some((name, _)) = moves.iter().find(|(name, _)| !expected.contains_key(name.as_str())) {
+        return Some(DivideMismatch::ExtraMove { path: path.clone(), mv: name.clone() });
+    }
+    let mut expected_moves: Vec<&str> = expected.keys().copied().collect();
+    expected_moves.sort_unstable();
+    if let Some(name) = expected_moves.iter().find(|name| !moves.iter().any(|(mv, _)| mv == *name)) {
+        return Some(DivideMismatch::MissingMove { path: path.clone(), mv: name.to_string() });
+    }
+
+    let legality = LegalityInfo::new(position);
+    for (name, mv) in moves {
+        let game_continues = make_move_if_game_continues(mv, position, &legality);
+        let found = match (depth, game_continues) {
+            (1, _) => 1,
+            (_, true) => perft(position, depth - 1),
+            (_, false) => 0,
+        };
+        let expected_nodes = expected[name.as_str()];
+        if found != expected_nodes {
+            path.push(name.clone());
+            let child_mismatch = if game_continues && depth > 1 {
+                find_mismatch_impl(position, depth - 1, reference, path)
+            } else {
+                None
+            };
+            path.pop();
+            if game_continues {
+                position.unmake_move();
+            }
+            return Some(child_mismatch.unwrap_or(DivideMismatch::WrongCount {
+                path: path.clone(),
+                mv: name,
+                expected: expected_nodes,
+                found,
+            }));
+        }
+        if game_continues {
+            position.unmake_move();
+        }
+    }
+    None
+}
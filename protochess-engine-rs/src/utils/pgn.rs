@@ -0,0 +1,149 @@
+use crate::{wrap_res, err_assert, err};
+
+/// A game read from a PGN file: the tag pairs and the moves of the main line (in SAN)
+#[must_use]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+}
+
+impl PgnGame {
+    /// Returns the value of a tag (for example `"FEN"` or `"Variant"`), if present
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Writes the game in PGN format. The move numbers start from the `FEN` tag, if present, and the
+/// movetext ends with the `Result` tag (or `*` if it's missing).
+impl std::fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{name} \"{}\"]", value.replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+        // Fields 2 and 6 of the FEN: player to move and fullmove number
+        let fen_fields: Vec<&str> = self.get_tag("FEN").unwrap_or_default().split_whitespace().collect();
+        let mut black_to_move = fen_fields.get(1) == Some(&"b");
+        let mut move_number = fen_fields.get(5).and_then(|n| n.parse::<u32>().ok()).unwrap_or(1);
+        let mut line_len = 0;
+        let mut tokens = Vec::with_capacity(self.moves.len() + 1);
+        for (i, mv) in self.moves.iter().enumerate() {
+            if !black_to_move {
+                tokens.push(format!("{move_number}. {mv}"));
+            } else if i == 0 {
+                tokens.push(format!("{move_number}... {mv}"));
+            } else {
+                tokens.push(mv.clone());
+            }
+            if black_to_move {
+                move_number += 1;
+            }
+            black_to_move = !black_to_move;
+        }
+        tokens.push(self.get_tag("Result").unwrap_or("*").to_string());
+        // Limit the lines to 80 characters
+        for (i, token) in tokens.iter().enumerate() {
+            if i > 0 && line_len + 1 + token.len() > 80 {
+                writeln!(f)?;
+                line_len = 0;
+            } else if i > 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+            write!(f, "{token}")?;
+            line_len += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Parses the contents of a PGN file.
+/// Comments (`{...}` and `;`), variations (`(...)`), move numbers and annotations (`$1`, `!?`) are skipped.
+pub fn parse_pgn(pgn: &str) -> wrap_res!(Vec<PgnGame>) {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut chars = pgn.chars().peekable();
+    let mut variation_depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                // Comments cannot be nested
+                err_assert!(chars.any(|c| c == '}'), "Unterminated comment in PGN");
+            },
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' { break; }
+                }
+            },
+            '(' => variation_depth += 1,
+            ')' => {
+                err_assert!(variation_depth > 0, "Unexpected ')' in PGN");
+                variation_depth -= 1;
+            },
+            '[' if variation_depth == 0 => {
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                if !game.moves.is_empty() {
+                    // A game without a result, start a new one
+                    games.push(std::mem::take(&mut game));
+                }
+                game.tags.push(parse_tag(&tag)?);
+            },
+            _ if c.is_whitespace() => (),
+            _ => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();[".contains(next) { break; }
+                    token.push(next);
+                    chars.next();
+                }
+                if variation_depth > 0 {
+                    continue;
+                }
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        games.push(std::mem::take(&mut game));
+                    },
+                    _ => {
+                        if let Some(mv) = parse_move_token(&token) {
+                            game.moves.push(mv);
+                        }
+                    },
+                }
+            },
+        }
+    }
+    err_assert!(variation_depth == 0, "Unterminated variation in PGN");
+    if !game.moves.is_empty() || !game.tags.is_empty() {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// Parses a tag pair in the format `Name "Value"`
+fn parse_tag(tag: &str) -> wrap_res!((String, String)) {
+    let (name, value) = match tag.trim().split_once(char::is_whitespace) {
+        Some(parts) => parts,
+        None => err!("Invalid PGN tag: '[{tag}]'"),
+    };
+    let value = value.trim();
+    err_assert!(value.len() >= 2 && value.starts_with('"') && value.ends_with('"'), "Invalid PGN tag: '[{tag}]'");
+    let value = value[1..value.len()-1].replace("\\\"", "\"");
+    Ok((name.to_string(), value))
+}
+
+/// Removes the move number and the annotations from a token. Returns `None` if the token doesn't contain a move.
+fn parse_move_token(token: &str) -> Option<String> {
+    if token.starts_with('$') {
+        return None;
+    }
+    // Move numbers: "12." or "12..." (possibly followed by the move without a space)
+    let mv = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if mv.len() < token.len() && !token[..token.len()-mv.len()].contains('.') {
+        // The token started with a digit but it's not a move number
+        return Some(token.trim_end_matches(['!', '?']).to_string());
+    }
+    let mv = mv.trim_end_matches(['!', '?']);
+    if mv.is_empty() { None } else { Some(mv.to_string()) }
+}
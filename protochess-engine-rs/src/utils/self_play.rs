@@ -0,0 +1,484 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc;
+
+use crate::position::create::variant_factory::VariantFactory;
+use crate::types::{Depth, GameMode};
+use crate::utils::debug::split_debug_fen;
+use crate::utils::epd::EpdEntry;
+use crate::utils::pgn::PgnGame;
+use crate::{wrap_res, err, err_assert, Engine, EvalParams, GameState, MakeMoveResultFlag, MakeMoveResultWinner,
+    NotationMode, SearchParams, Skill};
+
+/// Balanced positions after a few moves of common standard chess openings
+pub const DEFAULT_OPENINGS: [&str; 8] = [
+    "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+    "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pppp1ppp/4p3/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pp1ppppp/2p5/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/ppp1pppp/8/3p4/2PP4/8/PP2PPPP/RNBQKBNR b KQkq - 0 2",
+    "rnbqkb1r/pppppppp/5n2/8/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 1 2",
+    "rnbqkbnr/pppppppp/8/8/2P5/8/PP1PPPPP/RNBQKBNR b KQkq - 0 1",
+    "rnbqkbnr/ppp1pppp/8/3p4/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 2",
+];
+
+/// Limit of the search performed by each engine on every move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchLimit {
+    /// Search up to a fixed depth
+    Depth(Depth),
+    /// Search for a fixed number of seconds
+    Time(u64),
+    /// Search about a fixed number of nodes (see `Engine::get_best_move_nodes()`)
+    Nodes(u64),
+}
+
+/// Settings of one of the engines that take part in a match
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineConfig {
+    /// Name used in the `White` and `Black` tags of the PGN
+    pub name: String,
+    pub search_params: SearchParams,
+    pub eval_params: EvalParams,
+    /// Weakened play (see `Engine::set_skill()`), or `None` to play at full strength
+    pub skill: Option<Skill>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            name: "Protochess".to_string(),
+            search_params: SearchParams::default(),
+            eval_params: EvalParams::default(),
+            skill: None,
+        }
+    }
+}
+
+impl From<SearchParams> for EngineConfig {
+    fn from(search_params: SearchParams) -> Self {
+        EngineConfig { search_params, ..EngineConfig::default() }
+    }
+}
+
+/// Starting position of the games of a match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opening {
+    pub fen: String,
+    pub variant: GameMode,
+}
+
+impl Opening {
+    /// Reads a FEN optionally followed by the variant name (see `split_debug_fen()`)
+    pub fn from_debug_fen(fen: &str) -> Opening {
+        let (fen, variant) = split_debug_fen(fen);
+        Opening { fen, variant }
+    }
+
+    /// Returns a `GameState` that can be loaded into the engine with `Engine::set_state()`
+    pub fn game_state(&self) -> GameState {
+        let initial_state = VariantFactory::new(self.variant).make_initial_state();
+        GameState { initial_state, initial_fen: Some(self.fen.clone()), move_history: vec![] }
+    }
+}
+
+impl From<&EpdEntry> for Opening {
+    fn from(entry: &EpdEntry) -> Self {
+        Opening { fen: entry.fen.clone(), variant: entry.variant }
+    }
+}
+
+/// Result of a single game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameOutcome {
+    /// Points obtained by white (1 for a win, 0.5 for a draw, 0 for a loss)
+    pub fn white_score(&self) -> f64 {
+        match self {
+            GameOutcome::WhiteWins => 1.0,
+            GameOutcome::BlackWins => 0.0,
+            GameOutcome::Draw => 0.5,
+        }
+    }
+
+    /// Value of the `Result` tag of a PGN game
+    pub fn pgn_result(&self) -> &'static str {
+        match self {
+            GameOutcome::WhiteWins => "1-0",
+            GameOutcome::BlackWins => "0-1",
+            GameOutcome::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// A finished game: the starting position, the moves in SAN (see `NotationMode::Standard`) and the result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub opening: Opening,
+    pub moves: Vec<String>,
+    pub outcome: GameOutcome,
+    /// `true` if the game was adjudicated as a draw after reaching the maximum number of plies
+    pub adjudicated: bool,
+}
+
+impl GameRecord {
+    /// Converts the game to PGN, with the names of the players and the round number
+    pub fn to_pgn(&self, white: &str, black: &str, round: u32) -> PgnGame {
+        let mut tags = vec![
+            ("Event".to_string(), "Protochess match".to_string()),
+            ("Round".to_string(), round.to_string()),
+            ("White".to_string(), white.to_string()),
+            ("Black".to_string(), black.to_string()),
+            ("Result".to_string(), self.outcome.pgn_result().to_string()),
+        ];
+        if self.opening.variant != GameMode::Standard {
+            tags.push(("Variant".to_string(), self.opening.variant.to_string()));
+        }
+        tags.push(("FEN".to_string(), self.opening.fen.clone()));
+        tags.push(("SetUp".to_string(), "1".to_string()));
+        if self.adjudicated {
+            tags.push(("Termination".to_string(), "adjudication".to_string()));
+        }
+        PgnGame { tags, moves: self.moves.clone() }
+    }
+}
+
+/// Result of a match, from the point of view of the first engine
+#[must_use]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchResult {
+    pub fn num_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Adds the outcome of a game, where the first engine played white if `first_is_white`
+    pub fn add_game(&mut self, outcome: GameOutcome, first_is_white: bool) {
+        match (outcome, first_is_white) {
+            (GameOutcome::Draw, _) => self.draws += 1,
+            (GameOutcome::WhiteWins, true) | (GameOutcome::BlackWins, false) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    /// Fraction of the points obtained by the first engine (a draw is worth half a point)
+    pub fn score(&self) -> f64 {
+        if self.num_games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.num_games() as f64
+    }
+
+    /// Estimated Elo difference between the first and the second engine (positive if the first engine is stronger).
+    /// Returns `None` if one of the engines has obtained all the points.
+    pub fn elo_difference(&self) -> Option<f64> {
+        elo_from_score(self.score())
+    }
+
+    /// Half the width of the 95% confidence interval of `elo_difference()`.
+    /// Returns `None` if the interval is not bounded.
+    pub fn elo_error_margin(&self) -> Option<f64> {
+        let stddev = (self.score_variance() / self.num_games() as f64).sqrt();
+        let low = elo_from_score(self.score() - 1.96 * stddev)?;
+        let high = elo_from_score(self.score() + 1.96 * stddev)?;
+        Some((high - low) / 2.0)
+    }
+
+    // Variance of the score of a single game
+    fn score_variance(&self) -> f64 {
+        if self.num_games() == 0 {
+            return 0.0;
+        }
+        let score = self.score();
+        let squared_error = self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2);
+        squared_error / self.num_games() as f64
+    }
+}
+
+// Elo difference that corresponds to an expected score
+fn elo_from_score(score: f64) -> Option<f64> {
+    if score <= 0.0 || score >= 1.0 {
+        return None;
+    }
+    Some(400.0 * (score / (1.0 - score)).log10())
+}
+
+// Expected score of a player that is `elo` points stronger than the opponent
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl std::ops::AddAssign for MatchResult {
+    fn add_assign(&mut self, other: MatchResult) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+impl std::fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "+{} ={} -{} (score: {:.1}%", self.wins, self.draws, self.losses, 100.0 * self.score())?;
+        match (self.elo_difference(), self.elo_error_margin()) {
+            (Some(elo), Some(margin)) => write!(f, ", Elo: {elo:+.0} ± {margin:.0})"),
+            (Some(elo), None) => write!(f, ", Elo: {elo:+.0})"),
+            _ => write!(f, ")"),
+        }
+    }
+}
+
+/// Sequential probability ratio test: decides whether the first engine is `elo0` or `elo1` Elo points stronger
+/// than the second one, with a probability `alpha` of a false positive and `beta` of a false negative.
+/// Uses the normal approximation of the generalized SPRT, which takes the draw ratio into account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+/// State of a `Sprt` after some games
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    /// More games are needed
+    Continue,
+    /// The first engine is at most `elo0` points stronger (H0 is accepted)
+    AcceptH0,
+    /// The first engine is at least `elo1` points stronger (H1 is accepted)
+    AcceptH1,
+}
+
+impl Sprt {
+    /// Test with the usual error probabilities (`alpha = beta = 0.05`)
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// Lower and upper bounds of the log-likelihood ratio, where H0 and H1 are accepted respectively
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Log-likelihood ratio of H1 against H0, given the result of the games played so far
+    pub fn llr(&self, result: &MatchResult) -> f64 {
+        let variance = result.score_variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let score0 = score_from_elo(self.elo0);
+        let score1 = score_from_elo(self.elo1);
+        result.num_games() as f64 * (score1 - score0) * (2.0 * result.score() - score0 - score1) / (2.0 * variance)
+    }
+
+    pub fn status(&self, result: &MatchResult) -> SprtStatus {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr <= lower { SprtStatus::AcceptH0 }
+        else if llr >= upper { SprtStatus::AcceptH1 }
+        else { SprtStatus::Continue }
+    }
+}
+
+/// Plays a game from the given starting position, each player using its own search parameters.
+/// If the game is not over after `max_ply` plies, it's adjudicated as a draw.
+pub fn play_game(white: SearchParams, black: SearchParams, opening: &GameState, limit: SearchLimit, max_ply: u32) -> wrap_res!(GameOutcome) {
+    let configs = [white.into(), black.into()];
+    Ok(play_game_impl(&configs, opening, limit, max_ply, 0, None)?.0)
+}
+
+/// Same as `play_game()`, but also returns the FEN of the quiet positions of the game: positions where
+/// the player to move is not in check and the chosen move is not a capture.
+pub fn play_recorded_game(white: SearchParams, black: SearchParams, opening: &GameState, limit: SearchLimit, max_ply: u32) -> wrap_res!(GameOutcome, Vec<String>) {
+    let mut quiet_positions = Vec::new();
+    let configs = [white.into(), black.into()];
+    let (outcome, _moves, _adjudicated) = play_game_impl(&configs, opening, limit, max_ply, 0, Some(&mut quiet_positions))?;
+    Ok((outcome, quiet_positions))
+}
+
+/// Plays a game between 2 engine configurations and records its moves.
+/// Both engines use `seed` for their random choices (see `Engine::set_seed()`).
+pub fn play_config_game(white: &EngineConfig, black: &EngineConfig, opening: &Opening, limit: SearchLimit, max_ply: u32,
+    seed: u64) -> wrap_res!(GameRecord)
+{
+    let configs = [white.clone(), black.clone()];
+    let (outcome, moves, adjudicated) = play_game_impl(&configs, &opening.game_state(), limit, max_ply, seed, None)?;
+    Ok(GameRecord { opening: opening.clone(), moves, outcome, adjudicated })
+}
+
+// Returns the outcome, the moves in SAN and whether the game was adjudicated
+fn play_game_impl(configs: &[EngineConfig; 2], opening: &GameState, limit: SearchLimit, max_ply: u32, seed: u64,
+    mut quiet_positions: Option<&mut Vec<String>>) -> wrap_res!(GameOutcome, Vec<String>, bool)
+{
+    let mut engines = [new_engine(&configs[0], opening, seed)?, new_engine(&configs[1], opening, seed)?];
+    // The first engine keeps the game record
+    engines[0].set_notation_mode(NotationMode::Standard)?;
+    let mut moves = Vec::new();
+    for _ in 0..max_ply {
+        let player = engines[0].player_to_move() as usize;
+        let mv = match limit {
+            SearchLimit::Depth(depth) => engines[player].get_best_move(depth)?.0,
+            SearchLimit::Time(seconds) => engines[player].get_best_move_timeout(seconds)?.0,
+            SearchLimit::Nodes(nodes) => engines[player].get_best_move_nodes(nodes)?.0,
+        };
+        if let Some(positions) = &mut quiet_positions {
+            // En passant captures are not detected, but they are rare enough to be ignored
+            let is_capture = engines[0].get_piece_at(mv.to).is_ok();
+            let state = engines[0].get_state_diff();
+            if !is_capture && !state.in_check {
+                positions.push(state.fen);
+            }
+        }
+        let result = engines[0].make_move(&mv);
+        let other_result = engines[1].make_move(&mv);
+        err_assert!(result.flag == other_result.flag && result.winner == other_result.winner,
+            "The engines disagree on the result of {mv}");
+        moves.push(result.move_notation.clone().unwrap_or_else(|| mv.to_string()));
+        match result.flag {
+            MakeMoveResultFlag::Ok => {},
+            MakeMoveResultFlag::IllegalMove => err!("The engine played an illegal move: {mv}"),
+            _ => {
+                let outcome = match result.winner {
+                    MakeMoveResultWinner::White => GameOutcome::WhiteWins,
+                    MakeMoveResultWinner::Black => GameOutcome::BlackWins,
+                    MakeMoveResultWinner::None => GameOutcome::Draw,
+                };
+                return Ok((outcome, moves, false));
+            },
+        }
+    }
+    Ok((GameOutcome::Draw, moves, true))
+}
+
+/// Plays 2 games from each opening (one with each color) between 2 sets of search parameters.
+/// The result is from the point of view of the engine using `first`.
+pub fn play_match(first: SearchParams, second: SearchParams, openings: &[GameState], limit: SearchLimit, max_ply: u32) -> wrap_res!(MatchResult) {
+    let mut result = MatchResult::default();
+    for opening in openings {
+        for first_is_white in [true, false] {
+            let outcome = {
+                if first_is_white { play_game(first, second, opening, limit, max_ply)? }
+                else { play_game(second, first, opening, limit, max_ply)? }
+            };
+            result.add_game(outcome, first_is_white);
+        }
+    }
+    Ok(result)
+}
+
+/// Settings of a match played with `run_match()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchSettings {
+    /// The openings are played in order, each one twice (first with the first engine as white, then as black).
+    /// If there are more games than openings, the list is repeated.
+    pub openings: Vec<Opening>,
+    pub limit: SearchLimit,
+    /// Games that are not over after this many plies are adjudicated as a draw
+    pub max_ply: u32,
+    /// Maximum number of games
+    pub num_games: u32,
+    /// Number of games played at the same time, each one in its own thread
+    pub concurrency: u32,
+    /// Stop the match as soon as the test accepts one of the hypotheses
+    pub sprt: Option<Sprt>,
+    /// Seed of the random choices of the engines. The game with index `i` uses `seed + i`, so it can be replayed
+    /// with `play_config_game()`.
+    pub seed: u64,
+}
+
+/// A game played with `run_match()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchGame {
+    /// Index of the game in the match, starting at 0. Games that are played at the same time can finish in any order.
+    pub index: u32,
+    pub first_is_white: bool,
+    pub record: GameRecord,
+}
+
+/// Plays a match between 2 engine configurations, alternating colors. `on_game` is called after each game with the
+/// game and the total result so far (from the point of view of `first`). Returns the final result and the status
+/// of the SPRT (or `SprtStatus::Continue` if there is no test or it didn't finish).
+pub fn run_match(first: &EngineConfig, second: &EngineConfig, settings: &MatchSettings,
+    mut on_game: impl FnMut(&MatchGame, &MatchResult)) -> wrap_res!(MatchResult, SprtStatus)
+{
+    err_assert!(!settings.openings.is_empty(), "The match needs at least 1 opening");
+    err_assert!(settings.concurrency > 0, "The concurrency must be at least 1");
+    let next_game = AtomicU32::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut result = MatchResult::default();
+    let mut status = SprtStatus::Continue;
+    let mut error = None;
+    std::thread::scope(|scope| {
+        for _ in 0..settings.concurrency {
+            let sender = sender.clone();
+            let (next_game, stop) = (&next_game, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= settings.num_games {
+                        break;
+                    }
+                    let opening = &settings.openings[(index as usize / 2) % settings.openings.len()];
+                    let first_is_white = index % 2 == 0;
+                    let (white, black) = if first_is_white { (first, second) } else { (second, first) };
+                    let seed = settings.seed.wrapping_add(index as u64);
+                    let record = play_config_game(white, black, opening, settings.limit, settings.max_ply, seed);
+                    let game = record.map(|record| MatchGame { index, first_is_white, record });
+                    if sender.send(game).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // The loop ends when all the threads have finished
+        drop(sender);
+        for game in receiver {
+            if error.is_some() || status != SprtStatus::Continue {
+                // Discard the games that were already being played
+                continue;
+            }
+            match game {
+                Ok(game) => {
+                    result.add_game(game.record.outcome, game.first_is_white);
+                    on_game(&game, &result);
+                    if let Some(sprt) = settings.sprt {
+                        status = sprt.status(&result);
+                    }
+                },
+                Err(e) => error = Some(e),
+            }
+            if error.is_some() || status != SprtStatus::Continue {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok((result, status)),
+    }
+}
+
+fn new_engine(config: &EngineConfig, opening: &GameState, seed: u64) -> wrap_res!(Engine) {
+    let mut engine = Engine::default();
+    // Use a single thread and a fixed seed in order to make the games reproducible
+    engine.set_num_threads(1)?;
+    engine.set_seed(seed)?;
+    engine.set_search_params(config.search_params);
+    engine.set_eval_params(config.eval_params)?;
+    engine.set_skill(config.skill);
+    let result = engine.set_state(opening.clone())?;
+    err_assert!(result.flag == MakeMoveResultFlag::Ok, "The game is already over in the starting position");
+    Ok(engine)
+}
@@ -0,0 +1,225 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::position::create::position_factory::PositionFactory;
+use crate::searcher::eval;
+use crate::utils::self_play::{play_recorded_game, SearchLimit};
+use crate::{wrap_res, err, err_assert, Engine, EvalParams, GameState, InitialState, MakeMoveResultFlag, Position, SearchParams};
+
+/// A position in FEN format, labeled with the result of the game it was taken from
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledPosition {
+    pub fen: String,
+    /// Points obtained by white in the game (1 for a win, 0.5 for a draw, 0 for a loss)
+    pub result: f64,
+}
+
+impl LabeledPosition {
+    /// Parses a line with the format `<fen>; <result>`, where the result is `1-0`, `0-1` or `1/2-1/2`
+    pub fn parse(line: &str) -> wrap_res!(LabeledPosition) {
+        let (fen, result) = match line.rsplit_once(';') {
+            Some(parts) => parts,
+            None => err!("Expected '<fen>; <result>', got '{line}'"),
+        };
+        let result = match result.trim() {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            other => err!("Invalid result '{other}', expected 1-0, 0-1 or 1/2-1/2"),
+        };
+        Ok(LabeledPosition { fen: fen.trim().to_string(), result })
+    }
+}
+
+impl std::fmt::Display for LabeledPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let result = {
+            if self.result > 0.75 { "1-0" }
+            else if self.result < 0.25 { "0-1" }
+            else { "1/2-1/2" }
+        };
+        write!(f, "{}; {result}", self.fen)
+    }
+}
+
+/// Parses a file with one labeled position per line (see `LabeledPosition::parse()`).
+/// Empty lines and lines starting with `#` are ignored.
+pub fn parse_labeled_positions(contents: &str) -> wrap_res!(Vec<LabeledPosition>) {
+    let mut positions = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match LabeledPosition::parse(line) {
+            Ok(position) => positions.push(position),
+            Err(e) => err!("Line {}: {e}", i + 1),
+        }
+    }
+    Ok(positions)
+}
+
+/// Generates labeled positions by playing `num_games` games of the engine against itself, starting from `opening`.
+/// In order to get different games, the first `random_plies` moves of each game are chosen at random.
+/// Only the quiet positions (see `play_recorded_game()`) are returned.
+pub fn generate_positions(opening: &GameState, num_games: u32, random_plies: u32, limit: SearchLimit, max_ply: u32, seed: u64)
+    -> wrap_res!(Vec<LabeledPosition>)
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = Vec::new();
+    let mut game = 0;
+    let mut attempts = 0;
+    while game < num_games {
+        attempts += 1;
+        err_assert!(attempts <= 10 * num_games, "Could not find enough random openings");
+        let start = match random_opening(opening, random_plies, &mut rng)? {
+            Some(state) => state,
+            None => continue,
+        };
+        let params = SearchParams::default();
+        let (outcome, fens) = play_recorded_game(params, params, &start, limit, max_ply)?;
+        let result = outcome.white_score();
+        positions.extend(fens.into_iter().map(|fen| LabeledPosition { fen, result }));
+        game += 1;
+    }
+    Ok(positions)
+}
+
+/// Plays `random_plies` random moves from `opening`. Returns `None` if the game ends before that.
+fn random_opening(opening: &GameState, random_plies: u32, rng: &mut StdRng) -> wrap_res!(Option<GameState>) {
+    let mut engine = Engine::default();
+    if engine.set_state(opening.clone())?.flag != MakeMoveResultFlag::Ok {
+        return Ok(None);
+    }
+    for _ in 0..random_plies {
+        let moves: Vec<_> = engine.legal_moves().into_iter().flat_map(|list| list.moves).collect();
+        if moves.is_empty() {
+            return Ok(None);
+        }
+        let mv = moves[rng.gen_range(0, moves.len())];
+        if engine.make_move(&mv).flag != MakeMoveResultFlag::Ok {
+            return Ok(None);
+        }
+    }
+    Ok(Some(engine.get_state().clone()))
+}
+
+
+/// Texel's tuning method: finds the evaluation parameters that minimize the mean squared error between
+/// the result of the games and the win probability predicted by the static evaluation of their positions.
+/// See <https://www.chessprogramming.org/Texel%27s_Tuning_Method>
+pub struct Tuner {
+    variant: InitialState,
+    /// Positions of the training set and their results
+    positions: Vec<(Position, f64)>,
+    /// Scaling constant of the sigmoid that converts a score to a win probability
+    k: f64,
+}
+
+impl Tuner {
+    /// Creates a tuner for a given variant. All the positions must be of that variant.
+    pub fn new(variant: &InitialState, data: &[LabeledPosition]) -> wrap_res!(Tuner) {
+        err_assert!(!data.is_empty(), "The training set is empty");
+        let mut factory = PositionFactory::default();
+        let mut positions = Vec::with_capacity(data.len());
+        for labeled in data {
+            let state = GameState {
+                initial_state: variant.clone(),
+                initial_fen: Some(labeled.fen.clone()),
+                move_history: vec![],
+            };
+            let position = match factory.set_state(state, None) {
+                Ok(position) => position.unwrap(),
+                Err(e) => err!("Invalid position '{}': {e}", labeled.fen),
+            };
+            positions.push((position, labeled.result));
+        }
+        Ok(Tuner { variant: variant.clone(), positions, k: 1.0 })
+    }
+
+    /// Returns the number of positions in the training set
+    pub fn num_positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns the mean squared error of the training set, using the given parameters
+    pub fn error(&mut self, params: &EvalParams) -> wrap_res!(f64) {
+        let scores = self.white_scores(params)?;
+        Ok(self.error_for_scores(&scores, self.k))
+    }
+
+    /// Finds (and uses from now on) the scaling constant that minimizes the error with the given parameters
+    pub fn find_k(&mut self, params: &EvalParams) -> wrap_res!(f64) {
+        let scores = self.white_scores(params)?;
+        // The error has a single minimum as a function of k, use a ternary search
+        let (mut low, mut high) = (0.0, 10.0);
+        while high - low > 1e-4 {
+            let m1 = low + (high - low) / 3.0;
+            let m2 = high - (high - low) / 3.0;
+            if self.error_for_scores(&scores, m1) < self.error_for_scores(&scores, m2) {
+                high = m2;
+            } else {
+                low = m1;
+            }
+        }
+        self.k = (low + high) / 2.0;
+        Ok(self.k)
+    }
+
+    /// Optimizes the parameters with a local search: each parameter is increased or decreased by its step
+    /// (see `EvalParams::STEPS`) as long as the error is reduced. Stops after `max_iterations` passes over
+    /// all the parameters, or when no parameter can be improved.
+    /// After each pass, `callback` is called with the iteration number, the current parameters and their error.
+    pub fn tune(&mut self, initial: &EvalParams, max_iterations: u32, mut callback: impl FnMut(u32, &EvalParams, f64))
+        -> wrap_res!(EvalParams)
+    {
+        let mut best = initial.to_vec();
+        let mut best_error = self.error(initial)?;
+        for iteration in 1..=max_iterations {
+            let mut improved = false;
+            for i in 0..best.len() {
+                for direction in [1.0, -1.0] {
+                    let mut candidate = best.clone();
+                    candidate[i] += direction * EvalParams::STEPS[i];
+                    let error = self.error(&EvalParams::from_vec(&candidate)?)?;
+                    if error < best_error {
+                        best = candidate;
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+            callback(iteration, &EvalParams::from_vec(&best)?, best_error);
+            if !improved {
+                break;
+            }
+        }
+        EvalParams::from_vec(&best)
+    }
+
+    /// Static evaluation of all the positions (from the point of view of white), using the given parameters
+    fn white_scores(&mut self, params: &EvalParams) -> wrap_res!(Vec<f64>) {
+        // Compute the piece scores only once, then copy them to all the positions
+        let mut factory = PositionFactory::default();
+        factory.set_eval_params(*params);
+        let state = GameState { initial_state: self.variant.clone(), initial_fen: None, move_history: vec![] };
+        let template = factory.set_state(state, None)?.unwrap();
+
+        let mut scores = Vec::with_capacity(self.positions.len());
+        for (position, _) in &mut self.positions {
+            position.copy_eval_params_from(&template)?;
+            let score = eval::evaluate(position) as f64;
+            scores.push(if position.whos_turn == 0 { score } else { -score });
+        }
+        Ok(scores)
+    }
+
+    fn error_for_scores(&self, scores: &[f64], k: f64) -> f64 {
+        let total: f64 = self.positions.iter().zip(scores).map(|((_, result), score)| {
+            let predicted = 1.0 / (1.0 + 10f64.powf(-k * score / 400.0));
+            (result - predicted).powi(2)
+        }).sum();
+        total / self.positions.len() as f64
+    }
+}
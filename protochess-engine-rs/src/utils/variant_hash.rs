@@ -0,0 +1,135 @@
+use crate::{GlobalRules, PieceDefinition, PieceId};
+use crate::types::BCoord;
+
+/// Hash (FNV-1a) of the piece definitions and the rules of a variant, used to detect files (networks and tablebases)
+/// created for other variants. The order of the definitions doesn't matter. `extra` is hashed after them.
+pub fn variant_hash<'a>(definitions: impl Iterator<Item=&'a PieceDefinition>, rules: &GlobalRules, extra: &[u8]) -> u64 {
+    let mut descriptions: Vec<Vec<u8>> = definitions.map(definition_bytes).collect();
+    // Pieces available for both players appear twice in a position
+    descriptions.sort_unstable();
+    descriptions.dedup();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in rules_bytes(rules).into_iter().chain(descriptions.concat()).chain(extra.iter().copied()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// The fields are listed explicitly (without `..`), so that adding a field to the definitions or the rules
+// doesn't compile until it's decided whether it changes the hash. Changing the bytes requires a new `MAGIC` in
+// the files that store the hash (`nnue::Network` and `tablebase::Tablebase`).
+
+fn rules_bytes(rules: &GlobalRules) -> Vec<u8> {
+    let GlobalRules {
+        capturing_is_forced,
+        check_is_forbidden,
+        stalemated_player_loses,
+        invert_win_conditions,
+        repetitions_draw,
+        checks_to_lose,
+    } = rules;
+    vec![
+        *capturing_is_forced as u8,
+        *check_is_forbidden as u8,
+        *stalemated_player_loses as u8,
+        *invert_win_conditions as u8,
+        *repetitions_draw,
+        *checks_to_lose,
+    ]
+}
+
+fn definition_bytes(definition: &PieceDefinition) -> Vec<u8> {
+    let PieceDefinition {
+        ids,
+        // Only changes how the moves are written
+        notation_prefix: _,
+        is_leader,
+        castle_files,
+        is_castle_rook,
+        explode_on_capture,
+        explosion_deltas,
+        immune_to_explosion,
+        promotion_squares,
+        promo_vals,
+        double_jump_squares,
+        attack_sliding_deltas,
+        attack_jump_deltas,
+        attack_north,
+        attack_south,
+        attack_east,
+        attack_west,
+        attack_northeast,
+        attack_northwest,
+        attack_southeast,
+        attack_southwest,
+        translate_jump_deltas,
+        translate_sliding_deltas,
+        translate_north,
+        translate_south,
+        translate_east,
+        translate_west,
+        translate_northeast,
+        translate_northwest,
+        translate_southeast,
+        translate_southwest,
+        win_squares,
+    } = definition;
+    let mut bytes = Vec::new();
+    for id in ids {
+        push_id(&mut bytes, *id);
+    }
+    bytes.push(*is_leader as u8);
+    match castle_files {
+        Some((queenside, kingside)) => bytes.extend_from_slice(&[1, *queenside, *kingside]),
+        None => bytes.push(0),
+    }
+    bytes.extend_from_slice(&[*is_castle_rook as u8, *explode_on_capture as u8]);
+    push_deltas(&mut bytes, explosion_deltas);
+    bytes.push(*immune_to_explosion as u8);
+    push_squares(&mut bytes, promotion_squares);
+    for promotions in promo_vals {
+        push_len(&mut bytes, promotions.len());
+        for id in promotions {
+            push_id(&mut bytes, Some(*id));
+        }
+    }
+    push_squares(&mut bytes, double_jump_squares);
+    for (sliding_deltas, jump_deltas, directions) in [
+        (attack_sliding_deltas, attack_jump_deltas, [attack_north, attack_south, attack_east, attack_west,
+            attack_northeast, attack_northwest, attack_southeast, attack_southwest]),
+        (translate_sliding_deltas, translate_jump_deltas, [translate_north, translate_south, translate_east, translate_west,
+            translate_northeast, translate_northwest, translate_southeast, translate_southwest]),
+    ] {
+        push_len(&mut bytes, sliding_deltas.len());
+        for run in sliding_deltas {
+            push_deltas(&mut bytes, run);
+        }
+        push_deltas(&mut bytes, jump_deltas);
+        bytes.extend(directions.iter().map(|direction| **direction as u8));
+    }
+    push_squares(&mut bytes, win_squares);
+    bytes
+}
+
+fn push_len(bytes: &mut Vec<u8>, len: usize) {
+    bytes.extend_from_slice(&(len as u32).to_be_bytes());
+}
+
+fn push_id(bytes: &mut Vec<u8>, id: Option<PieceId>) {
+    bytes.extend_from_slice(&id.map_or(u32::MAX, |id| id as u32).to_be_bytes());
+}
+
+fn push_deltas(bytes: &mut Vec<u8>, deltas: &[(i8, i8)]) {
+    push_len(bytes, deltas.len());
+    for (dx, dy) in deltas {
+        bytes.extend_from_slice(&[*dx as u8, *dy as u8]);
+    }
+}
+
+fn push_squares(bytes: &mut Vec<u8>, squares: &[(BCoord, BCoord)]) {
+    push_len(bytes, squares.len());
+    for (x, y) in squares {
+        bytes.extend_from_slice(&[*x, *y]);
+    }
+}
@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod bench_test {
+    use protochess_engine_rs::utils::bench::{bench_positions, run_bench};
+
+    // Signature of `run_bench(3)`. Update it when a change to the search or the evaluation is intended to change
+    // the number of nodes, and only then.
+    const EXPECTED_SIGNATURE: u64 = 14248;
+
+    #[test]
+    fn signature_matches_expected() {
+        let result = run_bench(3).unwrap();
+        assert_eq!(result.entries.len(), bench_positions().len());
+        assert!(result.entries.iter().all(|entry| entry.nodes > 0));
+        assert_eq!(result.total_nodes(), result.entries.iter().map(|entry| entry.nodes).sum::<u64>());
+        assert_eq!(result.signature(), EXPECTED_SIGNATURE);
+        assert_eq!(run_bench(3).unwrap().signature(), EXPECTED_SIGNATURE);
+    }
+
+    #[test]
+    fn variants() {
+        let names: Vec<String> = bench_positions().into_iter().map(|position| position.name).collect();
+        for variant in ["ATOMIC", "HORDE", "ANTICHESS", "RACINGKINGS", "10x10"] {
+            assert!(names.iter().any(|name| name.ends_with(variant)), "{}", variant);
+        }
+        let custom = bench_positions().into_iter().find(|position| position.name.ends_with("10x10")).unwrap();
+        assert_eq!((custom.state.initial_state.board_width, custom.state.initial_state.board_height), (10, 10));
+    }
+}
@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod bitboard_test {
+    use numext_fixed_uint::U256;
+    use protochess_engine_rs::types::Bitboard;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn same_results_as_u256() {
+        // Bitboard used to wrap numext's U256, the hand-written operations must give the same results
+        let mut rng = StdRng::seed_from_u64(0x2545F4914F6CDD1D);
+        for i in 0..2000 {
+            let (a_words, b_words) = (random_words(&mut rng), random_words(&mut rng));
+            let (a, b) = (from_words(a_words), from_words(b_words));
+            let (x, y) = (U256(a_words), U256(b_words));
+            let shift = (i % 256) as u8;
+
+            assert_eq!(words(&(&a + &b)), x.overflowing_add(&y).0.0);
+            assert_eq!(words(&(&a - &b)), x.overflowing_sub(&y).0.0);
+            assert_eq!(words(&a.clone().overflowing_mul(&b)), x.overflowing_mul(&y).0.0);
+            assert_eq!(words(&(&a & &b)), (&x & &y).0);
+            assert_eq!(words(&(&a | &b)), (&x | &y).0);
+            assert_eq!(words(&(&a ^ &b)), (&x ^ &y).0);
+            assert_eq!(words(&!&a), (!&x).0);
+            assert_eq!(words(&(&a << shift)), (&x << shift).0);
+            assert_eq!(words(&(&a >> shift)), (&x >> shift).0);
+            assert_eq!(a.lowest_one(), x.lowest_one().map(|i| i as u8));
+            assert_eq!(a.highest_one(), x.highest_one().map(|i| i as u8));
+            assert_eq!(a.count_ones(), x.count_ones());
+            assert_eq!(a.is_zero(), x.is_zero());
+            assert_eq!(a.get_bit(shift), x.bit(shift as usize).unwrap());
+            assert_eq!(format!("{:x}", a), format!("{:x}", x));
+
+            let mut c = a.clone();
+            c.set_bit(shift);
+            c ^= 0xABCD_u16;
+            c -= &b;
+            let mut z = x.clone();
+            z.set_bit(shift as usize, true);
+            z ^= U256::from(0xABCD_u16);
+            z = z.overflowing_sub(&y).0;
+            assert_eq!(words(&c), z.0);
+            c.clear_bit(shift);
+            z.set_bit(shift as usize, false);
+            assert_eq!(words(&c), z.0);
+        }
+    }
+
+    #[test]
+    fn narrow_bitboards() {
+        // The bitboards of 1 and 2 words must give the lowest bits of the results of the 256-bit bitboard
+        let mut rng = StdRng::seed_from_u64(0x9E3779B97F4A7C15);
+        for i in 0..2000 {
+            let (a, b) = (from_words(random_words(&mut rng)), from_words(random_words(&mut rng)));
+            let shift = (i % 256) as u8;
+            same_lowest_bits::<1>(&a, &b, shift);
+            same_lowest_bits::<2>(&a, &b, shift);
+        }
+    }
+
+    fn same_lowest_bits<const W: usize>(a: &Bitboard, b: &Bitboard, shift: u8) {
+        // The bits that don't fit in the narrow bitboard are discarded before the operation
+        let (a, b) = (a.resize::<W>().resize::<4>(), b.resize::<W>().resize::<4>());
+        let (na, nb) = (a.resize::<W>(), b.resize::<W>());
+
+        assert_eq!(&na + &nb, (&a + &b).resize());
+        assert_eq!(&na - &nb, (&a - &b).resize());
+        assert_eq!(na.clone().overflowing_mul(&nb), a.clone().overflowing_mul(&b).resize());
+        assert_eq!(&na & &nb, (&a & &b).resize());
+        assert_eq!(&na | &nb, (&a | &b).resize());
+        assert_eq!(&na ^ &nb, (&a ^ &b).resize());
+        assert_eq!(!&na, (!&a).resize());
+        assert_eq!(&na << shift, (&a << shift).resize());
+        assert_eq!(&na >> shift, (&a >> shift).resize());
+        assert_eq!(na.lowest_one(), a.lowest_one());
+        assert_eq!(na.highest_one(), a.highest_one());
+        assert_eq!(na.count_ones(), a.count_ones());
+        assert_eq!(na.is_zero(), a.is_zero());
+        assert_eq!(na.get_bit(shift), a.get_bit(shift));
+
+        // Setting the bits that don't fit is ignored
+        let mut c = na.clone();
+        c.set_bit(shift);
+        let mut z = a.clone();
+        z.set_bit(shift);
+        assert_eq!(c, z.resize());
+        c.clear_bit(shift);
+        z.clear_bit(shift);
+        assert_eq!(c, z.resize());
+    }
+
+    // Dense, sparse and empty words
+    fn random_words(rng: &mut StdRng) -> [u64; 4] {
+        [0, 1, 2, 3].map(|_| match rng.gen_range(0, 3) {
+            0 => rng.gen(),
+            1 => rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>(),
+            _ => 0,
+        })
+    }
+
+    fn from_words(words: [u64; 4]) -> Bitboard {
+        let mut board = Bitboard::zero();
+        *board.get_inner_mut() = words;
+        board
+    }
+
+    fn words(board: &Bitboard) -> [u64; 4] {
+        *board.get_inner()
+    }
+}
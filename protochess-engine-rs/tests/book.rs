@@ -0,0 +1,185 @@
+#[cfg(test)]
+mod book_test {
+    use std::convert::TryFrom;
+
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveInfo, Position, OpeningBook, BookFormat};
+    use protochess_engine_rs::book::BookEntry;
+    use protochess_engine_rs::book::builder::BookBuilder;
+    use protochess_engine_rs::book::polyglot::{polyglot_key, encode_move, decode_move};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+    use protochess_engine_rs::types::GameMode;
+    use protochess_engine_rs::utils::pgn::parse_pgn;
+
+    fn load_fen(fen: &str) -> Position {
+        let mut factory = PositionFactory::default();
+        factory.set_state(GameState::default(), None).unwrap();
+        factory.load_fen(fen).unwrap()
+    }
+
+    fn mv(s: &str) -> MoveInfo {
+        MoveInfo::try_from(s).unwrap()
+    }
+
+    fn play(engine: &mut Engine, m: &str) {
+        let result = engine.make_move_str(m).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+    }
+
+    #[test]
+    fn polyglot_keys() {
+        // Test vectors from the Polyglot book format specification
+        let positions = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+            ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+            ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+            ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+            ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+            ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+        ];
+        for (fen, key) in positions {
+            assert_eq!(polyglot_key(&load_fen(fen)), Some(key), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn polyglot_only_standard_chess() {
+        let start_key = 0x463b96181691fc9c;
+        let book = OpeningBook::new(BookFormat::Polyglot, vec![BookEntry { key: start_key, mv: mv("e2e4"), weight: 1 }]);
+        let mut engine = Engine::default();
+        engine.set_opening_book(Some(book));
+        assert_eq!(engine.get_book_move(), Some(mv("e2e4")));
+
+        // Same pieces and starting position, but different rules
+        for mode in [GameMode::Atomic, GameMode::Antichess, GameMode::KingOfTheHill, GameMode::ThreeCheck] {
+            let state = GameState {
+                initial_state: VariantFactory::new(mode).make_initial_state(),
+                initial_fen: None,
+                move_history: vec![],
+            };
+            let mut factory = PositionFactory::default();
+            let pos = factory.set_state(state.clone(), None).unwrap().unwrap();
+            assert_eq!(polyglot_key(&pos), None, "{}", mode);
+            assert_eq!(engine.set_state(state).unwrap().flag, MakeMoveResultFlag::Ok);
+            assert_eq!(engine.get_book_move(), None, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn polyglot_moves() {
+        // e2e4, white short castling (king captures rook) and a promotion to queen
+        assert_eq!(encode_move(&mv("e2e4")), Some(0x031c));
+        assert_eq!(encode_move(&mv("e1h1")), Some(0x0107));
+        for m in ["e2e4", "e1h1", "a7a8=q", "b2b1=n"] {
+            assert_eq!(decode_move(encode_move(&mv(m)).unwrap()), mv(m));
+        }
+        // Moves outside the 8x8 board cannot be encoded
+        assert_eq!(encode_move(&mv("i1i2")), None);
+    }
+
+    #[test]
+    fn bytes_roundtrip() {
+        let entries = vec![
+            BookEntry { key: 2, mv: mv("e7e5"), weight: 3 },
+            BookEntry { key: 1, mv: mv("e2e4"), weight: 10 },
+            BookEntry { key: 1, mv: mv("a7a8=Q"), weight: 0 },
+        ];
+        for format in [BookFormat::Polyglot, BookFormat::Protochess] {
+            let book = OpeningBook::new(format, entries.clone());
+            let bytes = book.to_bytes().unwrap();
+            assert_eq!(bytes.len(), 16 * entries.len());
+            let loaded = OpeningBook::from_bytes(&bytes, format).unwrap();
+            assert_eq!(loaded.to_bytes().unwrap(), bytes);
+        }
+        assert!(OpeningBook::from_bytes(&[0; 15], BookFormat::Polyglot).is_err());
+    }
+
+    #[test]
+    fn pick_legal_moves() {
+        let mut pos = load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let key = pos.get_zobrist();
+        let entries = vec![
+            BookEntry { key, mv: mv("e2e4"), weight: 3 },
+            BookEntry { key, mv: mv("d2d4"), weight: 1 },
+            BookEntry { key, mv: mv("e2e5"), weight: 1000 }, // Illegal
+            BookEntry { key, mv: mv("c2c4"), weight: 0 }, // Never picked
+        ];
+        let book = OpeningBook::new(BookFormat::Protochess, entries);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut e4_count = 0;
+        for _ in 0..1000 {
+            let m = book.pick_move(&mut pos, &mut rng).unwrap();
+            assert!(m == mv("e2e4") || m == mv("d2d4"));
+            if m == mv("e2e4") { e4_count += 1; }
+        }
+        assert!(e4_count > 650 && e4_count < 850, "{}", e4_count);
+
+        let mut other = load_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(book.pick_move(&mut other, &mut rng), None);
+    }
+
+    #[test]
+    fn pgn_parsing() {
+        let pgn = r#"
+            [Event "Test"]
+            [Variant "King of the Hill"]
+
+            1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3!? $1 Nc6
+            ; Rest of line comment
+            3. Bb5 a6 1-0
+
+            [Event "Second"]
+            1.d4 d5 2.c4 *
+        "#;
+        let games = parse_pgn(pgn).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].get_tag("Event"), Some("Test"));
+        assert_eq!(games[0].get_tag("Variant"), Some("King of the Hill"));
+        assert_eq!(games[0].moves, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(games[1].get_tag("Variant"), None);
+        assert_eq!(games[1].moves, vec!["d4", "d5", "c4"]);
+
+        assert!(parse_pgn("1. e4 (e5").is_err());
+        assert!(parse_pgn("1. e4 {e5").is_err());
+    }
+
+    #[test]
+    fn build_and_play() {
+        let pgn = "1. e4 e5 2. Nf3 Nc6 *\n1. e4 e5 2. Bc4 *\n1. e4 c5 *";
+        let default_variant = GameState::default().initial_state;
+        for format in [BookFormat::Polyglot, BookFormat::Protochess] {
+            let mut builder = BookBuilder::new(format, 3, 0);
+            assert_eq!(builder.add_pgn(pgn, &default_variant).unwrap(), 3);
+            assert!(builder.add_pgn("1. e5 *", &default_variant).is_err());
+            let book = builder.build();
+            // e4, e5, c5, Nf3, Bc4
+            assert_eq!(book.len(), 5);
+
+            let mut engine = Engine::default();
+            engine.set_num_threads(1).unwrap();
+            engine.set_opening_book(Some(OpeningBook::from_bytes(&book.to_bytes().unwrap(), format).unwrap()));
+            assert_eq!(engine.get_best_move(3).unwrap(), (mv("e2e4"), 0));
+            assert_eq!(engine.get_nodes_searched(), 0);
+            play(&mut engine, "e2e4");
+            play(&mut engine, "c7c5");
+            // Only 1 game reaches this position, and it ends here
+            assert_eq!(engine.get_book_move(), None);
+            engine.undo().unwrap();
+            play(&mut engine, "e7e5");
+            let (m, _score, depth) = engine.get_best_move_timeout(1).unwrap();
+            assert!(m == mv("g1f3") || m == mv("f1c4"));
+            assert_eq!(depth, 0);
+            play(&mut engine, "g1f3");
+            // Nc6 is beyond the maximum ply
+            assert_eq!(engine.get_book_move(), None);
+            let _best = engine.get_best_move(2).unwrap();
+            assert!(engine.get_nodes_searched() > 0);
+        }
+    }
+}
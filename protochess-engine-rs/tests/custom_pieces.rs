@@ -3,19 +3,20 @@ extern crate protochess_engine_rs;
 #[cfg(test)]
 mod custom_pieces {
     use protochess_engine_rs::piece::{Piece, PieceFactory};
+    use protochess_engine_rs::EvalParams;
     use protochess_engine_rs::types::BDimensions;
     use protochess_engine_rs::utils::to_index;
 
     #[test]
     fn piece_factory_pawn() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let white_pawn = Piece::new(factory.make_pawn(true, 8, 8), 0, &dims);
+        let white_pawn = Piece::new(factory.make_pawn(true, 8, 8), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(white_pawn.get_piece_id(), 'P');
         assert_eq!(white_pawn.get_player(), 0);
         assert_eq!(white_pawn.get_material_score(), 100);
-        assert_eq!(white_pawn.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(white_pawn.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(white_pawn.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("White pawn positional scores:");
@@ -27,12 +28,12 @@ mod custom_pieces {
         }
         println!();
         
-        let black_pawn = Piece::new(factory.make_pawn(false, 8, 8), 1, &dims);
+        let black_pawn = Piece::new(factory.make_pawn(false, 8, 8), 1, &dims, &EvalParams::default(), 0);
         
         assert_eq!(black_pawn.get_piece_id(), 'p');
         assert_eq!(black_pawn.get_player(), 1);
         assert_eq!(black_pawn.get_material_score(), 100);
-        assert_eq!(black_pawn.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(black_pawn.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(black_pawn.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("Black pawn positional scores:");
@@ -46,14 +47,14 @@ mod custom_pieces {
     
     #[test]
     fn piece_factory_knight() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let knight = Piece::new(factory.make_knight(), 0, &dims);
+        let knight = Piece::new(factory.make_knight(), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(knight.get_piece_id(), 'N');
         assert_eq!(knight.get_player(), 0);
         assert_eq!(knight.get_material_score(), 320);
-        assert_eq!(knight.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(knight.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(knight.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("Knight positional scores:");
@@ -67,14 +68,14 @@ mod custom_pieces {
     
     #[test]
     fn piece_factory_bishop() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let bishop = Piece::new(factory.make_bishop(), 0, &dims);
+        let bishop = Piece::new(factory.make_bishop(), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(bishop.get_piece_id(), 'B');
         assert_eq!(bishop.get_player(), 0);
         assert_eq!(bishop.get_material_score(), 336);
-        assert_eq!(bishop.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(bishop.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(bishop.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("Bishop positional scores:");
@@ -88,14 +89,14 @@ mod custom_pieces {
     
     #[test]
     fn piece_factory_rook() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let rook = Piece::new(factory.make_rook(), 0, &dims);
+        let rook = Piece::new(factory.make_rook(), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(rook.get_piece_id(), 'R');
         assert_eq!(rook.get_player(), 0);
         assert_eq!(rook.get_material_score(), 528);
-        assert_eq!(rook.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(rook.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(rook.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("Rook positional scores:");
@@ -109,14 +110,14 @@ mod custom_pieces {
     
     #[test]
     fn piece_factory_queen() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let queen = Piece::new(factory.make_queen(), 0, &dims);
+        let queen = Piece::new(factory.make_queen(), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(queen.get_piece_id(), 'Q');
         assert_eq!(queen.get_player(), 0);
         assert_eq!(queen.get_material_score(), 1014);
-        assert_eq!(queen.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(queen.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(queen.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("Queen positional scores:");
@@ -130,14 +131,14 @@ mod custom_pieces {
     
     #[test]
     fn piece_factory_king() {
-        let dims = BDimensions::new_without_walls(8, 8).unwrap();
+        let dims: BDimensions = BDimensions::new_without_walls(8, 8).unwrap();
         let factory = PieceFactory::default();
-        let king = Piece::new(factory.make_king(8, 8), 0, &dims);
+        let king = Piece::new(factory.make_king(8, 8), 0, &dims, &EvalParams::default(), 0);
         
         assert_eq!(king.get_piece_id(), 'K');
         assert_eq!(king.get_player(), 0);
         assert_eq!(king.get_material_score(), 320 * 4);
-        assert_eq!(king.get_material_score_all(), 0); // No pieces on the board
+        assert_eq!(king.get_material_score_all::<false>(), 0); // No pieces on the board
         assert_eq!(king.get_positional_score_all::<false>(), 0); // No pieces on the board
         
         println!("King positional scores:");
@@ -0,0 +1,362 @@
+#!/usr/bin/env python3
+"""Writes the synthetic KQvK and KRvK Syzygy files used by tests/syzygy.rs.
+
+These are NOT the official tables. The values come from a retrograde analysis done here, and the files are encoded
+with the same layout as the official ones (indexing by symmetries, recursive pairing, canonical Huffman codes, sparse
+index, DTZ maps), but they are not byte-identical to the official files. They only show that the reader
+decodes what this script encodes. The official tables are tested by real_tables() when SYZYGY_PATH is set.
+
+Usage: python3 generate.py [output directory]
+"""
+
+import heapq
+import os
+import struct
+import sys
+from collections import Counter, deque
+
+WDL_MAGIC = bytes([0x71, 0xE8, 0x23, 0x5D])
+DTZ_MAGIC = bytes([0xD7, 0x66, 0x0C, 0xA5])
+KING, QUEEN, ROOK, BLACK = 6, 5, 4, 8
+
+FLAG_MAPPED, FLAG_WIN_PLIES, FLAG_LOSS_PLIES, FLAG_SINGLE_VALUE = 2, 4, 8, 128
+LOG_BLOCK_SIZE = 6
+LOG_SPAN = 8
+# Number of entries of an index with 3 different pieces and no pawns
+TABLE_SIZE = 31332
+
+
+def file_of(sq):
+    return sq & 7
+
+
+def rank_of(sq):
+    return sq >> 3
+
+
+def off_a1h8(sq):
+    return rank_of(sq) - file_of(sq)
+
+
+def adjacent(a, b):
+    return max(abs(file_of(a) - file_of(b)), abs(rank_of(a) - rank_of(b))) <= 1
+
+
+KING_MOVES = [[sq for sq in range(64) if sq != from_sq and adjacent(sq, from_sq)] for from_sq in range(64)]
+ROOK_DIRS = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+QUEEN_DIRS = ROOK_DIRS + [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+
+
+def slides(from_sq, dirs, occupied):
+    """Squares attacked by a slider, stopping at (and including) the first occupied square"""
+    result = []
+    for dx, dy in dirs:
+        x, y = file_of(from_sq) + dx, rank_of(from_sq) + dy
+        while 0 <= x < 8 and 0 <= y < 8:
+            sq = y * 8 + x
+            result.append(sq)
+            if sq in occupied:
+                break
+            x, y = x + dx, y + dy
+    return result
+
+
+def solve(dirs):
+    """Returns the distance to mate in plies of each position (white king, white piece, black king), with white and
+    black to move. Positions that are not won for white are missing."""
+    white_dtm, black_dtm = {}, {}
+    # Number of moves of black that have not been proven to lose
+    remaining = {}
+    predecessors_w = {}
+    predecessors_b = {}
+    queue = deque()
+    for wk in range(64):
+        for piece in range(64):
+            for bk in range(64):
+                if len({wk, piece, bk}) < 3 or adjacent(wk, bk):
+                    continue
+                pos = (wk, piece, bk)
+                # White to move, black must not be in check
+                if bk not in slides(piece, dirs, {wk, bk}):
+                    for to in KING_MOVES[wk]:
+                        if to != piece and not adjacent(to, bk):
+                            predecessors_b.setdefault((to, piece, bk), []).append(pos)
+                    for to in slides(piece, dirs, {wk, bk}):
+                        if to not in (wk, bk):
+                            predecessors_b.setdefault((wk, to, bk), []).append(pos)
+                # Black to move
+                captures = False
+                moves = 0
+                for to in KING_MOVES[bk]:
+                    if adjacent(to, wk):
+                        continue
+                    if to == piece:
+                        captures = True
+                    elif to not in slides(piece, dirs, {wk, piece}):
+                        moves += 1
+                        predecessors_w.setdefault((wk, piece, to), []).append(pos)
+                if captures:
+                    # Capturing the piece draws
+                    continue
+                if moves == 0:
+                    if bk in slides(piece, dirs, {wk, bk}):
+                        black_dtm[pos] = 0
+                        queue.append((pos, 1))
+                    continue
+                remaining[pos] = moves
+
+    # The positions are found in order of their distance to mate
+    while queue:
+        pos, black_to_move = queue.popleft()
+        if black_to_move:
+            for prev in predecessors_b.get(pos, []):
+                if prev not in white_dtm:
+                    white_dtm[prev] = black_dtm[pos] + 1
+                    queue.append((prev, 0))
+        else:
+            for prev in predecessors_w.get(pos, []):
+                if prev in remaining:
+                    remaining[prev] -= 1
+                    if remaining[prev] == 0:
+                        black_dtm[prev] = white_dtm[pos] + 1
+                        queue.append((prev, 1))
+    return white_dtm, black_dtm
+
+
+class Encoding:
+    def __init__(self):
+        self.map_b1h1h7 = [0] * 64
+        code = 0
+        for sq in range(64):
+            if off_a1h8(sq) < 0:
+                self.map_b1h1h7[sq] = code
+                code += 1
+        triangle = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27]
+        self.map_a1d1d4 = [0] * 64
+        ordered = [sq for sq in triangle if off_a1h8(sq) < 0] + [sq for sq in triangle if off_a1h8(sq) == 0]
+        for code, sq in enumerate(ordered):
+            self.map_a1d1d4[sq] = code
+
+    def index(self, squares):
+        """Index of 3 different pieces, in the order of the file"""
+        s = list(squares)
+        if file_of(s[0]) > 3:
+            s = [sq ^ 7 for sq in s]
+        if rank_of(s[0]) > 3:
+            s = [sq ^ 56 for sq in s]
+        for i in range(3):
+            if off_a1h8(s[i]) == 0:
+                continue
+            if off_a1h8(s[i]) > 0:
+                s = s[:i] + [((sq >> 3) | (sq << 3)) & 63 for sq in s[i:]]
+            break
+        adjust1 = int(s[1] > s[0])
+        adjust2 = int(s[2] > s[0]) + int(s[2] > s[1])
+        if off_a1h8(s[0]) != 0:
+            return (self.map_a1d1d4[s[0]] * 63 + (s[1] - adjust1)) * 62 + s[2] - adjust2
+        if off_a1h8(s[1]) != 0:
+            return (6 * 63 + rank_of(s[0]) * 28 + self.map_b1h1h7[s[1]]) * 62 + s[2] - adjust2
+        if off_a1h8(s[2]) != 0:
+            return (6 * 63 * 62 + 4 * 28 * 62 + rank_of(s[0]) * 7 * 28
+                    + (rank_of(s[1]) - adjust1) * 28 + self.map_b1h1h7[s[2]])
+        return (6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + rank_of(s[0]) * 7 * 6
+                + (rank_of(s[1]) - adjust1) * 6 + (rank_of(s[2]) - adjust2))
+
+
+def fill(entries):
+    """Index -> value table. The entries that don't match a legal position repeat the previous value."""
+    table = [None] * TABLE_SIZE
+    for idx, value in entries:
+        assert table[idx] in (None, value), "Symmetric positions with different values"
+        table[idx] = value
+    first = next(value for value in table if value is not None)
+    previous = first
+    for idx in range(TABLE_SIZE):
+        if table[idx] is None:
+            table[idx] = previous
+        previous = table[idx]
+    return table
+
+
+def pair_symbols(values):
+    """Recursive pairing: replaces the most frequent pair of adjacent symbols by a new symbol, several times.
+    Returns the symbols (a value, or a pair of symbols) and the compressed sequence."""
+    leaves = sorted(set(values))
+    symbols = [(value, None) for value in leaves]
+    lengths = [1] * len(symbols)
+    sequence = [leaves.index(value) for value in values]
+    for _ in range(200):
+        pairs = Counter(zip(sequence, sequence[1:]))
+        candidates = [(count, pair) for pair, count in pairs.items() if lengths[pair[0]] + lengths[pair[1]] <= 256]
+        if not candidates:
+            break
+        count, (left, right) = max(candidates)
+        if count < 16:
+            break
+        symbols.append((left, right))
+        lengths.append(lengths[left] + lengths[right])
+        new_symbol = len(symbols) - 1
+        paired = []
+        i = 0
+        while i < len(sequence):
+            if i + 1 < len(sequence) and sequence[i] == left and sequence[i + 1] == right:
+                paired.append(new_symbol)
+                i += 2
+            else:
+                paired.append(sequence[i])
+                i += 1
+        sequence = paired
+    return symbols, lengths, sequence
+
+
+def huffman_lengths(frequencies):
+    heap = [(freq, i, [i]) for i, freq in enumerate(frequencies)]
+    heapq.heapify(heap)
+    code_lengths = [0] * len(frequencies)
+    counter = len(frequencies)
+    while len(heap) > 1:
+        f1, _, s1 = heapq.heappop(heap)
+        f2, _, s2 = heapq.heappop(heap)
+        for sym in s1 + s2:
+            code_lengths[sym] += 1
+        heapq.heappush(heap, (f1 + f2, counter, s1 + s2))
+        counter += 1
+    return code_lengths
+
+
+def compress(values, flags):
+    """Returns the header (starting with the flags), the sparse index, the block lengths and the blocks"""
+    if len(set(values)) == 1:
+        return bytes([flags | FLAG_SINGLE_VALUE, values[0]]), b"", b"", b""
+
+    symbols, value_counts, sequence = pair_symbols(values)
+    frequencies = Counter(sequence)
+    code_lengths = huffman_lengths([max(frequencies[sym], 1) for sym in range(len(symbols))])
+    min_len, max_len = min(code_lengths), max(code_lengths)
+    assert max_len <= 32
+
+    # Canonical code: the longest codes get the lowest symbol numbers and the lowest codes
+    order = sorted(range(len(symbols)), key=lambda sym: (-code_lengths[sym], sym))
+    new_number = {sym: i for i, sym in enumerate(order)}
+    count = Counter(code_lengths)
+    lowest_sym = {}
+    base = {max_len: 0}
+    for length in range(max_len, min_len - 1, -1):
+        lowest_sym[length] = sum(n for l, n in count.items() if l > length)
+        if length < max_len:
+            total = base[length + 1] + count[length + 1]
+            assert total % 2 == 0
+            base[length] = total // 2
+    codes = {}
+    for sym in order:
+        length = code_lengths[sym]
+        codes[sym] = (base[length] + new_number[sym] - lowest_sym[length], length)
+
+    # Blocks of whole symbols
+    block_bits = 8 << LOG_BLOCK_SIZE
+    blocks, block_lengths = [], []
+    bits, used, block_values = 0, 0, 0
+    for sym in sequence:
+        code, length = codes[sym]
+        if used + length > block_bits or block_values + value_counts[sym] > 65536:
+            blocks.append((bits << (block_bits - used)).to_bytes(block_bits // 8, "big"))
+            block_lengths.append(block_values - 1)
+            bits, used, block_values = 0, 0, 0
+        bits = (bits << length) | code
+        used += length
+        block_values += value_counts[sym]
+    blocks.append((bits << (block_bits - used)).to_bytes(block_bits // 8, "big"))
+    block_lengths.append(block_values - 1)
+
+    # The sparse index stores the block and the offset of the entries k * span + span / 2
+    span = 1 << LOG_SPAN
+    starts = [0]
+    for length in block_lengths:
+        starts.append(starts[-1] + length + 1)
+    sparse = b""
+    block = 0
+    for k in range((len(values) + span - 1) // span):
+        idx = k * span + span // 2
+        while block + 1 < len(block_lengths) and starts[block + 1] <= idx:
+            block += 1
+        sparse += struct.pack("<IH", block, idx - starts[block])
+
+    header = bytes([flags, LOG_BLOCK_SIZE, LOG_SPAN, 0]) + struct.pack("<I", len(blocks))
+    header += bytes([max_len, min_len])
+    for length in range(min_len, max_len + 1):
+        header += struct.pack("<H", lowest_sym[length])
+    header += struct.pack("<H", len(symbols))
+    for sym in order:
+        left, right = symbols[sym]
+        if right is None:
+            left, right = left, 0xFFF
+        else:
+            left, right = new_number[left], new_number[right]
+        header += bytes([left & 0xFF, (left >> 8) | ((right & 0xF) << 4), right >> 4])
+    if len(symbols) % 2 == 1:
+        header += b"\0"
+    return header, sparse, b"".join(struct.pack("<H", length) for length in block_lengths), b"".join(blocks)
+
+
+def write_file(path, magic, piece, tables, dtz_map=b""):
+    # Not symmetric, no pawns. The 3 pieces form the first group, for both sides to move.
+    out = bytearray(magic + bytes([0x01, 0x00]))
+    for code in (KING, piece, KING | BLACK):
+        out.append(code | (code << 4))
+    out += b"\0" * (len(out) & 1)
+    compressed = [compress(values, flags) for values, flags in tables]
+    for header, _, _, _ in compressed:
+        out += header
+    if dtz_map:
+        out += dtz_map
+        out += b"\0" * (len(out) & 1)
+    for _, sparse, _, _ in compressed:
+        out += sparse
+    for _, _, block_lengths, _ in compressed:
+        out += block_lengths
+    for _, _, _, blocks in compressed:
+        out += b"\0" * (-len(out) % 64)
+        out += blocks
+    # Decoding reads a few bytes past the end of the last block
+    out += b"\0" * 8
+    with open(path, "wb") as f:
+        f.write(out)
+
+
+def write_tables(directory, name, piece, dirs):
+    encoding = Encoding()
+    white_dtm, black_dtm = solve(dirs)
+    white_wdl, black_wdl, white_dtz = [], [], []
+    for wk in range(64):
+        for sq in range(64):
+            for bk in range(64):
+                if len({wk, sq, bk}) < 3 or adjacent(wk, bk):
+                    continue
+                pos = (wk, sq, bk)
+                idx = encoding.index(pos)
+                # WDL values are stored as wdl + 2
+                black_wdl.append((idx, 0 if pos in black_dtm else 2))
+                if bk not in slides(sq, dirs, {wk, bk}):
+                    assert pos in white_dtm, "White always wins with white to move"
+                    white_wdl.append((idx, 4))
+                    white_dtz.append((idx, white_dtm[pos]))
+
+    write_file(os.path.join(directory, name + ".rtbw"), WDL_MAGIC, piece, [(fill(white_wdl), 0), (fill(black_wdl), 0)])
+
+    # The DTZ file only stores white to move, where every position is won. The stored values are mapped to the
+    # number of plies minus 1.
+    win_map = sorted({dtz - 1 for _, dtz in white_dtz})
+    symbols = fill([(idx, win_map.index(dtz - 1)) for idx, dtz in white_dtz])
+    dtz_map = bytes([len(win_map)] + win_map) + bytes([0, 0, 0])
+    flags = FLAG_MAPPED | FLAG_WIN_PLIES | FLAG_LOSS_PLIES
+    write_file(os.path.join(directory, name + ".rtbz"), DTZ_MAGIC, piece, [(symbols, flags)], dtz_map)
+
+
+def main():
+    directory = sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__))
+    write_tables(directory, "KQvK", QUEEN, QUEEN_DIRS)
+    write_tables(directory, "KRvK", ROOK, ROOK_DIRS)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod epd_test {
+    use std::convert::TryFrom;
+
+    use protochess_engine_rs::{Engine, MoveInfo};
+    use protochess_engine_rs::searcher::mate_in;
+    use protochess_engine_rs::types::GameMode;
+    use protochess_engine_rs::utils::epd::{parse_epd, EpdEntry};
+
+    #[test]
+    fn parse_operations() {
+        let entry = EpdEntry::try_from(r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001";"#).unwrap();
+        assert_eq!(entry.fen, "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - -");
+        assert_eq!(entry.id.as_deref(), Some("WAC.001"));
+        assert_eq!(entry.best_moves, vec!["Qg6"]);
+        assert_eq!(entry.variant, GameMode::Standard);
+
+        let entry = EpdEntry::try_from(r#"8/8/8/8/8/8/8/R3K2k w (a1,e1) e3(e4) +1+0 am O-O-O Kd1; dm 3; variant 3check; c0 "a; b""#).unwrap();
+        assert_eq!(entry.fen, "8/8/8/8/8/8/8/R3K2k w (a1,e1) e3(e4) +1+0");
+        assert_eq!(entry.avoid_moves, vec!["O-O-O", "Kd1"]);
+        assert_eq!(entry.mate_in, Some(3));
+        assert_eq!(entry.variant, GameMode::ThreeCheck);
+        assert!(entry.id.is_none());
+
+        assert!(EpdEntry::try_from("8/8/8/8/8/8/8/8 w -").is_err());
+        assert!(EpdEntry::try_from("8/8/8/8/8/8/8/8 w - - dm x;").is_err());
+        assert!(EpdEntry::try_from("8/8/8/8/8/8/8/8 w - - id \"unterminated;").is_err());
+    }
+
+    #[test]
+    fn solution_check() {
+        let entry = EpdEntry::try_from("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; dm 1;").unwrap();
+        let mv = MoveInfo::try_from("a1a8").unwrap();
+        assert!(entry.is_solved("Ra8#", &mv, Some(1)));
+        assert!(!entry.is_solved("Ra8#", &mv, None));
+        let entry = EpdEntry::try_from("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm a1a8;").unwrap();
+        assert!(entry.is_solved("Ra8#", &mv, None));
+        let entry = EpdEntry::try_from("6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra8;").unwrap();
+        assert!(!entry.is_solved("Ra8#", &mv, None));
+        assert!(entry.is_solved("Ra7", &MoveInfo::try_from("a1a7").unwrap(), None));
+    }
+
+    #[test]
+    fn fairy_suite() {
+        let contents = std::fs::read_to_string("epd/fairy.epd").unwrap();
+        let entries = parse_epd(&contents).unwrap();
+        assert_eq!(entries.len(), 5);
+        let mut engine = Engine::default();
+        for entry in entries {
+            let _result = engine.set_state(entry.game_state()).unwrap();
+            let (mv, score) = engine.get_best_move(3).unwrap();
+            let notation = engine.make_move(&mv).move_notation.unwrap();
+            assert!(entry.is_solved(&notation, &mv, mate_in(score)), "{:?} not solved by {notation}", entry.id);
+            assert!(engine.get_nodes_searched() > 0);
+        }
+    }
+}
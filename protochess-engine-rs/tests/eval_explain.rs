@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod eval_explain_test {
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveGen, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::searcher::eval;
+    use protochess_engine_rs::types::Depth;
+
+    fn position(fen: &str) -> Position {
+        PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    // Checks that the breakdown adds up to the result of evaluate(), in the current position and its descendants
+    fn assert_consistent(position: &mut Position, depth: Depth) {
+        let breakdown = eval::explain(position);
+        assert_eq!(breakdown.total, eval::evaluate(position), "{}", position);
+        for side in [&breakdown.white, &breakdown.black] {
+            let material: i32 = side.material.iter().map(|m| m.midgame).sum();
+            let positional: i32 = side.positional.iter().map(|p| p.midgame).sum();
+            assert_eq!(side.midgame, material + positional + side.terms_midgame);
+            let count: u32 = side.material.iter().map(|m| m.count).sum();
+            assert_eq!(count as usize, side.positional.len());
+        }
+        if depth == 0 {
+            return;
+        }
+        for mv in MoveGen::get_pseudo_moves(position, true) {
+            if !MoveGen::make_move_if_legal(mv, position) {
+                continue;
+            }
+            if !position.leader_is_captured() {
+                assert_consistent(position, depth - 1);
+            }
+            position.unmake_move();
+        }
+    }
+
+    #[test]
+    fn total_matches_evaluate() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1 ANTICHESS",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - +1+2 3CHECK",
+        ] {
+            assert_consistent(&mut position(fen), 2);
+        }
+    }
+
+    #[test]
+    fn breakdown() {
+        let breakdown = eval::explain(&position("4k3/8/8/8/8/8/3P4/R3K3 b - - 0 1"));
+        assert_eq!(breakdown.player_to_move, 1);
+        assert!(!breakdown.inverted);
+        assert!(breakdown.network.is_none());
+        // White has a king, a rook and a pawn, black only has a king
+        let ids: Vec<char> = breakdown.white.material.iter().map(|m| m.id).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&'K') && ids.contains(&'R') && ids.contains(&'P'));
+        assert_eq!(breakdown.black.material.len(), 1);
+        assert_eq!(breakdown.black.material[0].id, 'k');
+        assert!(breakdown.white.positional.iter().any(|p| p.id == 'R' && (p.x, p.y) == (0, 0)));
+        assert!(breakdown.total < 0);
+
+        // The score is negated when the goal is to lose
+        let breakdown = eval::explain(&position("4k3/8/8/8/8/8/3P4/R3K3 b - - 0 1 ANTICHESS"));
+        assert!(breakdown.inverted);
+        assert_eq!(breakdown.total, -breakdown.handcrafted);
+        assert!(breakdown.total > 0);
+    }
+
+    #[test]
+    fn check_penalty() {
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - +1+2 3CHECK")).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        let breakdown = engine.explain_eval();
+        let penalty = engine.get_eval_params().check_penalty;
+        // White has given 1 check and received 2
+        assert_eq!(breakdown.white.check_penalty, 2 * penalty);
+        assert_eq!(breakdown.black.check_penalty, penalty);
+        assert_eq!(breakdown.total, breakdown.handcrafted - 2 * penalty + penalty);
+    }
+}
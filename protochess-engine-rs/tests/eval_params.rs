@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod eval_params_test {
+    use protochess_engine_rs::{Engine, EvalParams, GameState, MakeMoveResultFlag, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::searcher::eval;
+    use protochess_engine_rs::utils::self_play::SearchLimit;
+    use protochess_engine_rs::utils::texel::{generate_positions, parse_labeled_positions, LabeledPosition, Tuner};
+
+    fn position(fen: &str, params: EvalParams) -> Position {
+        let mut factory = PositionFactory::default();
+        factory.set_eval_params(params);
+        factory.set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    #[test]
+    fn default_scores() {
+        // The default parameters are the constants that were used before they could be changed
+        let pos = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", EvalParams::default());
+        let scores: Vec<_> = pos.pieces[0].iter().map(|p| (p.get_piece_id(), p.get_material_score())).collect();
+        assert!(scores.contains(&('P', 100)));
+        assert!(scores.contains(&('N', 320)));
+        assert!(scores.contains(&('B', 336)));
+        assert!(scores.contains(&('R', 528)));
+        assert!(scores.contains(&('Q', 1014)));
+        assert_eq!(eval::evaluate(&pos), 0);
+    }
+
+    #[test]
+    fn parse_params() {
+        let params = EvalParams { attack_mul: 12.5, check_penalty: 300, ..EvalParams::default() };
+        assert_eq!(EvalParams::parse(&params.to_string()).unwrap(), params);
+
+        let parsed = EvalParams::parse("# Comment\n\ncheck_penalty = 300\nattack_mul=12.5\n").unwrap();
+        assert_eq!(parsed, params);
+        assert!(EvalParams::parse("unknown_param = 3").is_err());
+        assert!(EvalParams::parse("check_penalty = abc").is_err());
+        assert!(EvalParams::parse("check_penalty 300").is_err());
+        assert_eq!(EvalParams::from_vec(&params.to_vec()).unwrap(), params);
+    }
+
+    #[test]
+    fn change_params() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1";
+        let default_score = eval::evaluate(&position(fen, EvalParams::default()));
+        let params = EvalParams { attack_mul: 20.0, ..EvalParams::default() };
+        let stronger_rook = eval::evaluate(&position(fen, params));
+        assert!(stronger_rook < default_score);
+
+        // Copying the scores is equivalent to creating the position with the new parameters
+        let mut pos = position(fen, EvalParams::default());
+        pos.copy_eval_params_from(&position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", params)).unwrap();
+        assert_eq!(eval::evaluate(&pos), stronger_rook);
+
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen(fen)).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        engine.set_eval_params(params).unwrap();
+        assert_eq!(engine.get_eval_params(), params);
+        assert_eq!(engine.get_fen(protochess_engine_rs::FenDialect::Protochess).unwrap().split(' ').next(), fen.split(' ').next());
+    }
+
+    #[test]
+    fn tapered_eval() {
+        let start = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", EvalParams::default());
+        let (white, _) = start.pieces[0].get_material_score::<false>();
+        let (black, _) = start.pieces[1].get_material_score::<false>();
+        assert_eq!(eval::game_phase(&start, white + black - 2 * 1280), eval::MIDGAME_PHASE);
+
+        // The initial material depends on the variant, not on the loaded FEN
+        let fen = "4k3/pp3ppp/2n5/8/8/5N2/PP3PPP/4K3 w - - 0 1";
+        let pos = position(fen, EvalParams::default());
+        assert_eq!(pos.initial_material, start.initial_material);
+        assert_eq!(eval::game_phase(&pos, 0), 0);
+        let phase = eval::game_phase(&pos, 2 * (6 * 100 + 320));
+        assert!(phase > 0 && phase < eval::MIDGAME_PHASE, "{}", phase);
+
+        // In between, the score is interpolated
+        let fen = "4k3/pp3ppp/2n5/8/8/5N2/PPP2PPP/3RK3 w - - 0 1";
+        let midgame = EvalParams { endgame_phase_limit: -2.0, midgame_phase_limit: -1.0, ..EvalParams::default() };
+        let endgame = EvalParams { endgame_phase_limit: 2.0, midgame_phase_limit: 3.0, ..EvalParams::default() };
+        let midgame_score = eval::evaluate(&position(fen, midgame));
+        let endgame_score = eval::evaluate(&position(fen, endgame));
+        let score = eval::evaluate(&position(fen, EvalParams::default()));
+        assert!(midgame_score < endgame_score, "{} {}", midgame_score, endgame_score);
+        assert!(midgame_score < score && score < endgame_score, "{} {} {}", midgame_score, score, endgame_score);
+    }
+
+    #[test]
+    fn labeled_positions() {
+        let contents = "# Comment\nrnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1; 1/2-1/2\n\n8/8/8/4k3/8/8/3QK3/8 w - - 0 1 ; 1-0\n";
+        let positions = parse_labeled_positions(contents).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[1], LabeledPosition { fen: "8/8/8/4k3/8/8/3QK3/8 w - - 0 1".to_string(), result: 1.0 });
+        assert_eq!(LabeledPosition::parse(&positions[1].to_string()).unwrap(), positions[1]);
+        assert!(parse_labeled_positions("8/8/8/4k3/8/8/3QK3/8 w - - 0 1; 2-0").is_err());
+    }
+
+    #[test]
+    fn texel_tuning() {
+        let opening = GameState::default();
+        let data = generate_positions(&opening, 2, 6, SearchLimit::Depth(1), 40, 1).unwrap();
+        assert!(!data.is_empty());
+
+        let mut tuner = Tuner::new(&opening.initial_state, &data).unwrap();
+        let initial = EvalParams::default();
+        tuner.find_k(&initial).unwrap();
+        let initial_error = tuner.error(&initial).unwrap();
+        let mut iterations = 0;
+        let tuned = tuner.tune(&initial, 2, |_, _, _| iterations += 1).unwrap();
+        assert!((1..=2).contains(&iterations));
+        assert!(tuner.error(&tuned).unwrap() <= initial_error);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod eval_terms_test {
+    use protochess_engine_rs::{EvalParams, GameState, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::searcher::eval_terms::evaluate_terms;
+
+    // Parameters with all the terms disabled
+    fn no_terms() -> EvalParams {
+        EvalParams {
+            mobility_mul: 0,
+            leader_attack_penalty: 0,
+            leader_shield_bonus: 0,
+            passed_bonus: 0,
+            passed_bonus_endgame: 0,
+            blocked_penalty: 0,
+            hanging_penalty: 0,
+            ..EvalParams::default()
+        }
+    }
+
+    fn position(fen: &str, params: EvalParams) -> Position {
+        let mut factory = PositionFactory::default();
+        factory.set_eval_params(params);
+        factory.set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    #[test]
+    fn forward_direction() {
+        let pos = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", no_terms());
+        for (player, pawn_direction) in [(0, 1), (1, -1)] {
+            for piece in pos.pieces[player].iter() {
+                let expected = if piece.get_piece_id().eq_ignore_ascii_case(&'P') { pawn_direction } else { 0 };
+                assert_eq!(piece.get_forward_direction(), expected);
+            }
+        }
+        // The starting position is symmetric
+        assert_eq!(evaluate_terms(&position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", EvalParams::default())), (0, 0));
+    }
+
+    #[test]
+    fn mobility() {
+        let params = EvalParams { mobility_mul: 1, ..no_terms() };
+        // The rook attacks a2-a8 and b1-d1, the squares of the leaders don't count
+        assert_eq!(evaluate_terms(&position("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", params)), (10, 10));
+        assert_eq!(evaluate_terms(&position("4k3/8/8/8/8/8/8/R3K3 b - - 0 1", params)), (10, 10));
+    }
+
+    #[test]
+    fn leader_safety() {
+        // The queen attacks d7 and d8, next to the black king
+        let params = EvalParams { leader_attack_penalty: 1, ..no_terms() };
+        assert_eq!(evaluate_terms(&position("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", params)), (2, 0));
+
+        // Black is missing a pawn in front of the king
+        let params = EvalParams { leader_shield_bonus: 1, ..no_terms() };
+        assert_eq!(evaluate_terms(&position("rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", params)), (1, 0));
+    }
+
+    #[test]
+    fn passed_and_blocked() {
+        let params = EvalParams { passed_bonus: 1, passed_bonus_endgame: 2, ..no_terms() };
+        // The pawn on d5 has advanced 4 ranks
+        assert_eq!(evaluate_terms(&position("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1", params)), (4, 8));
+        // The black pawn on f7 has advanced 1 rank
+        assert_eq!(evaluate_terms(&position("4k3/5p2/8/3P4/8/8/8/4K3 w - - 0 1", params)), (4 - 1, 8 - 2));
+        assert_eq!(evaluate_terms(&position("4k3/4p3/8/3P4/8/8/8/4K3 w - - 0 1", params)), (0, 0));
+
+        let params = EvalParams { blocked_penalty: 1, ..no_terms() };
+        assert_eq!(evaluate_terms(&position("4k3/8/8/3n4/3P4/8/8/4K3 w - - 0 1", params)), (-1, -1));
+        assert_eq!(evaluate_terms(&position("4k3/8/8/3p4/3P4/8/8/4K3 w - - 0 1", params)), (0, 0));
+    }
+
+    #[test]
+    fn hanging_pieces() {
+        let params = EvalParams { hanging_penalty: 1, ..no_terms() };
+        // The knight is attacked by the rook, which is defended by the king
+        assert_eq!(evaluate_terms(&position("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1", params)), (1, 1));
+        // Both knights attack each other
+        assert_eq!(evaluate_terms(&position("4k3/8/8/3n4/8/4N3/8/4K3 w - - 0 1", params)), (0, 0));
+    }
+}
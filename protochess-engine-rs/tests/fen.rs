@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod fen_test {
+    use protochess_engine_rs::{Engine, FenDialect, GameState, MakeMoveResultFlag, MoveInfo};
+
+    #[test]
+    fn xfen_starting_pos() {
+        let engine = Engine::default();
+        let fen = engine.get_fen(FenDialect::XFen).unwrap();
+        assert_eq!(fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let fen = engine.get_fen(FenDialect::ShredderFen).unwrap();
+        assert_eq!(fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+    }
+
+    #[test]
+    fn xfen_roundtrip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1",
+            "r3k3/8/8/8/8/8/8/4K2R b Kq - 0 1",
+            // Chess960: rooks on b and d files, the inner rook uses its file
+            "1rkr3r/8/8/8/8/8/8/1R1RK2R w DQkd - 0 1",
+        ];
+        for fen in fens {
+            let mut engine = Engine::default();
+            engine.load_fen_dialect(fen, FenDialect::XFen).unwrap();
+            assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), fen);
+        }
+    }
+
+    #[test]
+    fn shredder_fen_chess960() {
+        let mut engine = Engine::default();
+        engine.load_fen_dialect("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", FenDialect::ShredderFen).unwrap();
+        let mut engine2 = Engine::default();
+        engine2.load_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFGhfg - 2 9").unwrap();
+        assert_eq!(engine.get_zobrist(), engine2.get_zobrist());
+        assert_eq!(engine.perft(3), 12189);
+        assert_eq!(engine.get_fen(FenDialect::ShredderFen).unwrap(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+    }
+
+    #[test]
+    fn fairy_stockfish_check_count() {
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 3CHECK")).unwrap();
+        assert!(result.flag == MakeMoveResultFlag::Ok);
+        // White has 1 check left (has given 2 checks), black has 3 checks left
+        engine.load_fen_dialect("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 1+3 0 1", FenDialect::FairyStockfish).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::Protochess).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w (a1,a8,e1,e8,h1,h8) - +2+0");
+        assert_eq!(engine.get_fen(FenDialect::FairyStockfish).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1+3 0 1");
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +2+0");
+        // Lichess-style check count is also accepted
+        engine.load_fen_dialect("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 +0+1", FenDialect::FairyStockfish).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::FairyStockfish).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+2 0 1");
+    }
+
+    #[test]
+    fn move_clocks() {
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 3CHECK")).unwrap();
+        assert!(result.flag == MakeMoveResultFlag::Ok);
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2+3 7 23";
+        engine.load_fen_dialect(fen, FenDialect::FairyStockfish).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::FairyStockfish).unwrap(), fen);
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 7 23 +1+0");
+        assert_eq!(engine.get_fen(FenDialect::Protochess).unwrap(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w (a1,a8,e1,e8,h1,h8) - 7 23 +1+0");
+        // The fullmove number is incremented after black moves, pawn moves and captures reset the halfmove clock
+        assert_eq!(engine.make_move(&MoveInfo { from: (5, 0), to: (2, 3), promotion: None }).flag, MakeMoveResultFlag::Ok);
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 8 23 +1+0");
+        assert_eq!(engine.make_move(&MoveInfo { from: (6, 7), to: (5, 5), promotion: None }).flag, MakeMoveResultFlag::Ok);
+        assert!(engine.get_fen(FenDialect::XFen).unwrap().ends_with(" - 9 24 +1+0"));
+        assert_eq!(engine.make_move(&MoveInfo { from: (3, 1), to: (3, 2), promotion: None }).flag, MakeMoveResultFlag::Ok);
+        assert!(engine.get_fen(FenDialect::XFen).unwrap().ends_with(" - 0 24 +1+0"));
+        assert_eq!(engine.make_move(&MoveInfo { from: (5, 5), to: (4, 3), promotion: None }).flag, MakeMoveResultFlag::Ok);
+        assert!(engine.get_fen(FenDialect::XFen).unwrap().ends_with(" - 0 25 +1+0"));
+        // The clocks survive a round-trip through the custom format
+        let fen = engine.get_fen(FenDialect::Protochess).unwrap();
+        engine.load_fen(&fen).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::Protochess).unwrap(), fen);
+        engine.load_fen_dialect("8/8/8/8/8/8/8/K6k w - - 0 0", FenDialect::XFen).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "8/8/8/8/8/8/8/K6k w - - 0 1 +0+0");
+        assert!(engine.load_fen_dialect("8/8/8/8/8/8/8/K6k w - - 0 1 2", FenDialect::XFen).is_err());
+    }
+
+    #[test]
+    fn fairy_stockfish_unsupported() {
+        let mut engine = Engine::default();
+        let holdings = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[Pp] w KQkq - 0 1";
+        assert!(engine.load_fen_dialect(holdings, FenDialect::FairyStockfish).is_err());
+        let promoted = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R+NBQKBNR w KQkq - 0 1";
+        assert!(engine.load_fen_dialect(promoted, FenDialect::FairyStockfish).is_err());
+        let checks = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1";
+        assert!(engine.load_fen_dialect(checks, FenDialect::FairyStockfish).is_err());
+        // Crazyhouse promoted markers are ignored
+        engine.load_fen_dialect("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ~KBNR w KQkq - 0 1", FenDialect::FairyStockfish).unwrap();
+        assert_eq!(engine.get_fen(FenDialect::XFen).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn xfen_unrepresentable() {
+        let mut engine = Engine::default();
+        // Custom en passant victim
+        engine.load_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w (ALL) e3(d4)").unwrap();
+        assert!(engine.get_fen(FenDialect::XFen).is_err());
+        assert!(engine.get_fen(FenDialect::Protochess).is_ok());
+        // Castling outside the back rank
+        engine.load_fen("4k3/8/8/8/8/8/R3K2R/8 w (a2,e2,h2)").unwrap();
+        assert!(engine.get_fen(FenDialect::ShredderFen).is_err());
+        // Non-standard input
+        assert!(engine.load_fen_dialect("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w (ALL) - 0 1", FenDialect::XFen).is_err());
+        assert!(engine.load_fen_dialect("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3(e4) 0 1", FenDialect::XFen).is_err());
+    }
+
+    #[test]
+    fn check_count_without_clocks() {
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen("rnbqkbnr/8/8/8/8/8/8/RNBQKBNR w (all) - +1+2 3CHECK")).unwrap();
+        assert!(result.flag == MakeMoveResultFlag::Ok);
+        assert_eq!(engine.get_fen(FenDialect::FairyStockfish).unwrap(), "rnbqkbnr/8/8/8/8/8/8/RNBQKBNR w KQkq - 2+1 0 1");
+    }
+}
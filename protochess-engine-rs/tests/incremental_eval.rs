@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod incremental_eval_test {
+    use protochess_engine_rs::{GameState, MoveGen, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::types::Centipawns;
+    use protochess_engine_rs::utils::perft::eval_perft;
+
+    fn position(fen: &str) -> Position {
+        PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    // Compares the incrementally updated scores with the ones computed from the bitboards
+    fn assert_scores(position: &Position) {
+        for set in &position.pieces {
+            let material: Centipawns = set.iter().map(|p| p.get_material_score_all::<false>()).sum();
+            let material_endgame: Centipawns = set.iter().map(|p| p.get_material_score_endgame() * p.get_num_pieces() as Centipawns).sum();
+            let leaders: Centipawns = set.iter().filter(|p| p.is_leader()).map(|p| p.get_material_score_all::<false>()).sum();
+            assert_eq!(set.get_material_score::<false>(), (material, leaders));
+            assert_eq!(set.get_material_score::<true>().0, material_endgame);
+            let positional: Centipawns = set.iter().map(|p| p.get_positional_score_all::<false>()).sum();
+            let positional_endgame: Centipawns = set.iter().map(|p| p.get_positional_score_all::<true>()).sum();
+            assert_eq!(set.get_positional_score::<false>(), positional);
+            assert_eq!(set.get_positional_score::<true>(), positional_endgame);
+        }
+    }
+
+    fn walk(position: &mut Position, depth: u8) {
+        assert_scores(position);
+        if depth == 0 {
+            return;
+        }
+        for mv in MoveGen::get_pseudo_moves(position, true) {
+            if !MoveGen::make_move_if_legal(mv, position) {
+                continue;
+            }
+            if !position.leader_is_captured() {
+                walk(position, depth - 1);
+            }
+            position.unmake_move();
+            assert_scores(position);
+        }
+    }
+
+    #[test]
+    fn scores_are_consistent() {
+        let positions = [
+            // Castling, en passant and promotions
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            // Explosions
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC",
+            // No leaders
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1 HORDE",
+        ];
+        for fen in positions {
+            walk(&mut position(fen), 3);
+        }
+    }
+
+    #[test]
+    fn eval_perft_checksum() {
+        // Sum of the evaluations of all the leaf nodes, computed before the scores were updated incrementally
+        // (and updated when the terms in eval_terms were added)
+        let (nodes, checksum) = eval_perft(&mut position("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 4);
+        assert_eq!((nodes, checksum), (422333, -71577405));
+        let (nodes, checksum) = eval_perft(&mut position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC"), 4);
+        assert_eq!((nodes, checksum), (3431552, -327168956));
+    }
+}
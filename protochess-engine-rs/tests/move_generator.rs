@@ -1,9 +1,13 @@
 #[cfg(test)]
 mod move_generator_test {
     use protochess_engine_rs::position::create::position_factory::PositionFactory;
-    use protochess_engine_rs::GameState;
+    use protochess_engine_rs::{GameState, GlobalRules, InitialState, Position};
     use protochess_engine_rs::move_generator::MoveGen;
-    use protochess_engine_rs::types::{Move, MoveType};
+    use protochess_engine_rs::piece::{PieceDefinition, PieceFactory};
+    use protochess_engine_rs::utils::from_index;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use protochess_engine_rs::types::{Bitboard, GameMode, Move, MoveBuffer, MoveSink, MoveType, MOVE_BUFFER_CAPACITY};
 
     #[test]
     fn capture_moves() {
@@ -41,4 +45,135 @@ mod move_generator_test {
         assert!(Move::new(0, 0, 0, MoveType::PromotionCapture, None).is_capture());
         assert!(!Move::new(0, 0, 0, MoveType::Null, None).is_capture());
     }
+    
+    #[test]
+    fn legal_moves_without_make_unmake() {
+        // The pins and checks used by get_legal_moves() must give the same moves as making each pseudo-legal move
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "r1k1r2q/p1ppp1pp/8/8/8/8/P1PPP1PP/R1K1R2Q w ACEace - 0 1",
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1 HORDE",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1 ANTICHESS",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ATOMIC",
+            "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1 RACINGKINGS",
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 +2+2 3CHECK",
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        for fen in fens {
+            let pos = PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap();
+            check_random_games(pos, &mut rng, fen);
+        }
+        // Nightriders attack with sliding deltas, which can be blocked but not along a line
+        let state = GameState { initial_state: nightrider_variant(), initial_fen: None, move_history: vec![] };
+        let pos = PositionFactory::default().set_state(state, None).unwrap().unwrap();
+        check_random_games(pos, &mut rng, "nightriders");
+    }
+
+    fn check_random_games(start: Position, rng: &mut StdRng, name: &str) {
+        for _ in 0..20 {
+            let mut pos = start.clone();
+            for _ in 0..80 {
+                let legal = MoveGen::get_legal_moves(&mut pos);
+                let mut expected = MoveGen::get_pseudo_moves(&mut pos, true);
+                check_staged_generation(&pos, &expected, name);
+                expected.retain(|mv| MoveGen::is_move_legal(*mv, &mut pos));
+                assert_eq!(legal, expected, "{name}");
+                if legal.is_empty() {
+                    break;
+                }
+                pos.make_move(legal[rng.gen_range(0, legal.len())]);
+                if pos.leader_is_captured() || pos.piece_is_on_winning_square() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_moves_match_step_by_step() {
+        // The ray tables must stop at the first occupied square (included) and at the edges of the 16x16 board
+        let deltas = [(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let mut occ = Bitboard::zero();
+            *occ.get_inner_mut() = [0; 4].map(|_| rng.gen::<u64>() & rng.gen::<u64>());
+            let index = rng.gen::<u8>();
+            let dirs: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+            let mut expected = Bitboard::zero();
+            for ((dx, dy), _) in deltas.iter().zip(&dirs).filter(|(_, enabled)| **enabled) {
+                let (mut x, mut y) = from_index(index);
+                while (0..16).contains(&(x as i8 + dx)) && (0..16).contains(&(y as i8 + dy)) {
+                    x = (x as i8 + dx) as u8;
+                    y = (y as i8 + dy) as u8;
+                    expected.set_bit_at(x, y);
+                    if occ.get_bit_at(x, y) {
+                        break;
+                    }
+                }
+            }
+            let moves = MoveGen::attack_tables().get_sliding_moves_bb(
+                index, &occ, dirs[0], dirs[1], dirs[2], dirs[3], dirs[4], dirs[5], dirs[6], dirs[7]);
+            assert_eq!(moves, expected, "Square {index}, directions {dirs:?}\n{occ}");
+        }
+    }
+
+    // The move picker of the search generates the captures and the quiet moves separately, into a MoveBuffer
+    fn check_staged_generation(pos: &Position, pseudo_moves: &[Move], name: &str) {
+        let mut buffer = MoveBuffer::new();
+        MoveGen::output_captures(pos, &mut buffer);
+        if !pos.global_rules.capturing_is_forced || buffer.is_empty() {
+            MoveGen::output_translations(pos, &mut buffer);
+        }
+        let staged: Vec<Move> = buffer.as_slice().iter().map(|scored| scored.mv).collect();
+        assert_eq!(staged, pseudo_moves, "{name}");
+        for mv in pseudo_moves {
+            assert!(MoveGen::is_pseudo_legal(*mv, pos), "{}: {}", name, mv);
+        }
+        assert!(!MoveGen::is_pseudo_legal(Move::null(), pos));
+    }
+
+    #[test]
+    fn move_buffer_spills_to_heap() {
+        let mut buffer = MoveBuffer::new();
+        let moves: Vec<Move> = (0..MOVE_BUFFER_CAPACITY + 50)
+            .map(|i| Move::new((i % 256) as u8, (i / 256) as u8, 0, MoveType::Quiet, None))
+            .collect();
+        for mv in &moves {
+            buffer.push(*mv);
+        }
+        assert_eq!(buffer.len(), moves.len());
+        assert!(buffer.as_slice().iter().map(|scored| scored.mv).eq(moves.iter().copied()));
+        assert!(buffer.contains(moves[MOVE_BUFFER_CAPACITY + 10]));
+        buffer.clear();
+        assert!(buffer.is_empty());
+        buffer.push(moves[0]);
+        assert_eq!(buffer.as_slice().len(), 1);
+    }
+
+    fn nightrider_variant() -> InitialState {
+        let factory = PieceFactory::new(GameMode::Standard);
+        let mut piece_types = factory.make_piece_set(8, 8);
+        let deltas = [(1, 2), (1, -2), (-1, 2), (-1, -2), (2, 1), (2, -1), (-2, 1), (-2, -1)];
+        let runs: Vec<Vec<(i8, i8)>> = deltas.iter().map(|(dx, dy)| (1..8).map(|i| (dx * i, dy * i)).collect()).collect();
+        for piece in piece_types.iter_mut().filter(|p| p.ids == [Some('N'), Some('n')]) {
+            *piece = PieceDefinition {
+                attack_jump_deltas: vec![],
+                translate_jump_deltas: vec![],
+                attack_sliding_deltas: runs.clone(),
+                translate_sliding_deltas: runs.clone(),
+                ..piece.clone()
+            };
+        }
+        InitialState {
+            fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+            player_to_move: 0,
+            piece_types,
+            board_width: 8,
+            board_height: 8,
+            global_rules: GlobalRules::default(),
+        }
+    }
 }
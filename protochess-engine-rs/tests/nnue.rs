@@ -0,0 +1,136 @@
+#[cfg(test)]
+#[cfg(feature = "nnue")]
+mod nnue_test {
+    use std::convert::TryInto;
+    use std::sync::Arc;
+
+    use protochess_engine_rs::{Engine, GameState, InitialState, MakeMoveResultFlag, MoveGen, Network, Position};
+    use protochess_engine_rs::nnue::trainer::Trainer;
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+    use protochess_engine_rs::searcher::eval;
+    use protochess_engine_rs::types::{Depth, GameMode};
+    use protochess_engine_rs::utils::texel::LabeledPosition;
+
+    fn position(fen: &str, network: &Arc<Network>) -> Position {
+        let mut factory = PositionFactory::default();
+        factory.set_network(Some(network.clone()));
+        factory.set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    fn network(mode: GameMode) -> Arc<Network> {
+        Arc::new(Network::new(&VariantFactory::new(mode).make_initial_state(), 16, 1))
+    }
+
+    // Compares the incrementally updated evaluation with the one computed from scratch
+    fn assert_incremental(position: &mut Position, network: &Network, depth: Depth) {
+        let incremental = eval::evaluate(position);
+        let scratch = network.evaluate(position);
+        assert!((incremental - scratch).abs() <= 1, "Incremental: {}, from scratch: {}\n{}", incremental, scratch, position);
+        if depth == 0 {
+            return;
+        }
+        for mv in MoveGen::get_pseudo_moves(position, true) {
+            if !MoveGen::make_move_if_legal(mv, position) {
+                continue;
+            }
+            if !position.leader_is_captured() {
+                assert_incremental(position, network, depth - 1);
+            }
+            position.unmake_move();
+        }
+    }
+
+    #[test]
+    fn incremental_updates() {
+        // Castling moves the king to another bucket, and captures explode in atomic chess
+        let standard = network(GameMode::Standard);
+        let mut pos = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &standard);
+        assert!(pos.get_nnue().is_some());
+        assert_eq!(eval::explain(&pos).network, Some(eval::evaluate(&pos)));
+        assert_incremental(&mut pos, &standard, 2);
+        let mut pos = position("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &standard);
+        assert_incremental(&mut pos, &standard, 2);
+
+        let atomic = network(GameMode::Atomic);
+        let mut pos = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC", &atomic);
+        assert!(pos.get_nnue().is_some());
+        assert_incremental(&mut pos, &atomic, 2);
+    }
+
+    #[test]
+    fn serialization() {
+        let network = Network::new(&VariantFactory::new(GameMode::Horde).make_initial_state(), 8, 2);
+        let bytes = network.to_bytes();
+        assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::from_bytes(b"not a network").is_err());
+    }
+
+    #[test]
+    fn variant_hash() {
+        // The file stores a hash of the pieces and rules, which must not change unless MAGIC changes
+        let hash_of = |variant: &InitialState| Network::new(variant, 8, 2).to_bytes()[5..13].to_vec();
+        let standard = VariantFactory::new(GameMode::Standard).make_initial_state();
+        assert_eq!(&Network::new(&standard, 8, 2).to_bytes()[..5], b"PCNN2");
+        assert_eq!(u64::from_be_bytes(hash_of(&standard).try_into().unwrap()), 0xb52e_c972_29a5_aa49);
+        // The notation doesn't change how the pieces move
+        let mut renamed = standard.clone();
+        renamed.piece_types[0].notation_prefix = [Some("X".to_string()), None];
+        assert_eq!(hash_of(&renamed), hash_of(&standard));
+        let mut modified = standard.clone();
+        modified.piece_types[0].attack_jump_deltas.push((3, 3));
+        assert_ne!(hash_of(&modified), hash_of(&standard));
+        let mut modified = standard.clone();
+        modified.global_rules.repetitions_draw += 1;
+        assert_ne!(hash_of(&modified), hash_of(&standard));
+    }
+
+    #[test]
+    fn fallback_to_handcrafted_eval() {
+        // A network for standard chess cannot be used in atomic chess, even if the pieces have the same ids
+        let standard = network(GameMode::Standard);
+        let pos = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC", &standard);
+        assert!(pos.get_nnue().is_none());
+        assert!(eval::explain(&pos).network.is_none());
+        let handcrafted = PositionFactory::default()
+            .set_state(GameState::from_debug_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC"), None)
+            .unwrap().unwrap();
+        assert_eq!(eval::evaluate(&pos), eval::evaluate(&handcrafted));
+
+        let mut engine = Engine::default();
+        engine.set_network(Some((*standard).clone())).unwrap();
+        assert!(engine.uses_network());
+        let (_mv, _score) = engine.get_best_move(3).unwrap();
+        let result = engine.set_state(GameState::from_debug_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ATOMIC")).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        assert!(!engine.uses_network());
+        engine.set_network(None).unwrap();
+        assert!(!engine.uses_network());
+    }
+
+    #[test]
+    fn training() {
+        let data: Vec<LabeledPosition> = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1; 1/2-1/2",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1; 0-1",
+            "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1; 1-0",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1; 1/2-1/2",
+            "4k3/8/8/8/8/8/8/R3K3 b - - 0 1; 1-0",
+            "r3k3/8/8/8/8/8/8/4K3 w - - 0 1; 0-1",
+        ].iter().map(|line| LabeledPosition::parse(line).unwrap()).collect();
+        let variant = VariantFactory::new(GameMode::Standard).make_initial_state();
+        let network = Network::new(&variant, 16, 3);
+        let mut trainer = Trainer::new(&variant, &data, network, 0.0, 0).unwrap();
+        let initial_error = trainer.error();
+        for _ in 0..100 {
+            trainer.train_epoch(0.1);
+        }
+        assert!(trainer.error() < initial_error / 4.0, "Initial error: {}, final error: {}", initial_error, trainer.error());
+
+        // The trained network prefers the side with more material
+        let trained = Arc::new(trainer.get_network().clone());
+        let pos = position("4k3/8/8/8/8/8/8/R3K3 b - - 0 1", &trained);
+        assert!(eval::evaluate(&pos) < 0);
+    }
+}
@@ -4,7 +4,13 @@ extern crate protochess_engine_rs;
 
 #[cfg(test)]
 mod perft {
-    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag};
+    use protochess_engine_rs::{Engine, GameState, GlobalRules, InitialState, MakeMoveResultFlag};
+    use protochess_engine_rs::piece::PieceFactory;
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::types::GameMode;
+    use protochess_engine_rs::utils::perft::{perft, perft_divide, perft_hashed, PerftStats, PerftTable};
+    #[cfg(feature = "parallel")]
+    use protochess_engine_rs::utils::perft::perft_parallel;
     // https://www.chessprogramming.org/Perft_Results
     
     #[test]
@@ -457,6 +463,111 @@ mod perft {
         ]);
     }
     
+    #[test]
+    fn stats() {
+        // https://www.chessprogramming.org/Perft_Results
+        let stats = |nodes, captures, en_passant, castles, promotions, checks, checkmates| PerftStats {
+            nodes, captures, en_passant, castles, promotions, checks, explosions: 0, checkmates,
+        };
+        let cases = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 4, stats(197281, 1576, 0, 0, 0, 469, 8)),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, stats(97862, 17102, 45, 3162, 0, 993, 1)),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, stats(43238, 3348, 123, 0, 0, 1680, 17)),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, stats(9467, 1021, 4, 0, 120, 38, 22)),
+        ];
+        for (fen, depth, expected) in cases {
+            let mut engine = engine(fen);
+            let result = engine.perft_stats(depth);
+            assert_eq!(result.len(), depth as usize);
+            assert_eq!(result[depth as usize - 1], expected, "{}", fen);
+            for (i, stats) in result.iter().enumerate() {
+                assert_eq!(stats.nodes, engine.perft(i as u8 + 1));
+            }
+        }
+
+        // In atomic chess, every capture is an explosion
+        let mut engine = engine("rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq - 0 1 ATOMIC");
+        let result = engine.perft_stats(3);
+        assert_eq!(result[2].nodes, 45237);
+        assert!(result.iter().all(|stats| stats.explosions == stats.captures));
+        assert!(result[2].explosions > 0);
+    }
+
+    #[test]
+    fn hashed_and_divide() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC",
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 +2+2 3CHECK",
+        ];
+        for fen in fens {
+            let mut position = PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap();
+            let expected = perft(&mut position, 4);
+            let mut table = PerftTable::default();
+            assert_eq!(perft_hashed(&mut position, 4, &mut table), expected, "{}", fen);
+            // The second time, the result is read from the table
+            assert_eq!(perft_hashed(&mut position, 4, &mut table), expected, "{}", fen);
+
+            let divide = perft_divide(&mut position, 4);
+            assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<usize>(), expected);
+            assert_eq!(divide.len(), perft(&mut position, 1));
+            assert!(divide.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn parallel() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let position = PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap();
+        assert_eq!(perft_parallel(&position, 3), 97862);
+    }
+
+    #[test]
+    fn narrow_bitboards() {
+        // The engine uses bitboards of 1 word on boards with up to 4 ranks and of 2 words up to 8 ranks,
+        // they must give the same results as the bitboards of 4 words
+        let boards = [
+            (16, 4, "rnbq4k4bnr/pppp8pppp/PPPP8PPPP/RNBQ4K4BNR w - - 0 1"),
+            (5, 5, "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1"),
+            (16, 8, "rnbqkbnr8/pppppppp8/16/16/16/16/PPPPPPPP8/RNBQKBNR8 w - - 0 1"),
+            (10, 10, "rnbqkbnr2/pppppppp2/10/10/10/10/10/10/PPPPPPPP2/RNBQKBNR2 w - - 0 1"),
+        ];
+        let mut engine = Engine::default();
+        for (width, height, fen) in boards {
+            let initial_state = InitialState {
+                fen: fen.to_string(),
+                player_to_move: 0,
+                piece_types: PieceFactory::new(GameMode::Standard).make_piece_set(width, height),
+                board_width: width,
+                board_height: height,
+                global_rules: GlobalRules::default(),
+            };
+            let state = GameState { initial_state, initial_fen: Some(fen.to_string()), move_history: vec![] };
+            assert_eq!(PositionFactory::default().set_state_sized::<1>(state.clone(), None).is_ok(), height <= 4);
+            assert_eq!(PositionFactory::default().set_state_sized::<2>(state.clone(), None).is_ok(), height <= 8);
+            let mut position = PositionFactory::default().set_state(state.clone(), None).unwrap().unwrap();
+            // The same engine is reused, so the width of its bitboards changes with the board
+            let result = engine.set_state(state).expect("Invalid test FEN");
+            assert!(result.flag == MakeMoveResultFlag::Ok);
+            for depth in 1..=3 {
+                assert_eq!(engine.perft(depth), perft(&mut position, depth), "{fen}");
+            }
+            assert_eq!(engine.perft_divide(3), perft_divide(&mut position, 3), "{fen}");
+            assert_eq!(engine.get_zobrist(), position.get_zobrist(), "{fen}");
+        }
+        let result = engine.set_state(GameState::default()).unwrap();
+        assert!(result.flag == MakeMoveResultFlag::Ok);
+        assert_eq!(engine.perft(3), 8902);
+    }
+
+    fn engine(fen: &str) -> Engine {
+        let mut engine = Engine::default();
+        let result = engine.set_state(GameState::from_debug_fen(fen)).expect("Invalid test FEN");
+        assert!(result.flag == MakeMoveResultFlag::Ok);
+        engine
+    }
+
     fn test_perft(fen: &str, results: Vec<usize>) {
         let gs = GameState::from_debug_fen(fen);        
         let mut engine = Engine::default();
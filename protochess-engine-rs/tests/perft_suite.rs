@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod perft_suite_test {
+    use protochess_engine_rs::{GameState, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::utils::perft::perft_divide;
+    use protochess_engine_rs::utils::perft_suite::{find_divide_mismatch, parse_perft_suite, DivideMismatch, DivideReference};
+
+    #[test]
+    fn suite_file() {
+        let entries = parse_perft_suite(include_str!("data/perft_suite.epd")).unwrap();
+        assert!(entries.len() > 30);
+        assert!(entries.iter().any(|entry| entry.variant == "3check"));
+        for entry in entries {
+            for check in entry.run(&[], 3).unwrap() {
+                assert!(check.passed(), "{}", check);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let entries = parse_perft_suite("# Comment\n\n8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1 ;variant RacingKings ;D2 421 ;D1 21\n").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].variant, "racingkings");
+        assert_eq!(entries[0].depths, vec![(1, 21), (2, 421)]);
+
+        assert!(parse_perft_suite("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+        assert!(parse_perft_suite("8/8/8/8/8/8/8/8 w - - 0 1 ;D1 x").is_err());
+        assert!(parse_perft_suite("8/8/8/8/8/8/8/8 w - - 0 1 ;E1 20").is_err());
+        let entries = parse_perft_suite("8/8/8/8/8/8/8/8 w - - 0 1 ;variant unknown ;D1 20").unwrap();
+        assert!(entries[0].run(&[], 1).is_err());
+    }
+
+    #[test]
+    fn divide_mismatch() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut position = load(fen);
+        let root = perft_divide(&mut position, 3);
+
+        let mut text = String::from("# Reference\n");
+        for (mv, nodes) in &root {
+            text.push_str(&format!("{mv}: {nodes}\n"));
+        }
+        text.push_str("\nNodes searched: 97862\n");
+        let reference = DivideReference::parse(&text).unwrap();
+        assert_eq!(find_divide_mismatch(&mut position, 3, &reference), Ok(None));
+
+        // Make the reference disagree 3 plies deep: a legal move is missing at the leaves
+        let corrupt = |divide: &[(String, usize)], mv: &str| -> Vec<(String, usize)> {
+            divide.iter().map(|(m, n)| (m.clone(), if m == mv { n + 1 } else { *n })).collect()
+        };
+        let mut reference = DivideReference::default();
+        reference.insert(&[], &corrupt(&root, "e1h1"));
+        let mut after_castle = load("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1");
+        reference.insert(&["E1H1"], &corrupt(&perft_divide(&mut after_castle, 2), "e8a8"));
+        let mut leaves = perft_divide(&mut load("2kr3r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 w - - 2 2"), 1);
+        let (removed, _) = leaves.remove(0);
+        reference.insert(&["e1h1", "e8a8"], &leaves);
+        assert_eq!(find_divide_mismatch(&mut position, 3, &reference), Ok(Some(DivideMismatch::ExtraMove {
+            path: vec!["e1h1".to_string(), "e8a8".to_string()],
+            mv: removed.to_lowercase(),
+        })));
+
+        // Without the results after a move, the wrong count is reported in the parent
+        let mut reference = DivideReference::default();
+        reference.insert(&[], &corrupt(&root, "e1h1"));
+        let found = root.iter().find(|(mv, _)| mv == "e1h1").unwrap().1;
+        assert_eq!(find_divide_mismatch(&mut position, 3, &reference), Ok(Some(DivideMismatch::WrongCount {
+            path: vec![],
+            mv: "e1h1".to_string(),
+            expected: found + 1,
+            found,
+        })));
+        // The position is unchanged
+        assert_eq!(perft_divide(&mut position, 3), root);
+
+        assert!(find_divide_mismatch(&mut position, 3, &DivideReference::default()).is_err());
+    }
+
+    fn load(fen: &str) -> Position {
+        PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+}
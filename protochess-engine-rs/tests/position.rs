@@ -1,10 +1,12 @@
+#![allow(clippy::field_reassign_with_default)]
+
 #[cfg(test)]
 mod position_test {
 
     use std::convert::TryFrom;
 
     use protochess_engine_rs::position::create::position_factory::PositionFactory;
-    use protochess_engine_rs::{GameState, MoveInfo};
+    use protochess_engine_rs::{GameState, MoveInfo, NotationMode};
     use protochess_engine_rs::types::Move;
     
     #[test]
@@ -275,6 +277,77 @@ mod position_test {
         ]);
     }
     
+    #[test]
+    fn standard_notation_pawn_captures() {
+        let mut factory = PositionFactory::default();
+        factory.set_notation_mode(NotationMode::Standard);
+        let mut state = GameState::default();
+        state.move_history = build_move_history(vec!["e2e4", "d7d5", "e4d5", "e7e5", "d5e6", "d8h4", "e6f7", "e8d7", "f7g8=Q"]);
+        factory.set_state(state, None)
+            .expect("Cannot load GameState")
+            .expect("set_state() returned None");
+        
+        assert_eq!(factory.get_notation(), &vec![
+            "e4", "d5",
+            "exd5", "e5",
+            "dxe6", "Qh4", // En passant is not marked
+            "exf7+", "Kd7",
+            "fxg8=Q",
+        ]);
+    }
+    
+    #[test]
+    fn compact_notation_pawn_captures() {
+        let mut factory = PositionFactory::default();
+        let mut state = GameState::default();
+        state.move_history = build_move_history(vec!["e2e4", "d7d5", "e4d5", "e7e5", "d5e6", "d8h4", "e6f7", "e8d7", "f7g8=Q"]);
+        factory.set_state(state, None)
+            .expect("Cannot load GameState")
+            .expect("set_state() returned None");
+        
+        assert_eq!(factory.get_notation(), &vec![
+            "e4", "d5",
+            "xd5", "e5",
+            "xe6 e.p.", "Qh4",
+            "xf7+", "Kd7",
+            "xg8=Q",
+        ]);
+    }
+    
+    #[test]
+    fn standard_notation_game_over() {
+        let mut factory = PositionFactory::default();
+        factory.set_notation_mode(NotationMode::Standard);
+        let mut state = GameState::from_debug_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 atomic");
+        state.move_history = build_move_history(vec!["g1f3", "d7d5", "f3e5", "g8f6", "e5f7"]);
+        factory.set_state(state, None)
+            .expect("Cannot load GameState")
+            .expect("set_state() returned None");
+        
+        // The king explodes: the game is over, but it's not a checkmate
+        assert_eq!(factory.get_notation(), &vec!["Nf3", "d5", "Ne5", "Nf6", "Nxf7"]);
+    }
+    
+    #[test]
+    fn standard_notation_ignores_pinned_pieces() {
+        let mut factory = PositionFactory::default();
+        factory.set_notation_mode(NotationMode::Standard);
+        let mut state = GameState::default();
+        // The knight on c3 is pinned, so Ne2 is not ambiguous
+        state.initial_fen = Some("4k3/8/8/b7/8/2N5/8/4K1N1 w - - 0 1".to_string());
+        state.move_history = build_move_history(vec!["g1e2"]);
+        factory.set_state(state.clone(), None)
+            .expect("Cannot load GameState")
+            .expect("set_state() returned None");
+        assert_eq!(factory.get_notation(), &vec!["Ne2"]);
+        
+        factory.set_notation_mode(NotationMode::Compact);
+        factory.set_state(state, None)
+            .expect("Cannot load GameState")
+            .expect("set_state() returned None");
+        assert_eq!(factory.get_notation(), &vec!["Nge2"]);
+    }
+    
     
     #[test]
     fn changing_fen_clears_move_notation() {
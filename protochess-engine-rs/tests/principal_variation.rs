@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod principal_variation {
     use protochess_engine_rs::position::create::position_factory::PositionFactory;
-    use protochess_engine_rs::{GameState, MoveGen};
+    use protochess_engine_rs::{GameState, MoveGen, SearchParams, Tablebases};
     use protochess_engine_rs::searcher::Searcher;
     #[test]
     fn starting_position_1() {
@@ -97,7 +97,7 @@ mod principal_variation {
     fn test_pv(fen: &str, depth: u8) {
         let gs = GameState::from_debug_fen(fen);
         let mut pos = PositionFactory::default().set_state(gs, None).unwrap().unwrap();
-        let (pv, _score, search_depth) = Searcher::get_best_move(&pos, depth, 1);
+        let (pv, _score, search_depth, _nodes) = Searcher::get_best_move(&pos, depth, 1, Tablebases::default(), SearchParams::default());
         assert!(search_depth == depth);
         // Make sure that the moves in the PV legal
         for m in pv {
@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod quiescence_test {
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveGen, MoveInfo, Position, SearchParams};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::searcher::mate_in;
+
+    fn position(fen: &str) -> Position {
+        PositionFactory::default().set_state(GameState::from_debug_fen(fen), None).unwrap().unwrap()
+    }
+
+    fn engine(fen: &str) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        let result = engine.set_state(GameState::from_debug_fen(fen)).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        engine
+    }
+
+    #[test]
+    fn quiet_checks() {
+        let pos = &mut position("4k3/8/8/8/8/8/4P3/R3K1N1 w - - 0 1");
+        let checks: Vec<MoveInfo> = MoveGen::get_quiet_checks(pos).into_iter().map(MoveInfo::from).collect();
+        assert_eq!(checks, vec![MoveInfo { from: (0, 0), to: (0, 7), promotion: None }]);
+        for mv in MoveGen::get_pseudo_moves(pos, true) {
+            assert_eq!(MoveGen::gives_check(mv, pos), checks.contains(&MoveInfo::from(mv)));
+        }
+        assert!(MoveGen::has_quiet_check(pos));
+        // The opponent has no leader
+        let pos = &mut position("8/8/8/8/8/8/4P3/R3K1N1 w - - 0 1");
+        assert!(MoveGen::get_quiet_checks(pos).is_empty());
+        assert!(!MoveGen::has_quiet_check(pos));
+    }
+
+    #[test]
+    fn has_quiet_check_matches_generation() {
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // Only a discovered check, a promotion with check and castling with check
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            "8/4P3/8/8/8/8/8/K3k3 w - - 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            // No quiet checks
+            "k7/8/8/8/8/8/P7/K7 w - - 0 1",
+            // Atomic and antichess (captures are forced)
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ATOMIC",
+            "rnbqkbnr/pppp1ppp/8/4p3/3P4/8/PPP1PPPP/RNBQKBNR w - - 0 1 ANTICHESS",
+        ];
+        for fen in positions {
+            let pos = &mut position(fen);
+            for mv in MoveGen::get_legal_moves(pos).into_iter().map(Some).chain([None]) {
+                if let Some(mv) = mv {
+                    pos.make_move(mv);
+                }
+                assert_eq!(MoveGen::has_quiet_check(pos), !MoveGen::get_quiet_checks(pos).is_empty(), "{} {:?}", fen, mv);
+                if mv.is_some() {
+                    pos.unmake_move();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gives_check_matches_make_move() {
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // Discovered checks by a rook, a bishop and en passant
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            "7k/8/8/8/3N4/8/1B6/K7 w - - 0 1",
+            "8/8/8/R2pP2k/8/8/8/K7 w - d6 0 1",
+            // The promoted piece checks through the square left by the pawn
+            "8/4P3/8/8/8/8/8/K3k3 w - - 0 1",
+            // Castling with check
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        ];
+        for fen in positions {
+            let pos = &mut position(fen);
+            check_moves(pos, fen);
+            for mv in MoveGen::get_legal_moves(pos) {
+                pos.make_move(mv);
+                check_moves(pos, fen);
+                pos.unmake_move();
+            }
+        }
+    }
+
+    // Compares MoveGen::gives_check() with making each pseudo-legal move and looking for checks
+    fn check_moves(pos: &mut Position, fen: &str) {
+        for mv in MoveGen::get_pseudo_moves(pos, true) {
+            pos.make_move(mv);
+            let expected = !pos.leader_is_captured() && MoveGen::in_check(pos);
+            pos.unmake_move();
+            assert_eq!(MoveGen::gives_check(mv, pos), expected, "{} {}", fen, mv);
+        }
+    }
+
+    #[test]
+    fn mate_at_the_horizon() {
+        // After Ra8#, the quiescence search detects that black has no evasions
+        let mut engine = engine("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let (mv, score) = engine.get_best_move(1).unwrap();
+        assert_eq!(mv, MoveInfo { from: (0, 0), to: (0, 7), promotion: None });
+        assert_eq!(mate_in(score), Some(1));
+    }
+
+    #[test]
+    fn optional_quiet_checks() {
+        // Mate in 2 (1. Ra6 bxa6 2. b7#), the last move is a quiet check at the first quiescence ply
+        let fen = "kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1";
+        let (_mv, score) = engine(fen).get_best_move(2).unwrap();
+        assert_eq!(mate_in(score), None);
+        let mut engine = engine(fen);
+        engine.set_search_params(SearchParams { qsearch_quiet_checks: true, ..SearchParams::default() });
+        let (mv, score) = engine.get_best_move(2).unwrap();
+        assert_eq!(mv, MoveInfo { from: (0, 0), to: (0, 5), promotion: None });
+        assert_eq!(mate_in(score), Some(2));
+    }
+
+    #[test]
+    fn three_check_limit() {
+        // White only needs one more check
+        let mut engine = engine("r3k3/8/8/8/8/8/8/4K2R w - - 0 1 +2+0 3CHECK");
+        let (mv, score) = engine.get_best_move(1).unwrap();
+        assert_eq!(mate_in(score), Some(1));
+        assert_eq!(engine.make_move(&mv).flag, MakeMoveResultFlag::CheckLimit);
+    }
+}
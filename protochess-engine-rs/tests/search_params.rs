@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod search_params_test {
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveInfo, SearchParams};
+    use protochess_engine_rs::searcher::mate_in;
+    use protochess_engine_rs::types::{Centipawns, Depth};
+    use protochess_engine_rs::utils::self_play::{play_match, MatchResult, SearchLimit};
+
+    fn search(fen: &str, params: SearchParams, depth: Depth) -> (MoveInfo, Centipawns, u64) {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        engine.set_search_params(params);
+        let result = engine.set_state(GameState::from_debug_fen(fen)).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        let (mv, score) = engine.get_best_move(depth).unwrap();
+        (mv, score, engine.get_nodes_searched())
+    }
+
+    #[test]
+    fn mates_are_found() {
+        let positions = [
+            ("r1b3nr/ppqk1Bbp/2pp4/4P1B1/3n4/3P4/PPP2QPP/R4RK1 w - - 1 0", 2),
+            ("kbK5/pp6/1P6/8/8/8/8/R7 w - - 0 1", 2),
+            ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 1),
+        ];
+        for (fen, moves) in positions {
+            for params in [SearchParams::default(), SearchParams::disabled()] {
+                let (_mv, score, _nodes) = search(fen, params, 2 * moves as Depth);
+                assert_eq!(mate_in(score), Some(moves), "{} {:?}", fen, params);
+            }
+        }
+    }
+
+    #[test]
+    fn pruning_reduces_nodes() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let (_mv, _score, pruned_nodes) = search(fen, SearchParams::default(), 6);
+        let (_mv, _score, nodes) = search(fen, SearchParams::disabled(), 6);
+        assert!(pruned_nodes < nodes, "{} >= {}", pruned_nodes, nodes);
+    }
+
+    #[test]
+    fn no_pruning_in_antichess() {
+        // Only the aspiration windows can change the search
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w - - 0 2 ANTICHESS";
+        let params = SearchParams { aspiration_window: None, ..SearchParams::default() };
+        assert_eq!(search(fen, params, 5), search(fen, SearchParams::disabled(), 5));
+    }
+
+    #[test]
+    fn non_positive_aspiration_window() {
+        // Windows that are not positive are ignored instead of widening forever
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let no_aspiration = SearchParams { aspiration_window: None, ..SearchParams::default() };
+        for window in [0, -50] {
+            let params = SearchParams { aspiration_window: Some(window), ..SearchParams::default() };
+            assert_eq!(search(fen, params, 5), search(fen, no_aspiration, 5));
+        }
+    }
+
+    #[test]
+    fn self_play() {
+        let openings = [GameState::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")];
+        let result = play_match(SearchParams::default(), SearchParams::disabled(), &openings, SearchLimit::Depth(2), 20).unwrap();
+        assert_eq!(result.num_games(), 2);
+
+        let result = MatchResult { wins: 3, draws: 2, losses: 3 };
+        assert_eq!(result.score(), 0.5);
+        assert_eq!(result.elo_difference(), Some(0.0));
+        assert!(MatchResult { wins: 6, draws: 2, losses: 2 }.elo_difference().unwrap() > 100.0);
+        assert_eq!(MatchResult { wins: 0, draws: 0, losses: 4 }.elo_difference(), None);
+    }
+}
@@ -0,0 +1,100 @@
+#[cfg(test)]
+mod see_test {
+    use protochess_engine_rs::{GameState, MoveGen, MoveInfo, Position};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+    use protochess_engine_rs::searcher::eval::score_move;
+    use protochess_engine_rs::searcher::see::{see, is_losing_capture};
+    use protochess_engine_rs::types::{Centipawns, GameMode};
+
+    fn position(fen: &str, mode: GameMode) -> Position {
+        let state = GameState {
+            initial_state: VariantFactory::new(mode).make_initial_state(),
+            initial_fen: Some(fen.to_string()),
+            move_history: vec![],
+        };
+        PositionFactory::default().set_state(state, None).unwrap().unwrap()
+    }
+
+    fn see_of(fen: &str, mode: GameMode, from: (u8, u8), to: (u8, u8)) -> Centipawns {
+        see_of_promotion(fen, mode, from, to, None)
+    }
+
+    fn see_of_promotion(fen: &str, mode: GameMode, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> Centipawns {
+        let mut pos = position(fen, mode);
+        let mv = MoveGen::get_legal_moves(&mut pos).into_iter()
+            .find(|mv| MoveInfo::from(*mv) == MoveInfo { from, to, promotion })
+            .unwrap_or_else(|| panic!("Move not found in {}", fen));
+        assert_eq!(is_losing_capture(&pos, mv), see(&pos, mv) < 0);
+        see(&pos, mv)
+    }
+
+    fn value(id: char) -> Centipawns {
+        position("k7/8/8/8/8/8/8/K7 w - - 0 1", GameMode::Standard).lookup_piece(id).unwrap().get_material_score()
+    }
+
+    #[test]
+    fn simple_exchanges() {
+        // Undefended pawn
+        assert_eq!(see_of("1k6/8/8/4p3/8/8/8/K3R3 w - - 0 1", GameMode::Standard, (4, 0), (4, 4)), value('p'));
+        // Pawn defended by a pawn
+        assert_eq!(see_of("1k6/8/3p4/4p3/8/8/8/K3R3 w - - 0 1", GameMode::Standard, (4, 0), (4, 4)), value('p') - value('R'));
+        // Capturing a more valuable piece
+        assert_eq!(see_of("1k6/8/3p4/4r3/8/5N2/8/K7 w - - 0 1", GameMode::Standard, (5, 2), (4, 4)), value('r') - value('N'));
+        // Not a capture
+        assert_eq!(see_of("1k6/8/8/4p3/8/8/8/K3R3 w - - 0 1", GameMode::Standard, (4, 0), (4, 1)), 0);
+    }
+
+    #[test]
+    fn xray_and_leaders() {
+        // The second rook recaptures through the first one
+        assert_eq!(see_of("1k2r3/8/8/4p3/8/8/4R3/K3R3 w - - 0 1", GameMode::Standard, (4, 1), (4, 4)), value('p'));
+        // The king can recapture an undefended rook
+        assert_eq!(see_of("8/8/8/8/3k4/4p3/8/K3R3 w - - 0 1", GameMode::Standard, (4, 0), (4, 2)), value('p') - value('R'));
+        // The king cannot recapture if the bishop defends the rook
+        assert_eq!(see_of("8/8/8/8/3k4/4p3/8/K3R1B1 w - - 0 1", GameMode::Standard, (4, 0), (4, 2)), value('p'));
+    }
+
+    #[test]
+    fn promotions() {
+        // Undefended rook: the pawn becomes a queen
+        let fen = "3r3k/4P3/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(see_of_promotion(fen, GameMode::Standard, (4, 6), (3, 7), Some('Q')), value('r') + value('Q') - value('P'));
+        assert_eq!(see_of_promotion(fen, GameMode::Standard, (4, 6), (3, 7), Some('N')), value('r') + value('N') - value('P'));
+        // The king recaptures the new queen
+        let fen = "2kr4/4P3/8/8/8/8/8/K7 w - - 0 1";
+        assert_eq!(see_of_promotion(fen, GameMode::Standard, (4, 6), (3, 7), Some('Q')), value('r') - value('P'));
+    }
+
+    #[test]
+    fn promotion_ordering() {
+        let score = |fen: &str, mode: GameMode, from: (u8, u8), to: (u8, u8), promotion: Option<char>| {
+            let mut pos = position(fen, mode);
+            let mv = MoveGen::get_legal_moves(&mut pos).into_iter()
+                .find(|mv| MoveInfo::from(*mv) == MoveInfo { from, to, promotion })
+                .unwrap_or_else(|| panic!("Move not found in {}", fen));
+            score_move(&pos, 0, mv)
+        };
+        // The explosion of dxc8=Q also destroys the queen on c7: the capture loses material, so it's searched
+        // after the quiet moves even though it promotes
+        let fen = "2n4k/2QP4/8/8/8/8/8/K7 w - - 0 1";
+        let losing = score(fen, GameMode::Atomic, (3, 6), (2, 7), Some('Q'));
+        assert!(losing < 0, "{}", losing);
+        assert!(losing < score(fen, GameMode::Atomic, (0, 0), (1, 0), None));
+        assert!(losing < score(fen, GameMode::Atomic, (3, 6), (3, 7), Some('Q')));
+        // The promotions that don't lose material are searched first
+        let winning = score("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1", GameMode::Standard, (4, 6), (3, 7), Some('Q'));
+        let quiet = score("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1", GameMode::Standard, (4, 6), (4, 7), Some('Q'));
+        assert!(winning > quiet && quiet > 0);
+    }
+
+    #[test]
+    fn explosions() {
+        // The explosion also destroys the queen, and the pawns are immune
+        assert_eq!(see_of("k7/8/8/3qp3/4P3/5N2/8/K7 w - - 0 1", GameMode::Atomic, (5, 2), (4, 4)), value('p') + value('q') - value('N'));
+        // The explosion destroys my own rook
+        let score = see_of("k7/8/8/4p3/3R4/5N2/8/K7 w - - 0 1", GameMode::Atomic, (5, 2), (4, 4));
+        assert_eq!(score, value('p') - value('N') - value('R'));
+        assert!(score < 0);
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod seed_test {
+    use std::convert::TryFrom;
+
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveInfo, BookFormat, OpeningBook, Skill};
+    use protochess_engine_rs::book::builder::BookBuilder;
+
+    fn engine_with_seed(seed: u64) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_seed(seed).unwrap();
+        engine
+    }
+
+    fn engine_with_zobrist_seed(seed: u64) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_zobrist_seed(seed).unwrap();
+        engine
+    }
+
+    #[test]
+    fn zobrist_seed() {
+        // The default zobrist keys use a seed of 0
+        assert_eq!(Engine::default().get_zobrist_seed(), 0);
+        assert_eq!(Engine::default().get_zobrist(), engine_with_zobrist_seed(0).get_zobrist());
+        assert_eq!(engine_with_zobrist_seed(1).get_zobrist(), engine_with_zobrist_seed(1).get_zobrist());
+        assert_ne!(engine_with_zobrist_seed(0).get_zobrist(), engine_with_zobrist_seed(1).get_zobrist());
+        // set_seed() also sets the zobrist seed
+        assert_eq!(engine_with_seed(1).get_zobrist_seed(), 1);
+        assert_eq!(engine_with_seed(1).get_zobrist(), engine_with_zobrist_seed(1).get_zobrist());
+        assert_eq!(engine_with_seed(0).get_zobrist(), Engine::default().get_zobrist());
+
+        // The keys are still updated consistently
+        let mut engine = engine_with_zobrist_seed(42);
+        for mv in ["e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "g1f3", "c6b5", "e1h1"] {
+            assert_eq!(engine.make_move(&MoveInfo::try_from(mv).unwrap()).flag, MakeMoveResultFlag::Ok);
+        }
+        let mut expected = engine_with_zobrist_seed(42);
+        let result = expected.set_state(GameState::from_debug_fen("rnbqkb1r/pp2pppp/5n2/1p1P4/8/5N2/PPPP1PPP/RNBQ1RK1 b (a1,a8,e8,h8) -")).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        assert_eq!(engine.get_zobrist(), expected.get_zobrist());
+    }
+
+    #[test]
+    fn book_moves() {
+        let build_book = |zobrist_seed: u64| {
+            let mut builder = BookBuilder::new(BookFormat::Protochess, 2, zobrist_seed);
+            for first in ["e2e4", "d2d4", "c2c4", "g1f3"] {
+                builder.add_game(&GameState::default(), &[first.to_string()]).unwrap();
+            }
+            builder.build()
+        };
+        let book_moves = |mut engine: Engine, book: &OpeningBook| -> Vec<Option<MoveInfo>> {
+            engine.set_opening_book(Some(book.clone()));
+            (0..10).map(|_| engine.get_book_move()).collect()
+        };
+        // The books are built with the zobrist seed of the engine
+        let (book_0, book_7) = (build_book(0), build_book(7));
+        assert_eq!(book_moves(engine_with_seed(0), &book_0), book_moves(engine_with_seed(0), &book_0));
+        assert_ne!(book_moves(engine_with_seed(0), &book_0), book_moves(engine_with_seed(7), &book_7));
+        assert!(book_moves(engine_with_seed(7), &book_7).iter().all(|mv| mv.is_some()));
+        // With a different zobrist seed, the position is not found in the book
+        assert!(book_moves(engine_with_seed(7), &book_0).iter().all(|mv| mv.is_none()));
+        assert!(book_moves(engine_with_zobrist_seed(7), &book_0).iter().all(|mv| mv.is_none()));
+        assert!(book_moves(engine_with_zobrist_seed(7), &book_7).iter().all(|mv| mv.is_some()));
+    }
+
+    #[test]
+    fn deterministic_search() {
+        let search = || {
+            let mut engine = engine_with_seed(3);
+            engine.set_deterministic(true);
+            let result = engine.set_state(GameState::from_debug_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")).unwrap();
+            assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+            let (mv, score) = engine.get_best_move(5).unwrap();
+            (mv, score, engine.get_nodes_searched())
+        };
+        assert_eq!(search(), search());
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        // Weakened play, so that the moves depend on the random choices too
+        let play = |seed: u64| -> Vec<(MoveInfo, u64)> {
+            let mut engine = engine_with_seed(seed);
+            engine.set_deterministic(true);
+            engine.set_skill(Some(Skill::from_level(5)));
+            (0..6).map(|_| {
+                let (mv, _score) = engine.get_best_move(3).unwrap();
+                assert_eq!(engine.make_move(&mv).flag, MakeMoveResultFlag::Ok);
+                (mv, engine.get_zobrist())
+            }).collect()
+        };
+        let game = play(11);
+        assert_eq!(game, play(11));
+        // Different seeds have different zobrist keys from the start
+        assert_ne!(engine_with_seed(11).get_zobrist(), engine_with_seed(12).get_zobrist());
+        let other_keys: Vec<u64> = play(12).into_iter().map(|(_mv, key)| key).collect();
+        assert!(game.iter().zip(&other_keys).all(|((_mv, key), other)| key != other));
+    }
+
+    #[test]
+    fn legal_moves_order() {
+        let mut engine = Engine::default();
+        let moves = engine.legal_moves();
+        assert_eq!(moves, engine.legal_moves());
+        assert!(moves.windows(2).all(|w| (w[0].x, w[0].y) < (w[1].x, w[1].y)));
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod self_play_test {
+    use protochess_engine_rs::{Engine, SearchParams, Skill};
+    use protochess_engine_rs::types::GameMode;
+    use protochess_engine_rs::utils::pgn::parse_pgn;
+    use protochess_engine_rs::utils::self_play::{play_config_game, run_match, EngineConfig, GameOutcome, GameRecord,
+        MatchResult, MatchSettings, Opening, SearchLimit, Sprt, SprtStatus};
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt::new(0.0, 10.0);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+        assert_eq!(sprt.status(&MatchResult::default()), SprtStatus::Continue);
+        assert_eq!(sprt.status(&MatchResult { wins: 10, draws: 20, losses: 9 }), SprtStatus::Continue);
+        assert_eq!(sprt.status(&MatchResult { wins: 600, draws: 200, losses: 200 }), SprtStatus::AcceptH1);
+        assert_eq!(sprt.status(&MatchResult { wins: 200, draws: 200, losses: 600 }), SprtStatus::AcceptH0);
+        // The ratio favors the hypothesis that is closer to the observed Elo
+        assert!(sprt.llr(&MatchResult { wins: 110, draws: 0, losses: 100 }) > 0.0);
+        assert!(sprt.llr(&MatchResult { wins: 100, draws: 0, losses: 100 }) < 0.0);
+    }
+
+    #[test]
+    fn elo_error_margin() {
+        let few = MatchResult { wins: 6, draws: 4, losses: 4 };
+        let many = MatchResult { wins: 600, draws: 400, losses: 400 };
+        assert_eq!(few.elo_difference(), many.elo_difference());
+        assert!(few.elo_error_margin().unwrap() > many.elo_error_margin().unwrap());
+        assert!(many.elo_error_margin().unwrap() > 0.0);
+        assert_eq!(MatchResult { wins: 4, draws: 0, losses: 0 }.elo_error_margin(), None);
+    }
+
+    #[test]
+    fn pgn_output() {
+        let record = GameRecord {
+            opening: Opening::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2 ATOMIC"),
+            moves: vec!["Nc6".to_string(), "Bb5".to_string(), "a6".to_string()],
+            outcome: GameOutcome::WhiteWins,
+            adjudicated: false,
+        };
+        let pgn = record.to_pgn("First", "Second", 3).to_string();
+        assert!(pgn.contains("2... Nc6 3. Bb5 a6 1-0"));
+        let games = parse_pgn(&pgn).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].moves, record.moves);
+        assert_eq!(games[0].get_tag("Variant"), Some("Atomic"));
+        assert_eq!(games[0].get_tag("Round"), Some("3"));
+        assert_eq!(games[0].get_tag("White"), Some("First"));
+        assert_eq!(games[0].get_tag("Result"), Some("1-0"));
+    }
+
+    #[test]
+    fn node_limit() {
+        let mut engine = Engine::default();
+        let (_mv, _score, depth) = engine.get_best_move_nodes(20_000).unwrap();
+        assert!(depth >= 2);
+        assert!(engine.get_nodes_searched() <= 20_001);
+
+        // Node-limited games are reproducible
+        let opening = Opening::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        let config = EngineConfig::default();
+        let play = || play_config_game(&config, &config, &opening, SearchLimit::Nodes(2000), 10, 0).unwrap();
+        let record = play();
+        assert_eq!(record, play());
+        assert_eq!(record.moves.len(), 10);
+        assert!(record.adjudicated);
+    }
+
+    #[test]
+    fn seeded_skill_games() {
+        // The weakened play picks random moves, the games only depend on the seed
+        let opening = Opening::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        let config = EngineConfig { skill: Some(Skill::from_level(0)), ..EngineConfig::default() };
+        let play = |seed| play_config_game(&config, &config, &opening, SearchLimit::Nodes(500), 8, seed).unwrap();
+        let records: Vec<GameRecord> = (0..4).map(play).collect();
+        assert_eq!(records[1], play(1));
+        assert!(records.iter().any(|record| record.moves != records[0].moves));
+
+        // The games of a match use the seed of the settings plus their index
+        let settings = MatchSettings {
+            openings: vec![opening.clone()],
+            limit: SearchLimit::Nodes(500),
+            max_ply: 8,
+            num_games: 3,
+            concurrency: 2,
+            sprt: None,
+            seed: 1,
+        };
+        let (result, _status) = run_match(&config, &config, &settings, |game, _| {
+            assert_eq!(game.record, records[game.index as usize + 1]);
+        }).unwrap();
+        assert_eq!(result.num_games(), 3);
+    }
+
+    #[test]
+    fn concurrent_match() {
+        let first = EngineConfig { name: "first".to_string(), ..EngineConfig::default() };
+        let second = EngineConfig::from(SearchParams::disabled());
+        let settings = MatchSettings {
+            openings: vec![
+                Opening::from_debug_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+                Opening::from_debug_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ATOMIC"),
+            ],
+            limit: SearchLimit::Depth(1),
+            max_ply: 12,
+            num_games: 5,
+            concurrency: 2,
+            sprt: None,
+            seed: 0,
+        };
+        let mut indices = Vec::new();
+        let (result, status) = run_match(&first, &second, &settings, |game, total| {
+            indices.push(game.index);
+            assert_eq!(game.first_is_white, game.index % 2 == 0);
+            let expected_variant = if game.index % 4 < 2 { GameMode::Standard } else { GameMode::Atomic };
+            assert_eq!(game.record.opening.variant, expected_variant);
+            assert!(game.record.moves.len() <= 12);
+            assert_eq!(total.num_games() as usize, indices.len());
+        }).unwrap();
+        assert_eq!(result.num_games(), 5);
+        assert_eq!(status, SprtStatus::Continue);
+        indices.sort_unstable();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+
+        // The match stops as soon as the test finishes. With these error probabilities, both bounds are 0.
+        let sprt = Sprt { elo0: 0.0, elo1: 10.0, alpha: 0.5, beta: 0.5 };
+        let settings = MatchSettings { sprt: Some(sprt), concurrency: 1, ..settings };
+        let (result, status) = run_match(&first, &second, &settings, |_, _| ()).unwrap();
+        assert_eq!(result.num_games(), 1);
+        assert_ne!(status, SprtStatus::Continue);
+    }
+}
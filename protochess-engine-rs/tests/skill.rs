@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod skill_test {
+    use std::convert::TryFrom;
+
+    use protochess_engine_rs::{Engine, GameState, MakeMoveResultFlag, MoveInfo, SearchParams, Skill};
+    use protochess_engine_rs::position::create::position_factory::PositionFactory;
+    use protochess_engine_rs::searcher::Searcher;
+    use protochess_engine_rs::tablebase::Tablebases;
+
+    fn play_game(seed: u64, skill: Skill, plies: usize) -> Vec<MoveInfo> {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        engine.set_skill(Some(skill));
+        engine.set_seed(seed).unwrap();
+        let mut moves = Vec::new();
+        for _ in 0..plies {
+            let (mv, _score) = engine.get_best_move(4).unwrap();
+            let _result = engine.make_move(&mv);
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn deterministic_with_seed() {
+        let skill = Skill::from_level(3);
+        assert_eq!(play_game(7, skill, 8), play_game(7, skill, 8));
+        // Different seeds usually lead to different games
+        let games: Vec<_> = (0..4).map(|seed| play_game(seed, skill, 8)).collect();
+        assert!(games.iter().any(|game| *game != games[0]));
+    }
+
+    #[test]
+    fn candidates() {
+        let state = GameState::from_debug_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        let pos = PositionFactory::default().set_state(state, None).unwrap().unwrap();
+        let (candidates, _nodes) = Searcher::get_candidate_moves(&pos, 3, 1000, u64::MAX, 5, Tablebases::default(), SearchParams::default());
+        assert_eq!(candidates.len(), 3);
+        for depth in &candidates {
+            assert_eq!(depth.len(), 5);
+            assert!(depth.windows(2).all(|w| w[0].1 >= w[1].1));
+            for (i, (mv, _)) in depth.iter().enumerate() {
+                assert!(!depth[i+1..].iter().any(|(other, _)| other == mv));
+            }
+        }
+
+        // Only 1 legal move
+        let state = GameState::from_debug_fen("7k/8/8/8/8/8/6q1/7K w - - 0 1");
+        let pos = PositionFactory::default().set_state(state, None).unwrap().unwrap();
+        let (candidates, _nodes) = Searcher::get_candidate_moves(&pos, 2, 1000, u64::MAX, 5, Tablebases::default(), SearchParams::default());
+        assert!(candidates.iter().all(|depth| depth.len() == 1));
+    }
+
+    #[test]
+    fn node_limit() {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        let skill = Skill::from_level(10);
+        engine.set_skill(Some(skill));
+        let (_mv, _score, depth) = engine.get_best_move_timeout(1000).unwrap();
+        assert!(depth >= 1);
+        assert!(engine.get_nodes_searched() <= skill.max_nodes + 1);
+    }
+
+    #[test]
+    fn strong_skill_wins_material() {
+        // The black queen is hanging
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        let result = engine.set_state(GameState::from_debug_fen("rnb1kbnr/pppp1ppp/8/4p1q1/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1")).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        engine.set_skill(Some(Skill::from_level(Skill::MAX_LEVEL)));
+        for seed in 0..10 {
+            engine.set_seed(seed).unwrap();
+            let (mv, _score) = engine.get_best_move(3).unwrap();
+            assert_eq!(mv, MoveInfo::try_from("f3g5").unwrap());
+        }
+    }
+
+    #[test]
+    fn elo() {
+        assert_eq!(Skill::from_elo(0), Skill::from_level(0));
+        assert_eq!(Skill::from_elo(5000), Skill::from_level(Skill::MAX_LEVEL));
+        assert!(Skill::from_elo(1200).max_nodes < Skill::from_elo(2000).max_nodes);
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(test)]
+#[cfg(feature = "syzygy")]
+mod syzygy_test {
+    use std::path::{Path, PathBuf};
+
+    use protochess_engine_rs::{Engine, GameState, InitialState, MakeMoveResultFlag, MoveInfo, ProbeResult, Syzygy, Tablebase, Wdl};
+    use protochess_engine_rs::position::create::variant_factory::VariantFactory;
+    use protochess_engine_rs::searcher::mate_in;
+    use protochess_engine_rs::types::GameMode;
+
+    // KQvK tables where every position has the same value: won for white to move, lost for black to move,
+    // and 7 plies until the next zeroing move
+    fn single_value_table(magic: [u8; 4], values: &[u8]) -> Vec<u8> {
+        // Flags (split), order of the groups, pieces (K, Q, k) for both sides to move, padding
+        let mut bytes = magic.to_vec();
+        bytes.extend([0x01, 0x00, 0x66, 0x55, 0xEE, 0x00]);
+        for value in values {
+            // Single value flag
+            bytes.extend([0x80, *value]);
+        }
+        bytes.resize(64, 0);
+        bytes
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("protochess_syzygy_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn synthetic_tables() -> PathBuf {
+        let dir = temp_dir("kqk");
+        std::fs::write(dir.join("KQvK.rtbw"), single_value_table([0x71, 0xE8, 0x23, 0x5D], &[4, 0])).unwrap();
+        std::fs::write(dir.join("KQvK.rtbz"), single_value_table([0xD7, 0x66, 0x0C, 0xA5], &[3])).unwrap();
+        dir
+    }
+
+    fn engine_with_tables(dir: &Path) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        engine.set_syzygy_path(Some(dir.to_str().unwrap())).unwrap();
+        engine
+    }
+
+    #[test]
+    fn synthetic_kqk() {
+        let dir = synthetic_tables();
+        let syzygy = Syzygy::new(dir.to_str().unwrap()).unwrap();
+        assert_eq!(syzygy.get_max_pieces(), 3);
+
+        let mut engine = engine_with_tables(&dir);
+        let positions = [
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", Some(Wdl::Win), Some(7)),
+            // The only legal move is Kb8, then white needs 7 more plies
+            ("k7/8/1K6/8/8/8/8/6Q1 b - - 0 1", Some(Wdl::Loss), Some(-8)),
+            // Black has the queen, the colors are flipped when probing
+            ("6q1/8/8/8/8/1k6/8/K7 b - - 0 1", Some(Wdl::Win), Some(7)),
+            // The queen can be captured
+            ("8/8/8/8/8/8/1kQ5/7K b - - 0 1", Some(Wdl::Draw), Some(0)),
+            ("k7/8/8/8/8/8/8/7K w - - 0 1", Some(Wdl::Draw), Some(0)),
+            // Too many pieces
+            ("k7/8/1K6/8/8/8/8/5RQ1 w - - 0 1", None, None),
+            // Missing table
+            ("k7/8/1K6/8/8/8/8/6R1 w - - 0 1", None, None),
+        ];
+        for (fen, wdl, dtz) in positions {
+            engine.load_fen(fen).unwrap();
+            assert_eq!(engine.probe_syzygy(), wdl, "{}", fen);
+            assert_eq!(engine.probe_syzygy_dtz(), dtz, "{}", fen);
+        }
+
+        // Only the checkmate (Qg8#) is kept at the root
+        engine.load_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        let (mv, score) = engine.get_best_move(2).unwrap();
+        assert_eq!(mv, MoveInfo { from: (6, 0), to: (6, 7), promotion: None });
+        // The position after the move is probed before searching it, so the score is a tablebase win
+        assert!(score > 0 && mate_in(score).is_none(), "{}", score);
+
+        engine.set_syzygy_path(None).unwrap();
+        assert_eq!(engine.probe_syzygy(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn other_variants_ignored() {
+        let dir = synthetic_tables();
+        let mut engine = engine_with_tables(&dir);
+        let state = GameState {
+            initial_state: VariantFactory::new(GameMode::KingOfTheHill).make_initial_state(),
+            initial_fen: Some("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1".to_string()),
+            move_history: vec![],
+        };
+        let result = engine.set_state(state).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        assert_eq!(engine.probe_syzygy(), None);
+        assert_eq!(engine.probe_syzygy_dtz(), None);
+        assert!(engine.get_best_move(2).is_ok());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let mut engine = Engine::default();
+        assert!(engine.set_syzygy_path(Some("/this/directory/does/not/exist")).is_err());
+        let dir = temp_dir("invalid");
+        // Empty directory
+        assert!(engine.set_syzygy_path(Some(dir.to_str().unwrap())).is_err());
+        // Files are only read when probing, a corrupted file is ignored
+        std::fs::write(dir.join("KQvK.rtbw"), [0x71, 0xE8, 0x23, 0x5D, 0x02]).unwrap();
+        std::fs::write(dir.join("README.txt"), "Not a table").unwrap();
+        engine.set_syzygy_path(Some(dir.to_str().unwrap())).unwrap();
+        engine.load_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        assert_eq!(engine.probe_syzygy(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Synthetic KQvK and KRvK tables written by tests/data/syzygy_synthetic/generate.py. They are not the official
+    // files: they only check that the reader agrees with that script and with the engine's own generator.
+    const SYNTHETIC_TABLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/syzygy_synthetic");
+
+    // Places the white king, a piece and the black king on an empty board
+    fn three_piece_fen(squares: [usize; 3], pieces: [char; 3], side: char) -> String {
+        let mut board = ['.'; 64];
+        for (&sq, &piece) in squares.iter().zip(pieces.iter()) {
+            board[sq] = piece;
+        }
+        let ranks: Vec<String> = (0..8).rev().map(|rank| {
+            let mut fen_rank = String::new();
+            let mut empty = 0;
+            for &c in &board[rank * 8..rank * 8 + 8] {
+                if c == '.' {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    fen_rank.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                fen_rank.push(c);
+            }
+            if empty > 0 {
+                fen_rank.push_str(&empty.to_string());
+            }
+            fen_rank
+        }).collect();
+        format!("{} {} - - 0 1", ranks.join("/"), side)
+    }
+
+    #[test]
+    fn synthetic_generated_tables() {
+        let mut engine = engine_with_tables(Path::new(SYNTHETIC_TABLES));
+        let positions = [
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", Wdl::Win, 1),
+            ("k7/8/1K6/8/8/8/8/7R b - - 0 1", Wdl::Loss, -2),
+            ("8/8/8/8/8/8/1k6/1R5K b - - 0 1", Wdl::Draw, 0),
+            ("k1K5/7R/8/8/8/8/8/8 b - - 0 1", Wdl::Draw, 0),
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", Wdl::Win, 1),
+            ("6q1/8/8/8/8/1k6/8/K7 b - - 0 1", Wdl::Win, 1),
+        ];
+        for (fen, wdl, dtz) in positions {
+            engine.load_fen(fen).unwrap();
+            assert_eq!(engine.probe_syzygy(), Some(wdl), "{}", fen);
+            assert_eq!(engine.probe_syzygy_dtz(), Some(dtz), "{}", fen);
+        }
+
+        // Compare a sample of the positions with the tables built by the engine. Without captures or pawns,
+        // the DTZ is the distance to mate.
+        for (piece, material) in [('Q', "QkK"), ('R', "RkK")] {
+            let mut dtm = Engine::default();
+            dtm.set_tablebase(Some(Tablebase::generate(&InitialState::default(), &[material]).unwrap()));
+            let mut checked = 0;
+            for index in (0..64 * 64 * 64).step_by(997) {
+                let squares = [index / 4096, index / 64 % 64, index % 64];
+                if squares[0] == squares[1] || squares[1] == squares[2] || squares[0] == squares[2] {
+                    continue;
+                }
+                // Every other position has the colors flipped
+                let pieces = if index % 2 == 0 {
+                    ['k', piece.to_ascii_lowercase(), 'K']
+                } else {
+                    ['K', piece, 'k']
+                };
+                for side in ['w', 'b'] {
+                    let fen = three_piece_fen(squares, pieces, side);
+                    if dtm.load_fen(&fen).is_err() {
+                        continue;
+                    }
+                    let expected = match dtm.probe_tablebase() {
+                        Some(ProbeResult::Win(plies)) => (Wdl::Win, plies as i32),
+                        Some(ProbeResult::Loss(plies)) => (Wdl::Loss, -(plies.max(1) as i32)),
+                        Some(ProbeResult::Draw) => (Wdl::Draw, 0),
+                        None => continue,
+                    };
+                    engine.load_fen(&fen).unwrap();
+                    assert_eq!(engine.probe_syzygy(), Some(expected.0), "{}", fen);
+                    assert_eq!(engine.probe_syzygy_dtz(), Some(expected.1), "{}", fen);
+                    checked += 1;
+                }
+            }
+            assert!(checked > 100, "{}", checked);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the official tables, run with SYZYGY_PATH=<dir> cargo test --features syzygy -- --ignored"]
+    fn real_tables() {
+        // SYZYGY_PATH must point to a directory with the official 3-piece tables
+        // (KQvK, KRvK and KPvK, with the .rtbw and .rtbz files)
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH is not set");
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        engine.set_syzygy_path(Some(&path)).unwrap();
+        let positions = [
+            // KRvK: Rh8# and the only move (Kb8) before it
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", Wdl::Win, 1),
+            ("k7/8/1K6/8/8/8/8/7R b - - 0 1", Wdl::Loss, -2),
+            ("8/8/8/8/8/8/1k6/1R5K b - - 0 1", Wdl::Draw, 0),
+            ("k1K5/7R/8/8/8/8/8/8 b - - 0 1", Wdl::Draw, 0),
+            // KQvK: Qg8#, the colors are flipped in the second position
+            ("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1", Wdl::Win, 1),
+            ("k7/8/1K6/8/8/8/8/6Q1 b - - 0 1", Wdl::Loss, -2),
+            ("6q1/8/8/8/8/1k6/8/K7 b - - 0 1", Wdl::Win, 1),
+            ("8/8/8/8/8/8/1kQ5/7K b - - 0 1", Wdl::Draw, 0),
+            // KPvK: e8=Q is a zeroing move (the colors are flipped in the third position), and the undefended pawn can
+            // be captured
+            ("8/3KP3/8/8/8/8/8/7k w - - 0 1", Wdl::Win, 1),
+            ("8/3KP3/8/8/8/8/8/7k b - - 0 1", Wdl::Loss, -2),
+            ("7K/8/8/8/8/8/3kp3/8 b - - 0 1", Wdl::Win, 1),
+            ("8/8/8/8/8/3k4/4P3/7K b - - 0 1", Wdl::Draw, 0),
+        ];
+        for (fen, wdl, dtz) in positions {
+            engine.load_fen(fen).unwrap();
+            assert_eq!(engine.probe_syzygy(), Some(wdl), "{}", fen);
+            assert_eq!(engine.probe_syzygy_dtz(), Some(dtz), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tablebase_test {
+    use protochess_engine_rs::{Engine, GameState, GlobalRules, InitialState, MakeMoveResultFlag, ProbeResult, Tablebase};
+    use protochess_engine_rs::piece::PieceFactory;
+    use protochess_engine_rs::searcher::mate_in;
+    use protochess_engine_rs::types::GameMode;
+
+    // Standard pieces on a 5x5 board
+    fn small_variant() -> InitialState {
+        InitialState {
+            fen: "rnbqk/ppppp/5/PPPPP/RNBQK".to_string(),
+            player_to_move: 0,
+            piece_types: PieceFactory::new(GameMode::Standard).make_piece_set(5, 5),
+            board_width: 5,
+            board_height: 5,
+            global_rules: GlobalRules::default(),
+        }
+    }
+
+    fn small_engine(fen: &str, tablebase: Option<Tablebase>) -> Engine {
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        let state = GameState { initial_state: small_variant(), initial_fen: Some(fen.to_string()), move_history: vec![] };
+        let result = engine.set_state(state).unwrap();
+        assert_eq!(result.flag, MakeMoveResultFlag::Ok);
+        engine.set_tablebase(tablebase);
+        engine
+    }
+
+    #[test]
+    fn standard_krk() {
+        let tb = Tablebase::generate(&InitialState::default(), &["RkK"]).unwrap();
+        assert_eq!(tb.get_materials(), vec!["KRk", "Kk"]);
+        assert_eq!(tb.get_max_pieces(), 3);
+        let positions = [
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", Some(ProbeResult::Win(1))),
+            ("k7/8/1K6/8/8/8/8/7R b - - 0 1", Some(ProbeResult::Loss(2))),
+            // Stalemate
+            ("k1K5/7R/8/8/8/8/8/8 b - - 0 1", Some(ProbeResult::Draw)),
+            // The rook can be captured
+            ("8/8/8/8/8/8/1k6/1R5K b - - 0 1", Some(ProbeResult::Draw)),
+            // Black is in check with white to move
+            ("k7/8/1K6/8/8/8/8/R7 w - - 0 1", None),
+            // Castling rights are not stored in the tablebase
+            ("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", None),
+            // Not in the tablebase
+            ("4k3/8/8/8/8/8/8/R3K2R w - - 0 1", None),
+        ];
+        let mut engine = Engine::default();
+        engine.set_num_threads(1).unwrap();
+        // The variant hash (after the magic) must not change unless the magic changes too
+        assert_eq!(tb.to_bytes()[5..13], 0xbd85_bb57_9257_4c09_u64.to_be_bytes());
+        engine.set_tablebase(Some(Tablebase::from_bytes(&tb.to_bytes()).unwrap()));
+        for (fen, expected) in positions {
+            engine.load_fen(fen).unwrap();
+            assert_eq!(engine.probe_tablebase(), expected, "{}", fen);
+        }
+
+        // The longest checkmate in KRK takes 16 moves
+        engine.load_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let result = engine.probe_tablebase().unwrap();
+        assert!(matches!(result, ProbeResult::Win(dtm) if dtm <= 31), "{:?}", result);
+        let (_mv, score) = engine.get_best_move(3).unwrap();
+        assert_eq!(mate_in(score), Some(16.min(match result { ProbeResult::Win(dtm) => (dtm as i32 + 1) / 2, _ => 0 })));
+    }
+
+    #[test]
+    fn matches_search() {
+        let tb = Tablebase::generate_up_to(&small_variant(), 3).unwrap();
+        // All materials with 1 extra piece
+        assert_eq!(tb.get_materials().len(), 11);
+        let fens = [
+            "k4/5/1K3/5/3Q1 w -",
+            "k4/5/1K3/5/4Q b -",
+            "k4/5/2K2/5/4R w -",
+            "2k2/5/2K2/5/R4 w -",
+            "k4/2K2/5/5/4N w -",
+            "k4/5/1K3/P4/5 w -",
+            "4k/5/5/p4/K4 b -",
+            "k4/5/K4/5/4q w -",
+        ];
+        for fen in fens {
+            let result = small_engine(fen, Some(tb.clone())).probe_tablebase().unwrap();
+            // Search without the tablebase, deep enough to find the mate
+            let depth = match result {
+                ProbeResult::Win(dtm) | ProbeResult::Loss(dtm) => dtm as u8 + 1,
+                ProbeResult::Draw => 5,
+            };
+            let (_mv, score) = small_engine(fen, None).get_best_move(depth).unwrap();
+            match result {
+                ProbeResult::Win(dtm) => assert_eq!(mate_in(score), Some((dtm as i32 + 1) / 2), "{}", fen),
+                ProbeResult::Loss(dtm) => assert_eq!(mate_in(score), Some(-(dtm as i32 + 1) / 2), "{}", fen),
+                ProbeResult::Draw => assert_eq!(mate_in(score), None, "{}", fen),
+            }
+        }
+    }
+
+    #[test]
+    fn search_uses_tablebase() {
+        let tb = Tablebase::generate(&small_variant(), &["KQk"]).unwrap();
+        // Not in the tablebase, but capturing the rook leads to a won KQk position
+        let fen = "2k2/5/2K2/5/rQ3 w -";
+        let mut engine = small_engine(fen, Some(tb.clone()));
+        assert_eq!(engine.probe_tablebase(), None);
+        let (_mv, score) = engine.get_best_move(2).unwrap();
+        assert!(mate_in(score).is_some());
+        // Without the tablebase, the mate is beyond the search depth
+        let (_mv, score) = small_engine(fen, None).get_best_move(2).unwrap();
+        assert_eq!(mate_in(score), None);
+
+        // In the tablebase, the best move is found at any depth
+        let fen = "k4/5/1K3/5/3Q1 w -";
+        let mut engine = small_engine(fen, Some(tb));
+        let (_mv, score) = engine.get_best_move(1).unwrap();
+        let ProbeResult::Win(dtm) = engine.probe_tablebase().unwrap() else { panic!("Expected a win") };
+        assert_eq!(mate_in(score), Some((dtm as i32 + 1) / 2));
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let state = InitialState::default();
+        assert!(Tablebase::generate(&state, &["KQ"]).is_err());
+        assert!(Tablebase::generate(&state, &["KKk"]).is_err());
+        assert!(Tablebase::generate(&state, &["KXk"]).is_err());
+        assert!(Tablebase::generate(&state, &["KQRBNk"]).is_err());
+        let three_check = protochess_engine_rs::position::create::variant_factory::VariantFactory::new(GameMode::ThreeCheck);
+        assert!(Tablebase::generate(&three_check.make_initial_state(), &["KQk"]).is_err());
+        assert!(Tablebase::from_bytes(b"PCTB1").is_err());
+        // Files with the previous format are rejected
+        let mut bytes = Tablebase::generate(&state, &["Kk"]).unwrap().to_bytes();
+        assert_eq!(&bytes[..5], b"PCTB3");
+        bytes[4] = b'2';
+        assert!(Tablebase::from_bytes(&bytes).is_err());
+
+        // A tablebase from another variant is ignored
+        let tb = Tablebase::generate(&small_variant(), &["KQk"]).unwrap();
+        let mut engine = Engine::default();
+        engine.load_fen("k7/8/1K6/8/8/8/8/7Q w - - 0 1").unwrap();
+        engine.set_tablebase(Some(tb));
+        assert_eq!(engine.probe_tablebase(), None);
+    }
+}
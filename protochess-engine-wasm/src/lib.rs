@@ -1,7 +1,9 @@
 mod utils;
 mod serialize_types;
 
-use protochess_engine_rs::Engine;
+use std::convert::TryFrom;
+
+use protochess_engine_rs::{Engine, EvalParams, FenDialect, NotationMode, OpeningBook, BookFormat, Tablebase, Skill};
 use serde_wasm_bindgen::to_value;
 use wasm_bindgen::prelude::*;
 
@@ -87,6 +89,18 @@ impl Protochess {
         Ok(())
     }
     
+    #[wasm_bindgen(js_name = loadFenDialect)]
+    pub fn load_fen_dialect(&mut self, fen: &str, dialect: &str) -> Result<(), String> {
+        let dialect = FenDialect::try_from(dialect)?;
+        self.engine.load_fen_dialect(fen, dialect)
+    }
+    
+    #[wasm_bindgen(js_name = getFen)]
+    pub fn get_fen(&self, dialect: &str) -> Result<String, String> {
+        let dialect = FenDialect::try_from(dialect)?;
+        self.engine.get_fen(dialect)
+    }
+    
     #[wasm_bindgen(js_name = getState)]
     pub fn get_state(&mut self) -> JsValue {
         let state = self.engine.get_state();
@@ -105,6 +119,94 @@ impl Protochess {
         to_value(hist).unwrap()
     }
     
+    #[wasm_bindgen(js_name = setNotationMode)]
+    pub fn set_notation_mode(&mut self, mode: &str) -> Result<(), String> {
+        let mode = NotationMode::try_from(mode)?;
+        self.engine.set_notation_mode(mode)
+    }
+    
+    #[wasm_bindgen(js_name = getEvalParams)]
+    pub fn get_eval_params(&self) -> String {
+        self.engine.get_eval_params().to_string()
+    }
+    
+    #[wasm_bindgen(js_name = setEvalParams)]
+    pub fn set_eval_params(&mut self, params: &str) -> Result<(), String> {
+        let params = EvalParams::parse(params)?;
+        self.engine.set_eval_params(params)
+    }
+    
+    /// Returns the static evaluation of the current position, with the contribution of each piece and term
+    #[wasm_bindgen(js_name = explainEval)]
+    pub fn explain_eval(&self) -> JsValue {
+        EvalBreakdownSer::to_js(self.engine.explain_eval())
+    }
+    
+    /// Sets the seed of the zobrist keys, the book moves and the weakened play.
+    /// Opening books in the Protochess format only work with the zobrist seed they were built with.
+    #[wasm_bindgen(js_name = setSeed)]
+    pub fn set_seed(&mut self, seed: u32) -> Result<(), String> {
+        self.engine.set_seed(seed as u64)
+    }
+    
+    /// Sets only the seed of the zobrist keys. Opening books in the Protochess format only work with the seed they were built with.
+    #[wasm_bindgen(js_name = setZobristSeed)]
+    pub fn set_zobrist_seed(&mut self, seed: u32) -> Result<(), String> {
+        self.engine.set_zobrist_seed(seed as u64)
+    }
+    
+    /// If `true`, the search is single-threaded and its result only depends on the position and the seed
+    #[wasm_bindgen(js_name = setDeterministic)]
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.engine.set_deterministic(deterministic);
+    }
+    
+    /// Weakens the engine to a skill level from 0 to 19, or plays at full strength if `undefined`
+    #[wasm_bindgen(js_name = setSkillLevel)]
+    pub fn set_skill_level(&mut self, level: Option<u8>) {
+        self.engine.set_skill(level.map(Skill::from_level));
+    }
+    
+    /// Weakens the engine to an approximate Elo rating, or plays at full strength if `undefined`
+    #[wasm_bindgen(js_name = setEloLimit)]
+    pub fn set_elo_limit(&mut self, elo: Option<u32>) {
+        self.engine.set_skill(elo.map(Skill::from_elo));
+    }
+    
+    #[wasm_bindgen(js_name = loadOpeningBook)]
+    pub fn load_opening_book(&mut self, bytes: &[u8], format: &str) -> Result<(), String> {
+        let format = BookFormat::try_from(format)?;
+        let book = OpeningBook::from_bytes(bytes, format)?;
+        self.engine.set_opening_book(Some(book));
+        Ok(())
+    }
+    
+    #[wasm_bindgen(js_name = clearOpeningBook)]
+    pub fn clear_opening_book(&mut self) {
+        self.engine.set_opening_book(None);
+    }
+    
+    /// Generates the tablebase for the current variant, starts using it and returns the file contents
+    #[wasm_bindgen(js_name = generateTablebase)]
+    pub fn generate_tablebase(&mut self, max_pieces: u32) -> Result<Vec<u8>, String> {
+        let tablebase = self.engine.generate_tablebase(max_pieces)?;
+        let bytes = tablebase.to_bytes();
+        self.engine.set_tablebase(Some(tablebase));
+        Ok(bytes)
+    }
+    
+    #[wasm_bindgen(js_name = loadTablebase)]
+    pub fn load_tablebase(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let tablebase = Tablebase::from_bytes(bytes)?;
+        self.engine.set_tablebase(Some(tablebase));
+        Ok(())
+    }
+    
+    #[wasm_bindgen(js_name = clearTablebase)]
+    pub fn clear_tablebase(&mut self) {
+        self.engine.set_tablebase(None);
+    }
+    
     #[wasm_bindgen(js_name = legalMoves)]
     pub fn legal_moves(&mut self) -> Result<JsValue, String> {
         let moves: SerVec<MoveListSer> = self.engine.legal_moves().into();
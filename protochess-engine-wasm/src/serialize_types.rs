@@ -160,3 +160,39 @@ generate_wrapper!(StateDiffSer, StateDiff, [
     in_check, bool,
     player_to_move, u8
 ]);
+
+generate_wrapper!(PieceMaterialSer, PieceMaterial, [
+    id, char,
+    count, u32,
+    midgame, i32,
+    endgame, i32
+]);
+
+generate_wrapper!(PiecePositionalSer, PiecePositional, [
+    id, char,
+    x, u8,
+    y, u8,
+    midgame, i32,
+    endgame, i32
+]);
+
+generate_wrapper!(SideEvalBreakdownSer, SideEvalBreakdown, [
+    material, SerVec<PieceMaterialSer>,
+    positional, SerVec<PiecePositionalSer>,
+    terms_midgame, i32,
+    terms_endgame, i32,
+    midgame, i32,
+    endgame, i32,
+    check_penalty, i32
+]);
+
+generate_wrapper!(EvalBreakdownSer, EvalBreakdown, [
+    white, SideEvalBreakdownSer,
+    black, SideEvalBreakdownSer,
+    phase, i32,
+    handcrafted, i32,
+    inverted, bool,
+    network, Option<i32>,
+    player_to_move, u8,
+    total, i32
+]);